@@ -0,0 +1,135 @@
+// src/emit.rs
+//! `--emit ndjson`: one JSON object per line on stdout while the scan runs, for wrappers that
+//! show live progress or start downstream steps before the tables are written. Events:
+//!
+//! - `{"event":"file","path":..,"experiment":..,"sample":..,"kind":..,"size":..,"md5":..}` when
+//!   a file is classified (`md5` is null while it is still to be hashed),
+//! - `{"event":"md5","path":..,"md5":..}` when a file is hashed later (`--hash-jobs`),
+//! - `{"event":"scan_done","root":..,"visited":..,"added":..}` after every scan root.
+//!
+//! Human readable messages go to stderr in this mode, so stdout stays valid NDJSON.
+use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde::Serialize;
+
+use crate::sample_files::ParsedFile;
+
+static NDJSON: AtomicBool = AtomicBool::new(false);
+
+/// What the scan writes to stdout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Emit {
+    /// messages and the final summary (default)
+    #[default]
+    Text,
+    /// a stream of JSON events, see the module documentation
+    Ndjson,
+}
+
+impl std::str::FromStr for Emit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" => Ok(Emit::Text),
+            "ndjson" | "jsonl" => Ok(Emit::Ndjson),
+            other => Err(format!("unknown emit mode '{}' (text, ndjson)", other)),
+        }
+    }
+}
+
+/// Select the stdout mode; call once at program start.
+pub fn set(mode: Emit) {
+    NDJSON.store(mode == Emit::Ndjson, Ordering::SeqCst);
+}
+
+/// True while stdout carries the event stream.
+pub fn ndjson() -> bool {
+    NDJSON.load(Ordering::SeqCst)
+}
+
+/// One line of the event stream.
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum Event<'a> {
+    File {
+        path: &'a str,
+        experiment: &'a str,
+        sample: &'a str,
+        kind: String,
+        size: u64,
+        md5: Option<&'a str>,
+    },
+    Md5 {
+        path: &'a str,
+        md5: &'a str,
+    },
+    ScanDone {
+        root: String,
+        visited: usize,
+        added: usize,
+    },
+}
+
+fn file_event(pf: &ParsedFile) -> Event<'_> {
+    Event::File {
+        path: &pf.path,
+        experiment: &pf.experiment,
+        sample: &pf.sample,
+        kind: pf.kind.file_type(),
+        size: pf.file_size(),
+        md5: pf.md5sum.as_deref(),
+    }
+}
+
+/// Write one event line; a closed stdout (e.g. `| head`) is ignored.
+fn write(event: Event) {
+    let Ok(line) = serde_json::to_string(&event) else { return };
+    let mut out = std::io::stdout().lock();
+    let _ = writeln!(out, "{}", line).and_then(|_| out.flush());
+}
+
+/// A file was classified by the walk.
+pub fn file(pf: &ParsedFile) {
+    if ndjson() {
+        write(file_event(pf));
+    }
+}
+
+/// The md5 of a file classified earlier is known now.
+pub fn md5(pf: &ParsedFile) {
+    if let (true, Some(md5)) = (ndjson(), &pf.md5sum) {
+        write(Event::Md5 { path: &pf.path, md5 });
+    }
+}
+
+/// A scan root is done.
+pub fn scan_done(root: &Path, visited: usize, added: usize) {
+    if ndjson() {
+        write(Event::ScanDone { root: root.to_string_lossy().into_owned(), visited, added });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sample_files::ParsedKind;
+
+    #[test]
+    fn file_event_has_null_md5_until_hashed() {
+        let mut pf = ParsedFile::test_file(
+            "exp1",
+            "sampleA",
+            ParsedKind::Fastq { lane: "L001".to_string(), role: "R1".to_string() },
+            "exp1/sampleA_L001_R1_001.fastq.gz",
+        );
+        let line = serde_json::to_string(&file_event(&pf)).unwrap();
+        assert!(line.starts_with(r#"{"event":"file","path":"exp1/sampleA_L001_R1_001.fastq.gz","#));
+        assert!(line.contains(r#""sample":"sampleA""#) && line.ends_with(r#""md5":null}"#));
+
+        pf.md5sum = Some("abc".to_string());
+        assert!(serde_json::to_string(&file_event(&pf)).unwrap().ends_with(r#""md5":"abc"}"#));
+    }
+}
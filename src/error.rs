@@ -0,0 +1,88 @@
+// src/error.rs
+use std::io;
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+/// Everything the public API can fail with.
+#[derive(Debug, Error)]
+pub enum GeoPrepError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    /// An output file could not be created.
+    #[error("could not create {}: {source}", path.display())]
+    Create { path: PathBuf, source: io::Error },
+
+    /// The file is not inside an experiment subfolder of the scan root.
+    #[error(
+        "{} is not inside an experiment folder below {} - start from the path containing your experiments in (unique) subfolders or set --experiment",
+        path.display(),
+        root.display()
+    )]
+    NoExperiment { root: PathBuf, path: PathBuf },
+
+    /// A scanned entry looked relevant but could not be classified.
+    #[error("parse failed for {}: {source}", path.display())]
+    Parse { path: PathBuf, source: Box<GeoPrepError> },
+
+    #[error("could not infer sample for path {}", path.display())]
+    NoSample { path: PathBuf },
+
+    #[error("could not determine read role ({known}) from FASTQ name: '{name}'")]
+    UnknownReadRole { name: String, known: String },
+
+    /// A user supplied table (PacBio map, rename plan) is malformed.
+    #[error("{} line {line}: {message}", path.display())]
+    InvalidInput { path: PathBuf, line: usize, message: String },
+
+    /// A configuration file (`--sample-rules`) is malformed.
+    #[error("{}: {message}", path.display())]
+    Config { path: PathBuf, message: String },
+
+    /// A `<prefix>_state.json` export could not be read or written.
+    #[error("state export {}: {message}", path.display())]
+    State { path: PathBuf, message: String },
+
+    /// `--on-duplicate-role error`: lanes with several FASTQs for one read role.
+    #[error("{} FASTQs share a read role with another file of their lane:\n  - {}", issues.len(), issues.join("\n  - "))]
+    DuplicateRoles { issues: Vec<String> },
+
+    /// `--strict`: conditions that are otherwise only warned about.
+    #[error("{} issues in strict mode:\n  - {}", issues.len(), issues.join("\n  - "))]
+    Strict { issues: Vec<String> },
+}
+
+pub type Result<T> = std::result::Result<T, GeoPrepError>;
+
+/// Exit codes of the command line tool, so pipelines and CI wrappers can gate on the result.
+pub mod exit_code {
+    /// nothing to report
+    pub const CLEAN: i32 = 0;
+    /// validation warnings only (`<prefix>_warnings.tsv` has rows)
+    pub const WARNINGS: i32 = 1;
+    /// unparsable files, conflicting files or name collisions; malformed input or config
+    pub const ERRORS: i32 = 2;
+    /// a file or folder could not be read or written
+    pub const IO_FAILURE: i32 = 3;
+
+    /// The code for a run with this many errors and warnings.
+    pub fn for_findings(errors: usize, warnings: usize) -> i32 {
+        match (errors, warnings) {
+            (0, 0) => CLEAN,
+            (0, _) => WARNINGS,
+            _ => ERRORS,
+        }
+    }
+}
+
+impl GeoPrepError {
+    /// `exit_code::IO_FAILURE` for file system errors, `exit_code::ERRORS` for all others.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            GeoPrepError::Io(_) | GeoPrepError::Create { .. } => exit_code::IO_FAILURE,
+            GeoPrepError::Parse { source, .. } => source.exit_code(),
+            _ => exit_code::ERRORS,
+        }
+    }
+}
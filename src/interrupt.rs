@@ -0,0 +1,48 @@
+// src/interrupt.rs
+//! Ctrl-C / SIGTERM handling. The first signal only sets a flag: reads in the long phases
+//! (hashing, `--stats`, gzip checks, 10x packaging) fail with an "interrupted" error, the walk
+//! stops, half written `.tmp` archives are removed and the caller saves what is finished.
+//! A second signal exits at once.
+use std::io::{self, Read};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Exit code after an interrupt (128 + SIGINT, as a shell reports it).
+pub const EXIT_CODE: i32 = 130;
+
+/// Install the signal handler; call once at program start.
+pub fn install() -> Result<(), ctrlc::Error> {
+    ctrlc::set_handler(|| {
+        if INTERRUPTED.swap(true, Ordering::SeqCst) {
+            eprintln!("\nInterrupted again, aborting.");
+            std::process::exit(EXIT_CODE);
+        }
+        eprintln!("\nInterrupt received, finishing up (press Ctrl-C again to abort) ...");
+    })
+}
+
+/// True once a SIGINT/SIGTERM was received.
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// `Err` after an interrupt, for the checks between chunks and files.
+pub fn check() -> io::Result<()> {
+    if interrupted() {
+        // not ErrorKind::Interrupted: io::copy and read_to_end retry those
+        Err(io::Error::other("interrupted"))
+    } else {
+        Ok(())
+    }
+}
+
+/// A reader that stops with an "interrupted" error after Ctrl-C.
+pub struct Checked<R: Read>(pub R);
+
+impl<R: Read> Read for Checked<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        check()?;
+        self.0.read(buf)
+    }
+}
@@ -1,2 +1,8 @@
+pub mod error;
+pub mod emit;
+pub mod interrupt;
+pub mod output;
+pub mod table;
 pub mod sample_files;
-pub use sample_files::{SampleFiles, ParsedFile, ParsedKind};
\ No newline at end of file
+pub use error::{GeoPrepError, Result};
+pub use sample_files::{SampleFiles, ParsedFile, ParsedKind, ScanOptions};
\ No newline at end of file
@@ -1,8 +1,47 @@
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
-use rust_geo_prep::sample_files::SampleFiles;
+use rust_geo_prep::emit::{self, Emit};
+use rust_geo_prep::error::exit_code;
+use rust_geo_prep::interrupt;
+use rust_geo_prep::sample_files::{GeoMetadata, PipelineSheet, SampleFiles};
+use rust_geo_prep::table::{TableFormat, TableStyle};
+use rust_geo_prep::sample_files::rename_plan::apply_renames;
+use rust_geo_prep::sample_files::clean::clean_artifacts;
+use rust_geo_prep::sample_files::scan_options::{parse_date, parse_size};
+use rust_geo_prep::sample_files::{
+    BundleFormat, DedupPolicy, DuplicateRole, Md5Store, RecompressTo, ScanState, StateDiff, SymlinkPolicy, TenxMode,
+};
+
+/// Messages for the user: stdout, or stderr while `--emit ndjson` streams events to stdout.
+macro_rules! say {
+    ($($arg:tt)*) => {
+        if emit::ndjson() { eprintln!($($arg)*) } else { println!($($arg)*) }
+    };
+}
+
+/// Archive the submission is prepared for.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Target {
+    /// GEO raw/processed file tables and metadata skeleton
+    Geo,
+    /// ENA Webin-CLI read manifests
+    Ena,
+}
+
+impl std::str::FromStr for Target {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "geo" => Ok(Target::Geo),
+            "ena" => Ok(Target::Ena),
+            other => Err(format!("unknown target '{}' (geo, ena)", other)),
+        }
+    }
+}
 
 /// Submitting data to GEO is complex. 
 /// This tool helps by collecting the different fastq files and grouping them into samples groups.
@@ -18,11 +57,18 @@ struct Opts {
     ///
     /// Can be specified multiple times:
     ///   --suffix .fastq.gz --suffix .fq.gz
+    ///
+    /// Uncompressed and bzip2/xz/zstd FASTQs are accepted with a warning (see --compress-plain
+    /// and --recompress-to). PacBio `.hifi_reads.bam` / `.subreads.bam` are walked without
+    /// --include-bam
     #[clap(
         short = 's',
         long = "suffix",
         multiple_occurrences = true,
-        default_values = &[".fastq.gz", ".fq.gz"]
+        default_values = &[
+            ".fastq.gz", ".fq.gz", ".fastq", ".fq", ".fastq.bz2", ".fq.bz2", ".fastq.xz", ".fq.xz", ".fastq.zst", ".fq.zst",
+            ".hifi_reads.bam", ".subreads.bam",
+        ]
     )]
     suffixes: Vec<String>,
 
@@ -37,53 +83,796 @@ struct Opts {
     )]
     exclude: Vec<String>,
 
+    /// Additional read role tokens besides R1/R2/I1/I2
+    ///
+    /// Can be specified multiple times:
+    ///   --role R3 --role UMI
+    #[clap(
+        long = "role",
+        multiple_occurrences = true,
+    )]
+    roles: Vec<String>,
+
+    /// Only parse and hash samples whose name matches this glob (`*`, `?`); can be repeated
+    #[clap(long = "only-sample", multiple_occurrences = true)]
+    only_samples: Vec<String>,
+
+    /// Skip samples whose name matches this glob (`*`, `?`); can be repeated
+    #[clap(long = "skip-sample", multiple_occurrences = true)]
+    skip_samples: Vec<String>,
+
+    /// Skip files smaller than this size, e.g. 1 for zero-byte placeholders (units K, M, G, T)
+    #[clap(long, parse(try_from_str = parse_size))]
+    min_size: Option<u64>,
+
+    /// Skip files larger than this size (units K, M, G, T)
+    #[clap(long, parse(try_from_str = parse_size))]
+    max_size: Option<u64>,
+
+    /// Skip files last modified before this date (YYYY-MM-DD, UTC), e.g. to top up a submission
+    #[clap(long, parse(try_from_str = parse_date))]
+    newer_than: Option<SystemTime>,
+
+    /// Do not descend more than this many folder levels below each input root
+    #[clap(long)]
+    max_depth: Option<usize>,
+
+    /// Ignore files and folders less than this many levels below each input root
+    #[clap(long)]
+    min_depth: Option<usize>,
+
+    /// Use this experiment label for every file, e.g. when the input root is the experiment folder
+    #[clap(long)]
+    experiment: Option<String>,
+
+    /// Use the folder this many levels below each input root as experiment (default 1, the first folder)
+    #[clap(long)]
+    experiment_depth: Option<usize>,
+
+    /// Use the folder above the nearest folder with this name as experiment, e.g. `fastq`
+    /// for year/project/run/fastq/ trees (falls back to --experiment-depth)
+    #[clap(long)]
+    experiment_marker: Option<String>,
+
+    /// Symbolic link handling: follow all links, none, or only links to files
+    #[clap(long, default_value = "yes", possible_values = &["yes", "no", "files-only"])]
+    follow_symlinks: SymlinkPolicy,
+
+    /// Also collect BAM/CRAM files (+ .bai/.crai/.csi indices) as raw data
+    #[clap(long)]
+    include_bam: bool,
+
+    /// Also collect h5ad, loom, RDS and CSV count matrices, and bulk RNA-seq quantifications
+    /// (*counts.tsv, featureCounts, salmon quant.sf, kallisto abundance.h5) and bigWig/bedGraph/
+    /// narrowPeak/broadPeak tracks as GEO processed data
+    #[clap(long)]
+    include_processed: bool,
+
+    /// Count reads, mean read length and total bases of every FASTQ (md5 table columns)
+    #[clap(long)]
+    stats: bool,
+
+    /// Read instrument, run, flowcell and lane from the first read header of every FASTQ
+    /// (md5 table columns, JSON export, lane check against the file name)
+    #[clap(long)]
+    read_headers: bool,
+
+    /// Smart-seq2 plates: FASTQ samples are <plate>_<well> (Plate1_A01_R1.fastq.gz -> Plate1_A01);
+    /// also writes <prefix>_plates.tsv with per plate totals
+    #[clap(long)]
+    plate_wells: bool,
+
+    /// Take FASTQ sample names from the SampleSheet.csv of the run folder (nearest parent folder);
+    /// FASTQs not in the sheet and index mismatches go to the warnings table
+    #[clap(long)]
+    sample_sheets: bool,
+
+    /// Read per lane read counts from the bcl2fastq Stats.json / BCL Convert Demultiplex_Stats.csv
+    /// of demultiplexed runs into the HTML report (their Reports/, Stats/, InterOp/ folders are
+    /// always skipped)
+    #[clap(long)]
+    demux_stats: bool,
+
+    /// Hash on this many threads after the walk, largest files first (1 = hash while walking)
+    #[clap(long, default_value = "1")]
+    hash_jobs: usize,
+
+    /// List folders on this many threads while walking (helps on network file systems)
+    #[clap(long, default_value = "1")]
+    walk_threads: usize,
+
+    /// Keep at most N visited-path keys in memory and spill the rest to sorted temporary files
+    /// (for trees with millions of entries)
+    #[clap(long, value_name = "N")]
+    spill_index: Option<usize>,
+
+    /// Bytes per read call when hashing and decompressing (e.g. 8M for fast NVMe)
+    #[clap(long, default_value = "1M", parse(try_from_str = parse_size))]
+    read_buffer: u64,
+
+    /// Memory-map files of at least this size for md5 hashing instead of reading them (e.g. 1G)
+    #[clap(long, parse(try_from_str = parse_size))]
+    mmap_min: Option<u64>,
+
+    /// Never compute an md5: only read existing sidecars/MD5SUMS and mark the rest PENDING
+    #[clap(long)]
+    reuse_md5_only: bool,
+
+    /// Hash only the first N MB of files without a cached md5, for the duplicate detector;
+    /// their md5 is PENDING (quick runs while grouping and naming are worked out)
+    #[clap(long, value_name = "MB")]
+    preview_hash: Option<u64>,
+
+    /// Only validate the tree (parsing, pairing, duplicates): print errors and warnings, write
+    /// no tables and exit with 0 (clean), 1 (warnings), 2 (errors) or 3 (IO failure)
+    #[clap(long)]
+    check: bool,
+
+    /// `ndjson` streams one JSON object per classified file (path, sample, kind, md5 when
+    /// ready) to stdout while scanning; all other messages then go to stderr
+    #[clap(long, default_value = "text", possible_values = &["text", "ndjson"])]
+    emit: Emit,
+
+    /// Fully decompress every gzip file to verify its CRC; problems go to the warnings table
+    #[clap(long)]
+    validate_gzip: bool,
+
+    /// Submit 10x matrix triplets as one archive per folder (zip) or as three files
+    /// <sample>_<filtered|raw>_{barcodes,features,matrix}.*.gz (individual)
+    #[clap(long, default_value = "zip", possible_values = &["zip", "individual"])]
+    tenx_mode: TenxMode,
+
+    /// Archive format of the packaged 10x matrix triplets (<sample>.zip or <sample>.tar.gz)
+    #[clap(long, default_value = "zip", possible_values = &["zip", "tar.gz"])]
+    bundle_format: BundleFormat,
+
+    /// Write the 10x archives below this staging folder (same relative layout as the input)
+    /// instead of next to the data, keeping the source tree untouched
+    #[clap(long)]
+    bundle_dir: Option<PathBuf>,
+
+    /// Compression level of uncompressed 10x archive members; gzipped members are always stored
+    #[clap(long, possible_values = &["0", "1", "2", "3", "4", "5", "6", "7", "8", "9"])]
+    zip_level: Option<u32>,
+
+    /// Worker threads packaging 10x archives (0 = up to 4, by available cores)
+    #[clap(long, default_value = "0")]
+    bundle_jobs: usize,
+
+    /// Cache md5 sums in one <file>.md5sum sidecar per file or in a single MD5SUMS file per directory
+    #[clap(long, default_value = "sidecar", possible_values = &["sidecar", "directory"])]
+    md5_store: Md5Store,
+
+    /// Which copy of a file found several times (same name and md5) is kept: keep-first,
+    /// keep-shortest-path, prefer-path:<glob> (e.g. prefer-path:*/primary/*) or off (keep all);
+    /// dropped copies are listed in <prefix>_dedup.tsv
+    #[clap(long, default_value = "keep-first")]
+    dedup: DedupPolicy,
+
+    /// Glob of the preferred copy when a file or lane was found several times, e.g.
+    /// '*/primary/*'; can be repeated, the earlier glob wins. Decides before --dedup
+    #[clap(long = "prefer-path", multiple_occurrences = true)]
+    prefer_paths: Vec<String>,
+
+    /// Which FASTQ keeps a read role when a lane has two files for it: keep-first, keep-newest,
+    /// keep-largest, error (fail after the scan with every affected lane) or split (keep both as
+    /// top-up runs, named by flowcell or parent folder). Files from different flowcells are
+    /// always kept as top-ups
+    #[clap(
+        long,
+        default_value = "keep-first",
+        possible_values = &["error", "keep-newest", "keep-largest", "keep-first", "split"]
+    )]
+    on_duplicate_role: DuplicateRole,
+
+    /// Use cached md5 sums even if the file size or modification time changed since hashing
+    #[clap(long)]
+    trust_cache: bool,
+
+    /// Recompute the md5 of every file with a cached md5 (sidecar or MD5SUMS entry); mismatches go to the warnings table
+    #[clap(long)]
+    sidecar_check: bool,
+
+    /// Write <prefix>_undetermined.tsv tallying the excluded Undetermined/Unmapped FASTQs per run
+    #[clap(long)]
+    undetermined_report: bool,
+
+    /// Write <prefix>_unclassified.tsv listing every file that was seen but not used, and why
+    #[clap(long)]
+    unclassified_report: bool,
+
+    /// Fail instead of warning on dropped duplicates (read roles, 10x bundles, ...), unparsable files and file name collisions
+    #[clap(long)]
+    strict: bool,
+
+    /// File format of the sample and md5 tables
+    #[clap(long, default_value = "tsv", possible_values = &["tsv", "csv", "xlsx"])]
+    format: TableFormat,
+
+    /// Columns of the sample table in output order, e.g. sample,tenx,h5,R1,R2
+    /// (groups: source, sample, organism, strategy, attributes, tenx, h5, fragments, alignment,
+    /// pacbio; plus read roles)
+    #[clap(long, use_value_delimiter = true)]
+    columns: Vec<String>,
+
+    /// Drop sample table columns that are empty for every sample
+    #[clap(long)]
+    hide_empty_columns: bool,
+
+    /// Start the TSV/CSV sample and md5 tables with a UTF-8 byte order mark (helps Excel)
+    #[clap(long)]
+    bom: bool,
+
+    /// Archive to prepare for: GEO tables and metadata skeleton, or ENA Webin-CLI manifests
+    /// in <prefix>_ena_manifests/ (one per run)
+    #[clap(long, default_value = "geo", possible_values = &["geo", "ena"])]
+    target: Target,
+
+    /// ENA study accession (PRJEB...) written to the STUDY line of every ENA manifest
+    #[clap(long)]
+    ena_study: Option<String>,
+
+    /// GEO series title for <prefix>_geo_metadata.tsv
+    #[clap(long)]
+    series_title: Option<String>,
+
+    /// Organism for every sample in <prefix>_geo_metadata.tsv (e.g. "Homo sapiens");
+    /// the [organism] section of --sample-rules takes precedence
+    #[clap(long)]
+    organism: Option<String>,
+
+    /// Library strategy for <prefix>_geo_metadata.tsv (e.g. RNA-Seq, ATAC-seq)
+    #[clap(long)]
+    library_strategy: Option<String>,
+
+    /// Molecule for <prefix>_geo_metadata.tsv (e.g. "polyA RNA")
+    #[clap(long)]
+    molecule: Option<String>,
+
+    /// Instrument model for <prefix>_geo_metadata.tsv (e.g. "Illumina NovaSeq 6000")
+    #[clap(long)]
+    instrument_model: Option<String>,
+
+    /// Write a sample sheet for reprocessing pipelines: <prefix>_samples.csv (nf-core:
+    /// sample,fastq_1,fastq_2) or <prefix>_units.tsv (snakemake: sample, unit, fq1, fq2)
+    #[clap(long, possible_values = &["nfcore", "snakemake"])]
+    pipeline_sheet: Option<PipelineSheet>,
+
+    /// Write <prefix>_sra_metadata.tsv, a run table for submitting the raw reads to SRA directly
+    #[clap(long)]
+    sra_metadata: bool,
+
+    /// Write <prefix>_report.md, a per-experiment Markdown overview for lab notebooks / issues
+    #[clap(long)]
+    markdown_report: bool,
+
+    /// Write <prefix>_merge_plan.sh concatenating all lanes of a sample into <sample>_<role>.fastq.gz
+    #[clap(long)]
+    merge_plan: bool,
+
+    /// Concatenate all lanes of a sample into <prefix>_merged_fastqs/<sample>_<role>.fastq.gz now
+    /// and write their md5 sums to <prefix>_merged_md5sum.tsv
+    #[clap(long)]
+    merge_lanes: bool,
+
+    /// Rename the copies in <prefix>_all_files_copied as listed in this (reviewed) rename plan and exit
+    #[clap(long)]
+    apply_renames: Option<PathBuf>,
+
+    /// Keep files that look like public archive downloads (SRR/ERR/GSM/... prefixes);
+    /// by default they are skipped and listed on stderr
+    #[clap(long)]
+    keep_accession_like: bool,
+
+    /// Keep zero byte files, empty gzip files and FASTQs without reads; by default they are
+    /// skipped and listed on stderr
+    #[clap(long)]
+    keep_empty: bool,
+
+    /// Write a gzip copy of every uncompressed .fastq/.fq (next to it, or below --bundle-dir)
+    /// and submit that instead; copies newer than the FASTQ are reused
+    #[clap(long)]
+    compress_plain: bool,
+
+    /// bzip2/xz/zstd (and uncompressed) FASTQs: keep them as they are, or gz to write a gzip
+    /// copy (next to them, or below --bundle-dir) that is hashed and uploaded instead
+    #[clap(long, default_value = "keep", possible_values = &["keep", "gz"])]
+    recompress_to: RecompressTo,
+
+    /// Two column file (movie, sample) mapping PacBio movie names to sample names
+    #[clap(long)]
+    pacbio_map: Option<PathBuf>,
+
+    /// TOML file with sample detection rules per file kind, tried before the built-in rules,
+    /// `[merge_samples]` and `[[split_samples]]` (see README "Custom Sample Rules")
+    #[clap(long)]
+    sample_rules: Option<PathBuf>,
+
+    /// Table (TSV or CSV) with a sample column and free-form attribute columns (treatment,
+    /// timepoint, ...) added to the sample table and <prefix>_geo_metadata.tsv
+    #[clap(long, value_name = "TABLE")]
+    attributes: Option<PathBuf>,
+
+    /// Sample table of an earlier run, edited by hand (<prefix>.tsv or <prefix>_long.tsv, TSV
+    /// or CSV): renamed samples and removed files are applied before any output is written
+    #[clap(long, value_name = "TABLE")]
+    amend: Option<PathBuf>,
+
+    /// Publish files without the experiment prefix; clashing file and sample names get `_1`,
+    /// `_2`, ... in a stable order instead (see the rename plan for the mapping)
+    #[clap(long)]
+    conflict_suffix: bool,
+
+    /// Replace spaces, non-ASCII and special characters in experiment, sample and file names
+    /// by `_` and shorten names over 100 characters; every change goes to <prefix>_name_fixes.tsv
+    #[clap(long)]
+    fix_names: bool,
+
+    /// Replace every sample name by Sample_001, Sample_002, ... in all tables, metadata and
+    /// upload names; the private mapping goes to <prefix>_anonymization_key.tsv
+    #[clap(long)]
+    anonymize: bool,
+
     /// Root directory. Each direct subfolder is an experiment.
-    #[clap(short, long )]
-    input: Option<PathBuf>,
+    ///
+    /// Can be specified multiple times to merge several storage locations:
+    ///   --input /proj/runA --input /archive/runB
+    #[clap(short, long, multiple_occurrences = true)]
+    input: Vec<PathBuf>,
+
+    /// Take the files from this list (one path per line, e.g. a LIMS export) instead of
+    /// scanning --input; relative paths are read from the folder of the list
+    #[clap(long, conflicts_with = "input")]
+    file_list: Option<PathBuf>,
+
+    /// Folder the experiments of --file-list are read below (default: the folder of the list)
+    #[clap(long, requires = "file-list")]
+    file_list_root: Option<PathBuf>,
+
+    #[clap(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Compare two <prefix>_state.json exports and report added, removed and changed files per sample
+    Diff {
+        /// the earlier state export
+        old: PathBuf,
+        /// the later state export
+        new: PathBuf,
+    },
+    /// Remove the files earlier scans wrote into the data tree (md5 sidecars, our MD5SUMS,
+    /// 10x archives with their contents listings and interrupted `.tmp` archives)
+    Clean {
+        /// scan roots to clean
+        #[clap(required = true)]
+        roots: Vec<PathBuf>,
+        /// only list what would be removed
+        #[clap(long)]
+        dry_run: bool,
+        /// also remove `<prefix>_state.json`
+        #[clap(long)]
+        prefix: Option<String>,
+    },
+}
+
+/// Report a failed output write; the failures are counted so the run ends with
+/// `exit_code::IO_FAILURE` instead of pretending every table was written.
+fn written<T, E: std::fmt::Display>(path: &str, result: std::result::Result<T, E>, failed: &mut usize) -> Option<T> {
+    match result {
+        Ok(value) => Some(value),
+        Err(e) => {
+            eprintln!("Could not write {}: {}", path, e);
+            *failed += 1;
+            None
+        }
+    }
+}
 
+/// `--check`: list errors and validation warnings on stdout and return the exit code.
+fn report_check(data: &SampleFiles) -> i32 {
+    let errors = data.strict_issues();
+    let warnings = data.validation_warnings();
+    for issue in &errors {
+        say!("ERROR\t{}", issue);
+    }
+    for w in &warnings {
+        say!("WARNING\t{}\t{}\t{}\t{}\t{}", w.category, w.experiment, w.sample, w.lane, w.message);
+    }
+    say!("{} sample(s), {} error(s), {} warning(s)", data.len(), errors.len(), warnings.len());
+    exit_code::for_findings(errors.len(), warnings.len())
+}
+
+fn run_diff(old: &Path, new: &Path) {
+    let load = |p: &Path| match ScanState::load(p) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("\n❌ Failed to read state export {}:", p.display());
+            eprintln!("   {e}\n");
+            std::process::exit(e.exit_code());
+        }
+    };
+    let diff = StateDiff::between(&load(old), &load(new));
+    print!("{}", diff.report());
+}
+
+fn run_clean(roots: &[PathBuf], dry_run: bool, prefix: Option<&str>) {
+    let mut found = Vec::new();
+    let mut skipped = Vec::new();
+    for root in roots {
+        match clean_artifacts(root, dry_run) {
+            Ok((paths, foreign)) => {
+                found.extend(paths);
+                skipped.extend(foreign);
+            }
+            Err(e) => {
+                eprintln!("\n❌ Failed to clean {}:", root.display());
+                eprintln!("   {e}\n");
+                std::process::exit(e.exit_code());
+            }
+        }
+    }
+    if let Some(state) = prefix.map(|p| PathBuf::from(format!("{p}_state.json"))).filter(|p| p.is_file()) {
+        if !dry_run {
+            if let Err(e) = std::fs::remove_file(&state) {
+                eprintln!("\n❌ Failed to remove {}: {e}\n", state.display());
+                std::process::exit(exit_code::IO_FAILURE);
+            }
+        }
+        found.push(state);
+    }
+    for p in &found {
+        say!("{}", p.display());
+    }
+    if dry_run {
+        for p in &skipped {
+            say!("skipped (not written by rust-geo-prep): {}", p.display());
+        }
+    }
+    let verb = if dry_run { "would be removed" } else { "removed" };
+    say!("{} file(s) {verb}", found.len());
 }
 
 
 fn main(){
-    let opts: Opts = Opts::parse();
+    let mut opts: Opts = Opts::parse();
+    emit::set(opts.emit);
+
+    if let Some(Command::Diff { old, new }) = &opts.command {
+        run_diff(old, new);
+        return;
+    }
+    if let Some(Command::Clean { roots, dry_run, prefix }) = &opts.command {
+        run_clean(roots, *dry_run, prefix.as_deref());
+        return;
+    }
+    if let Err(e) = interrupt::install() {
+        eprintln!("WARN: can not catch Ctrl-C ({e}); an interrupted run may leave .tmp files");
+    }
+
+    if opts.include_processed {
+        for suf in [
+            ".h5ad", ".loom", ".rds", ".RDS", ".csv", ".csv.gz",
+            // bulk RNA-seq quantifications
+            "counts.tsv", "counts.tsv.gz", ".featureCounts.txt", "quant.sf", "quant.sf.gz", "quant.genes.sf",
+            "abundance.h5", "abundance.tsv",
+            // ChIP-/ATAC-seq tracks and peaks
+            ".bw", ".bigWig", ".bigwig", ".bedGraph", ".bedGraph.gz", ".bedgraph.gz",
+            ".narrowPeak", ".narrowPeak.gz", ".broadPeak", ".broadPeak.gz",
+        ] {
+            if !opts.suffixes.iter().any(|s| s == suf) {
+                opts.suffixes.push(suf.to_string());
+            }
+        }
+    }
+    if opts.include_bam {
+        for suf in [".bam", ".cram", ".bai", ".crai", ".csi"] {
+            if !opts.suffixes.iter().any(|s| s == suf) {
+                opts.suffixes.push(suf.to_string());
+            }
+        }
+    }
     
-    let sample_file_path = format!("{}.tsv", opts.prefix);
-    let files_file_path = format!("{}_md5sum.tsv", opts.prefix);
+    let table_style = TableStyle { format: opts.format, bom: opts.bom };
+    let sample_file_path = format!("{}.{}", opts.prefix, opts.format.extension());
+    let files_file_path = format!("{}_md5sum.{}", opts.prefix, opts.format.extension());
     let pairs_file_path = format!("{}_pairs.tsv", opts.prefix);
+    let long_file_path = format!("{}_long.tsv", opts.prefix);
+    let raw_files_path = format!("{}_raw_files.tsv", opts.prefix);
+    let processed_files_path = format!("{}_processed_files.tsv", opts.prefix);
+    let warnings_path = format!("{}_warnings.tsv", opts.prefix);
     let collection_script_path = if cfg!(windows) {
         format!("{}_collection_script.ps1", opts.prefix)
     } else {
         format!("{}_collection_script.sh", opts.prefix)
     };
     let collection_dest = format!("{}_all_files_copied", opts.prefix);
+    let rename_plan_path = format!("{}_rename_plan.tsv", opts.prefix);
+    let state_path = format!("{}_state.json", opts.prefix);
+
+    if let Some(plan) = &opts.apply_renames {
+        match apply_renames(plan, &collection_dest) {
+            Ok(n) => say!("{} files renamed in {}", n, collection_dest),
+            Err(e) => {
+                eprintln!("\n❌ Failed to apply rename plan {}:", plan.display());
+                eprintln!("   {e}\n");
+                std::process::exit(e.exit_code());
+            }
+        }
+        return;
+    }
     
     //let sample_file_path_basename = format!("{}_basename_sample_lines.tsv", opts.prefix);
     //let files_file_path_basename = format!("{}_basename_files_md5sum_lines.tsv", opts.prefix);
 
-    println!("We are searching for files ending on either of these strings {:?}", opts.suffixes );
+    say!("We are searching for files ending on either of these strings {:?}", opts.suffixes );
 
-    let root = opts.input.as_deref().unwrap_or(Path::new("."));
+    let roots: Vec<PathBuf> = if opts.input.is_empty() {
+        vec![PathBuf::from(".")]
+    } else {
+        opts.input.clone()
+    };
 
     
-    let mut data = SampleFiles::new();
+    let mut data = opts.exclude.iter().fold(SampleFiles::builder(), |b, ex| b.exclude(ex.as_str()))
+        .suffixes(opts.suffixes.iter().cloned())
+        .role_tokens(opts.roles.iter().cloned())
+        .only_samples(opts.only_samples.iter().cloned())
+        .skip_samples(opts.skip_samples.iter().cloned())
+        .follow_symlinks(opts.follow_symlinks)
+        .max_depth(opts.max_depth)
+        .min_depth(opts.min_depth)
+        .min_size(opts.min_size)
+        .max_size(opts.max_size)
+        .newer_than(opts.newer_than)
+        .experiment(opts.experiment.clone())
+        .experiment_depth(opts.experiment_depth)
+        .experiment_marker(opts.experiment_marker.clone())
+        .include_bam(opts.include_bam)
+        .stats(opts.stats)
+        .read_headers(opts.read_headers)
+        .plate_wells(opts.plate_wells)
+        .sample_sheets(opts.sample_sheets)
+        .demux_stats(opts.demux_stats)
+        .hash_jobs(opts.hash_jobs)
+        .walk_threads(opts.walk_threads)
+        .spill_index(opts.spill_index)
+        .read_buffer(opts.read_buffer as usize)
+        .mmap_min(opts.mmap_min)
+        .reuse_md5_only(opts.reuse_md5_only)
+        .preview_hash(opts.preview_hash.map(|mb| mb * 1024 * 1024))
+        .audit_unclassified(opts.unclassified_report)
+        .validate_gzip(opts.validate_gzip)
+        .tenx_mode(opts.tenx_mode)
+        .bundle_format(opts.bundle_format)
+        .bundle_dir(opts.bundle_dir.clone())
+        .zip_level(opts.zip_level)
+        .bundle_jobs(opts.bundle_jobs)
+        .md5_store(opts.md5_store)
+        .trust_cache(opts.trust_cache)
+        .sample_columns(opts.columns.clone())
+        .hide_empty_columns(opts.hide_empty_columns)
+        .sidecar_check(opts.sidecar_check)
+        .keep_accession_like(opts.keep_accession_like)
+        .keep_empty(opts.keep_empty)
+        .compress_plain(opts.compress_plain)
+        .recompress_to(opts.recompress_to)
+        .dedup(opts.dedup.clone())
+        .prefer_paths(opts.prefer_paths.iter().cloned())
+        .on_duplicate_role(opts.on_duplicate_role)
+        .build();
+    if let Some(map) = &opts.pacbio_map {
+        if let Err(e) = data.options_mut().load_pacbio_map(map) {
+            eprintln!("\n❌ Failed to read PacBio movie map {}:", map.display());
+            eprintln!("   {e}\n");
+            std::process::exit(e.exit_code());
+        }
+    }
+    if let Some(rules) = &opts.sample_rules {
+        if let Err(e) = data.options_mut().load_sample_rules(rules) {
+            eprintln!("\n❌ Failed to read sample rules {}:", rules.display());
+            eprintln!("   {e}\n");
+            std::process::exit(e.exit_code());
+        }
+    }
+    if let Some(attributes) = &opts.attributes {
+        if let Err(e) = data.options_mut().load_sample_attributes(attributes) {
+            eprintln!("\n❌ Failed to read sample attributes {}:", attributes.display());
+            eprintln!("   {e}\n");
+            std::process::exit(e.exit_code());
+        }
+    }
     
-    let (added, visited) = match data.ingest_dir(root, &opts.suffixes, &opts.exclude) {
+    let scanned = match &opts.file_list {
+        Some(list) => data.ingest_file_list(list, opts.file_list_root.as_deref()),
+        None => roots.iter().try_fold((0usize, 0usize), |(added, visited), root| {
+            data.ingest(root).map(|(a, v)| (added + a, visited + v))
+        }),
+    };
+    let (added, visited) = match scanned {
         Err(e) => {
             eprintln!("\n❌ Failed while scanning input directories:");
             eprintln!("   {e}\n");
-            std::process::exit(1);
+            std::process::exit(e.exit_code());
         },
         Ok(i) => i,
     };
+    if interrupt::interrupted() {
+        if let Err(e) = data.write_state_json(&state_path) {
+            eprintln!("Could not write {}: {}", state_path, e);
+        }
+        eprintln!("\n⚠️  Interrupted: {} file(s) were finished, their state is in {}.", added, state_path);
+        eprintln!("   Finished md5 sums are kept next to the files and partial archives were removed.");
+        eprintln!("   Rerun the same command to resume; finished md5 sums are read back, not recomputed.\n");
+        std::process::exit(interrupt::EXIT_CODE);
+    }
+    if let Err(e) = data.check_duplicate_roles() {
+        eprintln!("\n❌ {e}\n");
+        std::process::exit(e.exit_code());
+    }
+    if let Some(table) = &opts.amend {
+        match data.amend(table) {
+            Ok(s) => say!(
+                "Applied {}: {} file(s) moved, {} removed, {} not found",
+                table.display(), s.moved, s.removed, s.unknown
+            ),
+            Err(e) => {
+                eprintln!("\n❌ Failed to apply the edited sample table {}:", table.display());
+                eprintln!("   {e}\n");
+                std::process::exit(e.exit_code());
+            }
+        }
+    }
+    for row in data.unused_attribute_rows() {
+        eprintln!("WARN: --attributes: no scanned sample {}", row);
+    }
+    if opts.conflict_suffix {
+        let n = data.use_conflict_suffixes();
+        say!("{} clashing file name(s) numbered instead of prefixed with the experiment", n);
+    }
+    if opts.anonymize {
+        let n = data.anonymize();
+        say!("{} sample(s) anonymized", n);
+    }
+    if opts.fix_names {
+        let n = data.fix_names();
+        say!("{} name(s) changed to GEO safe names", n);
+    }
+    if opts.check {
+        std::process::exit(report_check(&data));
+    }
+    if opts.strict {
+        if let Err(e) = data.check_strict() {
+            eprintln!("\n❌ {e}\n");
+            std::process::exit(e.exit_code());
+        }
+    }
 
-    let _ = data.write_sample_files_basename(&sample_file_path);
-    let _ = data.write_md5_files_basename(&files_file_path);
-    let _ = data.write_fastq_pairs_table(&pairs_file_path );
-    let _ = if cfg!(windows) {
+    let mut failed = 0usize;
+    written(&sample_file_path, data.write_sample_files_as(&sample_file_path, table_style), &mut failed);
+    written(&files_file_path, data.write_md5_files_as(&files_file_path, table_style), &mut failed);
+    written(&pairs_file_path, data.write_fastq_pairs_table(&pairs_file_path), &mut failed);
+    written(&long_file_path, data.write_sample_files_long(&long_file_path), &mut failed);
+    written(&rename_plan_path, data.write_rename_plan(&rename_plan_path), &mut failed);
+    written(&state_path, data.write_state_json(&state_path), &mut failed);
+    let summary_path = format!("{}_summary.txt", opts.prefix);
+    if let Some(text) = written(&summary_path, data.write_summary(&summary_path), &mut failed) {
+        say!("\n{}", text);
+    }
+    // a failed write still has the warnings it could not list
+    let n_warnings = written(&warnings_path, data.write_warnings_table(&warnings_path), &mut failed)
+        .unwrap_or_else(|| data.validation_warnings().len());
+    let target_files = match opts.target {
+        Target::Geo => {
+            let geo_metadata_path = format!("{}_geo_metadata.tsv", opts.prefix);
+            written(&raw_files_path, data.write_raw_files_table(&raw_files_path), &mut failed);
+            written(&processed_files_path, data.write_processed_files_table(&processed_files_path), &mut failed);
+            let geo_metadata = GeoMetadata {
+                series_title: opts.series_title.clone(),
+                organism: opts.organism.clone(),
+                library_strategy: opts.library_strategy.clone(),
+                molecule: opts.molecule.clone(),
+                instrument_model: opts.instrument_model.clone(),
+            };
+            written(&geo_metadata_path, data.write_geo_metadata(&geo_metadata_path, &geo_metadata), &mut failed);
+            format!(
+                "- GEO raw files     : {}\n\
+                 - GEO processed data: {}\n\
+                 - GEO metadata stub : {}\n",
+                raw_files_path, processed_files_path, geo_metadata_path
+            )
+        }
+        Target::Ena => {
+            let manifest_dir = format!("{}_ena_manifests", opts.prefix);
+            let manifests = data.write_ena_manifest(&manifest_dir, opts.ena_study.as_deref());
+            match written(&manifest_dir, manifests, &mut failed) {
+                Some(n) => format!("- ENA manifests     : {}/ ({} runs)\n", manifest_dir, n),
+                None => String::new(),
+            }
+        }
+    };
+    let report_path = format!("{}_report.html", opts.prefix);
+    written(&report_path, data.write_html_report(&report_path), &mut failed);
+    if let Some(sheet) = opts.pipeline_sheet {
+        let sheet_path = sheet.file_name(&opts.prefix);
+        if let Some(n) = written(&sheet_path, data.write_pipeline_sheet(&sheet_path, sheet), &mut failed) {
+            say!("Pipeline sample sheet ({} rows): {}", n, sheet_path);
+        }
+    }
+    if opts.sra_metadata {
+        let sra_path = format!("{}_sra_metadata.tsv", opts.prefix);
+        if written(&sra_path, data.write_sra_metadata(&sra_path), &mut failed).is_some() {
+            say!("SRA run metadata: {}", sra_path);
+        }
+    }
+    if opts.markdown_report {
+        let markdown_path = format!("{}_report.md", opts.prefix);
+        if written(&markdown_path, data.write_markdown_report(&markdown_path), &mut failed).is_some() {
+            say!("Markdown report: {}", markdown_path);
+        }
+    }
+    let dedup_path = format!("{}_dedup.tsv", opts.prefix);
+    if !data.dropped_copies().is_empty() {
+        if let Some(n) = written(&dedup_path, data.write_dedup_report(&dedup_path), &mut failed) {
+            say!("Dropped duplicate copies ({}): {}", n, dedup_path);
+        }
+    }
+    if opts.anonymize {
+        let key_path = format!("{}_anonymization_key.tsv", opts.prefix);
+        if let Some(n) = written(&key_path, data.write_anonymization_key(&key_path), &mut failed) {
+            say!("Anonymization key ({} samples, keep it private, do not upload): {}", n, key_path);
+        }
+    }
+    if opts.fix_names {
+        let fixes_path = format!("{}_name_fixes.tsv", opts.prefix);
+        if let Some(n) = written(&fixes_path, data.write_name_fixes(&fixes_path), &mut failed) {
+            say!("Name fixes ({}): {}", n, fixes_path);
+        }
+    }
+    if opts.unclassified_report {
+        let unclassified_path = format!("{}_unclassified.tsv", opts.prefix);
+        if let Some(n) = written(&unclassified_path, data.write_unclassified_report(&unclassified_path), &mut failed) {
+            say!("Unclassified files ({}): {}", n, unclassified_path);
+        }
+    }
+    if opts.plate_wells {
+        let plates_path = format!("{}_plates.tsv", opts.prefix);
+        if let Some(n) = written(&plates_path, data.write_plate_summary(&plates_path), &mut failed) {
+            say!("Plate summary ({} plates): {}", n, plates_path);
+        }
+    }
+    if opts.undetermined_report {
+        let undetermined_path = format!("{}_undetermined.tsv", opts.prefix);
+        if written(&undetermined_path, data.write_undetermined_report(&undetermined_path), &mut failed).is_some() {
+            say!("Undetermined/Unmapped report: {}", undetermined_path);
+        }
+    }
+    if opts.merge_plan {
+        let merge_plan_path = format!("{}_merge_plan.sh", opts.prefix);
+        let merge_dest = format!("{}_merged_fastqs", opts.prefix);
+        if written(&merge_plan_path, data.write_merge_plan_sh(&merge_plan_path, &merge_dest), &mut failed).is_some() {
+            say!("Lane merge plan: {}", merge_plan_path);
+        }
+    }
+    if opts.merge_lanes {
+        let merge_dest = format!("{}_merged_fastqs", opts.prefix);
+        let merged_md5_path = format!("{}_merged_md5sum.tsv", opts.prefix);
+        match data.merge_lanes(&merge_dest, &merged_md5_path) {
+            Ok(n) => say!("{} merged FASTQs written to {} (md5: {})", n, merge_dest, merged_md5_path),
+            Err(e) => {
+                eprintln!("\n❌ Failed to merge lanes into {}:", merge_dest);
+                eprintln!("   {e}\n");
+                std::process::exit(e.exit_code());
+            }
+        }
+    }
+    let script = if cfg!(windows) {
         data.write_collect_all_files_script_ps1(&collection_script_path, &collection_dest)
     } else {
         data.write_collect_all_files_script_sh(&collection_script_path, &collection_dest)
     };
+    written(&collection_script_path, script, &mut failed);
 
     //let _ = data.write_sample_files_basename(&sample_file_path_basename);
     //let _ = data.write_md5_files_basename(&files_file_path_basename);
@@ -96,12 +885,19 @@ fn main(){
 
 
 
-    println!(
+    say!(
         "\n{} target files detected; {} added and {} samples identified.\n\
          \nOutput files:\n\
          - Sample table      : {}\n\
          - MD5 checksum table: {}\n\
          - Pairs collection  : {}\n\
+         - Long sample table : {}\n\
+         {}\
+         - Rename plan       : {}\n\
+         - Upload summary    : {}\n\
+         - Scan state (JSON) : {}\n\
+         - Warnings ({:>4})   : {}\n\
+         - HTML report       : {}\n\
          - Collection script : {}\n\
          - Copy destination  : {}\n\
          \nNext steps:\n\
@@ -116,13 +912,26 @@ fn main(){
         sample_file_path,
         files_file_path,
         pairs_file_path,
+        long_file_path,
+        target_files,
+        rename_plan_path,
+        summary_path,
+        state_path,
+        n_warnings,
+        warnings_path,
+        report_path,
         collection_script_path,
         collection_dest,
         run_cmd
     );    
-    if data.force_experiment_prefix_export{
-        println!("Experiment names are part of the published file names as a sample id overlap was detected!")
+    if data.experiment_prefix_forced() && !opts.conflict_suffix {
+        say!("Experiment names were added to the sample and file names that are not unique across experiments.")
+    }
+    if failed > 0 {
+        eprintln!("\n❌ {} output file(s) could not be written\n", failed);
+        std::process::exit(exit_code::IO_FAILURE);
     }
+    std::process::exit(exit_code::for_findings(data.strict_issues().len(), n_warnings));
 }
 
 
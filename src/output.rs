@@ -0,0 +1,93 @@
+// src/output.rs
+//! Atomic output files: every table, script and report is written to `<name>.tmp`
+//! and only renamed to `<name>` by `commit()`, so an interrupted or failed run never
+//! leaves a half written file that looks complete.
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::error::{GeoPrepError, Result};
+
+/// A buffered writer to `<target>.tmp`; dropped without `commit()` it removes the tmp file.
+pub struct AtomicFile {
+    inner: Option<BufWriter<File>>,
+    tmp: PathBuf,
+    target: PathBuf,
+}
+
+impl AtomicFile {
+    /// The temporary path written to until `commit()`.
+    pub fn tmp_path(&self) -> &Path {
+        &self.tmp
+    }
+
+    /// Flush, sync and move the finished file into place.
+    pub fn commit(mut self) -> Result<()> {
+        let create_err = |source| GeoPrepError::Create { path: self.target.clone(), source };
+        let w = self.inner.take().expect("AtomicFile committed twice");
+        let file = w.into_inner().map_err(|e| create_err(e.into_error()))?;
+        file.sync_all().map_err(create_err)?;
+        drop(file);
+        fs::rename(&self.tmp, &self.target).map_err(create_err)?;
+        Ok(())
+    }
+}
+
+impl Write for AtomicFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.as_mut().expect("AtomicFile already committed").write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.as_mut().expect("AtomicFile already committed").flush()
+    }
+}
+
+impl Drop for AtomicFile {
+    fn drop(&mut self) {
+        if self.inner.take().is_some() {
+            let _ = fs::remove_file(&self.tmp);
+        }
+    }
+}
+
+/// `<path>.tmp`, keeping the full original file name (`x.tsv` -> `x.tsv.tmp`).
+pub(crate) fn tmp_path_for(path: &Path) -> PathBuf {
+    let mut name = path.file_name().map(|n| n.to_os_string()).unwrap_or_default();
+    name.push(".tmp");
+    path.with_file_name(name)
+}
+
+/// Open `<path>.tmp` for writing, naming `path` on failure. Call `commit()` when done.
+pub(crate) fn create_output<P: AsRef<Path>>(path: P) -> Result<AtomicFile> {
+    let target = path.as_ref().to_path_buf();
+    let tmp = tmp_path_for(&target);
+    let file = File::create(&tmp).map_err(|source| GeoPrepError::Create { path: target.clone(), source })?;
+    Ok(AtomicFile { inner: Some(BufWriter::new(file)), tmp, target })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn output_only_appears_on_commit() {
+        let tmp = tempfile::tempdir().unwrap();
+        let target = tmp.path().join("table.tsv");
+
+        let mut w = create_output(&target).unwrap();
+        writeln!(w, "a\tb").unwrap();
+        assert!(!target.exists());
+        assert!(w.tmp_path().exists());
+        w.commit().unwrap();
+        assert_eq!(fs::read_to_string(&target).unwrap(), "a\tb\n");
+        assert!(!tmp_path_for(&target).exists());
+
+        // an abandoned writer leaves the previous file untouched
+        let mut w = create_output(&target).unwrap();
+        writeln!(w, "partial").unwrap();
+        drop(w);
+        assert_eq!(fs::read_to_string(&target).unwrap(), "a\tb\n");
+        assert!(!tmp_path_for(&target).exists());
+    }
+}
@@ -0,0 +1,224 @@
+// src/sample_files/amend.rs
+//! `--amend`: read back a sample table of an earlier run that was edited by hand and apply the
+//! edits (renamed samples, removed files) to the freshly scanned model.
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use crate::error::{GeoPrepError, Result};
+use crate::sample_files::{ParsedFile, ParsedKind, SampleFiles};
+use crate::table::read_table;
+
+/// Columns of the wide sample table that do not list files.
+const WIDE_INFO_COLUMNS: [&str; 6] =
+    ["Source_Path(s)", "Sample_Lane", "Layout", "Organism", "Library_Strategy", "Strategy_Confidence"];
+
+/// True if the wide table with this `header` has a column that would list `pf`. Processed
+/// matrices and tracks never appear there, other kinds only if their column was written
+/// (`--columns` can leave it out).
+fn wide_table_lists(header: &[String], pf: &ParsedFile) -> bool {
+    let has = |name: &str| header.iter().any(|h| h == name);
+    match &pf.kind {
+        ParsedKind::Fastq { role, .. } => has(role),
+        ParsedKind::TenX { bundle, .. } => has("TenX") || has(&format!("TenX_{}", bundle)),
+        ParsedKind::H5 => has("H5") || has(&format!("H5_{}", pf.stem())),
+        ParsedKind::Fragments => has("Fragments"),
+        ParsedKind::Alignment { .. } => has("Alignment"),
+        ParsedKind::PacBio { .. } => has("PacBio"),
+        ParsedKind::ProcessedMatrix { .. } | ParsedKind::Track { .. } => false,
+    }
+}
+
+/// What `SampleFiles::amend` changed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct AmendSummary {
+    /// files moved to another sample (or experiment)
+    pub moved: usize,
+    /// files no longer listed in the table and dropped from the model
+    pub removed: usize,
+    /// table entries that match no scanned file
+    pub unknown: usize,
+}
+
+impl SampleFiles {
+    /// Apply an edited sample table: the wide `<prefix>.tsv` (files are matched by their
+    /// GEO name, the row's `Sample_Lane` is their sample) or the long `<prefix>_long.tsv`
+    /// (matched by `source_path`, `experiment` and `sample` may change). TSV or CSV.
+    ///
+    /// Files missing from the table are removed, files listed under another sample move there;
+    /// every table written afterwards follows the edited model. Files the wide table has no
+    /// column for (processed matrices, tracks, hidden columns) are left as they are.
+    pub fn amend<P: AsRef<Path>>(&mut self, table: P) -> Result<AmendSummary> {
+        let table = table.as_ref();
+        let rows = read_table(table)?;
+        let Some((header, body)) = rows.split_first() else {
+            return Err(not_a_sample_table(table));
+        };
+        let col = |name: &str| header.iter().position(|h| h == name);
+
+        // source path -> (experiment, sample) as listed in the table
+        let mut wanted: HashMap<String, (String, String)> = HashMap::new();
+        // files the table can not list; untouched by the edit
+        let mut unlisted: HashSet<String> = HashSet::new();
+        let mut summary = AmendSummary::default();
+        if let (Some(path_col), Some(sample_col)) = (col("source_path"), col("sample")) {
+            let known: HashMap<&str, &str> =
+                self.iter_all_parsed_files().into_iter().map(|pf| (pf.path.as_str(), pf.experiment.as_str())).collect();
+            let experiment_col = col("experiment");
+            for row in body {
+                let cell = |i: usize| row.get(i).map(|c| c.trim()).unwrap_or("");
+                let path = cell(path_col);
+                let Some(experiment) = known.get(path) else {
+                    summary.unknown += 1;
+                    eprintln!("WARN: --amend: no scanned file {}", path);
+                    continue;
+                };
+                let experiment = experiment_col.map(cell).filter(|e| !e.is_empty()).unwrap_or(experiment);
+                wanted.insert(path.to_string(), (experiment.to_string(), cell(sample_col).to_string()));
+            }
+        } else if let Some(sample_col) = col("Sample_Lane") {
+            let attribute_keys = &self.options.sample_attributes.keys;
+            unlisted = self
+                .iter_all_parsed_files()
+                .into_iter()
+                .filter(|pf| !wide_table_lists(header, pf))
+                .map(|pf| pf.path.clone())
+                .collect();
+            let by_geo_name: HashMap<String, (String, String)> = self
+                .iter_all_parsed_files()
+                .into_iter()
+                .map(|pf| (pf.geo_filename(), (pf.path.clone(), pf.experiment.clone())))
+                .collect();
+            for row in body {
+                let sample = row.get(sample_col).map(|c| c.trim()).unwrap_or("");
+                let files = row
+                    .iter()
+                    .zip(header)
+                    .filter(|(_, h)| !WIDE_INFO_COLUMNS.contains(&h.as_str()) && !attribute_keys.contains(h))
+                    .flat_map(|(cell, _)| cell.split(','))
+                    .map(str::trim)
+                    .filter(|name| !name.is_empty());
+                for name in files {
+                    match by_geo_name.get(name) {
+                        Some((path, experiment)) => {
+                            wanted.insert(path.clone(), (experiment.clone(), sample.to_string()));
+                        }
+                        None => {
+                            summary.unknown += 1;
+                            eprintln!("WARN: --amend: no scanned file is published as {}", name);
+                        }
+                    }
+                }
+            }
+        } else {
+            return Err(not_a_sample_table(table));
+        }
+
+        let current: Vec<(String, String, String)> = self
+            .iter_all_parsed_files()
+            .into_iter()
+            .map(|pf| (pf.path.clone(), pf.experiment.clone(), pf.sample.clone()))
+            .collect();
+        for (path, experiment, sample) in current {
+            match wanted.get(&path) {
+                None if unlisted.contains(&path) => {}
+                Some((e, s)) if *e == experiment && *s == sample => {}
+                // an emptied sample name removes the file as well
+                Some((e, s)) if !s.is_empty() => {
+                    if let Some(mut pf) = self.remove_from_model(&path) {
+                        pf.experiment = e.clone();
+                        pf.sample = s.clone();
+                        self.add_file(pf);
+                        summary.moved += 1;
+                    }
+                }
+                _ => {
+                    self.remove_from_model(&path);
+                    summary.removed += 1;
+                }
+            }
+        }
+        self.assign_upload_names();
+        Ok(summary)
+    }
+}
+
+fn not_a_sample_table(path: &Path) -> GeoPrepError {
+    GeoPrepError::InvalidInput {
+        path: path.to_path_buf(),
+        line: 1,
+        message: "not a sample table: needs a Sample_Lane column or sample and source_path columns".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sample_files::{ParsedFile, ParsedKind, SampleKey};
+    use crate::table::TableStyle;
+
+    fn data() -> SampleFiles {
+        let mut data = SampleFiles::new();
+        for (sample, role) in [("sampleA", "R1"), ("sampleA", "R2"), ("sampleB", "R1"), ("sampleB", "R2")] {
+            data.add_file(ParsedFile {
+                md5sum: Some(format!("md5_{sample}_{role}")),
+                ..ParsedFile::test_file(
+                    "exp1",
+                    sample,
+                    ParsedKind::Fastq { lane: "L001".to_string(), role: role.to_string() },
+                    &format!("/data/exp1/{sample}_L001_{role}_001.fastq.gz"),
+                )
+            });
+        }
+        data
+    }
+
+    fn key(sample: &str) -> SampleKey {
+        SampleKey { experiment: "exp1".to_string(), sample: sample.to_string() }
+    }
+
+    #[test]
+    fn edited_tables_rename_samples_and_drop_files() {
+        let tmp = tempfile::tempdir().unwrap();
+
+        // wide table: sampleB renamed to treated, its R2 cell cleared
+        let wide = tmp.path().join("samples.tsv");
+        let mut data = data();
+        // the wide table has no column for processed matrices: an amend must keep them
+        data.add_file(ParsedFile {
+            md5sum: Some("md5_h5ad".to_string()),
+            ..ParsedFile::test_file(
+                "exp1",
+                "sampleA",
+                ParsedKind::ProcessedMatrix { format: "h5ad".to_string() },
+                "/data/exp1/sampleA/sampleA.h5ad",
+            )
+        });
+        data.write_sample_files_as(&wide, TableStyle::default()).unwrap();
+        let edited = std::fs::read_to_string(&wide)
+            .unwrap()
+            .replace("\tsampleB\t", "\ttreated\t")
+            .replace("exp1_sampleB_L001_R2_001.fastq.gz", "");
+        std::fs::write(&wide, edited).unwrap();
+        let summary = data.amend(&wide).unwrap();
+        assert_eq!(summary, AmendSummary { moved: 1, removed: 1, unknown: 0 });
+        assert!(data.sample(&key("sampleB")).is_none());
+        assert_eq!(data.files_for_sample(&key("treated")).len(), 1);
+        assert_eq!(data.files_for_sample(&key("sampleA")).len(), 3);
+        assert!(data.files_for_sample(&key("sampleA")).iter().any(|pf| pf.path.ends_with("sampleA.h5ad")));
+
+        // long table: sampleA renamed to control, the row of sampleB R1 is deleted
+        let long = tmp.path().join("long.tsv");
+        let mut data = self::data();
+        data.write_sample_files_long(&long).unwrap();
+        let edited: Vec<String> = std::fs::read_to_string(&long)
+            .unwrap()
+            .lines()
+            .filter(|line| !line.contains("sampleB_L001_R1"))
+            .map(|line| line.replace("\tsampleA\t", "\tcontrol\t"))
+            .collect();
+        std::fs::write(&long, edited.join("\n")).unwrap();
+        assert_eq!(data.amend(&long).unwrap(), AmendSummary { moved: 2, removed: 1, unknown: 0 });
+        assert_eq!(data.files_for_sample(&key("control")).len(), 2);
+        assert_eq!(data.files_for_sample(&key("sampleB")).len(), 1);
+    }
+}
@@ -0,0 +1,190 @@
+// src/sample_files/anonymize.rs
+//! `--anonymize`: replace the detected sample names by `Sample_001`, `Sample_002`, ... in every
+//! output and upload name. The mapping goes to a private key file that must not be uploaded.
+//! The public tables and reports leave out the source paths and replace the old names in
+//! their messages; the local files (rename plan, collection script, state) keep the paths.
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+
+use crate::error::Result;
+use crate::output::create_output;
+use crate::sample_files::{ParsedFile, ParsedKind, SampleFiles};
+
+/// Upload name of `pf` (still carrying its original `sample`) under the anonymous `id`.
+/// FASTQ and PacBio names are rebuilt from the id, lane, role and movie; all other kinds
+/// keep their short name with the leading sample replaced.
+fn anonymous_name(pf: &ParsedFile, id: &str) -> String {
+    let base = pf.basename();
+    match &pf.kind {
+        ParsedKind::Fastq { lane, role } => {
+            let ext = base.find(".fastq").or_else(|| base.find(".fq")).or_else(|| base.find('.'));
+            format!("{}_{}_{}{}", id, lane, role, ext.map(|i| &base[i..]).unwrap_or_default())
+        }
+        ParsedKind::PacBio { movie, format } if *movie == pf.sample => format!("{}.{}", id, format),
+        ParsedKind::PacBio { movie, format } => format!("{}_{}.{}", id, movie, format),
+        _ => {
+            let short = pf.short_geo_filename();
+            match short.strip_prefix(pf.sample.as_str()) {
+                Some(rest) => format!("{}{}", id, rest),
+                None => format!("{}_{}", id, short),
+            }
+        }
+    }
+}
+
+impl SampleFiles {
+    /// Rename every sample to `Sample_<n>` (numbered in experiment, sample order, so reruns on
+    /// the same data give the same ids) and build the GEO file names from the new id.
+    /// An upload name that still contains an original sample name is recorded as an issue.
+    /// The mapping is kept for `write_anonymization_key`; returns the number of samples.
+    pub fn anonymize(&mut self) -> usize {
+        let keys: Vec<_> = self.samples.keys().cloned().collect();
+        let width = keys.len().to_string().len().max(3);
+        for (i, key) in keys.iter().enumerate() {
+            let id = format!("Sample_{:0width$}", i + 1);
+            let Some(mut rec) = self.samples.remove(key) else { continue };
+            let paths: Vec<String> = rec.all_paths().map(|pf| pf.path.clone()).collect();
+            for path in paths {
+                let Some(mut pf) = rec.remove_path(&path) else { continue };
+                pf.geo_name = Some(anonymous_name(&pf, &id));
+                pf.sample = id.clone();
+                self.add_file(pf);
+            }
+            self.anonymized.push((id, key.experiment.clone(), key.sample.clone()));
+        }
+
+        let mut leaks = Vec::new();
+        for pf in self.iter_all_parsed_files() {
+            let name = pf.geo_filename();
+            // the FASTQ names are built from the id, lane and role only
+            let checked = name.strip_prefix(pf.sample.as_str()).unwrap_or(&name);
+            if !matches!(pf.kind, ParsedKind::Fastq { .. })
+                && self.anonymized.iter().any(|(_, _, sample)| checked.contains(sample.as_str()))
+            {
+                leaks.push(format!(
+                    "--anonymize: the upload name of a {} file still contains an original sample name; \
+                     rename it (see the anonymization key)",
+                    pf.sample
+                ));
+            }
+        }
+        for leak in leaks {
+            eprintln!("ERROR: {}", leak);
+            self.issues.push(leak);
+        }
+        keys.len()
+    }
+
+    /// `text` for a public table or report: after `anonymize` every original sample name is
+    /// replaced by its id (longest names first), a name used in several experiments by
+    /// `Sample_anonymized`.
+    pub(crate) fn public_text(&self, text: &str) -> String {
+        if self.anonymized.is_empty() {
+            return text.to_string();
+        }
+        let mut ids: BTreeMap<&str, &str> = BTreeMap::new();
+        for (id, _, sample) in &self.anonymized {
+            ids.entry(sample.as_str())
+                .and_modify(|shared| *shared = "Sample_anonymized")
+                .or_insert(id.as_str());
+        }
+        let mut names: Vec<(&str, &str)> = ids.into_iter().collect();
+        names.sort_by_key(|(sample, _)| std::cmp::Reverse(sample.len()));
+        names.into_iter().fold(text.to_string(), |text, (sample, id)| text.replace(sample, id))
+    }
+
+    /// A source path or folder for a public table or report; empty after `anonymize`.
+    pub(crate) fn public_path(&self, path: &str) -> String {
+        if self.anonymized.is_empty() {
+            path.to_string()
+        } else {
+            String::new()
+        }
+    }
+
+    /// The private key of `anonymize`: which sample each anonymous id stands for. Keep this
+    /// file out of the upload.
+    ///
+    /// Output columns:
+    /// Anonymous_ID, Experiment, Sample
+    pub fn write_anonymization_key<P: AsRef<Path>>(&self, out_path: P) -> Result<usize> {
+        let mut w = create_output(out_path)?;
+        writeln!(w, "Anonymous_ID\tExperiment\tSample")?;
+        for (id, experiment, sample) in &self.anonymized {
+            writeln!(w, "{}\t{}\t{}", id, experiment, sample)?;
+        }
+        w.commit()?;
+        Ok(self.anonymized.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sample_files::{ParsedFile, ParsedKind};
+
+    #[test]
+    fn sample_ids_leave_names_and_tables() {
+        let mut data = SampleFiles::new();
+        for (sample, path, kind) in [
+            ("P0815", "exp1/P0815_S1_L001_R1_001.fastq.gz", ParsedKind::Fastq { lane: "L001".into(), role: "R1".into() }),
+            ("P0815", "exp1/P0815/outs/filtered_feature_bc_matrix.h5", ParsedKind::H5),
+            ("P0042", "exp1/P0042_S2_L001_R1_001.fastq.gz", ParsedKind::Fastq { lane: "L001".into(), role: "R1".into() }),
+            // the id glued to other characters is no word, but must not survive either
+            ("P0042", "exp1/P0042S2_L001_R2_001.fastq.gz", ParsedKind::Fastq { lane: "L001".into(), role: "R2".into() }),
+        ] {
+            let md5sum = Some(format!("{:x}", md5::compute(path)));
+            data.add_file(ParsedFile { md5sum, ..ParsedFile::test_file("exp1", sample, kind, path) });
+        }
+        assert_eq!(data.anonymize(), 2);
+        assert!(data.issues.is_empty());
+
+        let mut names: Vec<String> = data.iter_all_parsed_files().iter().map(|pf| pf.geo_filename()).collect();
+        names.sort();
+        assert_eq!(
+            names,
+            [
+                "Sample_001_L001_R1.fastq.gz",
+                "Sample_001_L001_R2.fastq.gz",
+                "Sample_002_L001_R1.fastq.gz",
+                "Sample_002_filtered_feature_bc_matrix.h5",
+            ]
+        );
+        assert_eq!(data.public_text("R2 missing for exp1/P0042/P0042S2_R1.fq.gz"), "R2 missing for exp1/Sample_001/Sample_001S2_R1.fq.gz");
+        assert_eq!(data.public_path("exp1/P0042"), "");
+
+        // the public tables carry no original name
+        let tmp = tempfile::tempdir().unwrap();
+        let long = tmp.path().join("long.tsv");
+        data.write_sample_files_long(&long).unwrap();
+        let wide = tmp.path().join("wide.tsv");
+        data.write_sample_files_basename(&wide).unwrap();
+        for table in [long, wide] {
+            let text = std::fs::read_to_string(table).unwrap();
+            assert!(!text.contains("P0042") && !text.contains("P0815"), "{text}");
+        }
+
+        let key = tmp.path().join("key.tsv");
+        data.write_anonymization_key(&key).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&key).unwrap(),
+            "Anonymous_ID\tExperiment\tSample\nSample_001\texp1\tP0042\nSample_002\texp1\tP0815\n"
+        );
+    }
+
+    #[test]
+    fn names_that_keep_a_sample_are_reported() {
+        let mut data = SampleFiles::new();
+        data.add_file(ParsedFile::test_file(
+            "exp1",
+            "P0042",
+            ParsedKind::Fragments,
+            "exp1/P0042/outs/patientP0042_fragments.tsv.gz",
+        ));
+        data.anonymize();
+        assert_eq!(data.iter_all_parsed_files()[0].geo_filename(), "Sample_001_patientP0042_fragments.tsv.gz");
+        assert_eq!(data.issues.len(), 1);
+        assert!(!data.issues[0].contains("P0042"));
+    }
+}
@@ -0,0 +1,320 @@
+// src/sample_files/archive.rs
+//! The one zip implementation (pure Rust `zip` crate, no `zip` binary needed) and its
+//! `.tar.gz` counterpart (`tar` + `flate2`).
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use flate2::{Compression, GzBuilder};
+use tar::HeaderMode;
+use walkdir::WalkDir;
+
+use crate::interrupt::{self, Checked};
+use crate::sample_files::checksum::md5_file;
+use crate::sample_files::containers::Container;
+use crate::sample_files::BundleFormat;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, DateTime};
+
+/// Zip the content of `dir` (paths relative to `dir`, forward slashes) into `zip_path`.
+/// Entries are added in file name order and the archive is written to `<zip_path>.tmp`
+/// first and renamed on success, so an interrupted run never leaves a partial zip.
+/// Timestamps and permissions are fixed, so the same input gives a byte-identical zip
+/// (and md5) on every platform and rerun.
+/// Members that are gzipped already (`.gz`) are stored; all others are deflated with `level`
+/// (None = Deflate default, 0 = store).
+pub fn zip_dir(dir: &Path, zip_path: &Path, level: Option<u32>) -> io::Result<()> {
+    // write to tmp then rename (avoid partial zips on crash)
+    let tmp_path = zip_path.with_extension("zip.tmp");
+
+    // ensure parent exists
+    if let Some(par) = zip_path.parent() {
+        fs::create_dir_all(par)?;
+    }
+
+    if let Err(e) = write_zip(dir, &tmp_path, level) {
+        // failed or interrupted: do not leave the partial archive behind
+        let _ = fs::remove_file(&tmp_path);
+        return Err(e);
+    }
+
+    // replace old zip if present
+    let _ = fs::remove_file(zip_path);
+    fs::rename(&tmp_path, zip_path)?;
+    Ok(())
+}
+
+fn write_zip(dir: &Path, tmp_path: &Path, level: Option<u32>) -> io::Result<()> {
+    let stored: FileOptions<()> = FileOptions::default()
+        .compression_method(CompressionMethod::Stored)
+        .last_modified_time(DateTime::default())
+        .unix_permissions(0o644);
+    let deflated = match level {
+        Some(0) => stored,
+        _ => stored
+            .compression_method(CompressionMethod::Deflated)
+            .compression_level(level.map(|l| l as i64)),
+    };
+
+    let mut zw = zip::ZipWriter::new(File::create(tmp_path)?);
+
+    for entry in WalkDir::new(dir)
+        .follow_links(false)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|e| e.ok())
+    {
+        let p = entry.path();
+
+        // skip the dir itself
+        if p == dir {
+            continue;
+        }
+
+        let rel = p.strip_prefix(dir).unwrap_or(p);
+        let rel_str = rel.to_string_lossy().replace('\\', "/"); // zip wants forward slashes
+        if entry.file_type().is_dir() {
+            // add directory entry (optional but fine)
+            zw.add_directory(rel_str, stored)?;
+        } else if entry.file_type().is_file() {
+            // re-deflating gzip data costs hours for ~0% gain
+            let opts = if rel_str.ends_with(".gz") { stored } else { deflated };
+            zw.start_file(rel_str, opts)?;
+            io::copy(&mut Checked(File::open(p)?), &mut zw)?;
+        }
+    }
+
+    zw.finish()?; // flush/close
+    Ok(())
+}
+
+/// Package `dir` as `out_path` in the given format (see `zip_dir` and `tar_gz_dir`) and list
+/// the members in `<out_path>.contents.tsv` (see `write_contents`).
+pub fn bundle_dir(dir: &Path, out_path: &Path, format: BundleFormat, level: Option<u32>) -> io::Result<()> {
+    match format {
+        BundleFormat::Zip => zip_dir(dir, out_path, level)?,
+        BundleFormat::TarGz => tar_gz_dir(dir, out_path, level)?,
+    }
+    write_contents(dir, out_path)
+}
+
+/// `<bundle>.contents.tsv`: the member listing uploaded next to a bundle.
+pub fn contents_path(bundle: &Path) -> PathBuf {
+    let mut name = bundle.file_name().map(|n| n.to_os_string()).unwrap_or_default();
+    name.push(".contents.tsv");
+    bundle.with_file_name(name)
+}
+
+/// List the files packaged from `dir` (archive order) with size and md5 in
+/// `contents_path(bundle)`, so reviewers can see what a bundle holds without downloading it.
+///
+/// Output columns:
+/// path, size, md5
+pub fn write_contents(dir: &Path, bundle: &Path) -> io::Result<()> {
+    let mut text = String::from("path\tsize\tmd5\n");
+    for entry in WalkDir::new(dir).follow_links(false).sort_by_file_name().into_iter().filter_map(|e| e.ok()) {
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let p = entry.path();
+        let rel = p.strip_prefix(dir).unwrap_or(p).to_string_lossy().replace('\\', "/");
+        text.push_str(&format!("{}\t{}\t{}\n", rel, entry.metadata()?.len(), md5_file(p)?));
+    }
+    let out = contents_path(bundle);
+    let tmp = crate::output::tmp_path_for(&out);
+    fs::write(&tmp, text)?;
+    fs::rename(&tmp, &out)
+}
+
+/// Gzip the decompressed content of `src` into `out_path` (`<out_path>.tmp` + rename) with a
+/// fixed header mtime, so the same FASTQ always gives the same md5.
+pub fn gzip_file(src: &Path, container: Container, out_path: &Path) -> io::Result<()> {
+    let tmp_path = crate::output::tmp_path_for(out_path);
+    if let Some(par) = out_path.parent() {
+        fs::create_dir_all(par)?;
+    }
+    let written = (|| {
+        let mut gz = GzBuilder::new().mtime(0).write(File::create(&tmp_path)?, Compression::default());
+        io::copy(&mut Checked(container.open(src)?), &mut gz)?;
+        gz.finish().map(|_| ())
+    })();
+    if let Err(e) = written {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(e);
+    }
+    fs::rename(&tmp_path, out_path)
+}
+
+/// Package several `(dir, out_path)` jobs on at most `workers` threads, printing one progress
+/// line per finished bundle. Failures are reported and skipped; returns the number written.
+pub fn bundle_dirs(jobs: &[(PathBuf, PathBuf)], format: BundleFormat, level: Option<u32>, workers: usize) -> usize {
+    let next = AtomicUsize::new(0);
+    let done = Mutex::new(0usize);
+    let written = AtomicUsize::new(0);
+    std::thread::scope(|scope| {
+        for _ in 0..workers.clamp(1, jobs.len().max(1)) {
+            scope.spawn(|| loop {
+                if interrupt::interrupted() {
+                    break;
+                }
+                let i = next.fetch_add(1, Ordering::SeqCst);
+                let Some((dir, out)) = jobs.get(i) else { break };
+                let result = bundle_dir(dir, out, format, level);
+                let mut done = done.lock().unwrap_or_else(|e| e.into_inner());
+                *done += 1;
+                match result {
+                    Ok(()) => {
+                        written.fetch_add(1, Ordering::SeqCst);
+                        eprintln!("[{}/{}] packaged {}", *done, jobs.len(), out.display());
+                    }
+                    Err(e) => eprintln!("[{}/{}] WARN: could not package {}: {}", *done, jobs.len(), dir.display(), e),
+                }
+            });
+        }
+    });
+    written.into_inner()
+}
+
+/// `.tar.gz` version of `zip_dir`: same entry order, `<out_path>.tmp` + rename, and
+/// deterministic headers (fixed mtime, owner and permissions) for a stable md5.
+/// `level` is the gzip level (None = 0, the members are gzipped already).
+pub fn tar_gz_dir(dir: &Path, tar_path: &Path, level: Option<u32>) -> io::Result<()> {
+    let tmp_path = tar_path.with_file_name(format!(
+        "{}.tmp",
+        tar_path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default()
+    ));
+    if let Some(par) = tar_path.parent() {
+        fs::create_dir_all(par)?;
+    }
+
+    if let Err(e) = write_tar_gz(dir, &tmp_path, level) {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(e);
+    }
+
+    let _ = fs::remove_file(tar_path);
+    fs::rename(&tmp_path, tar_path)?;
+    Ok(())
+}
+
+fn write_tar_gz(dir: &Path, tmp_path: &Path, level: Option<u32>) -> io::Result<()> {
+    let gz = GzBuilder::new()
+        .mtime(0)
+        .write(File::create(tmp_path)?, Compression::new(level.unwrap_or(0).min(9)));
+    let mut tw = tar::Builder::new(gz);
+    tw.mode(HeaderMode::Deterministic);
+    tw.follow_symlinks(false);
+
+    for entry in WalkDir::new(dir)
+        .follow_links(false)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|e| e.ok())
+    {
+        let p = entry.path();
+        if p == dir {
+            continue;
+        }
+        let rel = p.strip_prefix(dir).unwrap_or(p);
+        if entry.file_type().is_dir() || entry.file_type().is_file() {
+            interrupt::check()?;
+            tw.append_path_with_name(p, rel)?;
+        }
+    }
+
+    tw.into_inner()?.finish()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zips_relative_entries_in_name_order() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join("filtered_feature_bc_matrix");
+        fs::create_dir_all(&dir).unwrap();
+        for name in ["matrix.mtx.gz", "barcodes.tsv.gz", "features.tsv.gz", "README.txt"] {
+            fs::write(dir.join(name), name).unwrap();
+        }
+        let zip_path = tmp.path().join("sampleA_filtered.zip");
+        zip_dir(&dir, &zip_path, None).unwrap();
+
+        assert!(!zip_path.with_extension("zip.tmp").exists());
+        let mut archive = zip::ZipArchive::new(File::open(&zip_path).unwrap()).unwrap();
+        assert_eq!(archive.by_name("matrix.mtx.gz").unwrap().compression(), CompressionMethod::Stored);
+        assert_eq!(archive.by_name("README.txt").unwrap().compression(), CompressionMethod::Deflated);
+        let names: Vec<&str> = archive.file_names().collect();
+        let mut sorted = names.clone();
+        sorted.sort();
+        assert_eq!(names.len(), 4);
+        assert_eq!(sorted, vec!["README.txt", "barcodes.tsv.gz", "features.tsv.gz", "matrix.mtx.gz"]);
+
+        // same content later on -> same bytes
+        let again = tmp.path().join("again.zip");
+        zip_dir(&dir, &again, None).unwrap();
+        assert_eq!(fs::read(&zip_path).unwrap(), fs::read(&again).unwrap());
+    }
+
+    #[test]
+    fn bundles_come_with_a_contents_listing() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join("filtered_feature_bc_matrix");
+        fs::create_dir_all(&dir).unwrap();
+        for name in ["matrix.mtx.gz", "barcodes.tsv.gz", "features.tsv.gz"] {
+            fs::write(dir.join(name), name).unwrap();
+        }
+        let zip_path = tmp.path().join("sampleA_filtered.zip");
+        bundle_dir(&dir, &zip_path, BundleFormat::Zip, None).unwrap();
+        let listing = fs::read_to_string(contents_path(&zip_path)).unwrap();
+        let lines: Vec<&str> = listing.lines().collect();
+        assert_eq!(contents_path(&zip_path), tmp.path().join("sampleA_filtered.zip.contents.tsv"));
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], "path\tsize\tmd5");
+        // md5 of the 15 bytes "barcodes.tsv.gz"
+        assert_eq!(lines[1], format!("barcodes.tsv.gz\t15\t{:x}", md5::compute("barcodes.tsv.gz")));
+    }
+
+    #[test]
+    fn tar_gz_bundles_are_reproducible() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join("filtered_feature_bc_matrix");
+        fs::create_dir_all(&dir).unwrap();
+        for name in ["matrix.mtx.gz", "barcodes.tsv.gz", "features.tsv.gz"] {
+            fs::write(dir.join(name), name).unwrap();
+        }
+        let tar_path = tmp.path().join("sampleA.tar.gz");
+        bundle_dir(&dir, &tar_path, BundleFormat::TarGz, Some(6)).unwrap();
+        assert!(!tmp.path().join("sampleA.tar.gz.tmp").exists());
+
+        let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(File::open(&tar_path).unwrap()));
+        let names: Vec<String> = archive
+            .entries()
+            .unwrap()
+            .map(|e| e.unwrap().path().unwrap().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(names, vec!["barcodes.tsv.gz", "features.tsv.gz", "matrix.mtx.gz"]);
+
+        let again = tmp.path().join("again.tar.gz");
+        tar_gz_dir(&dir, &again, Some(6)).unwrap();
+        assert_eq!(fs::read(&tar_path).unwrap(), fs::read(&again).unwrap());
+    }
+
+    #[test]
+    fn bundle_jobs_run_on_a_worker_pool() {
+        let tmp = tempfile::tempdir().unwrap();
+        let jobs: Vec<(PathBuf, PathBuf)> = (0..5)
+            .map(|i| {
+                let dir = tmp.path().join(format!("s{i}/filtered_feature_bc_matrix"));
+                fs::create_dir_all(&dir).unwrap();
+                fs::write(dir.join("matrix.mtx.gz"), format!("m{i}")).unwrap();
+                (dir, tmp.path().join(format!("s{i}.zip")))
+            })
+            .collect();
+        assert_eq!(bundle_dirs(&jobs, BundleFormat::Zip, Some(9), 2), 5);
+        assert!(jobs.iter().all(|(_, out)| out.is_file()));
+    }
+}
@@ -0,0 +1,122 @@
+// src/sample_files/attributes.rs
+//! `--attributes attributes.tsv`: free-form per sample columns (treatment, timepoint,
+//! replicate, ...) merged into the sample table and the GEO metadata.
+//!
+//! ```text
+//! sample    treatment  timepoint  replicate
+//! sampleA   DMSO       0h         1
+//! sampleB   drug       24h        1
+//! ```
+//!
+//! TSV or CSV with a `sample` column; an optional `experiment` column restricts a row to one
+//! experiment (rows with an experiment win over rows without). Every other column is an
+//! attribute.
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::error::{GeoPrepError, Result};
+use crate::sample_files::SampleFiles;
+use crate::table::read_table;
+
+/// Attribute columns per sample; empty = no attribute columns.
+#[derive(Debug, Clone, Default)]
+pub struct SampleAttributes {
+    /// attribute names in file order
+    pub keys: Vec<String>,
+    /// (experiment or "", sample) -> values in `keys` order
+    values: HashMap<(String, String), Vec<String>>,
+}
+
+impl SampleAttributes {
+    /// Read an attribute table (see the module docs).
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let invalid = |line: usize, message: String| GeoPrepError::InvalidInput { path: path.to_path_buf(), line, message };
+        let rows = read_table(path)?;
+        let Some((header, body)) = rows.split_first() else {
+            return Err(invalid(1, "empty attribute table".to_string()));
+        };
+        let header: Vec<&str> = header.iter().map(|h| h.trim()).collect();
+        let col = |name: &str| header.iter().position(|h| h.eq_ignore_ascii_case(name));
+        let sample_col = col("sample").ok_or_else(|| invalid(1, "attribute table needs a sample column".to_string()))?;
+        let experiment_col = col("experiment");
+        let attribute_cols: Vec<usize> =
+            (0..header.len()).filter(|&i| i != sample_col && Some(i) != experiment_col).collect();
+
+        let mut values = HashMap::new();
+        for (i, row) in body.iter().enumerate() {
+            let cell = |c: usize| row.get(c).map(|v| v.trim().to_string()).unwrap_or_default();
+            let sample = cell(sample_col);
+            if sample.is_empty() {
+                continue;
+            }
+            let key = (experiment_col.map(cell).unwrap_or_default(), sample);
+            if values.insert(key.clone(), attribute_cols.iter().map(|&c| cell(c)).collect()).is_some() {
+                return Err(invalid(i + 2, format!("sample '{}' is listed twice", key.1)));
+            }
+        }
+        Ok(SampleAttributes { keys: attribute_cols.iter().map(|&c| header[c].to_string()).collect(), values })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Number of sample rows.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Attribute values of `sample` in `experiment`, in `keys` order.
+    pub fn get(&self, experiment: &str, sample: &str) -> Option<&[String]> {
+        self.values
+            .get(&(experiment.to_string(), sample.to_string()))
+            .or_else(|| self.values.get(&(String::new(), sample.to_string())))
+            .map(Vec::as_slice)
+    }
+}
+
+impl SampleFiles {
+    /// Rows of the `--attributes` table that match no scanned sample (typos, dropped samples).
+    pub fn unused_attribute_rows(&self) -> Vec<String> {
+        let mut out: Vec<String> = self
+            .options
+            .sample_attributes
+            .values
+            .keys()
+            .filter(|(experiment, sample)| {
+                !self.samples.keys().any(|k| k.sample == *sample && (experiment.is_empty() || k.experiment == *experiment))
+            })
+            .map(|(experiment, sample)| if experiment.is_empty() { sample.clone() } else { format!("{}/{}", experiment, sample) })
+            .collect();
+        out.sort();
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn experiment_rows_win_over_sample_rows() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("attributes.csv");
+        std::fs::write(
+            &path,
+            "Sample,treatment,experiment,timepoint\nsampleA,DMSO,,0h\nsampleA,drug,exp2,24h\nsampleB,drug\n",
+        )
+        .unwrap();
+        let attributes = SampleAttributes::load(&path).unwrap();
+        assert_eq!(attributes.keys, ["treatment", "timepoint"]);
+        assert_eq!(attributes.get("exp1", "sampleA").unwrap(), ["DMSO", "0h"]);
+        assert_eq!(attributes.get("exp2", "sampleA").unwrap(), ["drug", "24h"]);
+        assert_eq!(attributes.get("exp1", "sampleB").unwrap(), ["drug", ""]);
+        assert!(attributes.get("exp1", "sampleC").is_none());
+
+        std::fs::write(&path, "sample,treatment\nsampleA,DMSO\nsampleA,drug\n").unwrap();
+        assert!(matches!(SampleAttributes::load(&path), Err(GeoPrepError::InvalidInput { line: 3, .. })));
+        std::fs::write(&path, "name,treatment\nsampleA,DMSO\n").unwrap();
+        assert!(SampleAttributes::load(&path).is_err());
+    }
+}
@@ -0,0 +1,337 @@
+// src/sample_files/builder.rs
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use crate::sample_files::{
+    BundleFormat, DedupPolicy, DuplicateRole, Md5Store, RecompressTo, SampleFiles, SampleRules, SampleSheets, ScanOptions, SymlinkPolicy, TenxMode,
+};
+
+/// Configures a `SampleFiles` before scanning:
+///
+/// ```
+/// use rust_geo_prep::sample_files::{SampleFiles, SymlinkPolicy};
+///
+/// let data = SampleFiles::builder()
+///     .suffixes([".fastq.gz", ".fq.gz"])
+///     .exclude("geo_downloaded_data")
+///     .role_tokens(["R3"])
+///     .follow_symlinks(SymlinkPolicy::FilesOnly)
+///     .build();
+/// assert_eq!(data.options().extra_roles, vec!["R3".to_string()]);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SampleFilesBuilder {
+    options: ScanOptions,
+}
+
+impl SampleFilesBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// File suffixes treated as target files (empty = every file is considered).
+    pub fn suffixes<I, S>(mut self, suffixes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.options.suffixes = suffixes.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Ignore every path containing `name`; can be called repeatedly.
+    pub fn exclude<S: Into<String>>(mut self, name: S) -> Self {
+        self.options.excludes.push(name.into());
+        self
+    }
+
+    /// Read roles recognised in addition to R1/R2/I1/I2 (e.g. R3, UMI).
+    pub fn role_tokens<I, S>(mut self, roles: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.options.extra_roles = roles.into_iter().map(|r| r.into().to_ascii_uppercase()).collect();
+        self
+    }
+
+    pub fn follow_symlinks(mut self, policy: SymlinkPolicy) -> Self {
+        self.options.follow_symlinks = policy;
+        self
+    }
+
+    pub fn max_depth(mut self, depth: Option<usize>) -> Self {
+        self.options.max_depth = depth;
+        self
+    }
+
+    pub fn min_depth(mut self, depth: Option<usize>) -> Self {
+        self.options.min_depth = depth;
+        self
+    }
+
+    /// Skip files smaller than `bytes`.
+    pub fn min_size(mut self, bytes: Option<u64>) -> Self {
+        self.options.min_size = bytes;
+        self
+    }
+
+    /// Skip files larger than `bytes`.
+    pub fn max_size(mut self, bytes: Option<u64>) -> Self {
+        self.options.max_size = bytes;
+        self
+    }
+
+    /// Skip files last modified before `time`.
+    pub fn newer_than(mut self, time: Option<SystemTime>) -> Self {
+        self.options.newer_than = time;
+        self
+    }
+
+    /// Only parse and hash samples matching one of these globs (`*`, `?`).
+    pub fn only_samples<I, S>(mut self, globs: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.options.only_samples = globs.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Never parse or hash samples matching one of these globs.
+    pub fn skip_samples<I, S>(mut self, globs: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.options.skip_samples = globs.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Use this experiment label for every file (the scan root is the experiment folder).
+    pub fn experiment<S: Into<String>>(mut self, name: Option<S>) -> Self {
+        self.options.experiment = name.map(Into::into);
+        self
+    }
+
+    /// Take the experiment from the folder `depth` levels below the scan root instead of the first.
+    pub fn experiment_depth(mut self, depth: Option<usize>) -> Self {
+        self.options.experiment_depth = depth;
+        self
+    }
+
+    /// Take the experiment from the folder above the nearest ancestor named `marker` (e.g. `fastq`).
+    pub fn experiment_marker<S: Into<String>>(mut self, marker: Option<S>) -> Self {
+        self.options.experiment_marker = marker.map(Into::into);
+        self
+    }
+
+    pub fn include_bam(mut self, yes: bool) -> Self {
+        self.options.include_bam = yes;
+        self
+    }
+
+    pub fn stats(mut self, yes: bool) -> Self {
+        self.options.stats = yes;
+        self
+    }
+
+    pub fn read_headers(mut self, yes: bool) -> Self {
+        self.options.read_headers = yes;
+        self
+    }
+
+    /// Smart-seq2 plate naming: keep the well in the FASTQ sample name
+    pub fn plate_wells(mut self, yes: bool) -> Self {
+        self.options.plate_wells = yes;
+        self
+    }
+
+    /// take FASTQ sample names from the `SampleSheet.csv` of their run folder
+    pub fn sample_sheets(mut self, yes: bool) -> Self {
+        self.options.sample_sheets = yes.then(SampleSheets::default);
+        self
+    }
+
+    /// read counts from `Stats.json` / `Demultiplex_Stats.csv` of demultiplexed runs
+    pub fn demux_stats(mut self, yes: bool) -> Self {
+        self.options.demux_stats = yes;
+        self
+    }
+
+    /// hash on this many threads after the walk, largest files first (0/1 = serial)
+    pub fn hash_jobs(mut self, workers: usize) -> Self {
+        self.options.hash_jobs = workers;
+        self
+    }
+
+    /// list folders on this many threads (0/1 = serial walk)
+    pub fn walk_threads(mut self, threads: usize) -> Self {
+        self.options.walk_threads = threads;
+        self
+    }
+
+    /// keep at most `keys` visited-path keys in memory and spill the rest to temporary files
+    pub fn spill_index(mut self, keys: Option<usize>) -> Self {
+        self.options.spill_index = keys;
+        self
+    }
+
+    /// bytes per read call when hashing and decompressing (default 1 MiB)
+    pub fn read_buffer(mut self, bytes: usize) -> Self {
+        self.options.hash_io.buffer = bytes;
+        self
+    }
+
+    /// memory-map files of at least this size for hashing (None = always read)
+    pub fn mmap_min(mut self, bytes: Option<u64>) -> Self {
+        self.options.hash_io.mmap_min = bytes;
+        self
+    }
+
+    /// never compute an md5, only read cached ones; the rest is shown as PENDING
+    pub fn reuse_md5_only(mut self, yes: bool) -> Self {
+        self.options.hash_io.reuse_only = yes;
+        self
+    }
+
+    /// hash only the first `bytes` of files without a cached md5, for the duplicate detector
+    pub fn preview_hash(mut self, bytes: Option<u64>) -> Self {
+        self.options.hash_io.preview = bytes;
+        self
+    }
+
+    pub fn audit_unclassified(mut self, yes: bool) -> Self {
+        self.options.audit_unclassified = yes;
+        self
+    }
+
+    pub fn validate_gzip(mut self, yes: bool) -> Self {
+        self.options.validate_gzip = yes;
+        self
+    }
+
+    pub fn tenx_mode(mut self, mode: TenxMode) -> Self {
+        self.options.tenx_mode = mode;
+        self
+    }
+
+    pub fn bundle_format(mut self, format: BundleFormat) -> Self {
+        self.options.bundle_format = format;
+        self
+    }
+
+    /// Write the 10x archives below this staging folder instead of next to the data.
+    pub fn bundle_dir<P: Into<PathBuf>>(mut self, dir: Option<P>) -> Self {
+        self.options.bundle_dir = dir.map(Into::into);
+        self
+    }
+
+    /// Compression level 0-9 of uncompressed 10x archive members (`.gz` members are stored).
+    pub fn zip_level(mut self, level: Option<u32>) -> Self {
+        self.options.zip_level = level;
+        self
+    }
+
+    /// Worker threads packaging 10x archives (0 = automatic).
+    pub fn bundle_jobs(mut self, workers: usize) -> Self {
+        self.options.bundle_jobs = workers;
+        self
+    }
+
+    pub fn md5_store(mut self, store: Md5Store) -> Self {
+        self.options.md5_store = store;
+        self
+    }
+
+    pub fn trust_cache(mut self, yes: bool) -> Self {
+        self.options.trust_cache = yes;
+        self
+    }
+
+    pub fn sidecar_check(mut self, yes: bool) -> Self {
+        self.options.sidecar_check = yes;
+        self
+    }
+
+    pub fn dedup(mut self, policy: DedupPolicy) -> Self {
+        self.options.dedup = policy;
+        self
+    }
+
+    /// Globs in priority order: of several copies the one under the earliest glob is kept.
+    pub fn prefer_paths<I, S>(mut self, globs: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.options.prefer_paths = globs.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn on_duplicate_role(mut self, strategy: DuplicateRole) -> Self {
+        self.options.on_duplicate_role = strategy;
+        self
+    }
+
+    pub fn compress_plain(mut self, yes: bool) -> Self {
+        self.options.compress_plain = yes;
+        self
+    }
+
+    pub fn recompress_to(mut self, target: RecompressTo) -> Self {
+        self.options.recompress_to = target;
+        self
+    }
+
+    pub fn keep_empty(mut self, yes: bool) -> Self {
+        self.options.keep_empty = yes;
+        self
+    }
+
+    pub fn keep_accession_like(mut self, yes: bool) -> Self {
+        self.options.keep_accession_like = yes;
+        self
+    }
+
+    /// Columns of the sample table in output order: `source`, `sample`, `tenx`, `h5`,
+    /// `fragments`, `alignment`, `pacbio` and read roles (`R1`, `R2`, ...).
+    pub fn sample_columns<I, S>(mut self, columns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.options.sample_columns = columns.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn hide_empty_columns(mut self, yes: bool) -> Self {
+        self.options.hide_empty_columns = yes;
+        self
+    }
+
+    /// PacBio movie name -> sample name
+    pub fn pacbio_movie_samples(mut self, map: HashMap<String, String>) -> Self {
+        self.options.pacbio_movie_samples = map;
+        self
+    }
+
+    /// user sample detection rules, tried before the built-in ones
+    pub fn sample_rules(mut self, rules: SampleRules) -> Self {
+        self.options.sample_rules = rules;
+        self
+    }
+
+    pub fn build(self) -> SampleFiles {
+        let mut data = SampleFiles::new();
+        data.options = self.options;
+        data
+    }
+}
+
+impl SampleFiles {
+    pub fn builder() -> SampleFilesBuilder {
+        SampleFilesBuilder::new()
+    }
+}
@@ -0,0 +1,336 @@
+// src/sample_files/checksum.rs
+//! All md5 sums are computed here with the in-crate `md5` implementation, so the
+//! results are identical on Linux, macOS and Windows (no `md5sum` binary needed).
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+
+use crate::error::Result;
+use crate::interrupt::{self, Checked};
+use crate::output::create_output;
+
+/// Name of the per-directory checksum file written with `Md5Store::Directory`.
+pub const MD5SUMS_FILE: &str = "MD5SUMS";
+
+/// Default read size for hashing and decompressing (1 MiB).
+pub const DEFAULT_READ_BUFFER: usize = 1024 * 1024;
+
+/// How files are read for hashing (`--read-buffer`, `--mmap-min`) and whether missing md5
+/// sums are computed now or left PENDING (`--reuse-md5-only`, `--preview-hash`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HashIo {
+    /// bytes per read call
+    pub buffer: usize,
+    /// memory-map files of at least this size instead of reading them (None = never)
+    pub mmap_min: Option<u64>,
+    /// only read cached md5 sums, never compute one
+    pub reuse_only: bool,
+    /// instead of the md5, hash this many leading bytes for the duplicate detector
+    pub preview: Option<u64>,
+}
+
+impl HashIo {
+    /// Missing md5 sums are not computed in this run.
+    pub fn deferred(&self) -> bool {
+        self.reuse_only || self.preview.is_some()
+    }
+}
+
+impl Default for HashIo {
+    fn default() -> Self {
+        HashIo { buffer: DEFAULT_READ_BUFFER, mmap_min: None, reuse_only: false, preview: None }
+    }
+}
+
+/// Shown instead of an md5 that `--reuse-md5-only` / `--preview-hash` did not compute.
+pub const PENDING_MD5: &str = "PENDING";
+
+/// md5 hex digest of a file, read in 1 MiB chunks.
+pub fn md5_file(path: &Path) -> io::Result<String> {
+    md5_file_with(path, HashIo::default())
+}
+
+/// md5 hex digest of a file, read in `io.buffer` chunks or memory-mapped if it is at least
+/// `io.mmap_min` bytes (falls back to reading if the mapping fails).
+pub fn md5_file_with(path: &Path, io: HashIo) -> io::Result<String> {
+    let mut f = File::open(path)?;
+    let len = f.metadata()?.len();
+    if io.mmap_min.is_some_and(|min| len >= min && len > 0) {
+        // SAFETY: the map is only read while hashing; a file truncated by another process
+        // during that time can fault, the same risk `md5sum` via mmap would take.
+        if let Ok(map) = unsafe { memmap2::Mmap::map(&f) } {
+            let mut ctx = md5::Context::new();
+            for chunk in map.chunks(io.buffer.max(1)) {
+                interrupt::check()?;
+                ctx.consume(chunk);
+            }
+            return Ok(format!("{:x}", ctx.compute()));
+        }
+    }
+    let mut ctx = md5::Context::new();
+    let mut buf = vec![0u8; io.buffer.max(1)];
+    loop {
+        interrupt::check()?;
+        let n = match f.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        ctx.consume(&buf[..n]);
+    }
+    Ok(format!("{:x}", ctx.compute()))
+}
+
+/// md5 of the first `bytes` of a file plus its length (`--preview-hash`): the same for copies
+/// and different for nearly all other files, at a fraction of the cost. Not a file checksum.
+pub fn preview_md5(path: &Path, bytes: u64) -> io::Result<String> {
+    let f = File::open(path)?;
+    let len = f.metadata()?.len();
+    let mut ctx = md5::Context::new();
+    io::copy(&mut Checked(f).take(bytes), &mut ctx)?;
+    ctx.consume(len.to_le_bytes());
+    Ok(format!("{:x}", ctx.compute()))
+}
+
+/// First token of a checksum line if it is a 32 digit hex md5 (`md5sum` or bare format).
+pub(crate) fn parse_md5_line(line: &str) -> Option<String> {
+    let token = line.split_whitespace().next()?;
+    if token.len() == 32 && token.bytes().all(|b| b.is_ascii_hexdigit()) {
+        Some(token.to_ascii_lowercase())
+    } else {
+        None
+    }
+}
+
+/// A cached md5 with the size and mtime (unix seconds) of the file when it was hashed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CachedMd5 {
+    pub md5: String,
+    pub size: Option<u64>,
+    pub mtime: Option<u64>,
+}
+
+impl CachedMd5 {
+    /// Record `md5` together with the current size and mtime of `path`.
+    pub fn for_file(md5: String, path: &Path) -> Self {
+        let (size, mtime) = match size_and_mtime(path) {
+            Some((size, mtime)) => (Some(size), Some(mtime)),
+            None => (None, None),
+        };
+        CachedMd5 { md5, size, mtime }
+    }
+
+    /// True if `path` still has the recorded size and mtime; entries without them never match.
+    pub fn matches(&self, path: &Path) -> bool {
+        match (self.size, self.mtime, size_and_mtime(path)) {
+            (Some(size), Some(mtime), Some(now)) => (size, mtime) == now,
+            _ => false,
+        }
+    }
+
+    /// Checksum lines for `name`: a `# size=.. mtime=..` comment (ignored by `md5sum -c`)
+    /// followed by the standard `<md5>  <name>` line.
+    fn lines(&self, name: &str) -> String {
+        match (self.size, self.mtime) {
+            (Some(size), Some(mtime)) => format!("# size={} mtime={}\n{}  {}\n", size, mtime, self.md5, name),
+            _ => format!("{}  {}\n", self.md5, name),
+        }
+    }
+}
+
+fn size_and_mtime(path: &Path) -> Option<(u64, u64)> {
+    let md = fs::metadata(path).ok()?;
+    let mtime = md.modified().ok()?.duration_since(UNIX_EPOCH).ok()?.as_secs();
+    Some((md.len(), mtime))
+}
+
+/// (file name, entry) pairs of a checksum file in `md5sum` format. A bare digest yields an
+/// empty name, the binary marker (`<md5> *<name>`) is accepted and a preceding
+/// `# size=.. mtime=..` comment is attached to the following entry.
+pub(crate) fn parse_md5_entries(text: &str) -> Vec<(String, CachedMd5)> {
+    let mut out = Vec::new();
+    let (mut size, mut mtime) = (None, None);
+    for line in text.lines() {
+        if let Some(comment) = line.strip_prefix('#') {
+            for field in comment.split_whitespace() {
+                match field.split_once('=') {
+                    Some(("size", v)) => size = v.parse().ok(),
+                    Some(("mtime", v)) => mtime = v.parse().ok(),
+                    _ => {}
+                }
+            }
+            continue;
+        }
+        if let Some(md5) = parse_md5_line(line) {
+            let name = line.trim_start()[32..].trim_start().trim_start_matches('*').to_string();
+            out.push((name, CachedMd5 { md5, size: size.take(), mtime: mtime.take() }));
+        }
+        size = None;
+        mtime = None;
+    }
+    out
+}
+
+/// The entry of a `<file>.md5sum` sidecar, if there is a readable one.
+pub(crate) fn read_sidecar(path: &Path) -> Option<CachedMd5> {
+    let text = fs::read_to_string(path).ok()?;
+    parse_md5_entries(&text).into_iter().next().map(|(_, entry)| entry)
+}
+
+/// Write a `<file>.md5sum` sidecar for the file called `name`.
+pub(crate) fn write_sidecar(path: &Path, name: &str, entry: &CachedMd5) -> io::Result<()> {
+    fs::write(path, entry.lines(name))
+}
+
+/// File name -> entry from an `MD5SUMS` file; missing or unreadable = empty.
+pub(crate) fn read_md5sums(path: &Path) -> BTreeMap<String, CachedMd5> {
+    let text = fs::read_to_string(path).unwrap_or_default();
+    parse_md5_entries(&text).into_iter().filter(|(name, _)| !name.is_empty()).collect()
+}
+
+/// Add or replace the entry for `name` in an `MD5SUMS` file, keeping it sorted by file name.
+pub(crate) fn update_md5sums(path: &Path, name: &str, entry: &CachedMd5) -> Result<()> {
+    // parallel hashing (`--hash-jobs`) updates the same MD5SUMS from several threads
+    static LOCK: Mutex<()> = Mutex::new(());
+    let _guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut entries = read_md5sums(path);
+    if entries.get(name) == Some(entry) {
+        return Ok(());
+    }
+    entries.insert(name.to_string(), entry.clone());
+    let mut w = create_output(path)?;
+    for (name, entry) in &entries {
+        w.write_all(entry.lines(name).as_bytes())?;
+    }
+    w.commit()
+}
+
+/// Passes reads through and (optionally) hashes every byte on the way.
+pub struct Md5Reader<R: Read> {
+    inner: R,
+    ctx: Option<md5::Context>,
+}
+
+impl<R: Read> Md5Reader<R> {
+    pub fn new(inner: R, with_md5: bool) -> Self {
+        Md5Reader { inner, ctx: if with_md5 { Some(md5::Context::new()) } else { None } }
+    }
+
+    /// Digest of everything read so far; None if hashing was disabled.
+    pub fn hex(self) -> Option<String> {
+        self.ctx.map(|ctx| format!("{:x}", ctx.compute()))
+    }
+}
+
+impl<R: Read> Read for Md5Reader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        interrupt::check()?;
+        let n = self.inner.read(buf)?;
+        if let Some(ctx) = self.ctx.as_mut() {
+            ctx.consume(&buf[..n]);
+        }
+        Ok(n)
+    }
+}
+
+/// Passes writes through and hashes every byte written.
+pub struct Md5Writer<W: Write> {
+    inner: W,
+    ctx: md5::Context,
+}
+
+impl<W: Write> Md5Writer<W> {
+    pub fn new(inner: W) -> Self {
+        Md5Writer { inner, ctx: md5::Context::new() }
+    }
+
+    /// Flush and return the inner writer with the digest of everything written.
+    pub fn finish(mut self) -> io::Result<(W, String)> {
+        self.inner.flush()?;
+        Ok((self.inner, format!("{:x}", self.ctx.compute())))
+    }
+}
+
+impl<W: Write> Write for Md5Writer<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.ctx.consume(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reader_writer_and_file_agree() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("x.txt");
+        std::fs::write(&path, b"hello world\n").unwrap();
+        let expected = "6f5902ac237024bdd0c176cb93063dc4";
+
+        assert_eq!(md5_file(&path).unwrap(), expected);
+        let small = HashIo { buffer: 5, ..Default::default() };
+        assert_eq!(md5_file_with(&path, small).unwrap(), expected);
+        let mapped = HashIo { mmap_min: Some(1), ..Default::default() };
+        assert_eq!(md5_file_with(&path, mapped).unwrap(), expected);
+
+        let mut w = Md5Writer::new(Vec::new());
+        w.write_all(b"hello ").unwrap();
+        w.write_all(b"world\n").unwrap();
+        let (bytes, hex) = w.finish().unwrap();
+        assert_eq!(bytes, b"hello world\n");
+        assert_eq!(hex, expected);
+    }
+
+    #[test]
+    fn md5sums_entries_are_updated_in_place() {
+        let tmp = tempfile::tempdir().unwrap();
+        let md5sums = tmp.path().join(MD5SUMS_FILE);
+        let a = "6f5902ac237024bdd0c176cb93063dc4";
+        let b = "d41d8cd98f00b204e9800998ecf8427e";
+        let entry = |md5: &str| CachedMd5 { md5: md5.to_string(), size: None, mtime: None };
+
+        update_md5sums(&md5sums, "b.fastq.gz", &entry(b)).unwrap();
+        update_md5sums(&md5sums, "a.fastq.gz", &entry(b)).unwrap();
+        update_md5sums(&md5sums, "a.fastq.gz", &entry(a)).unwrap();
+        assert_eq!(
+            fs::read_to_string(&md5sums).unwrap(),
+            format!("{a}  a.fastq.gz\n{b}  b.fastq.gz\n")
+        );
+        let entries = read_md5sums(&md5sums);
+        assert_eq!(entries.get("a.fastq.gz").map(|e| e.md5.as_str()), Some(a));
+    }
+
+    #[test]
+    fn cached_md5_tracks_size_and_mtime() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("x.fastq.gz");
+        let sidecar = tmp.path().join("x.fastq.gz.md5sum");
+        fs::write(&path, b"hello world\n").unwrap();
+
+        let entry = CachedMd5::for_file("6f5902ac237024bdd0c176cb93063dc4".to_string(), &path);
+        write_sidecar(&sidecar, "x.fastq.gz", &entry).unwrap();
+        let text = fs::read_to_string(&sidecar).unwrap();
+        assert!(text.starts_with("# size=12 mtime="));
+        assert!(text.ends_with("6f5902ac237024bdd0c176cb93063dc4  x.fastq.gz\n"));
+
+        let read = read_sidecar(&sidecar).unwrap();
+        assert_eq!(read, entry);
+        assert!(read.matches(&path));
+        // legacy entries without size/mtime are never trusted
+        assert!(!CachedMd5 { size: None, ..read.clone() }.matches(&path));
+
+        fs::write(&path, b"replaced\n").unwrap();
+        assert!(!read.matches(&path));
+    }
+}
@@ -0,0 +1,112 @@
+// src/sample_files/clean.rs
+//! `clean` subcommand: remove what the scan wrote into the project tree.
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use walkdir::WalkDir;
+
+use crate::error::Result;
+use crate::sample_files::checksum::{parse_md5_entries, MD5SUMS_FILE};
+use crate::sample_files::ParsedFile;
+
+/// True for an `MD5SUMS` file written by this tool (entries carry `# size=.. mtime=..`
+/// comments); checksum lists delivered by a sequencing facility are left alone.
+fn is_own_md5sums(path: &Path) -> bool {
+    fs::read_to_string(path).is_ok_and(|text| text.lines().any(|l| l.starts_with("# size=")))
+}
+
+/// True for a `<file>.md5sum` sidecar written by this tool: it carries the `# size=.. mtime=..`
+/// comment, or its entry names the file next to it, which still exists.
+fn is_own_sidecar(path: &Path) -> bool {
+    let Ok(text) = fs::read_to_string(path) else { return false };
+    let entries = parse_md5_entries(&text);
+    if entries.iter().any(|(_, entry)| entry.size.is_some()) {
+        return true;
+    }
+    let sibling = path.file_name().and_then(|n| n.to_str()).and_then(|n| n.strip_suffix(".md5sum"));
+    sibling.is_some_and(|sibling| {
+        path.with_file_name(sibling).is_file() && entries.iter().any(|(name, _)| name == sibling)
+    })
+}
+
+/// Artifacts of earlier scans below `root`: our `.md5sum` sidecars and `MD5SUMS` files and the
+/// 10x archives (plus interrupted `.tmp` archives) next to their triplet folders, and the
+/// `.md5sum` files that were not written by this tool and are left alone. Both sorted.
+pub fn find_artifacts<P: AsRef<Path>>(root: P) -> (Vec<PathBuf>, Vec<PathBuf>) {
+    let mut out = BTreeSet::new();
+    let mut foreign = BTreeSet::new();
+    for entry in WalkDir::new(root.as_ref()).follow_links(false).into_iter().filter_map(|e| e.ok()) {
+        let p = entry.path();
+        if entry.file_type().is_dir() {
+            out.extend(ParsedFile::tenx_bundle_artifacts(p).into_iter().filter(|a| a.is_file()));
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy();
+        if name.ends_with(".md5sum") && !is_own_sidecar(p) {
+            foreign.insert(p.to_path_buf());
+        } else if name.ends_with(".md5sum") || (name == MD5SUMS_FILE && is_own_md5sums(p)) {
+            out.insert(p.to_path_buf());
+        }
+    }
+    (out.into_iter().collect(), foreign.into_iter().collect())
+}
+
+/// Delete the artifacts of `find_artifacts(root)` (nothing with `dry_run`) and return them
+/// with the skipped foreign `.md5sum` files.
+pub fn clean_artifacts<P: AsRef<Path>>(root: P, dry_run: bool) -> Result<(Vec<PathBuf>, Vec<PathBuf>)> {
+    let (found, skipped) = find_artifacts(root);
+    if !dry_run {
+        for p in &found {
+            fs::remove_file(p)?;
+        }
+    }
+    Ok((found, skipped))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_generated_files_are_removed() {
+        let tmp = tempfile::tempdir().unwrap();
+        let outs = tmp.path().join("exp1/sampleA/outs");
+        let triplet = outs.join("filtered_feature_bc_matrix");
+        fs::create_dir_all(&triplet).unwrap();
+        for name in ["matrix.mtx.gz", "barcodes.tsv.gz", "features.tsv.gz"] {
+            fs::write(triplet.join(name), name).unwrap();
+        }
+        let exp = tmp.path().join("exp1");
+        fs::write(exp.join("s_L001_R1_001.fastq.gz"), "r1").unwrap();
+        fs::write(exp.join("s_L001_R2_001.fastq.gz"), "r2").unwrap();
+        let md5 = "0123456789abcdef0123456789abcdef";
+        // ours: with the size/mtime comment, or naming the file next to it
+        let ours = format!("# size=2 mtime=1\n{md5}  s_L001_R1_001.fastq.gz\n");
+        fs::write(exp.join("s_L001_R1_001.fastq.gz.md5sum"), ours).unwrap();
+        fs::write(exp.join("s_L001_R2_001.fastq.gz.md5sum"), format!("{md5}  s_L001_R2_001.fastq.gz\n")).unwrap();
+        // delivered with the data: kept
+        fs::write(exp.join("s_L001_I1_001.fastq.gz.md5sum"), format!("{md5}  s_L001_I1_001.fastq.gz\n")).unwrap();
+        fs::write(exp.join("facility.md5sum"), "x").unwrap();
+        fs::write(exp.join(MD5SUMS_FILE), "# size=2 mtime=1\nabc  s\n").unwrap();
+        fs::write(tmp.path().join(MD5SUMS_FILE), "0123  delivered.fastq.gz\n").unwrap();
+        fs::write(outs.join("sampleA_filtered.zip"), "zip").unwrap();
+        fs::write(outs.join("sampleA_filtered.tar.gz.tmp"), "partial").unwrap();
+        fs::write(outs.join("other.zip"), "user data").unwrap();
+
+        let (dry, skipped) = clean_artifacts(tmp.path(), true).unwrap();
+        assert_eq!(dry.len(), 5);
+        assert_eq!(skipped, [exp.join("facility.md5sum"), exp.join("s_L001_I1_001.fastq.gz.md5sum")]);
+        assert!(outs.join("sampleA_filtered.zip").exists());
+
+        clean_artifacts(tmp.path(), false).unwrap();
+        assert!(exp.join("facility.md5sum").exists());
+        assert!(exp.join("s_L001_I1_001.fastq.gz.md5sum").exists());
+        assert!(!outs.join("sampleA_filtered.zip").exists());
+        assert!(!exp.join(MD5SUMS_FILE).exists());
+        assert!(tmp.path().join(MD5SUMS_FILE).exists());
+        assert!(outs.join("other.zip").exists());
+        assert!(exp.join("s_L001_R1_001.fastq.gz").exists());
+        assert!(find_artifacts(tmp.path()).0.is_empty());
+    }
+}
@@ -0,0 +1,94 @@
+// src/sample_files/column_layout.rs
+//! User selected column order for the wide sample table (`--columns`).
+
+/// Column groups of the sample table besides the read roles (R1, R2, I1, ...).
+pub const COLUMN_GROUPS: [&str; 11] = [
+    "source", "sample", "layout", "organism", "strategy", "attributes", "tenx", "h5", "fragments", "alignment", "pacbio",
+];
+
+/// Reorder/select the columns of `rows` (header first).
+///
+/// `groups[i]` names the group of column `i` and, for read role columns, the lane block it
+/// belongs to. `columns` lists groups and roles in output order (empty = keep all); a group
+/// expands to all its columns, roles are emitted per lane block at the position of the first
+/// role. With `hide_empty` columns without any value below the header are dropped.
+pub(crate) fn apply_layout(
+    rows: Vec<Vec<String>>,
+    groups: &[(String, usize)],
+    columns: &[String],
+    hide_empty: bool,
+) -> Vec<Vec<String>> {
+    let mut keep: Vec<usize> = if columns.is_empty() {
+        (0..groups.len()).collect()
+    } else {
+        let is_group = |c: &str| COLUMN_GROUPS.contains(&c);
+        let wanted: Vec<String> = columns
+            .iter()
+            .map(|c| if is_group(&c.to_ascii_lowercase()) { c.to_ascii_lowercase() } else { c.to_ascii_uppercase() })
+            .collect();
+        let roles: Vec<&String> = wanted.iter().filter(|c| !is_group(c)).collect();
+        let lanes = groups.iter().filter(|(g, _)| !is_group(g)).map(|(_, lane)| lane + 1).max().unwrap_or(0);
+
+        let mut keep = Vec::new();
+        let mut roles_done = false;
+        for c in &wanted {
+            if is_group(c) {
+                keep.extend(groups.iter().enumerate().filter(|(_, (g, _))| g == c).map(|(i, _)| i));
+            } else if !roles_done {
+                roles_done = true;
+                for lane in 0..lanes {
+                    for role in &roles {
+                        keep.extend(groups.iter().position(|(g, l)| g == *role && *l == lane));
+                    }
+                }
+            }
+        }
+        for role in &roles {
+            if !groups.iter().any(|(g, _)| g == *role) {
+                eprintln!("WARN: column '{}' is not present in the sample table", role);
+            }
+        }
+        keep
+    };
+
+    if hide_empty {
+        keep.retain(|&i| rows.iter().skip(1).any(|row| row.get(i).is_some_and(|v| !v.is_empty())));
+    }
+
+    rows.into_iter()
+        .map(|row| keep.iter().map(|&i| row.get(i).cloned().unwrap_or_default()).collect())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn groups() -> Vec<(String, usize)> {
+        [("source", 0), ("sample", 0), ("tenx", 0), ("R1", 0), ("R2", 0), ("R1", 1), ("R2", 1)]
+            .iter()
+            .map(|(g, l)| (g.to_string(), *l))
+            .collect()
+    }
+
+    fn rows() -> Vec<Vec<String>> {
+        vec![
+            ["Source_Path(s)", "Sample_Lane", "TenX", "R1", "R2", "R1", "R2"].map(String::from).to_vec(),
+            ["/d", "A", "", "a1", "a2", "", ""].map(String::from).to_vec(),
+        ]
+    }
+
+    #[test]
+    fn columns_are_selected_in_user_order() {
+        let columns: Vec<String> = ["sample", "r2", "R1", "tenx"].map(String::from).to_vec();
+        let out = apply_layout(rows(), &groups(), &columns, false);
+        assert_eq!(out[0], ["Sample_Lane", "R2", "R1", "R2", "R1", "TenX"]);
+        assert_eq!(out[1], ["A", "a2", "a1", "", "", ""]);
+    }
+
+    #[test]
+    fn empty_columns_can_be_hidden() {
+        let out = apply_layout(rows(), &groups(), &[], true);
+        assert_eq!(out[0], ["Source_Path(s)", "Sample_Lane", "R1", "R2"]);
+    }
+}
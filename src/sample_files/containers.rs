@@ -0,0 +1,123 @@
+// src/sample_files/containers.rs
+//! Compression containers of FASTQ files. GEO takes gzip; plain, bzip2, xz and zstd FASTQs
+//! are recognised as well and can be transcoded to gzip on the way (`--compress-plain`,
+//! `--recompress-to gz`), in which case the gzip copy is hashed and uploaded.
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+
+use bzip2::read::MultiBzDecoder;
+use flate2::read::MultiGzDecoder;
+use lzma_rust2::XzReader;
+
+/// How a (FASTQ) file is compressed, from its last extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Container {
+    Plain,
+    Gzip,
+    Bzip2,
+    Xz,
+    Zstd,
+}
+
+impl Container {
+    /// Container of any file name: `.gz`, `.bz2`, `.xz`, `.zst`, else plain.
+    pub fn of_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("gz") => Container::Gzip,
+            Some("bz2") => Container::Bzip2,
+            Some("xz") => Container::Xz,
+            Some("zst") => Container::Zstd,
+            _ => Container::Plain,
+        }
+    }
+
+    /// Container of a FASTQ name (`.fastq`, `.fq.gz`, `.fastq.bz2`, `.fq.xz`, `.fastq.zst`),
+    /// None for every other file.
+    pub fn of_fastq(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?;
+        let container = Self::of_path(path);
+        let stem = name.strip_suffix(container.extension()).unwrap_or(name);
+        (stem.ends_with(".fastq") || stem.ends_with(".fq")).then_some(container)
+    }
+
+    /// File name extension including the dot; empty for plain files.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Container::Plain => "",
+            Container::Gzip => ".gz",
+            Container::Bzip2 => ".bz2",
+            Container::Xz => ".xz",
+            Container::Zstd => ".zst",
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Container::Plain => "uncompressed",
+            Container::Gzip => "gzip",
+            Container::Bzip2 => "bzip2",
+            Container::Xz => "xz",
+            Container::Zstd => "zstd",
+        }
+    }
+
+    /// Decompressed content of `path` (all members of multi-stream files).
+    pub fn open(&self, path: &Path) -> io::Result<Box<dyn Read>> {
+        let f = BufReader::new(File::open(path)?);
+        Ok(match self {
+            Container::Plain => Box::new(f),
+            Container::Gzip => Box::new(MultiGzDecoder::new(f)),
+            Container::Bzip2 => Box::new(MultiBzDecoder::new(f)),
+            Container::Xz => Box::new(XzReader::new(f, true)),
+            Container::Zstd => Box::new(zstd::stream::read::Decoder::with_buffer(f)?),
+        })
+    }
+}
+
+/// Name of the gzip version of a FASTQ: `x.fastq.bz2` -> `x.fastq.gz`, `x.fq` -> `x.fq.gz`.
+pub fn gzip_name(name: &str) -> String {
+    let container = Container::of_path(Path::new(name));
+    format!("{}.gz", name.strip_suffix(container.extension()).unwrap_or(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn every_container_decodes_to_the_same_reads() {
+        let reads = b"@r1\nACGT\n+\nIIII\n";
+        let tmp = tempfile::tempdir().unwrap();
+        let write = |name: &str, bytes: Vec<u8>| {
+            let path = tmp.path().join(name);
+            std::fs::write(&path, bytes).unwrap();
+            path
+        };
+        let mut bz = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+        bz.write_all(reads).unwrap();
+        let mut xz = lzma_rust2::XzWriter::new(Vec::new(), lzma_rust2::XzOptions::default()).unwrap();
+        xz.write_all(reads).unwrap();
+        let files = [
+            write("a_R1.fastq", reads.to_vec()),
+            write("a_R1.fastq.bz2", bz.finish().unwrap()),
+            write("a_R1.fq.xz", xz.finish().unwrap()),
+            write("a_R1.fastq.zst", zstd::encode_all(&reads[..], 3).unwrap()),
+        ];
+        let found: Vec<Option<Container>> = files.iter().map(|p| Container::of_fastq(p)).collect();
+        assert_eq!(
+            found,
+            [Some(Container::Plain), Some(Container::Bzip2), Some(Container::Xz), Some(Container::Zstd)]
+        );
+        for (path, container) in files.iter().zip(found) {
+            let mut content = Vec::new();
+            container.unwrap().open(path).unwrap().read_to_end(&mut content).unwrap();
+            assert_eq!(content, reads, "{}", path.display());
+        }
+
+        assert_eq!(Container::of_fastq(Path::new("matrix.mtx.gz")), None);
+        assert_eq!(gzip_name("a_R1.fastq.bz2"), "a_R1.fastq.gz");
+        assert_eq!(gzip_name("a_R1.fq"), "a_R1.fq.gz");
+    }
+}
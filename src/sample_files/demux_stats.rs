@@ -0,0 +1,155 @@
+// src/sample_files/demux_stats.rs
+//! Read counts from the demultiplexer's own reports (`--demux-stats`): bcl2fastq
+//! `Stats/Stats.json` and BCL Convert `Reports/Demultiplex_Stats.csv`.
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::sample_files::fastq_header::filename_lane_number;
+
+/// Folders of a demultiplexed run that never hold submission data.
+pub const DEMUX_REPORT_DIRS: [&str; 3] = ["Reports", "Stats", "InterOp"];
+
+/// True for the `Reports/`, `Stats/` and `InterOp/` folders of a demultiplexed run: `InterOp`
+/// always, the others if they (or their parent) carry bcl2fastq/BCL Convert files.
+pub fn is_demux_report_dir(dir: &Path) -> bool {
+    let Some(name) = dir.file_name().and_then(|n| n.to_str()) else {
+        return false;
+    };
+    if !DEMUX_REPORT_DIRS.contains(&name) {
+        return false;
+    }
+    name == "InterOp"
+        || ["Stats.json", "Demultiplex_Stats.csv", "FastqSummaryF1L1.txt"]
+            .iter()
+            .any(|f| dir.join(f).is_file())
+        || dir
+            .parent()
+            .is_some_and(|run| ["RunInfo.xml", "SampleSheet.csv"].iter().any(|f| run.join(f).is_file()))
+}
+
+/// Clusters (read pairs) per sample and lane as counted by the demultiplexer.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DemuxStats {
+    /// sample (bcl2fastq `SampleName`, BCL Convert `SampleID`) -> lane -> reads
+    pub reads: BTreeMap<String, BTreeMap<u32, u64>>,
+}
+
+impl DemuxStats {
+    /// bcl2fastq `Stats.json`: `ConversionResults[].DemuxResults[]`.
+    pub fn from_stats_json(text: &str) -> Option<Self> {
+        let json: Value = serde_json::from_str(text).ok()?;
+        let mut stats = DemuxStats::default();
+        for lane in json.get("ConversionResults")?.as_array()? {
+            let Some(lane_no) = lane.get("LaneNumber").and_then(Value::as_u64) else { continue };
+            for res in lane.get("DemuxResults").and_then(Value::as_array).into_iter().flatten() {
+                let sample = res
+                    .get("SampleName")
+                    .or_else(|| res.get("SampleId"))
+                    .and_then(Value::as_str)
+                    .filter(|s| !s.is_empty());
+                if let (Some(sample), Some(n)) = (sample, res.get("NumberReads").and_then(Value::as_u64)) {
+                    stats.add(sample, lane_no as u32, n);
+                }
+            }
+        }
+        Some(stats)
+    }
+
+    /// BCL Convert `Demultiplex_Stats.csv`: `Lane,SampleID,...,# Reads,...`.
+    pub fn from_demultiplex_csv(text: &str) -> Option<Self> {
+        let mut lines = text.lines();
+        let header: Vec<&str> = lines.next()?.split(',').map(str::trim).collect();
+        let col = |name: &str| header.iter().position(|h| h.eq_ignore_ascii_case(name));
+        let (lane_col, sample_col, reads_col) = (col("Lane")?, col("SampleID")?, col("# Reads")?);
+        let mut stats = DemuxStats::default();
+        for line in lines {
+            let cells: Vec<&str> = line.split(',').map(str::trim).collect();
+            let (Some(lane), Some(sample), Some(reads)) =
+                (cells.get(lane_col), cells.get(sample_col), cells.get(reads_col))
+            else {
+                continue;
+            };
+            if let (Ok(lane), Ok(reads)) = (lane.parse(), reads.parse()) {
+                stats.add(sample, lane, reads);
+            }
+        }
+        Some(stats)
+    }
+
+    /// Stats of a report folder (`Stats.json` and/or `Demultiplex_Stats.csv`), None if it has none.
+    pub fn from_report_dir(dir: &Path) -> Option<Self> {
+        let json = fs::read_to_string(dir.join("Stats.json")).ok().and_then(|t| Self::from_stats_json(&t));
+        let csv = fs::read_to_string(dir.join("Demultiplex_Stats.csv"))
+            .ok()
+            .and_then(|t| Self::from_demultiplex_csv(&t));
+        match (json, csv) {
+            (None, None) => None,
+            (a, b) => {
+                let mut stats = a.unwrap_or_default();
+                stats.merge(b.unwrap_or_default());
+                Some(stats)
+            }
+        }
+    }
+
+    fn add(&mut self, sample: &str, lane: u32, reads: u64) {
+        *self.reads.entry(sample.to_string()).or_default().entry(lane).or_default() += reads;
+    }
+
+    pub fn merge(&mut self, other: DemuxStats) {
+        for (sample, lanes) in other.reads {
+            for (lane, reads) in lanes {
+                self.add(&sample, lane, reads);
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.reads.is_empty()
+    }
+
+    /// Reads of `sample` in the lane of a lane key (`S1_L002`), None if the demultiplexer
+    /// reported nothing for it.
+    pub fn lane_reads(&self, sample: &str, lane_key: &str) -> Option<u64> {
+        self.reads.get(sample)?.get(&filename_lane_number(lane_key)?).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn both_report_formats_are_read() {
+        let json = r#"{"ConversionResults":[
+            {"LaneNumber":1,"DemuxResults":[{"SampleId":"S1","SampleName":"sampleA","NumberReads":1000}]},
+            {"LaneNumber":2,"DemuxResults":[{"SampleId":"S1","SampleName":"sampleA","NumberReads":900}]}]}"#;
+        let csv = "Lane,SampleID,Sample_Project,Index,# Reads,# Perfect Index Reads\n\
+                   1,sampleB,P1,ACGT-TTGA,500,480\n1,Undetermined,,,20,0\n";
+        let mut stats = DemuxStats::from_stats_json(json).unwrap();
+        stats.merge(DemuxStats::from_demultiplex_csv(csv).unwrap());
+
+        assert_eq!(stats.lane_reads("sampleA", "S1_L002"), Some(900));
+        assert_eq!(stats.lane_reads("sampleB", "L001"), Some(500));
+        assert_eq!(stats.lane_reads("sampleB", "L002"), None);
+        assert!(DemuxStats::from_stats_json("{}").is_none());
+    }
+
+    #[test]
+    fn report_folders_are_recognised() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (reports, stats, data) = (tmp.path().join("Reports"), tmp.path().join("Stats"), tmp.path().join("data"));
+        for d in [&reports, &stats, &data] {
+            fs::create_dir_all(d).unwrap();
+        }
+        assert!(!is_demux_report_dir(&reports));
+        fs::write(stats.join("Stats.json"), "{}").unwrap();
+        assert!(is_demux_report_dir(&stats));
+        fs::write(tmp.path().join("RunInfo.xml"), "<RunInfo/>").unwrap();
+        assert!(is_demux_report_dir(&reports));
+        assert!(!is_demux_report_dir(&data));
+    }
+}
@@ -0,0 +1,100 @@
+// src/sample_files/empty_files.rs
+//! Files whose names look right but hold no data: zero byte files, gzip files without content
+//! and FASTQs without a single read record. Sequencing cores deliver those for failed
+//! libraries; `SampleFiles::add_parsed` skips them (unless `ScanOptions::keep_empty`) so they
+//! are never offered to GEO.
+use std::io::Read;
+use std::path::Path;
+
+use crate::sample_files::{Container, ParsedFile, ParsedKind, SampleFiles};
+
+/// Only files up to this size are opened; anything larger holds data.
+const PLACEHOLDER_MAX_BYTES: u64 = 64 * 1024;
+
+/// Why `pf` is a placeholder instead of data, None for real (or unreadable) files.
+pub(crate) fn placeholder_reason(pf: &ParsedFile) -> Option<&'static str> {
+    let path = Path::new(&pf.path);
+    if path.is_dir() || pf.is_tenx_bundle() {
+        return None;
+    }
+    let fastq = matches!(pf.kind, ParsedKind::Fastq { .. });
+    if fastq && pf.stats.as_ref().is_some_and(|s| s.reads == 0) {
+        return Some("FASTQ without reads");
+    }
+    let size = std::fs::metadata(path).ok()?.len();
+    if size == 0 {
+        return Some("empty file (0 bytes)");
+    }
+    if size > PLACEHOLDER_MAX_BYTES {
+        return None;
+    }
+    let reader = Container::of_path(path).open(path).ok()?;
+    let mut content = Vec::new();
+    // broken gzip streams are reported by the gzip check, not here
+    reader.take(16 * PLACEHOLDER_MAX_BYTES).read_to_end(&mut content).ok()?;
+    match content.iter().find(|b| !b.is_ascii_whitespace()) {
+        None if fastq => Some("FASTQ without reads"),
+        None => Some("no content"),
+        Some(b) if fastq && *b != b'@' => Some("placeholder text instead of FASTQ records"),
+        Some(_) => None,
+    }
+}
+
+impl SampleFiles {
+    /// Empty and placeholder files skipped while scanning, with the reason.
+    pub fn placeholders(&self) -> &[(ParsedFile, String)] {
+        &self.placeholders
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::io::Write;
+
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    #[test]
+    fn empty_and_placeholder_files_are_skipped() {
+        let tmp = tempfile::tempdir().unwrap();
+        let gz = |name: &str, content: &[u8]| {
+            let path = tmp.path().join(name);
+            let mut enc = GzEncoder::new(File::create(&path).unwrap(), Compression::default());
+            enc.write_all(content).unwrap();
+            enc.finish().unwrap();
+            path
+        };
+        let files = [
+            ("good", gz("good_S1_L001_R1_001.fastq.gz", b"@r1\nACGT\n+\nIIII\n")),
+            ("no_reads", gz("no_reads_S1_L001_R1_001.fastq.gz", b"")),
+            ("failed", gz("failed_S1_L001_R1_001.fastq.gz", b"library failed QC\n")),
+            ("zero", tmp.path().join("zero_S1_L001_R1_001.fastq.gz")),
+        ];
+        std::fs::write(&files[3].1, b"").unwrap();
+
+        let mut data = SampleFiles::new();
+        for (sample, path) in &files {
+            data.add_parsed(ParsedFile::test_file(
+                "exp1",
+                sample,
+                ParsedKind::Fastq { lane: "L001".to_string(), role: "R1".to_string() },
+                &path.to_string_lossy(),
+            ));
+        }
+
+        let samples: Vec<&str> = data.samples.keys().map(|k| k.sample.as_str()).collect();
+        assert_eq!(samples, ["good"]);
+        let skipped: Vec<(&str, &str)> =
+            data.placeholders().iter().map(|(pf, reason)| (pf.sample.as_str(), reason.as_str())).collect();
+        assert_eq!(
+            skipped,
+            [
+                ("no_reads", "FASTQ without reads"),
+                ("failed", "placeholder text instead of FASTQ records"),
+                ("zero", "empty file (0 bytes)"),
+            ]
+        );
+    }
+}
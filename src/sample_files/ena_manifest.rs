@@ -0,0 +1,102 @@
+// src/sample_files/ena_manifest.rs
+use std::io::Write;
+use std::path::Path;
+
+use crate::error::Result;
+use crate::output::create_output;
+use crate::sample_files::geo_metadata::FILL_IN;
+use crate::sample_files::{ParsedFile, SampleFiles};
+
+/// Webin-CLI manifest field for a raw file, `None` for files that are not submitted (indexes).
+fn manifest_field(pf: &ParsedFile) -> Option<&'static str> {
+    match pf.kind.file_type().as_str() {
+        "fastq" => Some("FASTQ"),
+        "bam" => Some("BAM"),
+        "cram" => Some("CRAM"),
+        _ => None,
+    }
+}
+
+impl SampleFiles {
+    /// ENA Webin-CLI read manifests, one `<sample>_<run>.manifest.txt` per run in `out_dir`.
+    ///
+    /// A run is one lane of a sample (all its FASTQs) or one BAM/CRAM/PacBio file. Each manifest
+    /// has STUDY (`study` or `[fill in]`), SAMPLE, NAME, the library fields and one FASTQ/BAM/CRAM
+    /// line per file, followed by a `# <md5>  <file>` comment with its checksum.
+    /// Returns the number of manifests written.
+    pub fn write_ena_manifest<P: AsRef<Path>>(&mut self, out_dir: P, study: Option<&str>) -> Result<usize> {
+        for pf in self.iter_all_parsed_files_mut().into_iter().filter(|pf| pf.kind.is_raw()) {
+            let _ = pf.ensure_md5sum()?;
+        }
+        std::fs::create_dir_all(out_dir.as_ref())?;
+
+        let mut written = 0;
+        for rec in self.samples.values() {
+            let sample = self.geo_sample_name(&rec.experiment, &rec.name);
+            let mut runs: Vec<(String, Vec<&ParsedFile>)> = rec
+                .lanes
+                .iter()
+                .map(|(lane_key, lane)| (lane_key.clone(), lane.reads.values().collect()))
+                .collect();
+            for pf in rec.alignments.values().chain(rec.long_reads.values()) {
+                runs.push((pf.stem(), vec![pf]));
+            }
+
+            for (run, files) in runs {
+                let files: Vec<&ParsedFile> = files.into_iter().filter(|pf| manifest_field(pf).is_some()).collect();
+                if files.is_empty() {
+                    continue;
+                }
+                let name = format!("{}_{}", sample, run);
+                let mut w = create_output(out_dir.as_ref().join(format!("{}.manifest.txt", name)))?;
+                writeln!(w, "STUDY\t{}", study.unwrap_or(FILL_IN))?;
+                writeln!(w, "SAMPLE\t{}", FILL_IN)?;
+                writeln!(w, "NAME\t{}", name)?;
+                for field in ["INSTRUMENT", "LIBRARY_SOURCE", "LIBRARY_SELECTION", "LIBRARY_STRATEGY"] {
+                    writeln!(w, "{}\t{}", field, FILL_IN)?;
+                }
+                for pf in files {
+                    let file_name = pf.geo_filename();
+                    writeln!(w, "{}\t{}", manifest_field(pf).unwrap_or("FASTQ"), file_name)?;
+                    writeln!(w, "# {}  {}", pf.md5_display(), file_name)?;
+                }
+                w.commit()?;
+                written += 1;
+            }
+        }
+        Ok(written)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sample_files::ParsedKind;
+
+    #[test]
+    fn one_manifest_per_lane() {
+        let mut data = SampleFiles::new();
+        for lane in ["L001", "L002"] {
+            for role in ["R1", "R2"] {
+                data.add_file(ParsedFile {
+                    md5sum: Some(format!("md5_{lane}_{role}")),
+                    ..ParsedFile::test_file(
+                        "exp1",
+                        "sampleA",
+                        ParsedKind::Fastq { lane: lane.to_string(), role: role.to_string() },
+                        &format!("exp1/sampleA_{lane}_{role}_001.fastq.gz"),
+                    )
+                });
+            }
+        }
+        let tmp = tempfile::tempdir().unwrap();
+        assert_eq!(data.write_ena_manifest(tmp.path(), Some("PRJEB1234")).unwrap(), 2);
+
+        let text = std::fs::read_to_string(tmp.path().join("sampleA_L002.manifest.txt")).unwrap();
+        assert!(text.starts_with("STUDY\tPRJEB1234\nSAMPLE\t[fill in]\nNAME\tsampleA_L002\n"));
+        assert!(text.ends_with(
+            "FASTQ\texp1_sampleA_L002_R1_001.fastq.gz\n# md5_L002_R1  exp1_sampleA_L002_R1_001.fastq.gz\n\
+             FASTQ\texp1_sampleA_L002_R2_001.fastq.gz\n# md5_L002_R2  exp1_sampleA_L002_R2_001.fastq.gz\n"
+        ));
+    }
+}
@@ -0,0 +1,92 @@
+// src/sample_files/fastq_header.rs
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::sample_files::Container;
+
+/// Sequencing run information from the first read header of a FASTQ (`--read-headers`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FastqHeader {
+    pub instrument: String,
+    pub run: String,
+    pub flowcell: String,
+    pub lane: u32,
+    /// sample index (`ACGT` or `ACGT+TTGA`) from the comment field, if present
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index: Option<String>,
+}
+
+impl FastqHeader {
+    /// Parse an Illumina (CASAVA 1.8+) read header:
+    /// `@<instrument>:<run>:<flowcell>:<lane>:<tile>:<x>:<y> <read>:<filtered>:<control>:<index>`.
+    /// Other header styles return None.
+    pub fn parse(line: &str) -> Option<Self> {
+        let mut parts = line.strip_prefix('@')?.split_whitespace();
+        let id = parts.next()?;
+        let index = parts
+            .next()
+            .and_then(|comment| comment.split(':').nth(3))
+            .filter(|i| !i.is_empty() && i.chars().all(|c| c.is_ascii_alphabetic() || c == '+'))
+            .map(str::to_string);
+        let fields: Vec<&str> = id.split(':').collect();
+        if fields.len() < 7 {
+            return None;
+        }
+        Some(FastqHeader {
+            instrument: fields[0].to_string(),
+            run: fields[1].to_string(),
+            flowcell: fields[2].to_string(),
+            lane: fields[3].parse().ok()?,
+            index,
+        })
+    }
+
+    /// Read and parse the first line of a (gzip, bzip2, xz, zstd compressed) FASTQ.
+    pub fn from_file(path: &Path) -> io::Result<Option<Self>> {
+        let reader = Container::of_path(path).open(path)?;
+        let mut line = String::new();
+        BufReader::new(reader).read_line(&mut line)?;
+        Ok(Self::parse(line.trim_end()))
+    }
+}
+
+/// Lane number of a filename lane token (`L001`, `S1_L002`), None if it has none.
+pub fn filename_lane_number(lane: &str) -> Option<u32> {
+    let (_, digits) = lane.rsplit_once('L')?;
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn illumina_headers_are_parsed() {
+        let h = FastqHeader::parse("@A01234:123:HXXXXXDSX:2:1101:1000:2000 1:N:0:ACGT").unwrap();
+        assert_eq!(h.instrument, "A01234");
+        assert_eq!(h.run, "123");
+        assert_eq!(h.flowcell, "HXXXXXDSX");
+        assert_eq!(h.lane, 2);
+        assert_eq!(h.index.as_deref(), Some("ACGT"));
+        assert_eq!(FastqHeader::parse("@SRR1234.1 1 length=50"), None);
+        assert_eq!(filename_lane_number("S1_L002"), Some(2));
+        assert_eq!(filename_lane_number("1"), None);
+    }
+
+    #[test]
+    fn first_header_is_read_from_gzip() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("s_L001_R1_001.fastq.gz");
+        let mut gz = flate2::write::GzEncoder::new(std::fs::File::create(&path).unwrap(), flate2::Compression::default());
+        gz.write_all(b"@M0001:7:000000000-ABCDE:1:1101:1:1 1:N:0:1\nACGT\n+\nIIII\n").unwrap();
+        gz.finish().unwrap();
+        let h = FastqHeader::from_file(&path).unwrap().unwrap();
+        assert_eq!((h.flowcell.as_str(), h.lane), ("000000000-ABCDE", 1));
+    }
+}
@@ -0,0 +1,125 @@
+// src/sample_files/fastq_stats.rs
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+use flate2::read::MultiGzDecoder;
+
+use crate::interrupt::Checked;
+use crate::sample_files::checksum::Md5Reader;
+
+/// Read statistics of one FASTQ file (`--stats`).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FastqStats {
+    pub reads: u64,
+    pub bases: u64,
+}
+
+impl FastqStats {
+    pub fn mean_read_length(&self) -> f64 {
+        if self.reads == 0 {
+            0.0
+        } else {
+            self.bases as f64 / self.reads as f64
+        }
+    }
+
+    /// Count records and bases of an uncompressed FASTQ stream (4 lines per record).
+    pub fn from_reader<R: BufRead>(mut r: R) -> io::Result<Self> {
+        let mut stats = FastqStats::default();
+        let mut line: Vec<u8> = Vec::new();
+        let mut idx = 0u64;
+        loop {
+            line.clear();
+            if r.read_until(b'\n', &mut line)? == 0 {
+                break;
+            }
+            if idx % 4 == 1 {
+                let seq = line.strip_suffix(b"\n").unwrap_or(&line);
+                let seq = seq.strip_suffix(b"\r").unwrap_or(seq);
+                stats.reads += 1;
+                stats.bases += seq.len() as u64;
+            }
+            idx += 1;
+        }
+        Ok(stats)
+    }
+
+    /// Decompress a gzipped FASTQ once and return (md5 of the compressed file, stats).
+    /// The md5 is only computed when `with_md5` is set (no sidecar available); the compressed
+    /// file is read in `buffer` byte chunks. Decompressing to the end verifies every gzip CRC.
+    pub fn md5_and_stats(path: &Path, with_md5: bool, buffer: usize) -> io::Result<(Option<String>, Self)> {
+        let f = File::open(path)?;
+        let mut hashing = Md5Reader::new(BufReader::with_capacity(buffer.max(1), f), with_md5);
+
+        let stats = {
+            let gz = MultiGzDecoder::new(&mut hashing);
+            Self::from_reader(BufReader::with_capacity(1024 * 1024, gz))?
+        };
+
+        // the decoder may stop before trailing bytes; hash whatever is left
+        io::copy(&mut hashing, &mut io::sink())?;
+
+        Ok((hashing.hex(), stats))
+    }
+}
+
+/// Fully decompress a gzip file so that flate2 verifies every member's CRC32 and size.
+/// Returns a description of the problem for corrupt or truncated archives.
+pub fn gzip_integrity_error(path: &Path) -> Option<String> {
+    let f = match File::open(path) {
+        Ok(f) => f,
+        Err(e) => return Some(format!("can not open: {e}")),
+    };
+    let mut gz = MultiGzDecoder::new(BufReader::with_capacity(1024 * 1024, Checked(f)));
+    io::copy(&mut gz, &mut io::sink()).err().map(|e| gzip_error_message(&e))
+}
+
+/// Problem description for an error raised while decompressing a gzip stream.
+pub fn gzip_error_message(e: &io::Error) -> String {
+    if e.kind() == io::ErrorKind::UnexpectedEof {
+        "truncated gzip stream".to_string()
+    } else {
+        format!("corrupt gzip data: {e}")
+    }
+}
+
+/// Feeds every byte read from `inner` into an md5 context.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_reads_and_bases() {
+        let fq = "@r1\nACGTACGT\n+\nFFFFFFFF\n@r2\nACGT\n+\nFFFF\n";
+        let stats = FastqStats::from_reader(fq.as_bytes()).unwrap();
+        assert_eq!(stats, FastqStats { reads: 2, bases: 12 });
+        assert_eq!(stats.mean_read_length(), 6.0);
+    }
+
+    #[test]
+    fn gzip_integrity_detects_truncation() {
+        use flate2::write::GzEncoder;
+        use flate2::Compression;
+        use std::io::Write;
+
+        let dir = tempfile::tempdir().unwrap();
+        let good = dir.path().join("good.fastq.gz");
+        let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+        gz.write_all("@r1\nACGT\n+\nFFFF\n".repeat(100).as_bytes()).unwrap();
+        let bytes = gz.finish().unwrap();
+        std::fs::write(&good, &bytes).unwrap();
+        assert_eq!(gzip_integrity_error(&good), None);
+
+        let truncated = dir.path().join("truncated.fastq.gz");
+        std::fs::write(&truncated, &bytes[..bytes.len() / 2]).unwrap();
+        assert!(gzip_integrity_error(&truncated).is_some());
+    }
+
+    #[test]
+    fn empty_stream_has_zero_mean() {
+        let stats = FastqStats::from_reader("".as_bytes()).unwrap();
+        assert_eq!(stats.reads, 0);
+        assert_eq!(stats.mean_read_length(), 0.0);
+    }
+}
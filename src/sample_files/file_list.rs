@@ -0,0 +1,189 @@
+// src/sample_files/file_list.rs
+//! `--file-list manifest.txt`: take the files from an explicit list (e.g. a LIMS export)
+//! instead of walking a directory.
+//!
+//! ```text
+//! # one path per line, relative paths are read from the folder of the list
+//! /proj/runA/exp1/sampleA_S1_L001_R1_001.fastq.gz
+//! exp2/sampleB_S2_L001_R1_001.fastq.gz
+//! ```
+//!
+//! Every path goes through `ParsedFile::from_path_with` as if the folder of the list (or
+//! `--file-list-root`) had been scanned, so the experiment is the first folder below it (or
+//! `--experiment`, `--experiment-depth`, ...). The walk filters (`--suffix`, `--exclude`,
+//! depth) do not apply: the list decides.
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::emit;
+use crate::error::{GeoPrepError, Result};
+use crate::sample_files::paths::portable_path;
+use crate::sample_files::sample_files::package_bundles;
+use crate::sample_files::{ParsedFile, SampleFiles};
+
+/// The listed paths with their line numbers; blank lines and `#` comments are skipped.
+pub fn read_file_list<P: AsRef<Path>>(path: P) -> Result<Vec<(usize, PathBuf)>> {
+    let path = path.as_ref();
+    let text = std::fs::read_to_string(path)?;
+    let base = path.parent().unwrap_or(Path::new(""));
+    Ok(text
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(i, line)| (i, base.join(line)))
+        .collect())
+}
+
+/// `path` as `root/<relative path>`, None if it is not below `root`. Both are compared
+/// canonicalized, so `../` and absolute list entries still match a relative root.
+fn below_root(root: &Path, canonical_root: &Path, path: &Path) -> Option<PathBuf> {
+    let rel = path.canonicalize().ok()?.strip_prefix(canonical_root).ok()?.to_path_buf();
+    Some(root.join(rel))
+}
+
+impl SampleFiles {
+    /// Add the files named in `list` (see the module docs); the experiments are read below
+    /// `root`, by default the folder of the list. A listed path that does not exist or lies
+    /// outside of the root is recorded as an issue, one that is no data file is reported;
+    /// returns (added, listed files).
+    pub fn ingest_file_list<P: AsRef<Path>>(&mut self, list: P, root: Option<&Path>) -> Result<(usize, usize)> {
+        let list = list.as_ref();
+        let scan_root = match root.or(list.parent()) {
+            Some(root) if !root.as_os_str().is_empty() => root.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        let canonical_root = scan_root.canonicalize()?;
+        let mut seen = HashSet::new();
+        let mut paths = Vec::new();
+        for (line, p) in read_file_list(list)? {
+            let message = if !p.is_file() {
+                format!("no such file: {}", p.display())
+            } else if let Some(p) = below_root(&scan_root, &canonical_root, &p) {
+                if seen.insert(p.clone()) {
+                    paths.push(p);
+                }
+                continue;
+            } else {
+                format!("not below the file list root {}: {}", scan_root.display(), p.display())
+            };
+            let e = GeoPrepError::InvalidInput { path: list.to_path_buf(), line, message };
+            eprintln!("WARN: {}", e);
+            self.issues.push(e.to_string());
+        }
+        eprintln!("Reading {} file(s) from {} (experiments below {})", paths.len(), list.display(), scan_root.display());
+
+        let options = self.options.clone();
+        let bundles: Vec<_> = paths.iter().filter_map(|p| ParsedFile::pending_tenx_bundle(&scan_root, p, &options)).collect();
+        package_bundles(&bundles, &options);
+
+        let mut scanned = Vec::new();
+        for p in &paths {
+            match ParsedFile::from_path_with(&scan_root, p, &options) {
+                Ok(Some(pf)) => {
+                    emit::file(&pf);
+                    scanned.push(Ok(pf));
+                }
+                Ok(None) => {
+                    let reason = ParsedFile::unclassified_reason(p, &options);
+                    eprintln!("WARN: listed file not used: {} ({})", p.display(), reason);
+                    if options.audit_unclassified {
+                        self.unclassified.push((portable_path(p), reason.to_string()));
+                    }
+                }
+                Err(e) => scanned.push(Err(GeoPrepError::Parse { path: p.clone(), source: Box::new(e) })),
+            }
+        }
+        let (parsed_ok, added, ignored_backup) = self.add_scanned(scanned, options.hash_jobs);
+
+        eprintln!(
+            "File list done. listed={} parsed={} added={} ignored_backup={} name_conflicts={}",
+            paths.len(),
+            parsed_ok,
+            added,
+            ignored_backup,
+            self.conflicting_basenames.len()
+        );
+        emit::scan_done(&scan_root, paths.len(), added);
+        Ok((added, paths.len()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sample_files::SampleKey;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    fn write_fastqs(dir: &Path, names: &[&str]) {
+        for name in names {
+            let path = dir.join(name);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            let mut enc = GzEncoder::new(std::fs::File::create(&path).unwrap(), Compression::default());
+            // distinct reads, so no file is dropped as a renamed copy of another
+            enc.write_all(format!("@{name}\nACGT\n+\nIIII\n").as_bytes()).unwrap();
+            enc.finish().unwrap();
+        }
+    }
+
+    #[test]
+    fn listed_files_are_added_without_a_walk() {
+        let tmp = tempfile::tempdir().unwrap();
+        let data_dir = tmp.path().join("data");
+        write_fastqs(
+            &data_dir,
+            &[
+                "exp1/sampleA_S1_L001_R1_001.fastq.gz",
+                "exp1/sampleA_S1_L001_R2_001.fastq.gz",
+                "exp1/sampleC_S3_L001_R1_001.fastq.gz",
+                "exp2/sampleB_S2_L001_R1_001.fastq.gz",
+            ],
+        );
+        let list = tmp.path().join("manifest.txt");
+        let listed = format!(
+            "# LIMS export\n{}\ndata/exp1/sampleA_S1_L001_R2_001.fastq.gz\n\ndata/exp2/sampleB_S2_L001_R1_001.fastq.gz\ndata/exp2/missing_S4_L001_R1_001.fastq.gz\n",
+            data_dir.join("exp1/sampleA_S1_L001_R1_001.fastq.gz").display()
+        );
+        std::fs::write(&list, listed).unwrap();
+
+        let mut data = SampleFiles::builder().build();
+        assert_eq!(data.ingest_file_list(&list, Some(&data_dir)).unwrap(), (3, 3));
+        let key = |experiment: &str, sample: &str| SampleKey { experiment: experiment.into(), sample: sample.into() };
+        assert_eq!(data.files_for_sample(&key("exp1", "sampleA")).len(), 2);
+        assert_eq!(data.files_for_sample(&key("exp2", "sampleB")).len(), 1);
+        // not listed, so not scanned
+        assert!(data.sample(&key("exp1", "sampleC")).is_none());
+        assert_eq!(data.issues.len(), 1);
+        assert!(data.issues[0].contains("line 6: no such file"));
+    }
+
+    #[test]
+    fn experiments_are_read_below_the_list_folder() {
+        let tmp = tempfile::tempdir().unwrap();
+        write_fastqs(
+            tmp.path(),
+            &[
+                "exp1/sA/sA_S1_L001_R1_001.fastq.gz",
+                "exp1/sB/sB_S2_L001_R1_001.fastq.gz",
+                "other/sC_S3_L001_R1_001.fastq.gz",
+            ],
+        );
+        // one experiment with a folder per sample: the shared folder of the files is exp1
+        let list = tmp.path().join("manifest.txt");
+        let listed = "exp1/sA/sA_S1_L001_R1_001.fastq.gz\nexp1/sB/sB_S2_L001_R1_001.fastq.gz\n";
+        std::fs::write(&list, listed).unwrap();
+        let mut data = SampleFiles::builder().build();
+        assert_eq!(data.ingest_file_list(&list, None).unwrap(), (2, 2));
+        let experiments: Vec<&str> = data.samples.keys().map(|k| k.experiment.as_str()).collect();
+        assert_eq!(experiments, ["exp1", "exp1"]);
+
+        // with an explicit root, files outside of it are reported
+        std::fs::write(&list, "exp1/sA/sA_S1_L001_R1_001.fastq.gz\nother/sC_S3_L001_R1_001.fastq.gz\n").unwrap();
+        let mut data = SampleFiles::builder().build();
+        assert_eq!(data.ingest_file_list(&list, Some(&tmp.path().join("exp1"))).unwrap(), (1, 1));
+        assert_eq!(data.samples.keys().next().unwrap().experiment, "sA");
+        assert!(data.issues[0].contains("line 2: not below the file list root"));
+    }
+}
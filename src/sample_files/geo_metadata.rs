@@ -0,0 +1,230 @@
+// src/sample_files/geo_metadata.rs
+use std::io::Write;
+use std::path::Path;
+
+use crate::error::Result;
+use crate::output::create_output;
+use crate::sample_files::{Confidence, SampleFiles};
+
+/// Placeholder for every value the submitter still has to provide.
+pub const FILL_IN: &str = "[fill in]";
+
+/// Series level values for the GEO metadata skeleton; unset fields become `[fill in]`.
+#[derive(Debug, Clone, Default)]
+pub struct GeoMetadata {
+    pub series_title: Option<String>,
+    pub organism: Option<String>,
+    pub library_strategy: Option<String>,
+    pub molecule: Option<String>,
+    pub instrument_model: Option<String>,
+}
+
+/// SAMPLES columns that `--attributes` columns of the same name fill instead of adding a
+/// `characteristics: <name>` column.
+const SAMPLE_FIELDS: [&str; 4] = ["title", "source name", "tissue", "description"];
+
+/// One sample of the SAMPLES section.
+struct SampleRow<'a> {
+    name: String,
+    organism: &'a str,
+    library_strategy: String,
+    attributes: Option<&'a [String]>,
+    processed: Vec<String>,
+    raw: Vec<String>,
+    layout: &'a str,
+}
+
+/// Attribute column name without a `characteristics:` prefix.
+fn attribute_field(key: &str) -> &str {
+    match key.get(..16) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("characteristics:") => key[16..].trim(),
+        _ => key.trim(),
+    }
+}
+
+fn or_fill(v: &Option<String>) -> &str {
+    v.as_deref().unwrap_or(FILL_IN)
+}
+
+impl SampleFiles {
+    /// Pre-filled skeleton of the GEO metadata spreadsheet: SERIES, SAMPLES (one row per sample
+    /// with layout and file names from the scan) and PROTOCOLS, as tab separated sections.
+    /// `--attributes` columns fill title, source name, tissue and description or become extra
+    /// `characteristics:` columns. Everything else the file system cannot tell is left as
+    /// `[fill in]`.
+    pub fn write_geo_metadata<P: AsRef<Path>>(&self, out_path: P, meta: &GeoMetadata) -> Result<()> {
+        let mut w = create_output(out_path)?;
+
+        writeln!(w, "SERIES")?;
+        writeln!(w, "title\t{}", or_fill(&meta.series_title))?;
+        for field in ["summary", "overall design", "contributor", "supplementary file"] {
+            writeln!(w, "{}\t{}", field, FILL_IN)?;
+        }
+        writeln!(w)?;
+
+        let rows: Vec<SampleRow> = self
+            .samples
+            .values()
+            .map(|rec| {
+                let (raw, processed): (Vec<_>, Vec<_>) = rec.all_paths().partition(|pf| pf.kind.is_raw());
+                let mut raw: Vec<String> = raw.iter().map(|pf| pf.geo_filename()).collect();
+                let mut processed: Vec<String> = processed.iter().map(|pf| pf.geo_filename()).collect();
+                raw.sort();
+                processed.sort();
+                let layout = if raw.is_empty() { "" } else { rec.library_layout() };
+                let organism = self
+                    .organism(&rec.experiment, &rec.name)
+                    .or(meta.organism.as_deref())
+                    .unwrap_or(FILL_IN);
+                // rule file entry, then --library-strategy, then the guess from the files
+                let library_strategy = match self.library_strategy(&rec.experiment, &rec.name) {
+                    Some((s, Confidence::Config)) => s,
+                    guess => meta.library_strategy.clone().or(guess.map(|(s, _)| s)).unwrap_or_else(|| FILL_IN.to_string()),
+                };
+                SampleRow {
+                    name: self.geo_sample_name(&rec.experiment, &rec.name),
+                    organism,
+                    library_strategy,
+                    attributes: self.options.sample_attributes.get(&rec.experiment, &rec.name),
+                    processed,
+                    raw,
+                    layout,
+                }
+            })
+            .collect();
+        let max_processed = rows.iter().map(|r| r.processed.len()).max().unwrap_or(0).max(1);
+        let max_raw = rows.iter().map(|r| r.raw.len()).max().unwrap_or(0).max(1);
+
+        // attribute columns: index of the one filling each SAMPLE_FIELDS column, the rest are
+        // added as characteristics after the tissue
+        let keys = &self.options.sample_attributes.keys;
+        let field_col = |field: &str| keys.iter().position(|k| attribute_field(k).eq_ignore_ascii_case(field));
+        let fixed: Vec<Option<usize>> = SAMPLE_FIELDS.iter().map(|f| field_col(f)).collect();
+        let extra: Vec<usize> = (0..keys.len()).filter(|i| !fixed.contains(&Some(*i))).collect();
+        let cell = |row: &SampleRow, col: Option<usize>| -> String {
+            match (row.attributes, col) {
+                (Some(values), Some(i)) => values.get(i).cloned().unwrap_or_default(),
+                _ => FILL_IN.to_string(),
+            }
+        };
+
+        writeln!(w, "SAMPLES")?;
+        write!(
+            w,
+            "Sample name\ttitle\tsource name\torganism\tcharacteristics: tissue\tmolecule\t\
+             single or paired-end\tlibrary strategy\tinstrument model\tdescription"
+        )?;
+        for &i in &extra {
+            write!(w, "\tcharacteristics: {}", attribute_field(&keys[i]))?;
+        }
+        for i in 1..=max_processed {
+            write!(w, "\tprocessed data file {}", i)?;
+        }
+        for i in 1..=max_raw {
+            write!(w, "\traw file {}", i)?;
+        }
+        writeln!(w)?;
+        for row in &rows {
+            write!(
+                w,
+                "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                row.name,
+                cell(row, fixed[0]),
+                cell(row, fixed[1]),
+                row.organism,
+                cell(row, fixed[2]),
+                or_fill(&meta.molecule),
+                row.layout,
+                row.library_strategy,
+                or_fill(&meta.instrument_model),
+                cell(row, fixed[3])
+            )?;
+            for &i in &extra {
+                write!(w, "\t{}", cell(row, Some(i)))?;
+            }
+            for i in 0..max_processed {
+                write!(w, "\t{}", row.processed.get(i).map(String::as_str).unwrap_or_default())?;
+            }
+            for i in 0..max_raw {
+                write!(w, "\t{}", row.raw.get(i).map(String::as_str).unwrap_or_default())?;
+            }
+            writeln!(w)?;
+        }
+        writeln!(w)?;
+
+        writeln!(w, "PROTOCOLS")?;
+        for field in [
+            "growth protocol",
+            "treatment protocol",
+            "extract protocol",
+            "library construction protocol",
+            "data processing step",
+            "genome build/assembly",
+            "processed data files format and content",
+        ] {
+            writeln!(w, "{}\t{}", field, FILL_IN)?;
+        }
+        w.commit()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sample_files::{ParsedFile, ParsedKind};
+
+    #[test]
+    fn samples_are_prefilled_from_the_scan() {
+        let mut data = SampleFiles::new();
+        for role in ["R1", "R2"] {
+            data.add_file(ParsedFile::test_file(
+                "exp1",
+                "sampleA",
+                ParsedKind::Fastq { lane: "L001".to_string(), role: role.to_string() },
+                &format!("exp1/sampleA_L001_{role}_001.fastq.gz"),
+            ));
+        }
+        let meta = GeoMetadata {
+            organism: Some("Mus musculus".to_string()),
+            library_strategy: Some("RNA-Seq".to_string()),
+            ..Default::default()
+        };
+        let tmp = tempfile::tempdir().unwrap();
+        let out = tmp.path().join("geo_metadata.tsv");
+        data.write_geo_metadata(&out, &meta).unwrap();
+        let text = std::fs::read_to_string(&out).unwrap();
+
+        assert!(text.starts_with("SERIES\ntitle\t[fill in]\n"));
+        let row = text.lines().find(|l| l.starts_with("sampleA\t")).unwrap();
+        let cells: Vec<&str> = row.split('\t').collect();
+        assert_eq!(cells[3], "Mus musculus");
+        assert_eq!(cells[6], "paired-end");
+        assert_eq!(cells[7], "RNA-Seq");
+        assert_eq!(&cells[11..], ["exp1_sampleA_L001_R1_001.fastq.gz", "exp1_sampleA_L001_R2_001.fastq.gz"]);
+        assert!(text.contains("PROTOCOLS\ngrowth protocol\t[fill in]\n"));
+
+        // the [organism] section of --sample-rules wins and adds a sample table column
+        data.options_mut().sample_rules.organism.samples.insert("exp1/sampleA".to_string(), "Homo sapiens".to_string());
+        data.write_geo_metadata(&out, &meta).unwrap();
+        let text = std::fs::read_to_string(&out).unwrap();
+        assert!(text.contains("\nsampleA\t[fill in]\t[fill in]\tHomo sapiens\t"));
+        let table = tmp.path().join("samples.tsv");
+        data.write_sample_files_as(&table, crate::table::TableStyle::default()).unwrap();
+        let text = std::fs::read_to_string(&table).unwrap();
+        assert!(text.starts_with("Source_Path(s)\tSample_Lane\tLayout\tOrganism\t"));
+        assert!(text.contains("\tsampleA\tpaired-end\tHomo sapiens\t"));
+
+        // --attributes fill the title and add characteristics columns to both tables
+        let attributes = tmp.path().join("attributes.tsv");
+        std::fs::write(&attributes, "sample\ttreatment\ttitle\nsampleA\tDMSO\tcontrol 1\n").unwrap();
+        data.options_mut().load_sample_attributes(&attributes).unwrap();
+        data.write_geo_metadata(&out, &meta).unwrap();
+        let text = std::fs::read_to_string(&out).unwrap();
+        assert!(text.contains("\tdescription\tcharacteristics: treatment\tprocessed data file 1\t"));
+        assert!(text.contains("\nsampleA\tcontrol 1\t[fill in]\tHomo sapiens\t"));
+        assert!(text.contains("\t[fill in]\tDMSO\t\texp1_sampleA_L001_R1_001.fastq.gz\t"));
+        data.write_sample_files_as(&table, crate::table::TableStyle::default()).unwrap();
+        let text = std::fs::read_to_string(&table).unwrap();
+        assert!(text.contains("\tsampleA\tpaired-end\tHomo sapiens\tDMSO\tcontrol 1\t"));
+    }
+}
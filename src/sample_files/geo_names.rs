@@ -0,0 +1,177 @@
+// src/sample_files/geo_names.rs
+//! GEO friendly sample and file names: ASCII letters, digits, `_`, `-` and `.`, at most
+//! `MAX_NAME_LEN` characters. `name_warnings` reports violations, `fix_names` (`--fix-names`)
+//! rewrites them and records every change for `*_name_fixes.tsv`.
+use std::collections::BTreeSet;
+use std::io::Write;
+use std::path::Path;
+
+use crate::error::Result;
+use crate::output::create_output;
+use crate::sample_files::{SampleFiles, ValidationWarning};
+
+/// Longest sample or file name that is accepted without a warning.
+pub const MAX_NAME_LEN: usize = 100;
+
+fn allowed(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.')
+}
+
+/// Why `name` is not a safe GEO name; empty if it is fine.
+pub fn name_problems(name: &str) -> Vec<&'static str> {
+    let mut out = Vec::new();
+    if name.contains(char::is_whitespace) {
+        out.push("spaces");
+    }
+    if !name.is_ascii() {
+        out.push("non-ASCII characters");
+    }
+    if name.chars().any(|c| c.is_ascii() && !c.is_whitespace() && !allowed(c)) {
+        out.push("characters other than letters, digits, '_', '-' and '.'");
+    }
+    if name.chars().count() > MAX_NAME_LEN {
+        out.push("more than 100 characters");
+    }
+    out
+}
+
+/// `name` with every run of other characters replaced by one `_` (no leading/trailing `_`),
+/// cut to `MAX_NAME_LEN` characters while keeping the extension:
+/// `Patient 3 (relapse)` -> `Patient_3_relapse`.
+pub fn sanitize_name(name: &str) -> String {
+    let mut out = String::with_capacity(name.len());
+    let mut replaced = false;
+    for c in name.chars() {
+        if allowed(c) && !(c == '_' && replaced) {
+            out.push(c);
+            replaced = false;
+        } else {
+            if !out.ends_with('_') {
+                out.push('_');
+            }
+            replaced = true;
+        }
+    }
+    let mut out = out.trim_matches('_').replace("_.", ".");
+    if out.len() > MAX_NAME_LEN {
+        let ext = match out.find('.') {
+            Some(dot) if out.len() - dot < MAX_NAME_LEN / 2 => out.split_off(dot),
+            _ => String::new(),
+        };
+        out.truncate(MAX_NAME_LEN - ext.len());
+        out = out.trim_end_matches('_').to_string() + &ext;
+    }
+    out
+}
+
+impl SampleFiles {
+    /// Sample and file names that would break a GEO upload (spaces, non-ASCII, special
+    /// characters, overlong names).
+    pub fn name_warnings(&self) -> Vec<ValidationWarning> {
+        let mut out = Vec::new();
+        for rec in self.samples.values() {
+            let warn = |what: &str, name: String| {
+                let problems = name_problems(&name);
+                (!problems.is_empty()).then(|| ValidationWarning {
+                    category: "geo_name".to_string(),
+                    experiment: rec.experiment.clone(),
+                    sample: rec.name.clone(),
+                    lane: String::new(),
+                    message: format!("{} name '{}' has {} (fix with --fix-names)", what, name, problems.join(", ")),
+                })
+            };
+            out.extend(warn("sample", self.geo_sample_name(&rec.experiment, &rec.name)));
+            out.extend(rec.all_paths().filter_map(|pf| warn("file", pf.geo_filename())));
+        }
+        out
+    }
+
+    /// `--fix-names`: sanitize experiment and sample names (the files move to the fixed sample)
+    /// and then every GEO file name that is still not safe. Each change is recorded once in
+    /// `name_fixes`; returns the number of changes.
+    pub fn fix_names(&mut self) -> usize {
+        let before = self.name_fixes.len();
+        let mut fixed: BTreeSet<(&'static str, String, String)> = BTreeSet::new();
+        let current: Vec<(String, String, String)> = self
+            .iter_all_parsed_files()
+            .into_iter()
+            .map(|pf| (pf.path.clone(), pf.experiment.clone(), pf.sample.clone()))
+            .collect();
+        for (path, experiment, sample) in current {
+            let (new_experiment, new_sample) = (sanitize_name(&experiment), sanitize_name(&sample));
+            if (&new_experiment, &new_sample) == (&experiment, &sample) {
+                continue;
+            }
+            if new_experiment != experiment {
+                fixed.insert(("experiment", experiment, new_experiment.clone()));
+            }
+            if new_sample != sample {
+                fixed.insert(("sample", sample, new_sample.clone()));
+            }
+            if let Some(mut pf) = self.remove_from_model(&path) {
+                pf.experiment = new_experiment;
+                pf.sample = new_sample;
+                self.add_file(pf);
+            }
+        }
+        self.name_fixes.extend(fixed.into_iter().map(|(what, from, to)| (what.to_string(), from, to)));
+
+        let mut file_fixes = Vec::new();
+        for pf in self.iter_all_parsed_files_mut() {
+            let name = pf.geo_filename();
+            if !name_problems(&name).is_empty() {
+                let new_name = sanitize_name(&name);
+                pf.geo_name = Some(new_name.clone());
+                file_fixes.push(("file".to_string(), name, new_name));
+            }
+        }
+        file_fixes.sort();
+        self.name_fixes.extend(file_fixes);
+        self.name_fixes.len() - before
+    }
+
+    /// Every change made by `fix_names`.
+    ///
+    /// Output columns:
+    /// Kind, Original, Fixed
+    pub fn write_name_fixes<P: AsRef<Path>>(&self, out_path: P) -> Result<usize> {
+        let mut w = create_output(out_path)?;
+        writeln!(w, "Kind\tOriginal\tFixed")?;
+        for (what, from, to) in &self.name_fixes {
+            writeln!(w, "{}\t{}\t{}", what, from, to)?;
+        }
+        w.commit()?;
+        Ok(self.name_fixes.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sample_files::{ParsedFile, ParsedKind, SampleKey};
+
+    #[test]
+    fn unsafe_names_are_reported_and_fixed() {
+        assert_eq!(sanitize_name("Patient 3 (relapse)"), "Patient_3_relapse");
+        assert_eq!(sanitize_name("Zelllinie_ä_R1 .fastq.gz"), "Zelllinie_R1.fastq.gz");
+        let long = sanitize_name(&format!("{}.fastq.gz", "x".repeat(150)));
+        assert_eq!((long.len(), long.ends_with("x.fastq.gz")), (MAX_NAME_LEN, true));
+
+        let mut data = SampleFiles::new();
+        data.add_file(ParsedFile::test_file(
+            "Patient 3 (relapse)",
+            "sampleA",
+            ParsedKind::Fastq { lane: "L001".to_string(), role: "R1".to_string() },
+            "Patient 3 (relapse)/sampleA_R1.fastq.gz",
+        ));
+        let warnings = data.name_warnings();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].message.starts_with("file name 'Patient 3 (relapse)_sampleA_R1.fastq.gz' has spaces, "));
+
+        assert_eq!(data.fix_names(), 1);
+        let key = SampleKey { experiment: "Patient_3_relapse".to_string(), sample: "sampleA".to_string() };
+        assert_eq!(data.files_for_sample(&key)[0].geo_filename(), "Patient_3_relapse_sampleA_R1.fastq.gz");
+        assert!(data.name_warnings().is_empty());
+        assert_eq!(data.name_fixes, [("experiment".to_string(), "Patient 3 (relapse)".to_string(), "Patient_3_relapse".to_string())]);
+    }
+}
@@ -0,0 +1,235 @@
+// src/sample_files/html_report.rs
+use std::fmt::Write as _;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use crate::error::Result;
+use crate::output::create_output;
+use crate::sample_files::archive::contents_path;
+use crate::sample_files::reports::human_bytes;
+use crate::sample_files::{ParsedFile, ParsedKind, SampleFiles};
+
+/// Click a header to sort by that column; cells with `data-v` sort numerically.
+const SORT_SCRIPT: &str = r#"<script>
+document.querySelectorAll("table.sortable th").forEach(function (th, col) {
+  th.addEventListener("click", function () {
+    var body = th.closest("table").tBodies[0];
+    var asc = th.dataset.dir !== "asc";
+    th.dataset.dir = asc ? "asc" : "desc";
+    var key = function (tr) { var c = tr.cells[col]; return c.dataset.v !== undefined ? Number(c.dataset.v) : c.textContent; };
+    Array.from(body.rows).sort(function (a, b) {
+      var x = key(a), y = key(b);
+      var r = (typeof x === "number") ? x - y : String(x).localeCompare(String(y));
+      return asc ? r : -r;
+    }).forEach(function (tr) { body.appendChild(tr); });
+  });
+});
+</script>"#;
+
+const STYLE: &str = "<style>\
+body{font-family:sans-serif;margin:2em}\
+table{border-collapse:collapse;margin-bottom:2em}\
+th,td{border:1px solid #ccc;padding:3px 8px;text-align:left}\
+th{background:#eee;cursor:pointer}\
+td.num{text-align:right}\
+.warn{color:#a40000}\
+</style>";
+
+/// Minimal HTML escaping for text and attribute values.
+fn esc(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// (files, bytes, files without md5) of a group of files.
+fn tally<'a>(files: impl Iterator<Item = &'a ParsedFile>) -> (usize, u64, usize) {
+    files.fold((0, 0, 0), |(n, bytes, no_md5), pf| {
+        (n + 1, bytes + pf.file_size(), no_md5 + usize::from(pf.md5sum.is_none()))
+    })
+}
+
+fn md5_status(files: usize, no_md5: usize) -> String {
+    if no_md5 == 0 {
+        "ok".to_string()
+    } else {
+        format!("{} of {} missing", no_md5, files)
+    }
+}
+
+impl SampleFiles {
+    /// Self-contained HTML overview for reviewers: one sortable row per sample lane (plus one
+    /// for the non-FASTQ files of a sample) with file counts, sizes, md5 status and findings,
+    /// followed by the validation warnings, the member listings of the 10x bundles and the
+    /// skipped Undetermined/Unmapped, empty and placeholder files.
+    /// With `--demux-stats` a Reads column shows the demultiplexer's count for each lane.
+    pub fn html_report(&self) -> String {
+        let warnings = self.validation_warnings();
+        let (n_files, n_bytes, _) = tally(self.samples.values().flat_map(|rec| rec.all_paths()));
+
+        let mut out = String::new();
+        let _ = writeln!(out, "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\">");
+        let _ = writeln!(out, "<title>GEO submission report</title>{}</head><body>", STYLE);
+        let _ = writeln!(out, "<h1>GEO submission report</h1>");
+        let _ = writeln!(
+            out,
+            "<p>{} samples, {} files, {} ({} bytes), {} warnings, {} skipped files</p>",
+            self.samples.len(),
+            n_files,
+            human_bytes(n_bytes),
+            n_bytes,
+            warnings.len(),
+            self.undetermined.len() + self.placeholders.len()
+        );
+
+        let with_reads = !self.demux_stats.is_empty();
+        let _ = writeln!(
+            out,
+            "<h2>Samples</h2>\n<table class=\"sortable\"><thead><tr>\
+            <th>Experiment</th><th>Sample</th><th>Lane</th><th>Files</th><th>Size</th>{}<th>MD5</th><th>Warnings</th>\
+            </tr></thead><tbody>",
+            if with_reads { "<th>Reads</th>" } else { "" }
+        );
+        for rec in self.samples.values() {
+            let mut rows: Vec<(String, (usize, u64, usize))> = rec
+                .lanes
+                .iter()
+                .map(|(lane_key, lane)| (lane_key.clone(), tally(lane.reads.values())))
+                .collect();
+            let other = tally(rec.all_paths().filter(|pf| !matches!(pf.kind, ParsedKind::Fastq { .. })));
+            if other.0 > 0 {
+                rows.push(("-".to_string(), other));
+            }
+            for (lane, (files, bytes, no_md5)) in rows {
+                let findings: Vec<&str> = warnings
+                    .iter()
+                    .filter(|w| w.experiment == rec.experiment && w.sample == rec.name)
+                    .filter(|w| w.lane == lane || (lane == "-" && w.lane.is_empty()))
+                    .map(|w| w.category.as_str())
+                    .collect();
+                let reads = match self.demux_stats.lane_reads(&rec.name, &lane) {
+                    Some(n) => format!("<td class=\"num\" data-v=\"{}\">{}</td>", n, n),
+                    None if with_reads => "<td class=\"num\" data-v=\"0\"></td>".to_string(),
+                    None => String::new(),
+                };
+                let _ = writeln!(
+                    out,
+                    "<tr><td>{}</td><td>{}</td><td>{}</td><td class=\"num\" data-v=\"{}\">{}</td>\
+                     <td class=\"num\" data-v=\"{}\">{}</td>{}<td>{}</td><td class=\"warn\">{}</td></tr>",
+                    esc(&rec.experiment),
+                    esc(&rec.name),
+                    esc(&lane),
+                    files,
+                    files,
+                    bytes,
+                    human_bytes(bytes),
+                    reads,
+                    md5_status(files, no_md5),
+                    esc(&findings.join(", "))
+                );
+            }
+        }
+        let _ = writeln!(out, "</tbody></table>");
+
+        let _ = writeln!(out, "<h2>Warnings</h2>\n<table class=\"sortable\"><thead><tr>\
+            <th>Category</th><th>Experiment</th><th>Sample</th><th>Lane</th><th>Message</th>\
+            </tr></thead><tbody>");
+        for w in &warnings {
+            let _ = writeln!(
+                out,
+                "<tr><td class=\"warn\">{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                esc(&w.category),
+                esc(&w.experiment),
+                esc(&w.sample),
+                esc(&w.lane),
+                esc(&w.message)
+            );
+        }
+        let _ = writeln!(out, "</tbody></table>");
+
+        let bundles: Vec<(&str, &ParsedFile)> = self
+            .samples
+            .values()
+            .flat_map(|rec| rec.tenx.values().map(move |pf| (rec.name.as_str(), pf)))
+            .filter(|(_, pf)| pf.is_tenx_bundle())
+            .collect();
+        if !bundles.is_empty() {
+            let _ = writeln!(out, "<h2>Bundle contents</h2>\n<table class=\"sortable\"><thead><tr>\
+                <th>Experiment</th><th>Sample</th><th>Bundle</th><th>Members</th><th>Listing</th>\
+                </tr></thead><tbody>");
+            for (sample, pf) in bundles {
+                let listing = contents_path(Path::new(&pf.path));
+                // header line excluded
+                let members = fs::read_to_string(&listing).map(|t| t.lines().count().saturating_sub(1)).ok();
+                let listing = self.public_path(&listing.to_string_lossy());
+                let _ = writeln!(
+                    out,
+                    "<tr><td>{}</td><td>{}</td><td>{}</td><td class=\"num\" data-v=\"{}\">{}</td><td>{}</td></tr>",
+                    esc(&pf.experiment),
+                    esc(sample),
+                    esc(&pf.geo_filename()),
+                    members.unwrap_or(0),
+                    members.map(|n| n.to_string()).unwrap_or_default(),
+                    match members {
+                        Some(_) if listing.is_empty() => String::new(),
+                        Some(_) => format!("<a href=\"{}\">{}</a>", esc(&listing), esc(&listing)),
+                        None => "<span class=\"warn\">missing</span>".to_string(),
+                    }
+                );
+            }
+            let _ = writeln!(out, "</tbody></table>");
+        }
+
+        let _ = writeln!(out, "<h2>Skipped files</h2>\n<table class=\"sortable\"><thead><tr>\
+            <th>Experiment</th><th>Path</th><th>Size</th><th>Reason</th>\
+            </tr></thead><tbody>");
+        let mut skipped: Vec<(&ParsedFile, &str)> = self.undetermined.iter().map(|pf| (pf, "Undetermined/Unmapped")).collect();
+        skipped.extend(self.placeholders.iter().map(|(pf, reason)| (pf, reason.as_str())));
+        skipped.sort_by(|a, b| a.0.path.cmp(&b.0.path));
+        for (pf, reason) in skipped {
+            let bytes = pf.file_size();
+            let _ = writeln!(
+                out,
+                "<tr><td>{}</td><td>{}</td><td class=\"num\" data-v=\"{}\">{}</td><td>{}</td></tr>",
+                esc(&pf.experiment),
+                esc(&self.public_path(&pf.path)),
+                bytes,
+                human_bytes(bytes),
+                esc(reason)
+            );
+        }
+        let _ = writeln!(out, "</tbody></table>\n{}\n</body></html>", SORT_SCRIPT);
+        out
+    }
+
+    /// Write `html_report()` to `out_path` (e.g. `<prefix>_report.html`).
+    pub fn write_html_report<P: AsRef<Path>>(&self, out_path: P) -> Result<()> {
+        let mut w = create_output(out_path)?;
+        w.write_all(self.html_report().as_bytes())?;
+        w.commit()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn report_lists_lanes_and_escapes_names() {
+        let mut data = SampleFiles::new();
+        for role in ["R1", "R2"] {
+            data.add_file(ParsedFile::test_file(
+                "exp1",
+                "a<b",
+                ParsedKind::Fastq { lane: "L001".to_string(), role: role.to_string() },
+                &format!("exp1/a_L001_{role}_001.fastq.gz"),
+            ));
+        }
+        let html = data.html_report();
+        assert!(html.contains("<td>a&lt;b</td><td>L001</td>"));
+        assert!(html.contains("2 of 2 missing"));
+        assert!(!html.contains("a<b"));
+    }
+}
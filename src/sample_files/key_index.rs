@@ -0,0 +1,135 @@
+// src/sample_files/key_index.rs
+//! Compact bookkeeping for very large trees: strings are reduced to 128-bit keys, and the
+//! set of visited paths can spill sorted runs of keys to disk (`--spill-index`).
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// 128-bit key of a string (its md5); collisions are not a practical concern.
+pub fn key128(s: &[u8]) -> u128 {
+    u128::from_le_bytes(md5::compute(s).0)
+}
+
+/// One sorted run of keys on disk, 16 little endian bytes per key.
+struct Run {
+    file: File,
+    path: PathBuf,
+    len: u64,
+}
+
+impl Run {
+    fn write(keys: &mut [u128]) -> io::Result<Self> {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        keys.sort_unstable();
+        let path = std::env::temp_dir().join(format!(
+            "rust-geo-prep-{}-{}.keys",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::SeqCst)
+        ));
+        let mut w = BufWriter::new(File::create(&path)?);
+        for key in keys.iter() {
+            w.write_all(&key.to_le_bytes())?;
+        }
+        w.flush()?;
+        let file = File::open(&path)?;
+        Ok(Run { file, path, len: keys.len() as u64 })
+    }
+
+    fn contains(&mut self, key: u128) -> io::Result<bool> {
+        let (mut lo, mut hi) = (0u64, self.len);
+        let mut buf = [0u8; 16];
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            self.file.seek(SeekFrom::Start(mid * 16))?;
+            self.file.read_exact(&mut buf)?;
+            match u128::from_le_bytes(buf).cmp(&key) {
+                std::cmp::Ordering::Equal => return Ok(true),
+                std::cmp::Ordering::Less => lo = mid + 1,
+                std::cmp::Ordering::Greater => hi = mid,
+            }
+        }
+        Ok(false)
+    }
+}
+
+impl Drop for Run {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// A set of 128-bit keys. With a memory limit the keys are written to sorted temporary runs
+/// whenever the limit is reached, so memory stays flat; lookups then also binary search the
+/// runs on disk. If spilling fails the keys simply stay in memory.
+#[derive(Default)]
+pub struct KeySet {
+    mem: HashSet<u128>,
+    limit: Option<usize>,
+    runs: Vec<Run>,
+}
+
+impl KeySet {
+    /// Keep at most `limit` keys in memory (None = no limit, nothing goes to disk).
+    pub fn with_limit(limit: Option<usize>) -> Self {
+        KeySet { limit: limit.filter(|&n| n > 0), ..Default::default() }
+    }
+
+    pub fn contains(&mut self, key: u128) -> bool {
+        self.mem.contains(&key) || self.runs.iter_mut().any(|run| run.contains(key).unwrap_or(false))
+    }
+
+    /// Add `key`; false if it was present already.
+    pub fn insert(&mut self, key: u128) -> bool {
+        if self.contains(key) {
+            return false;
+        }
+        self.mem.insert(key);
+        if self.limit.is_some_and(|limit| self.mem.len() >= limit) {
+            let mut keys: Vec<u128> = self.mem.iter().copied().collect();
+            match Run::write(&mut keys) {
+                Ok(run) => {
+                    self.runs.push(run);
+                    self.mem = HashSet::new();
+                }
+                Err(e) => {
+                    eprintln!("WARN: can not spill the path index to disk ({e}); keeping it in memory");
+                    self.limit = None;
+                }
+            }
+        }
+        true
+    }
+
+    pub fn len(&self) -> usize {
+        self.mem.len() + self.runs.iter().map(|run| run.len as usize).sum::<usize>()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spilled_keys_are_still_found() {
+        let mut set = KeySet::with_limit(Some(4));
+        for i in 0..10u32 {
+            assert!(set.insert(key128(format!("/data/file{i}").as_bytes())));
+        }
+        assert_eq!(set.runs.len(), 2);
+        assert_eq!(set.len(), 10);
+        for i in 0..10u32 {
+            assert!(!set.insert(key128(format!("/data/file{i}").as_bytes())));
+        }
+        assert!(!set.contains(key128(b"/data/file10")));
+
+        let paths: Vec<PathBuf> = set.runs.iter().map(|run| run.path.clone()).collect();
+        drop(set);
+        assert!(paths.iter().all(|p| !p.exists()));
+    }
+}
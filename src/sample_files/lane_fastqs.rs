@@ -1,25 +1,90 @@
 //lane_fastqs.rs
 
-use std::collections::{BTreeMap};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+
+use crate::sample_files::DuplicateRole;
 use crate::ParsedFile;
 
+/// Read roles recognised in every FASTQ name, in table order.
+pub const DEFAULT_ROLES: [&str; 4] = ["I1", "I2", "R1", "R2"];
+
+/// Stable role order: I1/I2/R1/R2 first, then user-configured roles in the given order,
+/// then anything else alphabetically. Later chunks (`R1_002`) follow their role.
+pub fn order_roles(mut roles: BTreeSet<String>, extra_roles: &[String]) -> Vec<String> {
+    let mut out = Vec::new();
+    for r in DEFAULT_ROLES.iter().copied().chain(extra_roles.iter().map(|s| s.as_str())) {
+        if roles.remove(r) {
+            out.push(r.to_string());
+        }
+        let chunks: Vec<String> = roles.iter().filter(|c| c.as_str() != r && base_role(c) == r).cloned().collect();
+        for c in chunks {
+            roles.remove(&c);
+            out.push(c);
+        }
+    }
+    out.extend(roles);
+    out
+}
+
+/// Read role without the chunk of a later Illumina part (`R1_002` -> `R1`).
+pub fn base_role(role: &str) -> &str {
+    match role.rsplit_once('_') {
+        Some((base, chunk)) if chunk.len() == 3 && chunk.chars().all(|c| c.is_ascii_digit()) => base,
+        _ => role,
+    }
+}
+
+/// R1/R2 gz files whose smaller file is below this fraction of the larger one are reported.
+/// Kept generous because barcode reads (10x R1) are legitimately much shorter than R2.
+pub const PAIR_MIN_SIZE_RATIO: f64 = 0.25;
+
 #[derive(Debug, Default)]
 pub struct LaneFastqs {
-    pub reads: BTreeMap<String, ParsedFile>,
+    pub(crate) reads: BTreeMap<String, ParsedFile>,
+    /// FASTQs dropped because their role was already taken in this lane
+    pub(crate) duplicates: Vec<ParsedFile>,
 }
 
 impl LaneFastqs {
-    /// Add a FASTQ for a lane under a specific role (R1/R2/I1/...)
+    /// role -> FASTQ
+    pub fn reads(&self) -> &BTreeMap<String, ParsedFile> {
+        &self.reads
+    }
+
+    /// FASTQs ignored because their role was already taken in this lane.
+    pub fn duplicates(&self) -> &[ParsedFile] {
+        &self.duplicates
+    }
+
+    /// Add a FASTQ for a lane under a specific role (R1/R2/I1/...); a second file for the
+    /// role is ignored (see `add_read_with`).
     pub fn add_read(&mut self, role: &str, path: ParsedFile) {
-        if let Some(existing) = self.reads.get(role) {
-            eprintln!(
-                "Duplicate read role '{}' for lane: already have '{}', tried to add '{}' - file is ignored!",
-                role, existing.path, path.path
-            );
-        }else {
-           self.reads.insert(role.to_string(), path); 
+        self.add_read_with(role, path, DuplicateRole::KeepFirst)
+    }
+
+    /// `add_read` where `strategy` decides which file keeps an already taken role; the other
+    /// one goes to `duplicates`.
+    pub fn add_read_with(&mut self, role: &str, path: ParsedFile, strategy: DuplicateRole) {
+        let Some(existing) = self.reads.get(role) else {
+            self.reads.insert(role.to_string(), path);
+            return;
+        };
+        let modified = |pf: &ParsedFile| fs::metadata(&pf.path).and_then(|md| md.modified()).ok();
+        let replace = match strategy {
+            // `Split` ends up here only if the runs could not be told apart
+            DuplicateRole::KeepFirst | DuplicateRole::Error | DuplicateRole::Split => false,
+            DuplicateRole::KeepNewest => modified(&path) > modified(existing),
+            DuplicateRole::KeepLargest => path.file_size() > existing.file_size(),
+        };
+        let (kept, dropped) = if replace { (&path.path, &existing.path) } else { (&existing.path, &path.path) };
+        eprintln!("Duplicate read role '{}' for lane: keeping '{}', '{}' is ignored!", role, kept, dropped);
+        if replace {
+            let old = self.reads.insert(role.to_string(), path).expect("role was taken");
+            self.duplicates.push(old);
+        } else {
+            self.duplicates.push(path);
         }
-        
     }
 
     /// Render FASTQ cells for this lane in the provided `roles` order.
@@ -29,17 +94,44 @@ impl LaneFastqs {
     {
         roles
             .iter()
-            .map(|role| self.reads.get(role).map(|p| fmt(&p) ).unwrap_or_default())
+            .map(|role| self.reads.get(role).map(fmt).unwrap_or_default())
             .collect()
     }
 
-    /// Render FASTQ cells for this lane as simple paired info row
-    pub fn pair_row(&self ) -> Vec<String> 
-    {
-        self.reads
-            .values()
-            .map(|p| p.geo_filename() )
-            .collect()
+    /// "paired-end" if this lane has both R1 and R2, "single" otherwise.
+    pub fn layout(&self) -> &'static str {
+        if self.reads.contains_key("R1") && self.reads.contains_key("R2") {
+            "paired-end"
+        } else {
+            "single"
+        }
     }
 
-}
\ No newline at end of file
+    /// Compare R1 and R2 of this lane and describe a likely truncated/mismatched pair.
+    /// Uses read counts when both files have `--stats`, otherwise the file sizes
+    /// with `min_size_ratio` as tolerance. Returns None for consistent or unpaired lanes.
+    pub fn pairing_issue(&self, min_size_ratio: f64) -> Option<String> {
+        let r1 = self.reads.get("R1")?;
+        let r2 = self.reads.get("R2")?;
+
+        if let (Some(s1), Some(s2)) = (&r1.stats, &r2.stats) {
+            if s1.reads != s2.reads {
+                return Some(format!(
+                    "read count mismatch: R1 has {} reads, R2 has {} reads ({} / {})",
+                    s1.reads, s2.reads, r1.basename(), r2.basename()
+                ));
+            }
+            return None;
+        }
+
+        let (size1, size2) = (r1.file_size(), r2.file_size());
+        let (small, large) = if size1 < size2 { (size1, size2) } else { (size2, size1) };
+        if large > 0 && (small as f64) < (large as f64) * min_size_ratio {
+            return Some(format!(
+                "file size mismatch: R1 is {} bytes, R2 is {} bytes ({} / {})",
+                size1, size2, r1.basename(), r2.basename()
+            ));
+        }
+        None
+    }
+}
@@ -0,0 +1,127 @@
+// src/sample_files/library_strategy.rs
+//! Suggested library strategy per sample, guessed from the files found: fragments files
+//! mean ATAC-Seq, 10x matrices and single cell objects scRNA-Seq, transcript quantifications
+//! RNA-Seq and peak files ChIP-Seq (or ATAC-Seq if the folders say so). Folder and file
+//! names (`atac`, `chip`, `scrna`, `rnaseq`, ...) are the weak fallback. The
+//! `[library_strategy]` section of `--sample-rules` replaces the guess.
+use crate::sample_files::{ParsedKind, SampleFiles, SampleKey, SampleRecord};
+
+/// How much a library strategy can be trusted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Confidence {
+    /// set in the rule file
+    Config,
+    /// file types that only one strategy produces
+    High,
+    /// folder or file names only
+    Low,
+}
+
+impl Confidence {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Confidence::Config => "config",
+            Confidence::High => "high",
+            Confidence::Low => "low",
+        }
+    }
+}
+
+/// Strategy named by the folder and file names of `rec`, from lower case alphanumeric tokens.
+fn strategy_from_names(rec: &SampleRecord) -> Option<&'static str> {
+    let mut found = None;
+    for pf in rec.all_paths() {
+        let lower = pf.path.to_ascii_lowercase();
+        for token in lower.split(|c: char| !c.is_ascii_alphanumeric()) {
+            let strategy = if token.contains("atac") {
+                "ATAC-Seq"
+            } else if token.starts_with("chip") || token == "cutandrun" || token == "cutntag" {
+                "ChIP-Seq"
+            } else if token.starts_with("scrna") || token.starts_with("snrna") {
+                "scRNA-Seq"
+            } else if token == "rna" || token.starts_with("rnaseq") {
+                "RNA-Seq"
+            } else {
+                continue;
+            };
+            // the more specific single cell name wins over plain RNA-Seq
+            if found.is_none() || (found == Some("RNA-Seq") && strategy == "scRNA-Seq") {
+                found = Some(strategy);
+            }
+        }
+    }
+    found
+}
+
+/// Library strategy of `rec` from its files.
+fn infer(rec: &SampleRecord) -> Option<(&'static str, Confidence)> {
+    let processed = |formats: &[&str]| {
+        rec.processed.values().any(|pf| match &pf.kind {
+            ParsedKind::ProcessedMatrix { format } | ParsedKind::Track { format } => formats.contains(&format.as_str()),
+            _ => false,
+        })
+    };
+    let named = strategy_from_names(rec);
+    if !rec.fragments.is_empty() {
+        Some(("ATAC-Seq", Confidence::High))
+    } else if !rec.tenx.is_empty() || !rec.h5_files.is_empty() || processed(&["h5ad", "loom"]) {
+        Some(("scRNA-Seq", Confidence::High))
+    } else if processed(&["salmon", "kallisto", "featureCounts"]) {
+        Some(("RNA-Seq", Confidence::High))
+    } else if processed(&["narrowPeak", "broadPeak"]) {
+        match named {
+            Some("ATAC-Seq") => Some(("ATAC-Seq", Confidence::High)),
+            Some("ChIP-Seq") => Some(("ChIP-Seq", Confidence::High)),
+            _ => Some(("ChIP-Seq", Confidence::Low)),
+        }
+    } else {
+        named.map(|s| (s, Confidence::Low))
+    }
+}
+
+impl SampleFiles {
+    /// Library strategy of a sample: the `[library_strategy]` rule file entry or the guess
+    /// from its files, None if there is no evidence.
+    pub fn library_strategy(&self, experiment: &str, sample: &str) -> Option<(String, Confidence)> {
+        if let Some(s) = self.options.sample_rules.library_strategy.lookup(experiment, sample) {
+            return Some((s.to_string(), Confidence::Config));
+        }
+        let rec = self.samples.get(&SampleKey { experiment: experiment.to_string(), sample: sample.to_string() })?;
+        infer(rec).map(|(s, c)| (s.to_string(), c))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sample_files::ParsedFile;
+
+    fn file(sample: &str, path: &str, kind: ParsedKind) -> ParsedFile {
+        ParsedFile::test_file("exp1", sample, kind, path)
+    }
+
+    #[test]
+    fn strategy_from_file_types_then_folder_names() {
+        let fastq = || ParsedKind::Fastq { lane: "L001".to_string(), role: "R1".to_string() };
+        let peaks = ParsedKind::Track { format: "narrowPeak".to_string() };
+        let mut data = SampleFiles::new();
+        data.add_file(file("atac1", "exp1/atac1/outs/fragments.tsv.gz", ParsedKind::Fragments));
+        data.add_file(file("rna1", "exp1/rna1/outs/filtered_feature_bc_matrix.h5", ParsedKind::H5));
+        data.add_file(file("h3k27", "exp1/chipseq/h3k27_peaks.narrowPeak", peaks.clone()));
+        data.add_file(file("peaks", "exp1/peaks/peaks_peaks.narrowPeak", peaks));
+        data.add_file(file("bulk", "exp1/RNAseq_2023/bulk_S1_L001_R1_001.fastq.gz", fastq()));
+        data.add_file(file("plain", "exp1/plain_S1_L001_R1_001.fastq.gz", fastq()));
+        let strategy = |sample: &str| data.library_strategy("exp1", sample).map(|(s, c)| (s, c.as_str()));
+
+        assert_eq!(strategy("atac1"), Some(("ATAC-Seq".to_string(), "high")));
+        assert_eq!(strategy("rna1"), Some(("scRNA-Seq".to_string(), "high")));
+        assert_eq!(strategy("h3k27"), Some(("ChIP-Seq".to_string(), "high")));
+        assert_eq!(strategy("peaks"), Some(("ChIP-Seq".to_string(), "low")));
+        assert_eq!(strategy("bulk"), Some(("RNA-Seq".to_string(), "low")));
+        assert_eq!(strategy("plain"), None);
+
+        data.options_mut().sample_rules.library_strategy.experiments.insert("exp1".to_string(), "Bisulfite-Seq".to_string());
+        let (strategy, confidence) = data.library_strategy("exp1", "plain").unwrap();
+        assert_eq!((strategy.as_str(), confidence), ("Bisulfite-Seq", Confidence::Config));
+    }
+}
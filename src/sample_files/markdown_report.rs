@@ -0,0 +1,123 @@
+// src/sample_files/markdown_report.rs
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::Write;
+use std::path::Path;
+
+use crate::error::Result;
+use crate::output::create_output;
+use crate::sample_files::reports::human_bytes;
+use crate::sample_files::{SampleFiles, SampleRecord};
+
+/// `|` would end a table cell early.
+fn cell(s: &str) -> String {
+    s.replace('|', "\\|")
+}
+
+impl SampleFiles {
+    /// Per-experiment Markdown summary (samples, FASTQs per lane, 10x/H5 bundles, totals)
+    /// for pasting into electronic lab notebooks or GitHub issues.
+    pub fn markdown_report(&self) -> String {
+        let mut per_exp: BTreeMap<&str, Vec<&SampleRecord>> = BTreeMap::new();
+        for rec in self.samples.values() {
+            per_exp.entry(rec.experiment.as_str()).or_default().push(rec);
+        }
+
+        let mut out = String::new();
+        let mut total = (0usize, 0u64);
+        let _ = writeln!(out, "# GEO submission overview\n");
+        for (experiment, records) in &per_exp {
+            let _ = writeln!(out, "## {}\n", experiment);
+            let _ = writeln!(out, "| Sample | Lane | FASTQs | 10x / H5 | Files | Size |");
+            let _ = writeln!(out, "|---|---|---|---|---:|---:|");
+            let mut exp_total = (0usize, 0u64);
+            for rec in records {
+                let mut bundles: Vec<String> = rec.tenx.values().map(|pf| pf.geo_filename()).collect();
+                bundles.extend(rec.h5_files.values().map(|pf| pf.geo_filename()));
+
+                let mut rows: Vec<(String, String, usize, u64)> = rec
+                    .lanes
+                    .iter()
+                    .map(|(lane_key, lane)| {
+                        let roles = lane.reads.keys().cloned().collect::<Vec<_>>().join(", ");
+                        let bytes = lane.reads.values().map(|pf| pf.file_size()).sum();
+                        (lane_key.clone(), roles, lane.reads.len(), bytes)
+                    })
+                    .collect();
+                let lane_files: usize = rows.iter().map(|r| r.2).sum();
+                let lane_bytes: u64 = rows.iter().map(|r| r.3).sum();
+                let (all_files, all_bytes) =
+                    rec.all_paths().fold((0, 0), |(n, b), pf| (n + 1, b + pf.file_size()));
+                if rows.is_empty() || all_files > lane_files {
+                    rows.push(("-".to_string(), String::new(), all_files - lane_files, all_bytes - lane_bytes));
+                }
+
+                for (i, (lane, roles, files, bytes)) in rows.iter().enumerate() {
+                    let (sample, tenx) = if i == 0 {
+                        (cell(&rec.name), cell(&bundles.join(", ")))
+                    } else {
+                        (String::new(), String::new())
+                    };
+                    let _ = writeln!(
+                        out,
+                        "| {} | {} | {} | {} | {} | {} |",
+                        sample, cell(lane), roles, tenx, files, human_bytes(*bytes)
+                    );
+                }
+                exp_total.0 += all_files;
+                exp_total.1 += all_bytes;
+            }
+            let _ = writeln!(
+                out,
+                "\n{} samples, {} files, {}\n",
+                records.len(),
+                exp_total.0,
+                human_bytes(exp_total.1)
+            );
+            total.0 += exp_total.0;
+            total.1 += exp_total.1;
+        }
+        let _ = writeln!(
+            out,
+            "**Total:** {} experiments, {} samples, {} files, {}",
+            per_exp.len(),
+            self.samples.len(),
+            total.0,
+            human_bytes(total.1)
+        );
+        out
+    }
+
+    /// Write `markdown_report()` to `out_path` (e.g. `<prefix>_report.md`).
+    pub fn write_markdown_report<P: AsRef<Path>>(&self, out_path: P) -> Result<()> {
+        let mut w = create_output(out_path)?;
+        w.write_all(self.markdown_report().as_bytes())?;
+        w.commit()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sample_files::{ParsedFile, ParsedKind};
+
+    fn pf(sample: &str, kind: ParsedKind, path: &str) -> ParsedFile {
+        ParsedFile::test_file("exp1", sample, kind, path)
+    }
+
+    #[test]
+    fn lanes_and_bundles_are_listed_per_experiment() {
+        let mut data = SampleFiles::new();
+        for role in ["R1", "R2"] {
+            let kind = ParsedKind::Fastq { lane: "L001".to_string(), role: role.to_string() };
+            data.add_file(pf("sampleA", kind, &format!("exp1/sampleA_L001_{role}_001.fastq.gz")));
+        }
+        data.add_file(pf("sampleA", ParsedKind::H5, "exp1/sampleA/outs/filtered_feature_bc_matrix.h5"));
+
+        let md = data.markdown_report();
+        assert!(md.contains("## exp1"));
+        assert!(md.contains("| sampleA | L001 | R1, R2 | exp1_sampleA_filtered_feature_bc_matrix.h5 | 2 |"));
+        assert!(md.contains("|  | - |  |  | 1 |"));
+        assert!(md.contains("**Total:** 1 experiments, 1 samples, 3 files"));
+    }
+}
@@ -0,0 +1,170 @@
+// src/sample_files/merge_plan.rs
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::error::Result;
+use crate::output::create_output;
+use crate::sample_files::checksum::Md5Writer;
+use crate::sample_files::lane_fastqs::{base_role, order_roles};
+use crate::sample_files::SampleFiles;
+
+/// All lanes of one sample and read role that GEO would like to receive as a single FASTQ.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeGroup {
+    /// GEO sample name (experiment-prefixed if sample ids overlap)
+    pub sample: String,
+    pub role: String,
+    /// `<sample>_<role>.fastq.gz`
+    pub output_name: String,
+    /// source FASTQs in lane order
+    pub sources: Vec<String>,
+}
+
+impl SampleFiles {
+    /// Group the FASTQs of every sample by read role, lanes in sorted lane order and the
+    /// chunks of a lane (`R1`, `R1_002`) in chunk order.
+    pub fn merge_groups(&self) -> Vec<MergeGroup> {
+        let mut out = Vec::new();
+        for rec in self.samples.values() {
+            let sample = self.geo_sample_name(&rec.experiment, &rec.name);
+            let roles = rec.all_roles_sorted(&self.options.extra_roles);
+            let bases = roles.iter().map(|r| base_role(r).to_string()).collect();
+            for role in order_roles(bases, &self.options.extra_roles) {
+                let sources: Vec<String> = rec
+                    .lane_keys_sorted()
+                    .iter()
+                    .flat_map(|lane| rec.lanes[lane].reads.iter())
+                    .filter(|(r, _)| base_role(r) == role)
+                    .map(|(_, pf)| pf.path.clone())
+                    .collect();
+                if sources.is_empty() {
+                    continue;
+                }
+                out.push(MergeGroup {
+                    output_name: format!("{}_{}.fastq.gz", sample, role),
+                    sample: sample.clone(),
+                    role,
+                    sources,
+                });
+            }
+        }
+        out.sort_by(|a, b| a.output_name.cmp(&b.output_name));
+        out
+    }
+
+    /// Bash script concatenating the lanes of each sample/role into DEST
+    /// (gzip members can simply be concatenated) and writing `merged_md5sum.tsv` in DEST.
+    pub fn write_merge_plan_sh<P: AsRef<Path>>(&self, script_path: P, dest: &str) -> Result<()> {
+        let mut w = create_output(script_path)?;
+
+        writeln!(w, "#!/usr/bin/env bash")?;
+        writeln!(w, "set -euo pipefail")?;
+        writeln!(w, "DEST=\"{}\"", dest)?;
+        writeln!(w, "mkdir -p \"$DEST\"")?;
+        writeln!(w, "MD5_TABLE=\"$DEST/merged_md5sum.tsv\"")?;
+        // md5sum on Linux, md5 on macOS
+        writeln!(w, "md5_of() {{ if command -v md5sum >/dev/null; then md5sum \"$1\" | cut -d' ' -f1; else md5 -q \"$1\"; fi; }}")?;
+        writeln!(w, "printf 'file_name\\tmd5sum\\n' > \"$MD5_TABLE\"")?;
+        writeln!(w)?;
+
+        for g in self.merge_groups() {
+            writeln!(w, "## {} {} ({} lane(s))", g.sample, g.role, g.sources.len())?;
+            let srcs: Vec<String> = g.sources.iter().map(|s| format!("\"{}\"", s)).collect();
+            writeln!(w, "cat {} > \"$DEST/{}\"", srcs.join(" "), g.output_name)?;
+            writeln!(
+                w,
+                "printf '%s\\t%s\\n' \"{0}\" \"$(md5_of \"$DEST/{0}\")\" >> \"$MD5_TABLE\"",
+                g.output_name
+            )?;
+            writeln!(w)?;
+        }
+        w.commit()
+    }
+
+    /// Perform the merge natively: concatenate every group into `dest` and write the md5
+    /// table of the merged files to `md5_table`. Returns the number of merged files.
+    pub fn merge_lanes<P: AsRef<Path>, Q: AsRef<Path>>(&self, dest: P, md5_table: Q) -> Result<usize> {
+        let dest = dest.as_ref();
+        fs::create_dir_all(dest)?;
+
+        let groups = self.merge_groups();
+        let mut rows: Vec<(String, String)> = Vec::with_capacity(groups.len());
+        for g in &groups {
+            let md5 = concat_with_md5(&g.sources, &dest.join(&g.output_name))?;
+            rows.push((g.output_name.clone(), md5));
+        }
+
+        let mut w = create_output(md5_table)?;
+        writeln!(w, "file_name\tmd5sum")?;
+        for (name, md5) in rows {
+            writeln!(w, "{}\t{}", name, md5)?;
+        }
+        w.commit()?;
+        Ok(groups.len())
+    }
+}
+
+/// Concatenate `sources` into `target` (via a tmp file) and return the md5 of the result.
+fn concat_with_md5(sources: &[String], target: &Path) -> io::Result<String> {
+    let tmp = target.with_extension("gz.tmp");
+    let mut out = Md5Writer::new(BufWriter::with_capacity(1024 * 1024, File::create(&tmp)?));
+    for src in sources {
+        io::copy(&mut File::open(src)?, &mut out)?;
+    }
+    let (file, md5) = out.finish()?;
+    drop(file);
+    fs::rename(&tmp, target)?;
+    Ok(md5)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::MultiGzDecoder;
+    use std::io::Read;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    fn write_gz(path: &Path, content: &str) {
+        let mut enc = GzEncoder::new(File::create(path).unwrap(), Compression::default());
+        enc.write_all(content.as_bytes()).unwrap();
+        enc.finish().unwrap();
+    }
+
+    #[test]
+    fn lanes_are_merged_per_sample_and_role() {
+        let tmp = tempfile::tempdir().unwrap();
+        let exp = tmp.path().join("exp1");
+        fs::create_dir_all(&exp).unwrap();
+        write_gz(&exp.join("sampleA_S1_L001_R1_001.fastq.gz"), "@a\nACGT\n+\nIIII\n");
+        write_gz(&exp.join("sampleA_S1_L002_R1_001.fastq.gz"), "@b\nTTTT\n+\nIIII\n");
+        write_gz(&exp.join("sampleA_S1_L001_R2_001.fastq.gz"), "@a\nGGGG\n+\nIIII\n");
+        // the second chunk of lane 1 goes right after the first one
+        write_gz(&exp.join("sampleA_S1_L001_R1_002.fastq.gz"), "@c\nCCCC\n+\nIIII\n");
+
+        let mut data = SampleFiles::new();
+        data.ingest_dir(tmp.path(), &[".fastq.gz".to_string()], &[]).unwrap();
+
+        let groups = data.merge_groups();
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].output_name, "sampleA_R1.fastq.gz");
+        assert_eq!(groups[0].sources.len(), 3);
+        assert!(groups[0].sources[0].contains("L001"));
+        assert!(groups[0].sources[1].ends_with("L001_R1_002.fastq.gz"));
+
+        let dest = tmp.path().join("merged");
+        let table = tmp.path().join("merged_md5sum.tsv");
+        assert_eq!(data.merge_lanes(&dest, &table).unwrap(), 2);
+
+        let mut text = String::new();
+        MultiGzDecoder::new(File::open(dest.join("sampleA_R1.fastq.gz")).unwrap())
+            .read_to_string(&mut text)
+            .unwrap();
+        assert_eq!(text, "@a\nACGT\n+\nIIII\n@c\nCCCC\n+\nIIII\n@b\nTTTT\n+\nIIII\n");
+
+        let md5 = format!("{:x}", md5::compute(fs::read(dest.join("sampleA_R1.fastq.gz")).unwrap()));
+        let table = fs::read_to_string(table).unwrap();
+        assert!(table.contains(&format!("sampleA_R1.fastq.gz\t{}", md5)));
+    }
+}
@@ -1,10 +1,67 @@
 // src/sample_files/mod.rs
 pub mod parsed_file;
+#[allow(clippy::module_inception)]
 pub mod sample_files;
 pub mod lane_fastqs;
 pub mod sample_record;
+pub mod scan_options;
+pub mod fastq_stats;
+pub mod fastq_header;
+pub mod triplet;
+pub mod validation;
+pub mod reports;
+pub mod html_report;
+pub mod markdown_report;
+pub mod geo_metadata;
+pub mod sra_metadata;
+pub mod ena_manifest;
+pub mod pipeline_sheet;
+pub mod merge_plan;
+pub mod rename_plan;
+pub mod state;
+pub mod builder;
+pub mod scan_iter;
+pub mod checksum;
+pub mod archive;
+pub mod clean;
+pub mod sample_rules;
+pub mod plates;
+pub mod sample_sheet;
+pub mod demux_stats;
+pub mod paths;
+pub mod column_layout;
+pub mod key_index;
+pub mod walk;
+pub mod amend;
+pub mod geo_names;
+pub mod anonymize;
+pub mod attributes;
+pub mod library_strategy;
+pub mod empty_files;
+pub mod containers;
+pub mod file_list;
 
 pub use parsed_file::{ParsedFile, ParsedKind};
 pub use sample_files::{SampleFiles, SampleKey};
 pub use lane_fastqs::LaneFastqs;
-pub use sample_record::SampleRecord;
\ No newline at end of file
+pub use sample_record::SampleRecord;
+pub use scan_options::{
+    BundleFormat, DedupPolicy, DuplicateRole, Md5Store, RecompressTo, ScanOptions, SymlinkPolicy, TenxMode,
+};
+pub use fastq_stats::FastqStats;
+pub use fastq_header::FastqHeader;
+pub use checksum::HashIo;
+pub use validation::ValidationWarning;
+pub use geo_metadata::GeoMetadata;
+pub use pipeline_sheet::PipelineSheet;
+pub use merge_plan::MergeGroup;
+pub use state::{ScanState, StateDiff};
+pub use builder::SampleFilesBuilder;
+pub use scan_iter::ScanIter;
+pub use sample_rules::SampleRules;
+pub use sample_sheet::{SampleSheet, SampleSheets};
+pub use demux_stats::DemuxStats;
+pub use amend::AmendSummary;
+pub use attributes::SampleAttributes;
+pub use library_strategy::Confidence;
+pub use containers::Container;
\ No newline at end of file
@@ -1,37 +1,175 @@
 // src/sample_files/parsed_file.rs
 use std::fs;
-use std::fs::File;
-use std::io::{self, BufRead, BufReader, Read};
+use std::io;
 use std::path::{Component, Path, PathBuf};
 
 
 use walkdir::WalkDir;
-use std::io::Write;
 
+use crate::error::{GeoPrepError, Result};
+use crate::emit;
+use crate::interrupt;
+use crate::sample_files::{BundleFormat, FastqHeader, FastqStats, HashIo, Md5Store, RecompressTo, ScanOptions, TenxMode};
+use crate::sample_files::archive::{bundle_dir, contents_path, gzip_file, write_contents};
+use crate::sample_files::containers::{gzip_name, Container};
+use crate::sample_files::checksum::{
+    md5_file_with, preview_md5, read_md5sums, read_sidecar, update_md5sums, write_sidecar, CachedMd5,
+    MD5SUMS_FILE, PENDING_MD5,
+};
+use crate::sample_files::fastq_stats::{gzip_error_message, gzip_integrity_error};
+use crate::sample_files::lane_fastqs::DEFAULT_ROLES;
+use crate::sample_files::paths::portable_path;
+use crate::sample_files::triplet::triplet_dimension_issue;
+
+/// Single cell library types that are part of the FASTQ sample name (`sampleA_HTO_S5_...`
+/// is sample `sampleA_HTO`), so the libraries of one biological sample do not merge.
+pub const LIBRARY_TOKENS: [&str; 7] = ["GEX", "HTO", "ADT", "ATAC", "CITE", "VDJ", "CRISPR"];
 
 #[derive(Debug, Clone)]
 pub enum ParsedKind {
-    TenX,
+    /// Packaged 10x matrix triplet; `bundle` is the label of the triplet folder (`filtered`, `raw`, ...),
+    /// `format` the archive type (`zip`, `tar.gz`). With `TenxMode::Individual` every member is
+    /// its own file: `bundle` is `<label>_<member>` (`filtered_barcodes`), `format` its extension.
+    TenX { bundle: String, format: String },
     H5,
     Fastq { lane: String, role: String },
+    /// CellRanger-ATAC/ARC fragments file (`fragments.tsv.gz`) or its tabix index (`.tbi`).
+    Fragments,
+    /// BAM/CRAM used as raw data (or its index); `format` is the file extension (bam, cram, bai, crai, csi).
+    /// Only produced with `ScanOptions::include_bam`.
+    Alignment { format: String },
+    /// PacBio HiFi/subreads BAM or CCS FASTQ; `movie` is the movie name (`m64012_220101_123456`),
+    /// `format` the data type suffix (`hifi_reads.bam`, `subreads.bam`, `ccs.fastq.gz`).
+    PacBio { movie: String, format: String },
+    /// Other processed data (h5ad, loom, RDS, CSV count matrices); `format` is the file type label.
+    ProcessedMatrix { format: String },
+    /// ChIP-/ATAC-seq signal track or peak call (bigWig, bedGraph, narrowPeak, broadPeak).
+    Track { format: String },
+}
+
+impl ParsedKind {
+    /// GEO splits a submission into raw files and processed data files.
+    /// FASTQs and BAM/CRAM files are raw; 10x bundles, H5 files and other matrices are processed.
+    pub fn is_processed(&self) -> bool {
+        !self.is_raw()
+    }
+
+    pub fn is_raw(&self) -> bool {
+        matches!(
+            self,
+            ParsedKind::Fastq { .. } | ParsedKind::Alignment { .. } | ParsedKind::PacBio { .. }
+        )
+    }
+
+    /// File type as reported in the GEO file tables.
+    pub fn file_type(&self) -> String {
+        match self {
+            ParsedKind::TenX { format, .. } => format!("10x mtx ({})", format),
+            ParsedKind::H5 => "h5".to_string(),
+            ParsedKind::Fastq { .. } => "fastq".to_string(),
+            ParsedKind::Fragments => "fragments".to_string(),
+            ParsedKind::Alignment { format } => format.clone(),
+            ParsedKind::PacBio { format, .. } => {
+                if format.ends_with(".bam") { "bam".to_string() } else { "fastq".to_string() }
+            }
+            ParsedKind::ProcessedMatrix { format } => format.clone(),
+            ParsedKind::Track { format } => format.clone(),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct ParsedFile {
-    pub sample: String,
-    pub experiment: String,
-    pub kind: ParsedKind,
-    pub path: String,            // authoritative source path
-    pub md5sum: Option<String>,  // computed for files; None for dirs until archived
+    pub(crate) sample: String,
+    pub(crate) experiment: String,
+    pub(crate) kind: ParsedKind,
+    pub(crate) path: String,            // authoritative source path
+    pub(crate) md5sum: Option<String>,  // computed for files; None for dirs until archived
+    pub(crate) preview_md5: Option<String>, // `--preview-hash` digest while the md5 is pending
+    pub(crate) stats: Option<FastqStats>, // read statistics, only with --stats
+    pub(crate) header: Option<FastqHeader>, // first read header, only with --read-headers
+    pub(crate) gzip_errors: Vec<String>,  // corrupt/truncated gzip payloads, only with --validate-gzip
+    pub(crate) triplet_issue: Option<String>, // 10x triplet whose matrix dimensions do not match its barcodes/features
+    pub(crate) md5_store: Md5Store,       // where computed md5 sums are cached
+    pub(crate) trust_md5_cache: bool,     // use cached md5 sums even if size/mtime changed
+    pub(crate) hash_io: HashIo,           // read buffer, mmap threshold and deferred hashing
+    pub(crate) geo_name: Option<String>,  // upload name chosen after the scan, replaces `<experiment>_<name>`
 }
 
 impl ParsedFile {
 
-     fn tenx_zip_path(dir: &Path) -> Option<PathBuf> {
-        // put zip next to the directory, name it "<dirname>.zip"
+    // ---------- read-only accessors ----------
+
+    pub fn sample(&self) -> &str {
+        &self.sample
+    }
+
+    pub fn experiment(&self) -> &str {
+        &self.experiment
+    }
+
+    pub fn kind(&self) -> &ParsedKind {
+        &self.kind
+    }
+
+    /// Authoritative source path (the zip for 10x bundles).
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// md5 hex digest; None for directories and until computed.
+    pub fn md5sum(&self) -> Option<&str> {
+        self.md5sum.as_deref()
+    }
+
+    /// md5 for the tables: the digest, `PENDING` for a file whose hashing was deferred
+    /// (`--reuse-md5-only`, `--preview-hash`) or `none`.
+    pub fn md5_display(&self) -> &str {
+        match self.md5sum.as_deref() {
+            Some(md5) => md5,
+            None if self.hash_io.deferred() && !Path::new(&self.path).is_dir() => PENDING_MD5,
+            None => "none",
+        }
+    }
+
+    /// What the duplicate detector compares: the md5, else the `--preview-hash` digest.
+    pub(crate) fn dedup_key(&self) -> Option<String> {
+        self.md5sum.clone().or_else(|| self.preview_md5.as_ref().map(|p| format!("preview:{}", p)))
+    }
+
+    /// Read statistics, only with `ScanOptions::stats`.
+    pub fn stats(&self) -> Option<&FastqStats> {
+        self.stats.as_ref()
+    }
+
+    /// Instrument/run/flowcell/lane of the first read, only with `ScanOptions::read_headers`.
+    pub fn header(&self) -> Option<&FastqHeader> {
+        self.header.as_ref()
+    }
+
+    /// Mismatch between matrix dimensions and barcode/feature counts of a 10x triplet.
+    pub fn triplet_issue(&self) -> Option<&str> {
+        self.triplet_issue.as_deref()
+    }
+
+    /// Corrupt/truncated gzip payloads, only with `ScanOptions::validate_gzip`.
+    pub fn gzip_errors(&self) -> &[String] {
+        &self.gzip_errors
+    }
+
+    /// Archive path of a triplet folder: `<dirname>.zip` / `.tar.gz` next to the folder, or with
+    /// `options.bundle_dir` in the same relative location below that staging folder.
+    fn tenx_bundle_path(dir: &Path, scan_root: &Path, options: &ScanOptions) -> Option<PathBuf> {
         let parent = dir.parent().unwrap_or(dir);
         let name = Self::tenx_sample_label(dir)?;
-        Some(parent.join(format!("{name}.zip")))
+        let file_name = format!("{name}.{}", options.bundle_format.extension());
+        match &options.bundle_dir {
+            Some(staging) => {
+                let rel = parent.strip_prefix(scan_root).unwrap_or(Path::new(""));
+                Some(staging.join(rel).join(file_name))
+            }
+            None => Some(parent.join(file_name)),
+        }
     }
 
     fn find_ancestor_dir_named<'a>(start: &'a Path, marker: &str) -> Option<&'a Path> {
@@ -45,7 +183,7 @@ impl ParsedFile {
         }
         None
     }
-    fn folder_above_marker(start: &Path, marker: &str) -> Option<String> {
+    pub(crate) fn folder_above_marker(start: &Path, marker: &str) -> Option<String> {
         let marker_dir = Self::find_ancestor_dir_named(start, marker)?;
         let parent = marker_dir.parent()?;
 
@@ -55,91 +193,149 @@ impl ParsedFile {
             .map(|s| s.to_string())
     }
 
-    fn tenx_sample_label(triplet_dir: &std::path::Path) -> Option<String> {
+    /// Name of the folder directly below `marker` on the way down to `start`
+    /// (e.g. the sample folder in `outs/per_sample_outs/<sample>/count`).
+    pub(crate) fn folder_below_marker(start: &Path, marker: &str) -> Option<String> {
+        let mut below: Option<&Path> = None;
+        let mut cur = Some(start);
+
+        while let Some(p) = cur {
+            if p.file_name().and_then(|s| s.to_str()) == Some(marker) {
+                return below
+                    .and_then(|b| b.file_name())
+                    .and_then(|s| s.to_str())
+                    .map(|s| s.to_string());
+            }
+            below = Some(p);
+            cur = p.parent();
+        }
+        None
+    }
+
+    /// Sample name for CellRanger outputs.
+    /// `cellranger multi` nests results as `<run>/outs/per_sample_outs/<sample>/count/...`,
+    /// all other pipelines use `<sample>/outs/...`.
+    fn cellranger_sample(p: &Path) -> Option<String> {
+        Self::folder_below_marker(p, "per_sample_outs")
+            .or_else(|| Self::folder_above_marker(p, "outs"))
+    }
+
+    /// For `cellranger multi` outputs the run folder (above `outs`) is the experiment.
+    fn cellranger_multi_run(p: &Path) -> Option<String> {
+        Self::find_ancestor_dir_named(p, "per_sample_outs")?;
+        Self::folder_above_marker(p, "outs")
+    }
+
+    /// Bundle label of a triplet folder: `filtered`, `raw`, or the folder name itself (e.g. `velocyto`).
+    fn tenx_bundle_label(triplet_dir: &Path) -> Option<String> {
         let leaf = triplet_dir.file_name()?.to_str()?;
 
-        let suffix = match leaf {
-            "filtered_feature_bc_matrix" => "filtered",
-            "raw_feature_bc_matrix" => "raw",
+        let label = match leaf {
+            "filtered_feature_bc_matrix" | "sample_filtered_feature_bc_matrix" => "filtered",
+            "raw_feature_bc_matrix" | "sample_raw_feature_bc_matrix" => "raw",
             _ => leaf,
         };
+        Some(label.to_string())
+    }
 
-        let sample = Self::folder_above_marker(triplet_dir, "outs")?;
+    fn tenx_sample_label(triplet_dir: &std::path::Path) -> Option<String> {
+        let suffix = Self::tenx_bundle_label(triplet_dir)?;
+        let sample = Self::cellranger_sample(triplet_dir)?;
         Some(format!("{sample}_{suffix}"))
     }
 
-    fn materialize_tenx_zip(dir: &Path) -> io::Result<PathBuf> {
+    /// Every archive a scan may have written next to the triplet folder `dir` (zip, tar.gz,
+    /// their interrupted `.tmp` files and contents listings); empty if `dir` is not a triplet folder.
+    pub(crate) fn tenx_bundle_artifacts(dir: &Path) -> Vec<PathBuf> {
+        if !Self::looks_like_10x_triplet_dir(dir).unwrap_or(false) {
+            return Vec::new();
+        }
+        let (Some(parent), Some(name)) = (dir.parent(), Self::tenx_sample_label(dir)) else {
+            return Vec::new();
+        };
+        [BundleFormat::Zip, BundleFormat::TarGz]
+            .iter()
+            .flat_map(|f| {
+                let file = format!("{name}.{}", f.extension());
+                [
+                    parent.join(&file),
+                    parent.join(format!("{file}.tmp")),
+                    parent.join(format!("{file}.contents.tsv")),
+                ]
+            })
+            .collect()
+    }
 
-        use zip::write::FileOptions;
-        use zip::CompressionMethod;
+    /// `(triplet dir, archive path)` if `p` is a member of a triplet that will be packaged by
+    /// this scan and the archive does not exist yet (jobs for `archive::bundle_dirs`).
+    pub(crate) fn pending_tenx_bundle(scan_root: &Path, p: &Path, options: &ScanOptions) -> Option<(PathBuf, PathBuf)> {
+        if options.tenx_mode != TenxMode::Bundle {
+            return None;
+        }
+        let dir = Self::tenx_triplet_dir_from_file(p)?;
+        if !Self::looks_like_10x_triplet_dir(&dir).ok()? || !options.sample_selected(&Self::cellranger_sample(p)?) {
+            return None;
+        }
+        let out = Self::tenx_bundle_path(&dir, scan_root, options)?;
+        let exists = fs::metadata(&out).map(|md| md.is_file() && md.len() > 0).unwrap_or(false);
+        (!exists).then_some((dir, out))
+    }
 
-        let opts: FileOptions<()> = FileOptions::default()
-            .compression_method(CompressionMethod::Deflated)
-            .unix_permissions(0o644);
-        let zip_path = match Self::tenx_zip_path(dir){
+    fn materialize_tenx_bundle(dir: &Path, scan_root: &Path, options: &ScanOptions) -> io::Result<PathBuf> {
+        let zip_path = match Self::tenx_bundle_path(dir, scan_root, options) {
             Some(p) => p,
             None => {
                 eprintln!("This path is not a 10x matrix triplet path: {}", dir.display() );
                 return Err(io::Error::new(
                         io::ErrorKind::Unsupported,
-                        "materialize_tenx_zip could not identify a usable file name",
+                        "materialize_tenx_bundle could not identify a usable file name",
                     ))
             }
         };
 
-        // reuse if already exists and has some content
+        // reuse if already exists and has some content (bundles of older runs get their listing)
         if let Ok(md) = fs::metadata(&zip_path) {
             if md.is_file() && md.len() > 0 {
+                if !contents_path(&zip_path).is_file() {
+                    write_contents(dir, &zip_path)?;
+                }
                 return Ok(zip_path);
             }
         }
 
-        // write to tmp then rename (avoid partial zips on crash)
-        let tmp_path = zip_path.with_extension("zip.tmp");
+        bundle_dir(dir, &zip_path, options.bundle_format, options.zip_level)?;
+        Ok(zip_path)
+    }
 
-        // ensure parent exists
-        if let Some(par) = zip_path.parent() {
-            fs::create_dir_all(par)?;
+    /// Whether a FASTQ in `container` is uploaded as a gzip copy (`--compress-plain`,
+    /// `--recompress-to gz`).
+    fn transcoded(container: Container, options: &ScanOptions) -> bool {
+        match container {
+            Container::Gzip => false,
+            Container::Plain => options.compress_plain || options.recompress_to == RecompressTo::Gz,
+            _ => options.recompress_to == RecompressTo::Gz,
         }
+    }
 
-        // create zip
-        let f = File::create(&tmp_path)?;
-        let mut zw = zip::ZipWriter::new(f);
-
-        for entry in WalkDir::new(dir).follow_links(false).into_iter().filter_map(Result::ok) {
-            let p = entry.path();
-
-            // skip the dir itself
-            if p == dir {
-                continue;
-            }
-
-            let rel = p.strip_prefix(dir).unwrap_or(p);
-            let rel_str = rel.to_string_lossy().replace('\\', "/"); // zip wants forward slashes
-
-            if entry.file_type().is_dir() {
-                // add directory entry (optional but fine)
-                zw.add_directory(rel_str, opts)?;
-            } else if entry.file_type().is_file() {
-                zw.start_file(rel_str, opts)?;
-
-                let mut rf = File::open(p)?;
-                let mut buf = vec![0u8; 1024 * 1024];
-                loop {
-                    let n = rf.read(&mut buf)?;
-                    if n == 0 { break; }
-                    zw.write_all(&buf[..n])?;
-                }
-            }
+    /// Gzip copy of a plain/bzip2/xz/zstd FASTQ (`x.fastq.bz2` -> `x.fastq.gz`), next to it or
+    /// with `options.bundle_dir` in the same relative location below that staging folder.
+    /// A copy that is newer than the FASTQ is reused.
+    fn gzip_copy(p: &Path, container: Container, scan_root: &Path, options: &ScanOptions) -> io::Result<PathBuf> {
+        let parent = p.parent().unwrap_or(Path::new(""));
+        let name = gzip_name(&p.file_name().map(|n| n.to_string_lossy()).unwrap_or_default());
+        let out = match &options.bundle_dir {
+            Some(staging) => staging.join(parent.strip_prefix(scan_root).unwrap_or(Path::new(""))).join(name),
+            None => parent.join(name),
+        };
+        let up_to_date = match (fs::metadata(p), fs::metadata(&out)) {
+            (Ok(src), Ok(copy)) => copy.is_file() && copy.len() > 0 && copy.modified()? >= src.modified()?,
+            _ => false,
+        };
+        if !up_to_date {
+            eprintln!("Compressing {} -> {}", p.display(), out.display());
+            gzip_file(p, container, &out)?;
         }
-
-        zw.finish()?; // flush/close
-
-        // replace old zip if present
-        let _ = fs::remove_file(&zip_path);
-        fs::rename(&tmp_path, &zip_path)?;
-
-        Ok(zip_path)
+        Ok(out)
     }
 
     fn looks_like_public_accession(fname: &str) -> bool {
@@ -181,28 +377,69 @@ impl ParsedFile {
         false
     }
 
+    /// The accession heuristic applied to the file name of `p`; index files contain ".bam.",
+    /// so they are checked by the name of their data file.
+    fn accession_like_file(p: &Path) -> bool {
+        let name = p.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+        Self::looks_like_public_accession(&Self::strip_index_suffix(&name))
+    }
+
     /// One entrypoint: decide if path is relevant, classify, infer sample+experiment, compute md5 if file.
-    pub fn from_path(scan_root: &Path, p: &Path) -> io::Result<Option<Self>> {
-        let md = match fs::metadata(p) {
-            Ok(m) => m,
-            Err(e) => return Err(e),
-        };
+    pub fn from_path(scan_root: &Path, p: &Path) -> Result<Option<Self>> {
+        Self::from_path_with(scan_root, p, &ScanOptions::default())
+    }
+
+    /// Like `from_path`, but honours the opt-in classifications of `options`.
+    pub fn from_path_with(scan_root: &Path, p: &Path, options: &ScanOptions) -> Result<Option<Self>> {
+        let md = fs::metadata(p)?;
 
-        let (effective_path ,kind) = if md.is_file() {
+        let alignment = if options.include_bam { Self::alignment_format(p) } else { None };
+
+        let (tenx_dir, kind) = if md.is_file() {
 
             let s = p.to_string_lossy();
-            if Self::looks_like_public_accession( &s ) {
-                // ignore public/archive-derived artifacts (SRR/ERR/DRR..., bam->fastq, annotated, etc.)
+            // ignore public/archive-derived artifacts (SRR/ERR/DRR..., bam->fastq, annotated, etc.)
+            if !options.keep_accession_like && Self::accession_like_file(p) {
+                eprintln!("Skipped accession-like file (use --keep-accession-like to keep): {}", p.display());
                 return Ok(None);
-            } else if s.ends_with(".fastq.gz") || s.ends_with(".fq.gz") {
-                let (lane, role) = Self::parse_fastq_lane_role(p)?;
+            }
+            if let Some((movie, format)) = Self::pacbio_movie_format(p) {
+                (None, ParsedKind::PacBio { movie, format: format.to_string() })
+            } else if let Some(format) = alignment {
+                (None, ParsedKind::Alignment { format: format.to_string() })
+            } else if let Some(container) = Container::of_fastq(p) {
+                if container != Container::Gzip && Path::new(&gzip_name(&s)).is_file() {
+                    eprintln!("Skipped {} FASTQ next to its gzipped version: {}", container.as_str(), p.display());
+                    return Ok(None);
+                }
+                let (lane, role) = Self::parse_fastq_lane_role(p, &options.extra_roles, options.plate_wells)?;
                 ( None, ParsedKind::Fastq { lane, role })
+            } else if let Some(format) = Self::track_format(p) {
+                (None, ParsedKind::Track { format: format.to_string() })
+            } else if let Some(format) = Self::processed_format(p) {
+                // before the H5 check: kallisto writes abundance.h5
+                (None, ParsedKind::ProcessedMatrix { format: format.to_string() })
             } else if s.ends_with(".h5") {
                 (None, ParsedKind::H5)
+            } else if Self::is_fragments_file(p) {
+                (None, ParsedKind::Fragments)
             } else if let Some(dir) = Self::tenx_triplet_dir_from_file(p) {
                 if Self::looks_like_10x_triplet_dir(&dir)? {
-                    let zip_path = Self::materialize_tenx_zip(&dir)?;
-                    (Some(zip_path), ParsedKind::TenX)
+                    // checked and zipped once the sample is known to be selected
+                    let bundle = Self::tenx_bundle_label(&dir).unwrap_or_default();
+                    let kind = match options.tenx_mode {
+                        TenxMode::Bundle => {
+                            let format = options.bundle_format.extension().to_string();
+                            ParsedKind::TenX { bundle, format }
+                        }
+                        TenxMode::Individual => {
+                            // barcodes.tsv.gz -> bundle `filtered_barcodes`, format `tsv.gz`
+                            let name = p.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+                            let (member, format) = name.split_once('.').unwrap_or((name, ""));
+                            ParsedKind::TenX { bundle: format!("{bundle}_{member}"), format: format.to_string() }
+                        }
+                    };
+                    (Some(dir), kind)
                 } else {
                     return Ok(None);
                 }
@@ -214,18 +451,87 @@ impl ParsedFile {
             return Ok(None);
         };
 
-        let sample = Self::detect_sample(&kind, p).ok_or_else(|| {
-            io::Error::new(
-                io::ErrorKind::InvalidData,
-                format!("Could not infer sample for path {}", p.display()),
-            )
-        })?;
-
-        let experiment = Self::first_component_under_root(scan_root, p)
-        .expect("Please start this tool from the path containing your experiments in (unique) subfolders");
-        let path = match effective_path {
-            Some(p) => p.to_string_lossy().to_string(),
-            None => p.to_string_lossy().to_string()
+        let sample = match &kind {
+            ParsedKind::PacBio { movie, .. } => {
+                let mapped = options.pacbio_movie_samples.get(movie).cloned();
+                if mapped.is_none()
+                    && !options.pacbio_movie_samples.is_empty()
+                    && Self::is_pacbio_movie_name(movie)
+                {
+                    eprintln!("WARN: PacBio movie '{}' is not in the movie map - using the movie as sample: {}", movie, p.display());
+                }
+                mapped
+            }
+            _ => None,
+        };
+        let sample = sample
+            .or_else(|| options.sample_rules.sample_for(&kind, p))
+            .or_else(|| match kind {
+                ParsedKind::Fastq { .. } => Self::sample_sheet_sample(p, options),
+                _ => None,
+            })
+            .or_else(|| match kind {
+                ParsedKind::Fastq { .. } if options.plate_wells => Self::plate_well_sample(p),
+                _ => None,
+            })
+            .or_else(|| Self::detect_sample(&kind, p))
+            .ok_or_else(|| GeoPrepError::NoSample { path: p.to_path_buf() })?;
+        // `[merge_samples]`: the lanes of a merged library stay apart as `<detected>_<lane>`
+        let (sample, kind) = match (options.sample_rules.merged_name(&sample), kind) {
+            (Some(geo), ParsedKind::Fastq { lane, role }) => {
+                (geo.to_string(), ParsedKind::Fastq { lane: format!("{sample}_{lane}"), role })
+            }
+            (Some(geo), kind) => (geo.to_string(), kind),
+            (None, kind) => (sample, kind),
+        };
+        // `[[split_samples]]`: lanes/flowcells of a reused library name become their own sample
+        let sample = match &kind {
+            ParsedKind::Fastq { lane, .. } => options
+                .sample_rules
+                .split_name(&sample, lane, || FastqHeader::from_file(p).ok().flatten().map(|h| h.flowcell))
+                .map(str::to_string)
+                .unwrap_or(sample),
+            _ => sample,
+        };
+        if !options.sample_selected(&sample) {
+            return Ok(None);
+        }
+
+        let experiment = Self::detect_experiment(scan_root, p, options)
+        .ok_or_else(|| GeoPrepError::NoExperiment { root: scan_root.to_path_buf(), path: p.to_path_buf() })?;
+
+        let mut gzip_errors: Vec<String> = Vec::new();
+        let mut triplet_issue: Option<String> = None;
+        let path = match tenx_dir {
+            Some(dir) => {
+                let bundled = options.tenx_mode == TenxMode::Bundle;
+                // individual members: report the triplet once, on the matrix
+                if bundled || p.file_name().is_some_and(|n| n == "matrix.mtx.gz") {
+                    triplet_issue = triplet_dimension_issue(&dir);
+                    if let Some(issue) = &triplet_issue {
+                        eprintln!("WARN: inconsistent 10x triplet {}: {}", dir.display(), issue);
+                    }
+                }
+                if bundled {
+                    if options.validate_gzip {
+                        gzip_errors = Self::gzip_errors_in_dir(&dir);
+                    }
+                    portable_path(&Self::materialize_tenx_bundle(&dir, scan_root, options)?)
+                } else {
+                    portable_path(p)
+                }
+            }
+            None => match Container::of_fastq(p).filter(|c| *c != Container::Gzip) {
+                Some(container) if Self::transcoded(container, options) => {
+                    portable_path(&Self::gzip_copy(p, container, scan_root, options)?)
+                }
+                Some(container) => {
+                    let hint = if container == Container::Plain { "--compress-plain" } else { "--recompress-to gz" };
+                    eprintln!("WARN: {} FASTQ, GEO requires gzip (use {}): {}", container.as_str(), hint, p.display());
+                    portable_path(p)
+                }
+                None => portable_path(p),
+            },
         };
 
         let mut pf = ParsedFile {
@@ -234,12 +540,197 @@ impl ParsedFile {
             kind,
             path,
             md5sum: None,
+            preview_md5: None,
+            stats: None,
+            header: None,
+            gzip_errors,
+            triplet_issue,
+            md5_store: options.md5_store,
+            trust_md5_cache: options.trust_cache,
+            hash_io: options.hash_io,
+            geo_name: None,
         };
 
-        let _ = pf.ensure_md5sum()?; // files -> Some(md5), dirs -> None
+        // with --stats the FASTQ is decompressed once for stats, md5 and the gzip check together
+        let stats_pass = options.stats && pf.is_gz_fastq();
+        // a gzip copy written by `--compress-plain`/`--recompress-to` is checked by its md5 only
+        let copied = Container::of_fastq(p).is_some_and(|c| c != Container::Gzip);
+        if options.validate_gzip && pf.path.ends_with(".gz") && !stats_pass && !copied {
+            if let Some(err) = gzip_integrity_error(p) {
+                eprintln!("WARN: gzip check failed for {}: {}", p.display(), err);
+                pf.gzip_errors.push(format!("{}: {}", pf.basename(), err));
+            }
+        }
+
+        if options.read_headers && matches!(pf.kind, ParsedKind::Fastq { .. }) {
+            match FastqHeader::from_file(p) {
+                Ok(header) => pf.header = header,
+                Err(e) => eprintln!("WARN: could not read the first FASTQ header of {}: {}", p.display(), e),
+            }
+        }
+
+        if options.stats {
+            // one read for stats + md5; a broken gzip must not drop the file from the tables
+            if let Err(e) = pf.ensure_stats() {
+                eprintln!("WARN: read statistics failed for {}: {}", p.display(), e);
+                if let (true, GeoPrepError::Io(e)) = (options.validate_gzip && stats_pass, &e) {
+                    pf.gzip_errors.push(format!("{}: {}", pf.basename(), gzip_error_message(e)));
+                }
+            }
+        }
+        // with --hash-jobs the caller hashes all files afterwards (`ensure_md5sums`); bundles
+        // are hashed right away, so they never show up without an md5
+        if options.hash_jobs <= 1 || pf.is_tenx_bundle() {
+            let _ = pf.ensure_md5sum()?; // files -> Some(md5), dirs -> None
+        }
+        // checks cut short by Ctrl-C must not end up as findings
+        interrupt::check()?;
         Ok(Some(pf))
     }
 
+    /// `fragments.tsv.gz` (+ `.tbi`) inside a CellRanger-ATAC/ARC `outs/` folder.
+    /// ARC prefixes the name (`atac_fragments.tsv.gz`), so match on the suffix.
+    fn is_fragments_file(p: &Path) -> bool {
+        let name = match p.file_name().and_then(|s| s.to_str()) {
+            Some(n) => n,
+            None => return false,
+        };
+        (name.ends_with("fragments.tsv.gz") || name.ends_with("fragments.tsv.gz.tbi"))
+            && Self::find_ancestor_dir_named(p, "outs").is_some()
+    }
+
+    /// PacBio deliveries: `<movie>.hifi_reads.bam`, `<movie>.subreads.bam`, `<movie>.ccs.fastq.gz`.
+    /// Returns (movie, format) where movie is the file name up to the first '.'.
+    fn pacbio_movie_format(p: &Path) -> Option<(String, &'static str)> {
+        let name = p.file_name()?.to_str()?;
+        let format = ["hifi_reads.bam", "subreads.bam", "ccs.fastq.gz"]
+            .into_iter()
+            .find(|f| name.ends_with(&format!(".{f}")))?;
+        let movie = name.split('.').next()?.to_string();
+        Some((movie, format))
+    }
+
+    /// PacBio movie names look like `m64012_220101_123456` (Sequel II) or
+    /// `m84011_220902_175841_s1` (Revio).
+    pub fn is_pacbio_movie_name(name: &str) -> bool {
+        let parts: Vec<&str> = name.split('_').collect();
+        if parts.len() < 3 || parts.len() > 4 {
+            return false;
+        }
+        let instrument = parts[0];
+        let ok_instrument = instrument.len() > 1
+            && instrument.starts_with('m')
+            && instrument[1..].chars().all(|c| c.is_ascii_alphanumeric());
+        let six_digits = |s: &str| s.len() == 6 && s.chars().all(|c| c.is_ascii_digit());
+        let ok_cell = parts.get(3).map(|c| c.starts_with('s') && c[1..].chars().all(|c| c.is_ascii_digit())).unwrap_or(true);
+        ok_instrument && six_digits(parts[1]) && six_digits(parts[2]) && ok_cell
+    }
+
+    /// BAM/CRAM files and their indices (`.bai`, `.crai`, `.csi`).
+    fn alignment_format(p: &Path) -> Option<&'static str> {
+        let lower = p.file_name()?.to_str()?.to_ascii_lowercase();
+        ["bam", "cram", "bai", "crai", "csi"]
+            .into_iter()
+            .find(|ext| lower.ends_with(&format!(".{ext}")))
+    }
+
+    fn strip_index_suffix(s: &str) -> String {
+        for ext in [".bai", ".crai", ".csi"] {
+            if let Some(stripped) = s.strip_suffix(ext) {
+                return stripped.to_string();
+            }
+        }
+        s.to_string()
+    }
+
+    /// Why `from_path_with` returned None for the file `p` (`--unclassified-report`).
+    pub fn unclassified_reason(p: &Path, options: &ScanOptions) -> &'static str {
+        if !options.keep_accession_like && Self::accession_like_file(p) {
+            "accession-like name (see --keep-accession-like)"
+        } else if Self::tenx_triplet_dir_from_file(p).is_some() {
+            "10x triplet member without a complete triplet"
+        } else if !options.include_bam && Self::alignment_format(p).is_some() {
+            "BAM/CRAM without --include-bam"
+        } else if !options.only_samples.is_empty() || !options.skip_samples.is_empty() {
+            "sample not selected (--only-sample/--skip-sample) or no matching file kind"
+        } else {
+            "no matching file kind"
+        }
+    }
+
+    /// Processed data files that are not CellRanger outputs: h5ad, loom, RDS and CSV count matrices,
+    /// and bulk RNA-seq quantifications (`*counts.tsv(.gz)`, featureCounts, salmon, kallisto).
+    fn processed_format(p: &Path) -> Option<&'static str> {
+        let lower = p.file_name()?.to_str()?.to_ascii_lowercase();
+        if lower == "quant.sf" || lower == "quant.sf.gz" || lower == "quant.genes.sf" {
+            Some("salmon")
+        } else if lower == "abundance.h5" || lower == "abundance.tsv" {
+            Some("kallisto")
+        } else if lower.ends_with(".featurecounts.txt") {
+            Some("featureCounts")
+        } else if lower.ends_with("counts.tsv") || lower.ends_with("counts.tsv.gz") {
+            Some("tsv")
+        } else if lower.ends_with(".h5ad") {
+            Some("h5ad")
+        } else if lower.ends_with(".loom") {
+            Some("loom")
+        } else if lower.ends_with(".rds") {
+            Some("rds")
+        } else if (lower.ends_with(".csv") || lower.ends_with(".csv.gz"))
+            && (lower.contains("count") || lower.contains("matrix"))
+        {
+            Some("csv")
+        } else {
+            None
+        }
+    }
+
+    /// Signal tracks and peak calls of ChIP-/ATAC-seq pipelines.
+    fn track_format(p: &Path) -> Option<&'static str> {
+        let lower = p.file_name()?.to_str()?.to_ascii_lowercase();
+        let lower = lower.strip_suffix(".gz").unwrap_or(&lower);
+        if lower.ends_with(".bw") || lower.ends_with(".bigwig") {
+            Some("bigWig")
+        } else if lower.ends_with(".bedgraph") {
+            Some("bedGraph")
+        } else if lower.ends_with(".narrowpeak") {
+            Some("narrowPeak")
+        } else if lower.ends_with(".broadpeak") {
+            Some("broadPeak")
+        } else {
+            None
+        }
+    }
+
+    /// Provisional sample of a track: the file name up to the first dot without the MACS2
+    /// suffixes (`sampleA_peaks.narrowPeak` -> `sampleA`). `SampleFiles` later moves the
+    /// track to the known sample with the longest matching name prefix.
+    fn track_sample(p: &Path) -> Option<String> {
+        let name = p.file_name()?.to_str()?;
+        let stem = name.split('.').next().filter(|s| !s.is_empty())?;
+        let stem = ["_peaks", "_treat_pileup", "_control_lambda"]
+            .iter()
+            .find_map(|suf| stem.strip_suffix(suf))
+            .unwrap_or(stem);
+        Some(stem.to_string())
+    }
+
+    /// gzip check of every `.gz` member of a triplet directory.
+    fn gzip_errors_in_dir(dir: &Path) -> Vec<String> {
+        let mut out = Vec::new();
+        for entry in WalkDir::new(dir).sort_by_file_name().into_iter().filter_map(|e| e.ok()) {
+            let p = entry.path();
+            if entry.file_type().is_file() && p.to_string_lossy().ends_with(".gz") {
+                if let Some(err) = gzip_integrity_error(p) {
+                    eprintln!("WARN: gzip check failed for {}: {}", p.display(), err);
+                    let rel = p.strip_prefix(dir).unwrap_or(p);
+                    out.push(format!("{}: {}", rel.display(), err));
+                }
+            }
+        }
+        out
+    }
+
     fn tenx_triplet_dir_from_file(p: &Path) -> Option<PathBuf> {
         let name = p.file_name()?.to_str()?;
         match name {
@@ -252,10 +743,33 @@ impl ParsedFile {
 
     // ---------- path helpers ----------
 
+    /// GEO upload name as decided by `SampleFiles::assign_upload_names` after the scan;
+    /// `<experiment>_<short name>` for a file outside of a scanned model.
     pub fn geo_filename(&self) -> String {
-        match self.kind {
-            ParsedKind::H5 => format!("{}_{}_{}", self.experiment, self.sample, self.basename() ),
-            _ => format!("{}_{}", self.experiment, self.basename() ),
+        match &self.geo_name {
+            Some(name) => name.clone(),
+            None => format!("{}_{}", self.experiment, self.short_geo_filename()),
+        }
+    }
+
+    /// Upload name without the experiment prefix: the basename, or `<sample>_<basename>` for
+    /// generic names.
+    pub(crate) fn short_geo_filename(&self) -> String {
+        let base = self.basename();
+        match &self.kind {
+            // individual 10x triplet members: <sample>_<filtered|raw>_barcodes.tsv.gz
+            ParsedKind::TenX { bundle, format } if !base.starts_with(&self.sample) => {
+                format!("{}_{}.{}", self.sample, bundle, format)
+            }
+            // these kinds often carry generic names (possorted_genome_bam.bam, fragments.tsv.gz, ...)
+            ParsedKind::H5
+            | ParsedKind::Fragments
+            | ParsedKind::Alignment { .. }
+            | ParsedKind::ProcessedMatrix { .. }
+            | ParsedKind::Track { .. } if !base.starts_with(&self.sample) => {
+                format!("{}_{}", self.sample, base)
+            }
+            _ => base,
         }
     }
     pub fn basename(&self) -> String {
@@ -266,6 +780,15 @@ impl ParsedFile {
             .to_string()
     }
 
+    /// Basename without its last extension (`molecule_info.h5` -> `molecule_info`).
+    pub fn stem(&self) -> String {
+        Path::new(&self.path)
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or(&self.path)
+            .to_string()
+    }
+
     pub fn same_basename(&self, other: &ParsedFile) -> bool {
         self.basename() == other.basename()
     }
@@ -278,6 +801,17 @@ impl ParsedFile {
         fs::metadata(&self.path).map(|m| m.is_dir()).unwrap_or(false)
     }
 
+    /// A 10x triplet archive written by this tool (not an individually listed member).
+    pub fn is_tenx_bundle(&self) -> bool {
+        matches!(&self.kind, ParsedKind::TenX { format, .. }
+            if [BundleFormat::Zip, BundleFormat::TarGz].iter().any(|f| f.extension() == format))
+    }
+
+    /// Size on disk in bytes (0 if the file can not be read).
+    pub fn file_size(&self) -> u64 {
+        fs::metadata(&self.path).map(|m| m.len()).unwrap_or(0)
+    }
+
     // ---------- kind detection ----------
 
     fn looks_like_10x_triplet_dir(dir: &Path) -> io::Result<bool> {
@@ -289,34 +823,120 @@ impl ParsedFile {
         Ok(mtx.is_file() && bar.is_file() && (feat.is_file() || genes.is_file()))
     }
 
-    fn parse_fastq_lane_role(p: &Path) -> io::Result<(String, String)> {
+    /// With `plate_wells` the role and lane are only searched after the well token, so wells
+    /// like `I1` are not mistaken for an index read.
+    fn parse_fastq_lane_role(p: &Path, extra_roles: &[String], plate_wells: bool) -> Result<(String, String)> {
         let fname = p
             .file_name()
             .and_then(|s| s.to_str())
             .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Non-utf8 filename"))?;
+        let tokens = match Self::plate_well(fname) {
+            Some((_, _, rest)) if plate_wells => rest,
+            _ => fname,
+        };
 
-        let lower = fname.to_ascii_lowercase();
-
-        let role = if Self::has_token(&lower, "r1") {
-            "R1"
-        } else if Self::has_token(&lower, "r2") {
-            "R2"
-        } else if Self::has_token(&lower, "i1") {
-            "I1"
-        } else if Self::has_token(&lower, "i2") {
-            "I2"
-        } else {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                format!("Could not determine read role (R1/R2/I1/I2) from FASTQ name: '{fname}'"),
-            ));
+        // BGI/MGI: <flowcell>_L01_<barcode>_1.fq.gz
+        if let Some((_flowcell, lane, _barcode, read)) = Self::parse_mgi_name(fname) {
+            return Ok((lane, format!("R{read}")));
         }
-        .to_string();
 
-        let lane = Self::find_lane_token(fname).unwrap_or_else(|| "1".to_string());
+        let lower = tokens.to_ascii_lowercase();
+
+        let role = DEFAULT_ROLES
+            .iter()
+            .map(|r| r.to_string())
+            .chain(extra_roles.iter().map(|r| r.to_ascii_uppercase()))
+            .find(|r| Self::has_token(&lower, &r.to_ascii_lowercase()))
+            .ok_or_else(|| {
+                let known: Vec<String> = DEFAULT_ROLES
+                    .iter()
+                    .map(|r| r.to_string())
+                    .chain(extra_roles.iter().cloned())
+                    .collect();
+                GeoPrepError::UnknownReadRole { name: fname.to_string(), known: known.join("/") }
+            })?;
+
+        let lane = Self::find_lane_token(tokens).unwrap_or_else(|| "1".to_string());
+        // split deliveries: `_R1_002` is the second part of the lane's R1, listed as role `R1_002`
+        let role = match Self::chunk_number(tokens, &role) {
+            Some(chunk) if chunk > 1 => format!("{role}_{chunk:03}"),
+            _ => role,
+        };
         Ok((lane, role))
     }
 
+    /// Illumina chunk number: the three digit token right after the read role at the end of
+    /// the name (`sampleA_S1_L001_R1_001.fastq.gz` -> 1).
+    fn chunk_number(fname: &str, role: &str) -> Option<u32> {
+        let stem = fname.split('.').next()?;
+        let (rest, digits) = stem.rsplit_once('_')?;
+        let after_role = rest.to_ascii_uppercase().ends_with(&format!("_{}", role.to_ascii_uppercase()));
+        if !after_role || digits.len() != 3 || !digits.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        digits.parse().ok()
+    }
+
+    /// True for a 96/384-well plate position: row A-P and column 1-24 (`A1`, `B07`, `P24`).
+    pub fn is_well(token: &str) -> bool {
+        let mut chars = token.chars();
+        let row_ok = chars.next().is_some_and(|c| ('A'..='P').contains(&c));
+        let col = chars.as_str();
+        row_ok
+            && (1..=2).contains(&col.len())
+            && col.chars().all(|c| c.is_ascii_digit())
+            && col.parse::<u32>().is_ok_and(|n| (1..=24).contains(&n))
+    }
+
+    /// Split a Smart-seq2 style name `<plate>_<well>_<rest>` at the first well token after the
+    /// first `_` field: `("Plate1", "A01", "_R1.fastq.gz")`.
+    pub fn plate_well(name: &str) -> Option<(&str, &str, &str)> {
+        let mut start = name.find('_')? + 1;
+        loop {
+            let end = name[start..].find(['_', '.']).map_or(name.len(), |i| start + i);
+            if Self::is_well(&name[start..end]) {
+                return Some((&name[..start - 1], &name[start..end], &name[end..]));
+            }
+            if end >= name.len() || name.as_bytes()[end] == b'.' {
+                return None;
+            }
+            start = end + 1;
+        }
+    }
+
+    /// Split a BGI/MGI FASTQ name `<flowcell>_L<nn>_<barcode>_<1|2>.fq.gz`
+    /// into (flowcell, lane, barcode, read number).
+    fn parse_mgi_name(fname: &str) -> Option<(String, String, String, char)> {
+        let name = fname.strip_suffix(Container::of_path(Path::new(fname)).extension()).unwrap_or(fname);
+        let stem = name.strip_suffix(".fq").or_else(|| name.strip_suffix(".fastq"))?;
+        let parts: Vec<&str> = stem.split('_').collect();
+        if parts.len() < 4 {
+            return None;
+        }
+        let n = parts.len();
+        let (flowcell, lane, barcode, read) = (parts[n - 4], parts[n - 3], parts[n - 2], parts[n - 1]);
+
+        let lane_ok = lane.len() == 3
+            && lane.starts_with('L')
+            && lane[1..].chars().all(|c| c.is_ascii_digit());
+        let read = match read {
+            "1" => '1',
+            "2" => '2',
+            _ => return None,
+        };
+        if !lane_ok
+            || flowcell.is_empty()
+            || barcode.is_empty()
+            || !barcode.chars().all(|c| c.is_ascii_alphanumeric())
+        {
+            return None;
+        }
+
+        // anything in front of the flowcell belongs to the flowcell token (e.g. a delivery prefix)
+        let flowcell = parts[..n - 3].join("_");
+        Some((flowcell, lane.to_string(), barcode.to_string(), read))
+    }
+
     fn has_token(lower: &str, tok: &str) -> bool {
         lower.contains(&format!("_{tok}")) || lower.contains(&format!("{tok}.")) || lower.contains(&format!("{tok}_"))
     }
@@ -399,31 +1019,137 @@ impl ParsedFile {
     fn detect_sample(kind: &ParsedKind, p: &Path) -> Option<String> {
         match kind {
             ParsedKind::Fastq { .. } => Self::sample_from_fastq_name(p),
-            ParsedKind::H5 => Self::folder_above_marker(p, "outs"),
-            ParsedKind::TenX => Self::folder_above_marker(p, "outs"),
+            ParsedKind::H5 => Self::h5_sample(p),
+            ParsedKind::TenX { .. } => Self::cellranger_sample(p),
+            ParsedKind::Fragments => Self::cellranger_sample(p),
+            // without a movie→sample mapping the movie itself is the best sample id we have
+            ParsedKind::PacBio { movie, .. } => Some(movie.clone()),
+            ParsedKind::Alignment { .. } => Self::find_ancestor_dir_named(p, "outs")
+                .and_then(|_| Self::cellranger_sample(p))
+                .or_else(|| Self::sample_from_fastq_name(p)),
+            ParsedKind::Track { .. } => Self::track_sample(p),
+            ParsedKind::ProcessedMatrix { .. } => Self::folder_above_marker(p, "outs")
+                .or_else(|| Self::parent_folder_name(p)),
         }
     }
 
+    /// Sample of an H5 matrix: the CellRanger sample (folder above `outs`, or below
+    /// `per_sample_outs`), else the folder holding the file, else the file stem.
+    fn h5_sample(p: &Path) -> Option<String> {
+        Self::cellranger_sample(p)
+            .or_else(|| Self::parent_folder_name(p).filter(|s| !s.is_empty()))
+            .or_else(|| p.file_stem().and_then(|s| s.to_str()).map(str::to_string))
+    }
+
+    fn parent_folder_name(p: &Path) -> Option<String> {
+        p.parent()?
+            .file_name()
+            .and_then(|s| s.to_str())
+            .map(|s| s.to_string())
+    }
 
 
+
+    /// `--sample-sheets`: name of the sample sheet row the FASTQ was demultiplexed for.
+    fn sample_sheet_sample(p: &Path, options: &ScanOptions) -> Option<String> {
+        let sheet = options.sample_sheets.as_ref()?.for_file(p)?;
+        sheet.row_for(p.file_name()?.to_str()?).map(|row| row.name().to_string())
+    }
+
+    /// `--plate-wells`: the sample is `<plate>_<well>` (`Plate1_A01_R1.fastq.gz` -> `Plate1_A01`).
+    fn plate_well_sample(p: &Path) -> Option<String> {
+        let (plate, well, _) = Self::plate_well(p.file_name()?.to_str()?)?;
+        Some(format!("{plate}_{well}"))
+    }
+
     fn sample_from_fastq_name(p: &Path) -> Option<String> {
-        // Default: cut at first marker token
         let fname = p.file_name()?.to_str()?;
+
+        // BGI/MGI files are named after the flowcell; the barcode identifies the library
+        if let Some((flowcell, _lane, barcode, _read)) = Self::parse_mgi_name(fname) {
+            return Some(format!("{flowcell}_{barcode}"));
+        }
+
+        // Default: cut at first marker token
         let cut = ["_S", "_L", "_R", "_I"]
             .iter()
             .filter_map(|tok| fname.find(tok))
             .min()
             .unwrap_or_else(|| fname.find('.').unwrap_or(fname.len()));
-        Some(fname[..cut].to_string())
+        // the libraries of one single cell sample (`sampleA_GEX_S1`, `sampleA_S2_HTO`) stay apart
+        match Self::library_token(fname) {
+            Some((pos, token)) => Some(format!("{}_{}", &fname[..cut.min(pos)], token)),
+            None => Some(fname[..cut].to_string()),
+        }
+    }
+
+    /// Position of the `_` in front of the first library type token (`LIBRARY_TOKENS`, any
+    /// case) and the token as written; tokens end at `_`, `-` or `.`.
+    fn library_token(fname: &str) -> Option<(usize, &str)> {
+        let mut pos = 0;
+        for token in fname.split(['_', '-', '.']) {
+            let library = LIBRARY_TOKENS.iter().any(|t| t.eq_ignore_ascii_case(token));
+            if library && pos > 0 && fname.as_bytes()[pos - 1] == b'_' {
+                return Some((pos - 1, token));
+            }
+            pos += token.len() + 1;
+        }
+        None
     }
 
     // ---------- experiment detection ----------
 
+    /// Experiment label of `p`: `options.experiment` if set, the folder above
+    /// `options.experiment_marker` if set and found,
+    /// the `cellranger multi` run, the folder at `options.experiment_depth` below the root,
+    /// or the first path component under the root.
+    fn detect_experiment(scan_root: &Path, p: &Path, options: &ScanOptions) -> Option<String> {
+        options
+            .experiment
+            .clone()
+            .or_else(|| {
+                let marker = options.experiment_marker.as_deref()?;
+                Self::folder_above_marker(p.parent()?, marker)
+            })
+            .or_else(|| Self::cellranger_multi_run(p))
+            .or_else(|| {
+                options
+                    .experiment_depth
+                    .and_then(|depth| Self::folder_at_depth(scan_root, p, depth))
+            })
+            .or_else(|| Self::first_component_under_root(scan_root, p))
+    }
+
+    /// Name of the folder `depth` levels below the root on the way to `p` (1 = first folder).
+    fn folder_at_depth(scan_root: &Path, p: &Path, depth: usize) -> Option<String> {
+        let rel = p.strip_prefix(scan_root).ok().unwrap_or(p).parent()?;
+        rel.components()
+            .filter_map(|c| match c {
+                Component::Normal(os) => Some(os.to_string_lossy().to_string()),
+                _ => None,
+            })
+            .nth(depth.checked_sub(1)?)
+    }
+
+    /// First folder below the scan root on the way to `p`. Files directly in the scan root
+    /// get the name of the root folder itself (with a warning).
     fn first_component_under_root(scan_root: &Path, p: &Path) -> Option<String> {
         let rel = p.strip_prefix(scan_root).ok().unwrap_or(p);
-        rel.components().find_map(|c| match c {
-            Component::Normal(os) => Some(os.to_string_lossy().to_string()),
-            _ => None,
+        let first_dir = rel.parent().and_then(|dir| {
+            dir.components().find_map(|c| match c {
+                Component::Normal(os) => Some(os.to_string_lossy().to_string()),
+                _ => None,
+            })
+        });
+        first_dir.or_else(|| {
+            let root = scan_root.canonicalize().unwrap_or_else(|_| scan_root.to_path_buf());
+            let name = root.file_name()?.to_string_lossy().to_string();
+            eprintln!(
+                "WARN: {} is directly in the scan root - using the root folder name '{}' as experiment (see --experiment)",
+                p.display(),
+                name
+            );
+            Some(name)
         })
     }
 
@@ -434,53 +1160,171 @@ impl ParsedFile {
         PathBuf::from(format!("{}.md5sum", self.path))
     }
 
-    pub fn ensure_md5sum(&mut self) -> io::Result<Option<&str>> {
+    /// The per-directory `MD5SUMS` file used with `Md5Store::Directory`.
+    fn md5sums_path(&self) -> PathBuf {
+        Path::new(&self.path).with_file_name(MD5SUMS_FILE)
+    }
+
+    /// Known, cached or freshly computed md5 (None for folders and deferred hashing). 10x
+    /// bundles are always hashed: they are our own reproducible archives, still in the page cache.
+    pub fn ensure_md5sum(&mut self) -> Result<Option<&str>> {
         if self.md5sum.is_some() {
             return Ok(self.md5sum.as_deref());
         }
 
-        let p = Path::new(&self.path);
-        let md = fs::metadata(p)?;
+        let md = fs::metadata(&self.path)?;
         if md.is_dir() {
             return Ok(None);
         }
 
-        let sidecar = self.md5_sidecar_path();
-        if sidecar.exists() {
-            if let Ok(file) = File::open(&sidecar) {
-                let mut reader = BufReader::new(file);
-                let mut line = String::new();
-                if reader.read_line(&mut line).is_ok() {
-                    let v = line.trim().to_string();
-                    if !v.is_empty() {
-                        self.md5sum = Some(v);
-                        return Ok(self.md5sum.as_deref());
+        if self.read_md5_sidecar() {
+            return Ok(self.md5sum.as_deref());
+        }
+        if self.hash_io.deferred() && !self.is_tenx_bundle() {
+            if let (Some(bytes), None) = (self.hash_io.preview, &self.preview_md5) {
+                self.preview_md5 = Some(preview_md5(Path::new(&self.path), bytes)?);
+            }
+            return Ok(None);
+        }
+
+        let md5 = md5_file_with(Path::new(&self.path), self.hash_io)?;
+        self.store_md5(md5);
+        Ok(self.md5sum.as_deref())
+    }
+
+    /// Hash every file without a known md5 on `workers` threads, largest file first, so a
+    /// 200 GB FASTQ does not start last and keep one thread busy alone at the end.
+    /// Failures are reported and leave the md5 unset; after Ctrl-C the remaining files are skipped.
+    pub fn ensure_md5sums(files: Vec<&mut ParsedFile>, workers: usize) {
+        let mut jobs: Vec<(u64, &mut ParsedFile)> = files
+            .into_iter()
+            .filter(|pf| pf.md5sum.is_none())
+            .map(|pf| (pf.file_size(), pf))
+            .collect();
+        jobs.sort_by_key(|job| std::cmp::Reverse(job.0));
+        let total = jobs.len();
+        let queue = std::sync::Mutex::new(jobs.into_iter());
+        std::thread::scope(|scope| {
+            for _ in 0..workers.clamp(1, total.max(1)) {
+                scope.spawn(|| loop {
+                    if interrupt::interrupted() {
+                        break;
                     }
-                }
+                    let Some((_, pf)) = queue.lock().unwrap_or_else(|e| e.into_inner()).next() else { break };
+                    match pf.ensure_md5sum() {
+                        Ok(_) => emit::md5(pf),
+                        Err(e) if !interrupt::interrupted() => eprintln!("WARN: md5 failed for {}: {}", pf.path, e),
+                        Err(_) => {}
+                    }
+                });
             }
+        });
+    }
+
+    /// Gzipped FASTQ payloads (Illumina/MGI FASTQ and PacBio CCS FASTQ).
+    pub fn is_gz_fastq(&self) -> bool {
+        match &self.kind {
+            ParsedKind::Fastq { .. } => self.path.ends_with(".gz"),
+            ParsedKind::PacBio { format, .. } => format.ends_with("fastq.gz"),
+            _ => false,
         }
+    }
 
-        let md5 = Self::compute_file_md5_incremental(p)?;
-        if let Err(e) = fs::write(&sidecar, format!("{md5}\n")) {
-            eprintln!("Warning: could not write sidecar file {}: {}", sidecar.display(), e);
+    /// Read count / length statistics for FASTQs. If the md5 is not known yet it is
+    /// computed in the same pass, so every FASTQ is decompressed and read only once.
+    pub fn ensure_stats(&mut self) -> Result<Option<&FastqStats>> {
+        if self.stats.is_some() || !self.is_gz_fastq() {
+            return Ok(self.stats.as_ref());
         }
-        self.md5sum = Some(md5);
-        Ok(self.md5sum.as_deref())
+
+        if self.md5sum.is_none() {
+            self.read_md5_sidecar();
+        }
+        let (md5, stats) =
+            FastqStats::md5_and_stats(Path::new(&self.path), self.md5sum.is_none(), self.hash_io.buffer)?;
+        if let Some(md5) = md5 {
+            self.store_md5(md5);
+        }
+        self.stats = Some(stats);
+        Ok(self.stats.as_ref())
     }
 
-    fn compute_file_md5_incremental(file_path: &Path) -> io::Result<String> {
-        let mut f = File::open(file_path)?;
-        let mut ctx = md5::Context::new();
-        let mut buf = vec![0u8; 1024 * 1024];
-        loop {
-            let n = f.read(&mut buf)?;
-            if n == 0 { break; }
-            ctx.consume(&buf[..n]);
+    /// Digest cached for this file: the `MD5SUMS` entry (with `Md5Store::Directory`) or the
+    /// `.md5sum` sidecar. Sidecars may be in `md5sum` format (`<md5>  <basename>`) or a bare digest.
+    fn sidecar_md5(&self) -> Option<CachedMd5> {
+        if self.md5_store == Md5Store::Directory {
+            if let Some(entry) = read_md5sums(&self.md5sums_path()).remove(&self.basename()) {
+                return Some(entry);
+            }
         }
-        Ok(format!("{:x}", ctx.compute()))
+        read_sidecar(&self.md5_sidecar_path())
+    }
+
+    /// Use the cached digest if the file still has the recorded size and mtime
+    /// (or unconditionally with `--trust-cache`).
+    fn read_md5_sidecar(&mut self) -> bool {
+        match self.sidecar_md5() {
+            Some(entry) if self.trust_md5_cache || entry.matches(Path::new(&self.path)) => {
+                self.md5sum = Some(entry.md5);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Recompute the md5 of a file that has a cached digest (sidecar or `MD5SUMS`) and
+    /// compare the two. `Ok(None)` for folders and files without a (readable) cache entry.
+    pub fn verify_md5_sidecar(&self) -> Result<Option<(String, String)>> {
+        if Path::new(&self.path).is_dir() {
+            return Ok(None);
+        }
+        let Some(stored) = self.sidecar_md5() else {
+            return Ok(None);
+        };
+        let actual = md5_file_with(Path::new(&self.path), self.hash_io)?;
+        Ok(Some((stored.md5, actual)))
+    }
+
+    fn store_md5(&mut self, md5: String) {
+        let entry = CachedMd5::for_file(md5, Path::new(&self.path));
+        if self.md5_store == Md5Store::Directory {
+            let md5sums = self.md5sums_path();
+            if let Err(e) = update_md5sums(&md5sums, &self.basename(), &entry) {
+                eprintln!("Warning: could not update {}: {}", md5sums.display(), e);
+            }
+        } else {
+            // `md5sum -c` format, so the sidecars can be checked without this tool
+            let sidecar = self.md5_sidecar_path();
+            if let Err(e) = write_sidecar(&sidecar, &self.basename(), &entry) {
+                eprintln!("Warning: could not write sidecar file {}: {}", sidecar.display(), e);
+            }
+        }
+        self.md5sum = Some(entry.md5);
     }
 }
 
+#[cfg(test)]
+impl ParsedFile {
+    /// Test fixture: a file of `kind` at `path` with every optional field unset.
+    pub(crate) fn test_file(experiment: &str, sample: &str, kind: ParsedKind, path: &str) -> Self {
+        ParsedFile {
+            sample: sample.to_string(),
+            experiment: experiment.to_string(),
+            kind,
+            path: path.to_string(),
+            md5sum: None,
+            preview_md5: None,
+            stats: None,
+            header: None,
+            gzip_errors: Vec::new(),
+            triplet_issue: None,
+            md5_store: Md5Store::Sidecar,
+            trust_md5_cache: false,
+            hash_io: HashIo::default(),
+            geo_name: None,
+        }
+    }
+}
 
 #[cfg(test)]
 mod tests {
@@ -596,6 +1440,20 @@ mod tests {
     }
 
     #[test]
+    fn h5_sample_follows_cellranger_layouts() {
+        let sample = |p: &str| ParsedFile::h5_sample(Path::new(p));
+        assert_eq!(sample("exp1/sampleA/outs/filtered_feature_bc_matrix.h5").as_deref(), Some("sampleA"));
+        assert_eq!(sample("exp1/sampleA/outs/molecule_info.h5").as_deref(), Some("sampleA"));
+        assert_eq!(
+            sample("exp1/run1/outs/per_sample_outs/sampleB/count/sample_filtered_feature_bc_matrix.h5").as_deref(),
+            Some("sampleB")
+        );
+        assert_eq!(sample("exp1/sampleC/filtered_feature_bc_matrix.h5").as_deref(), Some("sampleC"));
+        assert_eq!(sample("sampleD.h5").as_deref(), Some("sampleD"));
+    }
+
+    #[test]
+    #[allow(clippy::needless_borrow)]
     fn h5_geo_filename() {
         let p =  Path::new( "tests/data/test_h5/outs/filtered_feature_bc_matrix.h5");
         let root = Path::new("tests/data/");
@@ -603,4 +1461,484 @@ mod tests {
 
         assert_eq!(h5rep.geo_filename(), "test_h5_test_h5_filtered_feature_bc_matrix.h5");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn processed_format_detects_single_cell_and_count_files() {
+        assert_eq!(ParsedFile::processed_format(Path::new("exp/sampleA/adata.h5ad")), Some("h5ad"));
+        assert_eq!(ParsedFile::processed_format(Path::new("exp/sampleA/sampleA.loom")), Some("loom"));
+        assert_eq!(ParsedFile::processed_format(Path::new("exp/sampleA/seurat.RDS")), Some("rds"));
+        assert_eq!(ParsedFile::processed_format(Path::new("exp/sampleA/raw_counts.csv.gz")), Some("csv"));
+        assert_eq!(ParsedFile::processed_format(Path::new("exp/sampleA/metadata.csv")), None);
+        assert_eq!(ParsedFile::processed_format(Path::new("exp/sampleA/quant.sf")), Some("salmon"));
+        assert_eq!(ParsedFile::processed_format(Path::new("exp/sampleA/abundance.h5")), Some("kallisto"));
+        assert_eq!(ParsedFile::processed_format(Path::new("exp/sampleA.featureCounts.txt")), Some("featureCounts"));
+        assert_eq!(ParsedFile::processed_format(Path::new("exp/sampleA/gene_counts.tsv.gz")), Some("tsv"));
+        assert_eq!(ParsedFile::processed_format(Path::new("exp/sampleA.featureCounts.txt.summary")), None);
+        assert_eq!(ParsedFile::processed_format(Path::new("exp/sampleA/outs/filtered_feature_bc_matrix.h5")), None);
+        assert_eq!(ParsedFile::processed_format(Path::new("exp/sampleA_R1.fastq.gz")), None);
+    }
+
+    #[test]
+    fn tracks_are_detected_with_a_provisional_sample() {
+        assert_eq!(ParsedFile::track_format(Path::new("exp/sampleA.bw")), Some("bigWig"));
+        assert_eq!(ParsedFile::track_format(Path::new("exp/sampleA.bigWig")), Some("bigWig"));
+        assert_eq!(ParsedFile::track_format(Path::new("exp/sampleA.bedGraph.gz")), Some("bedGraph"));
+        assert_eq!(ParsedFile::track_format(Path::new("exp/sampleA_peaks.narrowPeak")), Some("narrowPeak"));
+        assert_eq!(ParsedFile::track_format(Path::new("exp/sampleA_peaks.broadPeak")), Some("broadPeak"));
+        assert_eq!(ParsedFile::track_format(Path::new("exp/sampleA.bed")), None);
+        assert_eq!(ParsedFile::track_sample(Path::new("exp/sampleA_peaks.narrowPeak")).as_deref(), Some("sampleA"));
+        assert_eq!(ParsedFile::track_sample(Path::new("exp/sampleA.rep1.bw")).as_deref(), Some("sampleA"));
+    }
+
+    #[test]
+    fn kinds_split_into_raw_and_processed() {
+        let fastq = ParsedKind::Fastq { lane: "L001".into(), role: "R1".into() };
+        assert!(fastq.is_raw());
+        assert!(ParsedKind::TenX { bundle: "filtered".into(), format: "zip".into() }.is_processed());
+        assert!(ParsedKind::H5.is_processed());
+        assert!(ParsedKind::ProcessedMatrix { format: "h5ad".into() }.is_processed());
+    }
+
+    #[test]
+    fn fragments_need_outs_context() {
+        let atac: PathBuf = ["root","exp1","sampleA","outs","fragments.tsv.gz"].iter().collect();
+        let arc: PathBuf = ["root","exp1","sampleA","outs","atac_fragments.tsv.gz.tbi"].iter().collect();
+        let loose: PathBuf = ["root","exp1","sampleA","fragments.tsv.gz"].iter().collect();
+
+        assert!(ParsedFile::is_fragments_file(&atac));
+        assert!(ParsedFile::is_fragments_file(&arc));
+        assert!(!ParsedFile::is_fragments_file(&loose));
+        assert_eq!(
+            ParsedFile::detect_sample(&ParsedKind::Fragments, &arc).as_deref(),
+            Some("sampleA")
+        );
+    }
+
+    #[test]
+    fn tenx_sample_label_cellranger_multi() {
+        let triplet_dir: PathBuf = [
+            "root","exp1","run1","outs","per_sample_outs","sampleB","count",
+            "sample_filtered_feature_bc_matrix",
+        ].iter().collect();
+
+        assert_eq!(
+            ParsedFile::tenx_sample_label(&triplet_dir).as_deref(),
+            Some("sampleB_filtered")
+        );
+        assert_eq!(ParsedFile::cellranger_multi_run(&triplet_dir).as_deref(), Some("run1"));
+    }
+
+    #[test]
+    fn cellranger_multi_run_is_none_for_count_layout() {
+        let triplet_dir: PathBuf =
+            ["root","exp1","sampleA","outs","filtered_feature_bc_matrix"].iter().collect();
+
+        assert_eq!(ParsedFile::cellranger_multi_run(&triplet_dir), None);
+        assert_eq!(ParsedFile::cellranger_sample(&triplet_dir).as_deref(), Some("sampleA"));
+    }
+
+    #[test]
+    fn experiment_depth_and_marker() {
+        let root = Path::new("/data");
+        let p = Path::new("/data/2024/projX/run7/fastq/sampleA_S1_L001_R1_001.fastq.gz");
+        let mut options = ScanOptions::default();
+        assert_eq!(ParsedFile::detect_experiment(root, p, &options).as_deref(), Some("2024"));
+
+        options.experiment_depth = Some(2);
+        assert_eq!(ParsedFile::detect_experiment(root, p, &options).as_deref(), Some("projX"));
+        options.experiment_depth = Some(9);
+        assert_eq!(ParsedFile::detect_experiment(root, p, &options).as_deref(), Some("2024"));
+
+        options.experiment_marker = Some("fastq".to_string());
+        assert_eq!(ParsedFile::detect_experiment(root, p, &options).as_deref(), Some("run7"));
+
+        let top = Path::new("/data/sampleA_S1_L001_R1_001.fastq.gz");
+        assert_eq!(ParsedFile::detect_experiment(root, top, &options).as_deref(), Some("data"));
+
+        options.experiment = Some("myExp".to_string());
+        assert_eq!(ParsedFile::detect_experiment(root, p, &options).as_deref(), Some("myExp"));
+    }
+
+    #[test]
+    fn tenx_bundle_labels() {
+        let filtered: PathBuf = ["sampleA","outs","filtered_feature_bc_matrix"].iter().collect();
+        let multi_raw: PathBuf = ["per_sample_outs","sampleB","count","sample_raw_feature_bc_matrix"].iter().collect();
+        let velocyto: PathBuf = ["sampleA","outs","velocyto"].iter().collect();
+
+        assert_eq!(ParsedFile::tenx_bundle_label(&filtered).as_deref(), Some("filtered"));
+        assert_eq!(ParsedFile::tenx_bundle_label(&multi_raw).as_deref(), Some("raw"));
+        assert_eq!(ParsedFile::tenx_bundle_label(&velocyto).as_deref(), Some("velocyto"));
+    }
+
+    #[test]
+    fn alignment_formats_and_sample() {
+        let bam: PathBuf = ["root","exp1","sampleA","outs","possorted_genome_bam.bam"].iter().collect();
+        let bai: PathBuf = ["root","exp1","sampleA","outs","possorted_genome_bam.bam.bai"].iter().collect();
+        let cram: PathBuf = ["root","exp1","aligned","sampleB.cram"].iter().collect();
+
+        assert_eq!(ParsedFile::alignment_format(&bam), Some("bam"));
+        assert_eq!(ParsedFile::alignment_format(&bai), Some("bai"));
+        assert_eq!(ParsedFile::alignment_format(&cram), Some("cram"));
+        assert_eq!(ParsedFile::alignment_format(Path::new("sampleA_R1.fastq.gz")), None);
+
+        let kind = ParsedKind::Alignment { format: "bam".into() };
+        assert!(kind.is_raw());
+        assert_eq!(ParsedFile::detect_sample(&kind, &bai).as_deref(), Some("sampleA"));
+        assert_eq!(ParsedFile::detect_sample(&kind, &cram).as_deref(), Some("sampleB"));
+        assert_eq!(ParsedFile::strip_index_suffix("x/sampleA.bam.bai"), "x/sampleA.bam");
+    }
+
+    #[test]
+    fn pacbio_movie_detection() {
+        let hifi = Path::new("run1/m64012_220101_123456.hifi_reads.bam");
+        let ccs = Path::new("run1/m84011_220902_175841_s1.ccs.fastq.gz");
+
+        assert_eq!(
+            ParsedFile::pacbio_movie_format(hifi),
+            Some(("m64012_220101_123456".to_string(), "hifi_reads.bam"))
+        );
+        assert_eq!(
+            ParsedFile::pacbio_movie_format(ccs),
+            Some(("m84011_220902_175841_s1".to_string(), "ccs.fastq.gz"))
+        );
+        assert_eq!(ParsedFile::pacbio_movie_format(Path::new("sampleA.bam")), None);
+
+        assert!(ParsedFile::is_pacbio_movie_name("m64012_220101_123456"));
+        assert!(ParsedFile::is_pacbio_movie_name("m84011_220902_175841_s1"));
+        assert!(!ParsedFile::is_pacbio_movie_name("sampleA_S1_L001"));
+    }
+
+    #[test]
+    fn accession_named_pacbio_bams_are_skipped() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::create_dir(tmp.path().join("exp1")).unwrap();
+        let downloaded = tmp.path().join("exp1/SRR1234567.subreads.bam");
+        let movie = tmp.path().join("exp1/m64012_220101_123456.subreads.bam");
+        fs::write(&downloaded, b"bam").unwrap();
+        fs::write(&movie, b"bam").unwrap();
+
+        let mut options = ScanOptions::default();
+        assert!(ParsedFile::from_path_with(tmp.path(), &downloaded, &options).unwrap().is_none());
+        let pf = ParsedFile::from_path_with(tmp.path(), &movie, &options).unwrap().unwrap();
+        assert!(matches!(&pf.kind, ParsedKind::PacBio { movie, .. } if movie == "m64012_220101_123456"));
+
+        options.keep_accession_like = true;
+        let pf = ParsedFile::from_path_with(tmp.path(), &downloaded, &options).unwrap().unwrap();
+        assert!(matches!(&pf.kind, ParsedKind::PacBio { movie, .. } if movie == "SRR1234567"));
+    }
+
+    #[test]
+    fn mgi_names_map_to_lane_role_and_sample() {
+        let p = Path::new("run/V300012345_L01_517_1.fq.gz");
+        assert_eq!(
+            ParsedFile::parse_fastq_lane_role(p, &[], false).unwrap(),
+            ("L01".to_string(), "R1".to_string())
+        );
+        let p2 = Path::new("run/V300012345_L02_517_2.fq.gz");
+        assert_eq!(
+            ParsedFile::parse_fastq_lane_role(p2, &[], false).unwrap(),
+            ("L02".to_string(), "R2".to_string())
+        );
+        assert_eq!(ParsedFile::sample_from_fastq_name(p).as_deref(), Some("V300012345_517"));
+
+        // Illumina names are untouched
+        assert_eq!(ParsedFile::parse_mgi_name("sampleA_S1_L001_R1_001.fastq.gz"), None);
+        assert_eq!(
+            ParsedFile::sample_from_fastq_name(Path::new("sampleA_S1_L001_R1_001.fastq.gz")).as_deref(),
+            Some("sampleA")
+        );
+    }
+
+    #[test]
+    fn later_chunks_get_their_own_role_column() {
+        let lane_role = |name: &str| ParsedFile::parse_fastq_lane_role(Path::new(name), &[], false).unwrap();
+        let pair = |lane: &str, role: &str| (lane.to_string(), role.to_string());
+        assert_eq!(lane_role("sampleA_S1_L001_R1_001.fastq.gz"), pair("S1_L001", "R1"));
+        assert_eq!(lane_role("sampleA_S1_L001_R1_002.fastq.gz"), pair("S1_L001", "R1_002"));
+        assert_eq!(lane_role("sampleA_S1_L001_R2_002.fastq.gz"), pair("S1_L001", "R2_002"));
+        // no lane token: the chunk stays in the default lane
+        assert_eq!(lane_role("sampleA_S1_R1_002.fastq.gz"), pair("1", "R1_002"));
+        assert_eq!(lane_role("sampleA_S1_R1_003.fq.gz"), pair("1", "R1_003"));
+        // numbers that are not a chunk after the role are left alone
+        assert_eq!(lane_role("sampleA_002_S1_L001_R1.fastq.gz"), pair("S1_L001", "R1"));
+        assert_eq!(ParsedFile::chunk_number("sampleA_S1_L001_R1_001.fastq.gz", "R1"), Some(1));
+    }
+
+    #[test]
+    fn library_type_tokens_keep_single_cell_libraries_apart() {
+        let sample = |name: &str| ParsedFile::sample_from_fastq_name(Path::new(name));
+        assert_eq!(sample("sampleA_HTO_S5_L001_R2_001.fastq.gz").as_deref(), Some("sampleA_HTO"));
+        assert_eq!(sample("sampleA_GEX_S1_L001_R2_001.fastq.gz").as_deref(), Some("sampleA_GEX"));
+        assert_eq!(sample("sampleA_S2_adt_L001_R1_001.fastq.gz").as_deref(), Some("sampleA_adt"));
+        assert_eq!(sample("sampleA_Rep1_ATAC_S3_L001_I2_001.fastq.gz").as_deref(), Some("sampleA_ATAC"));
+        // only whole tokens count
+        assert_eq!(sample("sampleA_GEXX_S1_L001_R1_001.fastq.gz").as_deref(), Some("sampleA_GEXX"));
+        assert_eq!(sample("HTO_S1_L001_R1_001.fastq.gz").as_deref(), Some("HTO"));
+    }
+
+    #[test]
+    fn plate_wells_keep_the_well_in_the_sample() {
+        assert_eq!(ParsedFile::plate_well("Plate1_A01_R1.fastq.gz"), Some(("Plate1", "A01", "_R1.fastq.gz")));
+        assert_eq!(ParsedFile::plate_well("SS2_Plate3_I12_S5_L001_R2_001.fastq.gz").map(|t| t.1), Some("I12"));
+        assert_eq!(ParsedFile::plate_well("Plate1_Q01_R1.fastq.gz"), None);
+        assert_eq!(ParsedFile::plate_well("sampleA_S1_L001_R1_001.fastq.gz"), None);
+
+        // the generic cut stops at `_I`; plate mode keeps the row I well
+        let p = Path::new("exp1/Plate2_I05_R1.fastq.gz");
+        assert_eq!(ParsedFile::sample_from_fastq_name(p).as_deref(), Some("Plate2"));
+        assert_eq!(ParsedFile::plate_well_sample(p).as_deref(), Some("Plate2_I05"));
+        let well_i1 = Path::new("Plate2_I1_L002_R2.fastq.gz");
+        assert_eq!(ParsedFile::parse_fastq_lane_role(well_i1, &[], false).unwrap().1, "I1");
+        assert_eq!(
+            ParsedFile::parse_fastq_lane_role(well_i1, &[], true).unwrap(),
+            ("L002".to_string(), "R2".to_string())
+        );
+    }
+
+    #[test]
+    fn extra_roles_are_recognised() {
+        let atac = Path::new("run/sampleA_S1_L001_R3_001.fastq.gz");
+        assert!(ParsedFile::parse_fastq_lane_role(atac, &[], false).is_err());
+
+        let extra = vec!["R3".to_string(), "UMI".to_string()];
+        assert_eq!(
+            ParsedFile::parse_fastq_lane_role(atac, &extra, false).unwrap(),
+            ("S1_L001".to_string(), "R3".to_string())
+        );
+        let umi = Path::new("run/sampleA_S1_L001_UMI_001.fastq.gz");
+        assert_eq!(ParsedFile::parse_fastq_lane_role(umi, &extra, false).unwrap().1, "UMI");
+    }
+
+    #[test]
+    fn unknown_read_role_is_a_typed_error() {
+        let err = ParsedFile::parse_fastq_lane_role(Path::new("run/sampleA.fastq.gz"), &[], false).unwrap_err();
+        assert!(matches!(err, GeoPrepError::UnknownReadRole { .. }));
+        assert_eq!(
+            err.to_string(),
+            "could not determine read role (I1/I2/R1/R2) from FASTQ name: 'sampleA.fastq.gz'"
+        );
+    }
+
+    #[test]
+    fn deselected_samples_are_not_hashed() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::create_dir(tmp.path().join("exp1")).unwrap();
+        let mut options = ScanOptions {
+            only_samples: vec!["sampleA".to_string(), "sample?".to_string()],
+            skip_samples: vec!["*B".to_string()],
+            ..Default::default()
+        };
+        for sample in ["sampleA", "sampleB", "sampleCD"] {
+            let path = tmp.path().join(format!("exp1/{sample}_S1_L001_R1_001.fastq.gz"));
+            fs::write(&path, b"@r\nACGT\n+\nIIII\n").unwrap();
+            let parsed = ParsedFile::from_path_with(tmp.path(), &path, &options).unwrap();
+            assert_eq!(parsed.is_some(), sample == "sampleA", "{sample}");
+            assert_eq!(path.with_extension("gz.md5sum").exists(), sample == "sampleA");
+        }
+        options.only_samples.clear();
+        assert!(options.sample_selected("sampleCD"));
+        assert!(!options.sample_selected("sampleB"));
+    }
+
+    #[test]
+    fn tenx_members_can_be_listed_individually() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join("exp1/sampleA/outs/filtered_feature_bc_matrix");
+        fs::create_dir_all(&dir).unwrap();
+        for name in ["matrix.mtx.gz", "barcodes.tsv.gz", "features.tsv.gz"] {
+            fs::write(dir.join(name), name).unwrap();
+        }
+        let options = ScanOptions { tenx_mode: TenxMode::Individual, ..Default::default() };
+        let pf = ParsedFile::from_path_with(tmp.path(), &dir.join("barcodes.tsv.gz"), &options)
+            .unwrap()
+            .unwrap();
+        assert!(matches!(&pf.kind, ParsedKind::TenX { bundle, .. } if bundle == "filtered_barcodes"));
+        assert_eq!(pf.geo_filename(), "exp1_sampleA_filtered_barcodes.tsv.gz");
+        assert_eq!(pf.triplet_issue, None);
+        assert!(!tmp.path().join("exp1/sampleA/outs/sampleA_filtered.zip").exists());
+    }
+
+    #[test]
+    fn bundles_can_be_staged_outside_the_source_tree() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().join("data");
+        let dir = root.join("exp1/sampleA/outs/filtered_feature_bc_matrix");
+        fs::create_dir_all(&dir).unwrap();
+        for name in ["matrix.mtx.gz", "barcodes.tsv.gz", "features.tsv.gz"] {
+            fs::write(dir.join(name), name).unwrap();
+        }
+        let staging = tmp.path().join("staging");
+        let options = ScanOptions { bundle_dir: Some(staging.clone()), ..Default::default() };
+        let pf = ParsedFile::from_path_with(&root, &dir.join("matrix.mtx.gz"), &options).unwrap().unwrap();
+
+        let zip = staging.join("exp1/sampleA/outs/sampleA_filtered.zip");
+        assert!(zip.is_file());
+        assert_eq!(pf.path, portable_path(&zip));
+        assert_eq!(pf.experiment, "exp1");
+        assert!(!root.join("exp1/sampleA/outs/sampleA_filtered.zip").exists());
+    }
+
+    #[test]
+    fn bundles_are_hashed_at_once_and_reproducibly() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join("exp1/sampleA/outs/filtered_feature_bc_matrix");
+        fs::create_dir_all(&dir).unwrap();
+        for name in ["matrix.mtx.gz", "barcodes.tsv.gz", "features.tsv.gz"] {
+            fs::write(dir.join(name), name).unwrap();
+        }
+        let hash_io = HashIo { reuse_only: true, ..HashIo::default() };
+        let options = ScanOptions { hash_jobs: 4, hash_io, ..Default::default() };
+        let matrix = dir.join("matrix.mtx.gz");
+        let first = ParsedFile::from_path_with(tmp.path(), &matrix, &options).unwrap().unwrap();
+        assert!(first.is_tenx_bundle());
+        assert!(first.md5sum.is_some());
+
+        // rebuilt after the members were touched: same archive, same md5
+        fs::remove_file(&first.path).unwrap();
+        fs::remove_file(first.md5_sidecar_path()).unwrap();
+        fs::File::options().write(true).open(&matrix).unwrap().set_modified(std::time::SystemTime::UNIX_EPOCH).unwrap();
+        let again = ParsedFile::from_path_with(tmp.path(), &matrix, &options).unwrap().unwrap();
+        assert_eq!(again.md5sum, first.md5sum);
+    }
+
+    #[test]
+    fn sidecars_use_md5sum_format_and_read_both() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("sampleA_S1_L001_R1_001.fastq.gz");
+        fs::write(&path, b"hello world\n").unwrap();
+        let mut pf = ParsedFile::test_file(
+            "exp1",
+            "sampleA",
+            ParsedKind::Fastq { lane: "S1_L001".to_string(), role: "R1".to_string() },
+            &path.to_string_lossy(),
+        );
+        let md5 = "6f5902ac237024bdd0c176cb93063dc4";
+        pf.ensure_md5sum().unwrap();
+        let sidecar = fs::read_to_string(pf.md5_sidecar_path()).unwrap();
+        assert!(sidecar.ends_with(&format!("{md5}  sampleA_S1_L001_R1_001.fastq.gz\n")));
+        assert_eq!(pf.verify_md5_sidecar().unwrap(), Some((md5.to_string(), md5.to_string())));
+
+        // legacy bare digests carry no size/mtime: recomputed, unless the cache is trusted
+        fs::write(pf.md5_sidecar_path(), "00000000000000000000000000000000\n").unwrap();
+        pf.md5sum = None;
+        pf.trust_md5_cache = true;
+        assert_eq!(pf.ensure_md5sum().unwrap(), Some("00000000000000000000000000000000"));
+        let (stored, actual) = pf.verify_md5_sidecar().unwrap().unwrap();
+        assert_ne!(stored, actual);
+
+        pf.md5sum = None;
+        pf.trust_md5_cache = false;
+        assert_eq!(pf.ensure_md5sum().unwrap(), Some(md5));
+    }
+
+    #[test]
+    fn stats_pass_also_checks_the_gzip() {
+        use std::io::Write;
+        let tmp = tempfile::tempdir().unwrap();
+        let exp = tmp.path().join("exp1");
+        fs::create_dir_all(&exp).unwrap();
+        let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gz.write_all(b"@r1\nACGT\n+\nIIII\n").unwrap();
+        let bytes = gz.finish().unwrap();
+        let fq = exp.join("sampleA_S1_L001_R1_001.fastq.gz");
+        fs::write(&fq, &bytes[..bytes.len() - 6]).unwrap();
+
+        let options = ScanOptions { stats: true, validate_gzip: true, ..Default::default() };
+        let pf = ParsedFile::from_path_with(tmp.path(), &fq, &options).unwrap().unwrap();
+        assert_eq!(pf.gzip_errors(), ["sampleA_S1_L001_R1_001.fastq.gz: truncated gzip stream"]);
+        assert!(pf.md5sum.is_some());
+    }
+
+    #[test]
+    fn hash_jobs_hash_everything_after_the_walk() {
+        let tmp = tempfile::tempdir().unwrap();
+        let exp = tmp.path().join("exp1");
+        fs::create_dir_all(&exp).unwrap();
+        for (i, size) in [10usize, 5000, 300, 70000].iter().enumerate() {
+            fs::write(exp.join(format!("sample{i}_S1_L001_R1_001.fastq.gz")), vec![b'A' + i as u8; *size]).unwrap();
+        }
+
+        let options = ScanOptions { hash_jobs: 3, ..Default::default() };
+        let mut files: Vec<ParsedFile> = (0..4)
+            .map(|i| {
+                let p = exp.join(format!("sample{i}_S1_L001_R1_001.fastq.gz"));
+                ParsedFile::from_path_with(tmp.path(), &p, &options).unwrap().unwrap()
+            })
+            .collect();
+        assert!(files.iter().all(|pf| pf.md5sum.is_none()));
+
+        ParsedFile::ensure_md5sums(files.iter_mut().collect(), 3);
+        for pf in &files {
+            let expected = crate::sample_files::checksum::md5_file(Path::new(&pf.path)).unwrap();
+            assert_eq!(pf.md5sum.as_deref(), Some(expected.as_str()));
+        }
+    }
+
+    #[test]
+    fn deferred_hashing_leaves_the_md5_pending() {
+        let tmp = tempfile::tempdir().unwrap();
+        let exp = tmp.path().join("exp1");
+        fs::create_dir_all(&exp).unwrap();
+        let a = exp.join("sampleA_S1_L001_R1_001.fastq.gz");
+        let b = exp.join("sampleB_S1_L001_R1_001.fastq.gz");
+        fs::write(&a, vec![b'A'; 3000]).unwrap();
+        fs::write(&b, vec![b'A'; 4000]).unwrap();
+
+        let mut options = ScanOptions::default();
+        options.hash_io.reuse_only = true;
+        let pf = ParsedFile::from_path_with(tmp.path(), &a, &options).unwrap().unwrap();
+        assert_eq!((pf.md5sum.as_deref(), pf.md5_display()), (None, "PENDING"));
+        assert!(!a.with_file_name("sampleA_S1_L001_R1_001.fastq.gz.md5sum").exists());
+
+        options.hash_io = HashIo { preview: Some(1024), ..Default::default() };
+        let pa = ParsedFile::from_path_with(tmp.path(), &a, &options).unwrap().unwrap();
+        let pb = ParsedFile::from_path_with(tmp.path(), &b, &options).unwrap().unwrap();
+        assert_eq!(pa.md5_display(), "PENDING");
+        // same first KiB, different size
+        assert!(pa.dedup_key().unwrap().starts_with("preview:"));
+        assert_ne!(pa.dedup_key(), pb.dedup_key());
+    }
+
+    #[test]
+    fn plain_fastqs_are_kept_or_compressed() {
+        let tmp = tempfile::tempdir().unwrap();
+        let exp = tmp.path().join("exp1");
+        fs::create_dir_all(&exp).unwrap();
+        let fq = exp.join("sampleA_S1_L001_R1_001.fastq");
+        fs::write(&fq, b"@r1\nACGT\n+\nIIII\n").unwrap();
+
+        let pf = ParsedFile::from_path_with(tmp.path(), &fq, &ScanOptions::default()).unwrap().unwrap();
+        assert_eq!((pf.sample.as_str(), pf.path.ends_with(".fastq")), ("sampleA", true));
+        assert!(matches!(&pf.kind, ParsedKind::Fastq { role, .. } if role == "R1"));
+
+        let staging = tmp.path().join("staging");
+        let options = ScanOptions { compress_plain: true, bundle_dir: Some(staging.clone()), ..Default::default() };
+        let pf = ParsedFile::from_path_with(tmp.path(), &fq, &options).unwrap().unwrap();
+        let copy = staging.join("exp1/sampleA_S1_L001_R1_001.fastq.gz");
+        assert_eq!(pf.path, portable_path(&copy));
+        assert_eq!(pf.md5sum.as_deref(), Some(crate::sample_files::checksum::md5_file(&copy).unwrap().as_str()));
+        use std::io::Read;
+        let mut content = String::new();
+        flate2::read::GzDecoder::new(fs::File::open(&copy).unwrap()).read_to_string(&mut content).unwrap();
+        assert_eq!(content, "@r1\nACGT\n+\nIIII\n");
+
+        // a gzipped version next to the plain file wins
+        fs::copy(&copy, exp.join("sampleA_S1_L001_R1_001.fastq.gz")).unwrap();
+        assert!(ParsedFile::from_path_with(tmp.path(), &fq, &ScanOptions::default()).unwrap().is_none());
+
+        // bzip2 is transcoded with --recompress-to gz, to the same bytes as the plain copy
+        let bz2 = exp.join("sampleB_S1_L001_R1_001.fastq.bz2");
+        fs::write(&bz2, bzip2_bytes(b"@r1\nACGT\n+\nIIII\n")).unwrap();
+        let options = ScanOptions { recompress_to: RecompressTo::Gz, bundle_dir: Some(staging.clone()), ..Default::default() };
+        let pf = ParsedFile::from_path_with(tmp.path(), &bz2, &options).unwrap().unwrap();
+        assert!(pf.path.ends_with("staging/exp1/sampleB_S1_L001_R1_001.fastq.gz"));
+        assert_eq!(fs::read(&pf.path).unwrap(), fs::read(&copy).unwrap());
+    }
+
+    fn bzip2_bytes(data: &[u8]) -> Vec<u8> {
+        use std::io::Write;
+        let mut bz = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+        bz.write_all(data).unwrap();
+        bz.finish().unwrap()
+    }
+}
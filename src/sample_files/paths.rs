@@ -0,0 +1,34 @@
+// src/sample_files/paths.rs
+//! Path spelling shared by all tables, so Windows and Unix runs list the same paths.
+use std::path::Path;
+
+/// `p` as written to the tables, without the Windows verbatim prefix that
+/// `fs::canonicalize` adds (`\\?\C:\data` -> `C:\data`, `\\?\UNC\srv\share` -> `\\srv\share`).
+/// Paths without such a prefix are returned unchanged.
+pub fn portable_path(p: &Path) -> String {
+    let s = p.to_string_lossy();
+    if let Some(rest) = s.strip_prefix(r"\\?\UNC\") {
+        return format!(r"\\{}", rest);
+    }
+    if let Some(rest) = s.strip_prefix(r"\\?\") {
+        // only drive paths can drop the prefix; `\\?\Volume{..}` and friends need it
+        let b = rest.as_bytes();
+        if b.len() >= 2 && b[0].is_ascii_alphabetic() && b[1] == b':' {
+            return rest.to_string();
+        }
+    }
+    s.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verbatim_prefixes_are_stripped() {
+        assert_eq!(portable_path(Path::new(r"\\?\C:\data\run1")), r"C:\data\run1");
+        assert_eq!(portable_path(Path::new(r"\\?\UNC\srv\share\run1")), r"\\srv\share\run1");
+        assert_eq!(portable_path(Path::new(r"\\?\Volume{1234}\run1")), r"\\?\Volume{1234}\run1");
+        assert_eq!(portable_path(Path::new("/data/run1")), "/data/run1");
+    }
+}
@@ -0,0 +1,113 @@
+// src/sample_files/pipeline_sheet.rs
+use std::io::Write;
+use std::path::Path;
+
+use crate::error::Result;
+use crate::output::create_output;
+use crate::sample_files::{ParsedFile, SampleFiles};
+
+/// Sample sheet flavour for reprocessing pipelines (`--pipeline-sheet`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PipelineSheet {
+    /// nf-core `samples.csv`: `sample,fastq_1,fastq_2`
+    NfCore,
+    /// snakemake-workflows `units.tsv`: `sample`, `unit`, `fq1`, `fq2`
+    Snakemake,
+}
+
+impl std::str::FromStr for PipelineSheet {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "nfcore" | "nf-core" => Ok(PipelineSheet::NfCore),
+            "snakemake" => Ok(PipelineSheet::Snakemake),
+            other => Err(format!("unknown pipeline sheet '{}' (nfcore, snakemake)", other)),
+        }
+    }
+}
+
+impl PipelineSheet {
+    /// `<prefix>_samples.csv` (nf-core) or `<prefix>_units.tsv` (snakemake).
+    pub fn file_name(&self, prefix: &str) -> String {
+        match self {
+            PipelineSheet::NfCore => format!("{}_samples.csv", prefix),
+            PipelineSheet::Snakemake => format!("{}_units.tsv", prefix),
+        }
+    }
+}
+
+/// Absolute path of a FASTQ, as pipelines are rarely started from the scan directory.
+fn absolute(pf: &ParsedFile) -> String {
+    std::path::absolute(&pf.path)
+        .map(|p| p.to_string_lossy().into_owned())
+        .unwrap_or_else(|_| pf.path.clone())
+}
+
+impl SampleFiles {
+    /// Sample sheet for a reprocessing pipeline: one row per lane with an R1 (R2 empty for
+    /// single-end), using the GEO sample name and absolute FASTQ paths. nf-core pipelines merge
+    /// the rows of one sample; for snakemake the lane is the `unit`. Returns the number of rows.
+    pub fn write_pipeline_sheet<P: AsRef<Path>>(&self, out_path: P, sheet: PipelineSheet) -> Result<usize> {
+        let mut w = create_output(out_path)?;
+        match sheet {
+            PipelineSheet::NfCore => writeln!(w, "sample,fastq_1,fastq_2")?,
+            PipelineSheet::Snakemake => writeln!(w, "sample\tunit\tfq1\tfq2")?,
+        }
+        let mut rows = 0;
+        for rec in self.samples.values() {
+            let sample = self.geo_sample_name(&rec.experiment, &rec.name);
+            for (lane_key, lane) in &rec.lanes {
+                let Some(r1) = lane.reads.get("R1") else { continue };
+                let r2 = lane.reads.get("R2").map(absolute).unwrap_or_default();
+                match sheet {
+                    PipelineSheet::NfCore => writeln!(w, "{},{},{}", sample, absolute(r1), r2)?,
+                    PipelineSheet::Snakemake => writeln!(w, "{}\t{}\t{}\t{}", sample, lane_key, absolute(r1), r2)?,
+                }
+                rows += 1;
+            }
+        }
+        w.commit()?;
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sample_files::ParsedKind;
+
+    #[test]
+    fn one_row_per_lane_with_absolute_paths() {
+        let mut data = SampleFiles::new();
+        let fastqs = [
+            ("sampleA", "L001", "R1"),
+            ("sampleA", "L001", "R2"),
+            ("sampleA", "L002", "R1"),
+            ("sampleA", "L002", "R2"),
+            ("sampleB", "L001", "R1"),
+        ];
+        for (sample, lane, role) in fastqs {
+            data.add_file(ParsedFile::test_file(
+                "exp1",
+                sample,
+                ParsedKind::Fastq { lane: lane.to_string(), role: role.to_string() },
+                &format!("/data/exp1/{sample}_{lane}_{role}_001.fastq.gz"),
+            ));
+        }
+        let tmp = tempfile::tempdir().unwrap();
+
+        let csv = tmp.path().join("samples.csv");
+        assert_eq!(data.write_pipeline_sheet(&csv, PipelineSheet::NfCore).unwrap(), 3);
+        let text = std::fs::read_to_string(&csv).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "sample,fastq_1,fastq_2");
+        assert_eq!(lines[2], "sampleA,/data/exp1/sampleA_L002_R1_001.fastq.gz,/data/exp1/sampleA_L002_R2_001.fastq.gz");
+        assert_eq!(lines[3], "sampleB,/data/exp1/sampleB_L001_R1_001.fastq.gz,");
+
+        let tsv = tmp.path().join("units.tsv");
+        data.write_pipeline_sheet(&tsv, PipelineSheet::Snakemake).unwrap();
+        let text = std::fs::read_to_string(&tsv).unwrap();
+        assert!(text.starts_with("sample\tunit\tfq1\tfq2\nsampleA\tL001\t/data/exp1/sampleA_L001_R1_001.fastq.gz\t"));
+    }
+}
@@ -0,0 +1,84 @@
+// src/sample_files/plates.rs
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+
+use crate::error::Result;
+use crate::output::create_output;
+use crate::sample_files::{ParsedFile, SampleFiles};
+
+#[derive(Default)]
+struct PlateTally {
+    wells: usize,
+    fastqs: usize,
+    bytes: u64,
+    /// (reads, bases) summed over FASTQs with `--stats`
+    stats: Option<(u64, u64)>,
+}
+
+impl SampleFiles {
+    /// Per plate totals of `--plate-wells` samples (`<plate>_<well>`), one row per plate.
+    /// Reads and bases are only filled when `--stats` counted them.
+    ///
+    /// Output columns:
+    /// Experiment, Plate, Wells, FASTQs, Total_Bytes, Reads, Bases
+    ///
+    /// Returns the number of plates.
+    pub fn write_plate_summary<P: AsRef<Path>>(&self, out_path: P) -> Result<usize> {
+        let mut plates: BTreeMap<(String, String), PlateTally> = BTreeMap::new();
+        for rec in self.samples.values() {
+            let Some((plate, _, "")) = ParsedFile::plate_well(&rec.name) else {
+                continue;
+            };
+            let t = plates.entry((rec.experiment.clone(), plate.to_string())).or_default();
+            t.wells += 1;
+            for pf in rec.lanes.values().flat_map(|lane| lane.reads.values()) {
+                t.fastqs += 1;
+                t.bytes += pf.file_size();
+                if let Some(s) = &pf.stats {
+                    let (reads, bases) = t.stats.get_or_insert((0, 0));
+                    *reads += s.reads;
+                    *bases += s.bases;
+                }
+            }
+        }
+
+        let mut w = create_output(out_path)?;
+        writeln!(w, "Experiment\tPlate\tWells\tFASTQs\tTotal_Bytes\tReads\tBases")?;
+        for ((experiment, plate), t) in &plates {
+            let (reads, bases) = t.stats.map(|(r, b)| (r.to_string(), b.to_string())).unwrap_or_default();
+            writeln!(w, "{}\t{}\t{}\t{}\t{}\t{}\t{}", experiment, plate, t.wells, t.fastqs, t.bytes, reads, bases)?;
+        }
+        w.commit()?;
+        Ok(plates.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sample_files::{FastqStats, ParsedKind};
+
+    #[test]
+    fn wells_are_tallied_per_plate() {
+        let mut data = SampleFiles::new();
+        for (sample, role) in [("Plate1_A01", "R1"), ("Plate1_A01", "R2"), ("Plate1_I05", "R1"), ("Plate2_B03", "R1")] {
+            data.add_file(ParsedFile {
+                stats: Some(FastqStats { reads: 10, bases: 500 }),
+                ..ParsedFile::test_file(
+                    "exp1",
+                    sample,
+                    ParsedKind::Fastq { lane: "1".to_string(), role: role.to_string() },
+                    &format!("exp1/{sample}_{role}.fastq.gz"),
+                )
+            });
+        }
+        let tmp = tempfile::tempdir().unwrap();
+        let out = tmp.path().join("plates.tsv");
+        assert_eq!(data.write_plate_summary(&out).unwrap(), 2);
+        let text = std::fs::read_to_string(&out).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[1], "exp1\tPlate1\t2\t3\t0\t30\t1500");
+        assert_eq!(lines[2], "exp1\tPlate2\t1\t1\t0\t10\t500");
+    }
+}
@@ -0,0 +1,115 @@
+// src/sample_files/rename_plan.rs
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+
+use crate::error::{GeoPrepError, Result};
+use crate::output::create_output;
+use crate::sample_files::SampleFiles;
+
+impl SampleFiles {
+    /// Write the rename plan: every collected file with the name the collection script
+    /// gives it and the GEO upload name. Curators may edit the `geo_name` column and
+    /// then run `apply_renames` on the collection directory.
+    ///
+    /// Output columns:
+    /// original_path, collected_name, geo_name
+    pub fn write_rename_plan<P: AsRef<Path>>(&self, out_path: P) -> Result<usize> {
+        let mut rows: Vec<(String, String)> = self
+            .iter_all_parsed_files()
+            .into_iter()
+            .map(|pf| (pf.path.clone(), pf.geo_filename()))
+            .collect();
+        rows.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+
+        let mut w = create_output(out_path)?;
+        writeln!(w, "original_path\tcollected_name\tgeo_name")?;
+        for (path, geo_name) in &rows {
+            writeln!(w, "{}\t{}\t{}", path, geo_name, geo_name)?;
+        }
+        w.commit()?;
+        Ok(rows.len())
+    }
+}
+
+/// Rename the copies in `collection_dir` as listed in a (possibly edited) rename plan.
+/// A copy is looked up under its `collected_name`, then under the original basename;
+/// the original files are never touched. Returns the number of renamed files.
+pub fn apply_renames<P: AsRef<Path>, Q: AsRef<Path>>(plan: P, collection_dir: Q) -> Result<usize> {
+    let plan = plan.as_ref();
+    let dir = collection_dir.as_ref();
+    let reader = BufReader::new(File::open(plan)?);
+    let invalid = |line: usize, message: String| GeoPrepError::InvalidInput {
+        path: plan.to_path_buf(),
+        line,
+        message,
+    };
+    let mut renamed = 0usize;
+
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        if i == 0 || line.trim().is_empty() {
+            continue; // header
+        }
+        let cols: Vec<&str> = line.split('\t').collect();
+        if cols.len() < 3 {
+            return Err(invalid(i + 1, format!("{} columns, expected 3", cols.len())));
+        }
+        let (original, collected, geo_name) = (cols[0], cols[1], cols[2].trim());
+        if geo_name.is_empty() || geo_name.contains('/') || geo_name.contains('\\') {
+            return Err(invalid(i + 1, format!("invalid geo_name '{}'", geo_name)));
+        }
+
+        let target = dir.join(geo_name);
+        let original_base = Path::new(original)
+            .file_name()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let Some(src) = [collected, original_base.as_str()]
+            .iter()
+            .map(|n| dir.join(n))
+            .find(|p| p.is_file() && *p != target)
+        else {
+            if !target.is_file() {
+                eprintln!("Not in {}: {} (skipped)", dir.display(), collected);
+            }
+            continue;
+        };
+        if target.exists() {
+            eprintln!("Refusing to overwrite {} with {}", target.display(), src.display());
+            continue;
+        }
+        fs::rename(&src, &target)?;
+        renamed += 1;
+    }
+    Ok(renamed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edited_plan_renames_collected_copies() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join("copied");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("exp1_a_R1.fastq.gz"), b"x").unwrap();
+        fs::write(dir.join("b_R1.fastq.gz"), b"y").unwrap();
+
+        let plan = tmp.path().join("plan.tsv");
+        fs::write(
+            &plan,
+            "original_path\tcollected_name\tgeo_name\n\
+             /data/exp1/a_R1.fastq.gz\texp1_a_R1.fastq.gz\tpatient1_R1.fastq.gz\n\
+             /data/exp1/b_R1.fastq.gz\texp1_b_R1.fastq.gz\texp1_b_R1.fastq.gz\n\
+             /data/exp1/c_R1.fastq.gz\texp1_c_R1.fastq.gz\texp1_c_R1.fastq.gz\n",
+        )
+        .unwrap();
+
+        assert_eq!(apply_renames(&plan, &dir).unwrap(), 2);
+        assert!(dir.join("patient1_R1.fastq.gz").is_file());
+        assert!(dir.join("exp1_b_R1.fastq.gz").is_file());
+        assert!(!dir.join("exp1_a_R1.fastq.gz").exists());
+    }
+}
@@ -0,0 +1,153 @@
+// src/sample_files/reports.rs
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::Write;
+use std::path::Path;
+
+use crate::error::Result;
+use crate::output::create_output;
+use crate::sample_files::SampleFiles;
+
+impl SampleFiles {
+    /// Tally of the excluded Undetermined/Unmapped FASTQs per run folder, so submitters can
+    /// confirm the exclusion and reviewers can judge the demultiplexing.
+    ///
+    /// Output columns:
+    /// Experiment, Run_Folder, Files, Total_Bytes
+    pub fn write_undetermined_report<P: AsRef<Path>>(&self, out_path: P) -> Result<()> {
+        // (experiment, folder) -> (files, bytes)
+        let mut per_run: BTreeMap<(String, String), (usize, u64)> = BTreeMap::new();
+        for pf in &self.undetermined {
+            let folder = Path::new(&pf.path)
+                .parent()
+                .map(|p| p.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let e = per_run.entry((pf.experiment.clone(), folder)).or_default();
+            e.0 += 1;
+            e.1 += pf.file_size();
+        }
+
+        let mut w = create_output(out_path)?;
+        writeln!(w, "Experiment\tRun_Folder\tFiles\tTotal_Bytes")?;
+        let mut total = (0usize, 0u64);
+        for ((experiment, folder), (files, bytes)) in per_run {
+            writeln!(w, "{}\t{}\t{}\t{}", experiment, folder, files, bytes)?;
+            total.0 += files;
+            total.1 += bytes;
+        }
+        writeln!(w, "TOTAL\t\t{}\t{}", total.0, total.1)?;
+        w.commit()
+    }
+
+    /// Every file the walker saw but did not classify, with the reason (suffix not selected,
+    /// excluded, accession-like, parse failure, ...), so users can confirm nothing relevant
+    /// slipped through. Needs `ScanOptions::audit_unclassified`.
+    ///
+    /// Output columns:
+    /// Path, Reason
+    pub fn write_unclassified_report<P: AsRef<Path>>(&self, out_path: P) -> Result<usize> {
+        let mut rows: Vec<&(String, String)> = self.unclassified.iter().collect();
+        rows.sort();
+        rows.dedup();
+        let mut w = create_output(out_path)?;
+        writeln!(w, "Path\tReason")?;
+        for (path, reason) in &rows {
+            writeln!(w, "{}\t{}", path, reason)?;
+        }
+        w.commit()?;
+        Ok(rows.len())
+    }
+
+    /// Human readable upload size summary: file counts per type, bytes per experiment
+    /// and sample, and the grand total that the collection script will copy.
+    /// Missing mates (`missing_mates`) are listed first so they are hard to overlook.
+    pub fn upload_summary(&self) -> String {
+        let mut per_type: BTreeMap<String, usize> = BTreeMap::new();
+        // (files, bytes) per experiment and per (experiment, sample)
+        let mut per_exp: BTreeMap<String, (usize, u64)> = BTreeMap::new();
+        let mut per_sample: BTreeMap<(String, String), (usize, u64)> = BTreeMap::new();
+        let mut total = (0usize, 0u64);
+
+        for rec in self.samples.values() {
+            for pf in rec.all_paths() {
+                let size = pf.file_size();
+                *per_type.entry(pf.kind.file_type()).or_default() += 1;
+                for tally in [
+                    per_exp.entry(rec.experiment.clone()).or_default(),
+                    per_sample.entry((rec.experiment.clone(), rec.name.clone())).or_default(),
+                    &mut total,
+                ] {
+                    tally.0 += 1;
+                    tally.1 += size;
+                }
+            }
+        }
+
+        let mut out = String::new();
+        let missing = self.missing_mates();
+        if !missing.is_empty() {
+            let _ = writeln!(out, "!!! MISSING MATES ({} lanes) - these lanes of paired-end samples lack R1 or R2:", missing.len());
+            for m in &missing {
+                let _ = writeln!(out, "  {}:{} {}: {}", m.experiment, m.sample, m.lane, m.message);
+            }
+            let _ = writeln!(out);
+        }
+        let _ = writeln!(out, "Upload size summary");
+        let _ = writeln!(out, "Files per type:");
+        for (file_type, n) in &per_type {
+            let _ = writeln!(out, "  {:<16}{:>8}", file_type, n);
+        }
+        let _ = writeln!(out, "Per experiment / sample:");
+        for (experiment, (files, bytes)) in &per_exp {
+            let _ = writeln!(out, "  {:<30}{:>6} files {:>12}", experiment, files, human_bytes(*bytes));
+            for ((_, sample), (files, bytes)) in per_sample.range((experiment.clone(), String::new())..)
+                .take_while(|((e, _), _)| e == experiment)
+            {
+                let _ = writeln!(out, "    {:<28}{:>6} files {:>12}", sample, files, human_bytes(*bytes));
+            }
+        }
+        let _ = writeln!(
+            out,
+            "Grand total: {} files, {} bytes ({})",
+            total.0, total.1, human_bytes(total.1)
+        );
+        out
+    }
+
+    /// Write `upload_summary()` to `out_path` and return the text for printing.
+    pub fn write_summary<P: AsRef<Path>>(&self, out_path: P) -> Result<String> {
+        let text = self.upload_summary();
+        let mut w = create_output(out_path)?;
+        w.write_all(text.as_bytes())?;
+        w.commit()?;
+        Ok(text)
+    }
+}
+
+/// `1536` -> `1.5 KiB`
+pub fn human_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn human_bytes_uses_binary_units() {
+        assert_eq!(human_bytes(512), "512 B");
+        assert_eq!(human_bytes(1536), "1.5 KiB");
+        assert_eq!(human_bytes(3 * 1024 * 1024 * 1024), "3.0 GiB");
+    }
+}
@@ -1,16 +1,26 @@
 // src/sample_files/sample_files.rs
 use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
-use std::io::{self, BufWriter, Write};
+use std::io::Write;
 // PathBuf is necessary!
 #[allow(unused_imports)]
 use std::path::{PathBuf, Path };
 
-use std::fs::File;
-
-use walkdir::WalkDir;
 
+use crate::error::{GeoPrepError, Result};
+use crate::emit;
+use crate::interrupt;
+use crate::output::create_output;
+use crate::table::{write_table, TableStyle};
 use crate::sample_files::sample_record::SampleRecord;
 use crate::sample_files::parsed_file::{ParsedFile, ParsedKind};
+use crate::sample_files::{DedupPolicy, DemuxStats, DuplicateRole, ScanOptions};
+use crate::sample_files::scan_iter::ScanIter;
+use crate::sample_files::key_index::key128;
+use crate::sample_files::archive::bundle_dirs;
+use crate::sample_files::column_layout::apply_layout;
+use crate::sample_files::lane_fastqs::{order_roles, DEFAULT_ROLES};
+use crate::sample_files::paths::portable_path;
+use crate::sample_files::empty_files::placeholder_reason;
 
 
 
@@ -20,13 +30,66 @@ pub struct SampleKey {
     pub sample: String,
 }
 
+/// Outcome of the backup check in `SampleFiles::add_parsed`.
+enum Backup {
+    /// first copy (or dedup is off): add it
+    New,
+    /// a copy is already kept: ignore this one
+    Drop,
+    /// this copy wins over the kept one (path), which has to leave the model
+    Replace(String),
+}
+
+/// (first read path, sample, lane key) of a lane compared by `SampleFiles::dedup_lanes`.
+type LaneRef = (String, SampleKey, String);
+
+/// The kept copy for a (basename, md5) pair; only what the backup and collision checks need.
+#[derive(Debug, Clone)]
+struct SeenCopy {
+    path: String,
+    experiment: String,
+}
+
 #[derive(Debug, Default)]
 pub struct SampleFiles {
-    pub samples: BTreeMap<SampleKey, SampleRecord>,
-    pub force_experiment_prefix_export: bool,
+    pub(crate) samples: BTreeMap<SampleKey, SampleRecord>,
+
+    /// basenames shared by different exported files; only these get the experiment prefix
+    pub(crate) conflicting_basenames: BTreeSet<String>,
+
+    /// `--conflict-suffix`: clashing names get `_1`, `_2`, ... instead of the experiment
+    pub(crate) conflict_suffixes: bool,
+
+    /// opt-in classifications used while scanning
+    pub(crate) options: ScanOptions,
+
+    /// Undetermined_*/Unmapped_* FASTQs: never part of a sample, kept for the report
+    pub(crate) undetermined: Vec<ParsedFile>,
+
+    /// empty and placeholder files skipped by `add_parsed`, with the reason
+    pub(crate) placeholders: Vec<(ParsedFile, String)>,
+
+    /// (path, reason) of files seen but not classified, only with `ScanOptions::audit_unclassified`
+    pub(crate) unclassified: Vec<(String, String)>,
 
-    // basename -> (md5 -> representative parsed file)
-    seen: HashMap<String, HashMap<String, ParsedFile>>,
+    /// conditions that are only warned about while scanning (dropped duplicates, unparsable
+    /// files, basename collisions); `check_strict` turns them into an error
+    pub(crate) issues: Vec<String>,
+
+    /// (md5, kept path, dropped path) of every copy removed by the dedup policy
+    pub(crate) dropped_copies: Vec<(String, String, String)>,
+
+    /// (kind, original, fixed) of every name changed by `fix_names`
+    pub(crate) name_fixes: Vec<(String, String, String)>,
+
+    /// (anonymous id, experiment, sample) of every sample renamed by `anonymize`
+    pub(crate) anonymized: Vec<(String, String, String)>,
+
+    /// demultiplexer read counts (`ScanOptions::demux_stats`)
+    pub(crate) demux_stats: DemuxStats,
+
+    // key128(basename) -> (key128(md5) -> kept copy)
+    seen: HashMap<u128, HashMap<u128, SeenCopy>>,
 }
 
 impl SampleFiles {
@@ -38,47 +101,80 @@ impl SampleFiles {
         self.samples.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    // ---------- read-only accessors ----------
 
-    fn is_excluded_path(p: &Path, excludes: &[String]) -> bool {
-        if excludes.is_empty() {
-            return false;
-        }
-        let p_str = p.to_string_lossy();
-        for ex in excludes {
-            if ex.is_empty() { continue; }
-            if p_str.contains(ex) { return true; }
-            if p.components().any(|c| c.as_os_str().to_string_lossy() == ex.as_str()) {
-                return true;
-            }
-        }
-        false
+    /// All samples in (experiment, sample) order.
+    pub fn samples(&self) -> impl Iterator<Item = (&SampleKey, &SampleRecord)> {
+        self.samples.iter()
     }
 
-    fn matches_suffixes(p: &Path, suffixes: &[String]) -> bool {
-        if suffixes.is_empty() {
-            return true; // treat empty as "no filter"
-        }
-        let s = p.to_string_lossy();
-        suffixes.iter().any(|suf| s.ends_with(suf))
+    pub fn sample(&self, key: &SampleKey) -> Option<&SampleRecord> {
+        self.samples.get(key)
+    }
+
+    /// Every exported file of one sample (bundles, H5, FASTQs, ...); empty for unknown keys.
+    pub fn files_for_sample(&self, key: &SampleKey) -> Vec<&ParsedFile> {
+        self.samples.get(key).map(|rec| rec.all_paths().collect()).unwrap_or_default()
+    }
+
+    /// Experiment names, sorted and unique.
+    pub fn experiments(&self) -> Vec<&str> {
+        let set: BTreeSet<&str> = self.samples.keys().map(|k| k.experiment.as_str()).collect();
+        set.into_iter().collect()
+    }
+
+    /// Read roles over all samples in table order (I1/I2/R1/R2, configured roles, rest).
+    pub fn roles(&self) -> Vec<String> {
+        let set: BTreeSet<String> = self
+            .samples
+            .values()
+            .flat_map(|rec| rec.all_roles_sorted(&[]))
+            .collect();
+        order_roles(set, &self.options.extra_roles)
+    }
+
+    pub fn options(&self) -> &ScanOptions {
+        &self.options
+    }
+
+    pub fn options_mut(&mut self) -> &mut ScanOptions {
+        &mut self.options
+    }
+
+    /// Read counts of the demultiplexing reports found while scanning (`--demux-stats`).
+    pub fn demux_stats(&self) -> &DemuxStats {
+        &self.demux_stats
+    }
+
+    /// Undetermined/Unmapped FASTQs excluded from the samples.
+    pub fn undetermined(&self) -> &[ParsedFile] {
+        &self.undetermined
+    }
+
+    /// True if any GEO sample or file name needed the experiment prefix to be unique.
+    pub fn experiment_prefix_forced(&self) -> bool {
+        !self.clashing_upload_names().is_empty()
+            || self.samples.keys().any(|k| self.sample_name_shared(&k.experiment, &k.sample))
     }
 
+    /// Basenames of different files that are only unique with the experiment prefix.
+    pub fn conflicting_basenames(&self) -> impl Iterator<Item = &str> {
+        self.conflicting_basenames.iter().map(String::as_str)
+    }
+
+
     pub fn ingest_dir<P: AsRef<Path>>(
         &mut self,
         scan_root: P,
         suffixes: &[String],
         excludes: &[String],
-    ) -> io::Result<(usize, usize)> {
+    ) -> Result<(usize, usize)> {
         let scan_root = scan_root.as_ref();
 
-        let mut visited_dirs: HashSet<(u64, u64)> = HashSet::new();
-        let mut visited_paths: HashSet<PathBuf> = HashSet::new();
-
-        let mut visited = 0usize;
-        let mut parsed_ok = 0usize;
-        let mut added = 0usize;
-        let mut ignored_backup = 0usize;
-        let mut ignored_unmatched = 0usize;
-
         eprintln!(
             "Scanning {} (suffixes: {:?}, excludes: {:?})",
             scan_root.display(),
@@ -86,181 +182,512 @@ impl SampleFiles {
             excludes
         );
 
-        for entry in WalkDir::new(scan_root).follow_links(true).into_iter().filter_map(Result::ok) {
-            let p = entry.path();
-            visited += 1;
-
-            // Exclude early
-            if Self::is_excluded_path(p, excludes) {
-                continue;
-            }
-
-            // Optional suffix filter early (lets user ignore e.g. .mtx.gz)
-            // Note: directories won't match suffixes; that's fine because TenX is triggered by matrix.mtx.gz.
-            if p.is_file() && !Self::matches_suffixes(p, suffixes) {
-                ignored_unmatched += 1;
-                continue;
+        let mut options = self.options.clone();
+        options.suffixes = suffixes.to_vec();
+        options.excludes = excludes.to_vec();
+
+        let hash_jobs = options.hash_jobs;
+        let mut files = ScanIter::new(scan_root, options.clone());
+        // one walk: the 10x archives are packaged in parallel before the files are classified
+        let (wanted, bundles) = files.collect_wanted();
+        package_bundles(&bundles, &options);
+        let mut scanned: Vec<Result<ParsedFile>> = Vec::new();
+        for p in &wanted {
+            if interrupt::interrupted() {
+                break;
             }
-
-            // directory loop protection (unix only; ok)
-            if let Ok(md) = p.metadata() {
-                if md.is_dir() {
-                    #[cfg(unix)]
-                    {
-                        use std::os::unix::fs::MetadataExt;
-                        let key = (md.dev(), md.ino());
-                        if !visited_dirs.insert(key) {
-                            continue;
-                        }
-                    }
+            if let Some(r) = files.classify(p) {
+                if let Ok(pf) = &r {
+                    emit::file(pf);
                 }
+                scanned.push(r);
             }
+        }
+        let (visited, ignored_unmatched) = (files.visited, files.ignored_unmatched);
+        self.unclassified
+            .extend(files.unclassified.drain(..).map(|(p, reason)| (portable_path(&p), reason)));
+        self.demux_stats.merge(std::mem::take(&mut files.demux_stats));
+        let (parsed_ok, added, ignored_backup) = self.add_scanned(scanned, hash_jobs);
 
-            // avoid reprocessing same path
-            let canon = std::fs::canonicalize(p).unwrap_or_else(|_| p.to_path_buf());
-            if !visited_paths.insert(canon) {
-                continue;
-            }
+        eprintln!(
+            "Scan done. visited={} parsed={} added={} ignored_unmatched={} ignored_backup={} name_conflicts={}",
+            visited,
+            parsed_ok,
+            added,
+            ignored_unmatched,
+            ignored_backup,
+            self.conflicting_basenames.len()
+        );
+
+        emit::scan_done(scan_root, visited, added);
 
-            // Parse
-            let mut parsed = match ParsedFile::from_path(scan_root, p) {
-                Ok(Some(pf)) => pf,
-                Ok(None) => continue, // not relevant
+        Ok((added, visited))
+    }
+
+    /// Hash and add the classified files of one scan, then resolve what needs all of them
+    /// (renamed lane copies, tracks by prefix) and report the skipped files.
+    /// Returns (parsed, added, dropped as backup copies).
+    ///
+    /// Files are added in path order, not in walk order: which copy `keep-first` keeps must
+    /// not depend on the file system or on `--walk-threads` scheduling.
+    pub(crate) fn add_scanned(&mut self, mut scanned: Vec<Result<ParsedFile>>, hash_jobs: usize) -> (usize, usize, usize) {
+        let mut parsed_ok = 0usize;
+        let mut added = 0usize;
+        let mut ignored_backup = 0usize;
+        let placeholders_before = self.placeholders.len();
+
+        scanned.sort_by_cached_key(|r| match r {
+            Ok(pf) => pf.path.clone(),
+            Err(e) => e.to_string(),
+        });
+
+        if hash_jobs > 1 {
+            let pending: Vec<&mut ParsedFile> = scanned.iter_mut().filter_map(|r| r.as_mut().ok()).collect();
+            eprintln!("Hashing on {} worker(s), largest files first", hash_jobs);
+            ParsedFile::ensure_md5sums(pending, hash_jobs);
+        }
+
+        for parsed in scanned {
+            let parsed = match parsed {
+                // after Ctrl-C keep only finished files; the rest is picked up by the rerun
+                Ok(pf) if interrupt::interrupted() && pf.md5sum.is_none() && !Path::new(&pf.path).is_dir() => continue,
+                Err(_) if interrupt::interrupted() => continue,
+                Ok(pf) => pf,
                 Err(e) => {
-                    eprintln!("WARN: parse failed for {}: {}", p.display(), e);
+                    eprintln!("WARN: {}", e);
+                    self.issues.push(e.to_string());
                     continue;
                 }
             };
-
             parsed_ok += 1;
 
-            // md5 (unless omit_md5 is set internally)
-            if let Err(e) = parsed.ensure_md5sum() {
-                eprintln!("WARN: md5 failed for {}: {}", parsed.path, e);
-                // you can choose continue or keep; I'd keep but mark md5sum None
+            let placeholders = self.placeholders.len();
+            if self.add_parsed(parsed) {
+                added += 1;
+            } else if self.placeholders.len() == placeholders {
+                ignored_backup += 1;
             }
+        }
+        for (kept, dropped, lanes) in self.dedup_lanes() {
+            eprintln!("Dropped {} lane(s) in {} as renamed copies of the lanes in {} (kept)", lanes, dropped, kept);
+        }
+        self.attach_tracks_by_prefix();
+
+        if !self.undetermined.is_empty() {
+            let bytes: u64 = self.undetermined.iter().map(|pf| pf.file_size()).sum();
+            eprintln!(
+                "Excluded {} Undetermined/Unmapped FASTQ(s) ({} bytes) from the samples",
+                self.undetermined.len(),
+                bytes
+            );
+        }
 
-            // backup dedup
-            if self.should_ignore_as_backup(&parsed) {
-                ignored_backup += 1;
-                continue;
+        if let Some(skipped) = self.placeholders.get(placeholders_before..).filter(|s| !s.is_empty()) {
+            eprintln!("WARN: skipped {} empty or placeholder file(s) (keep them with --keep-empty):", skipped.len());
+            for (pf, reason) in skipped {
+                eprintln!("  {} ({})", pf.path, reason);
             }
+        }
+        self.assign_upload_names();
+        (parsed_ok, added, ignored_backup)
+    }
 
-            self.update_export_flags(&parsed);
 
-            // add
-            self.add_file(parsed);
-            added += 1;
+    /// Scan `scan_root` with the suffixes and excludes configured in `options`
+    /// (see `SampleFiles::builder()`).
+    pub fn ingest<P: AsRef<Path>>(&mut self, scan_root: P) -> Result<(usize, usize)> {
+        let suffixes = self.options.suffixes.clone();
+        let excludes = self.options.excludes.clone();
+        self.ingest_dir(scan_root, &suffixes, &excludes)
+    }
+
+    /// Scan several roots into this one model. The experiment of a file is still the first
+    /// folder below the root it was found in, so `/proj/runA/exp1` and `/archive/runB/exp1`
+    /// both contribute to experiment `exp1`.
+    pub fn ingest_dirs<P: AsRef<Path>>(
+        &mut self,
+        scan_roots: &[P],
+        suffixes: &[String],
+        excludes: &[String],
+    ) -> Result<(usize, usize)> {
+        let mut total = (0usize, 0usize);
+        for root in scan_roots {
+            let (added, visited) = self.ingest_dir(root, suffixes, excludes)?;
+            total.0 += added;
+            total.1 += visited;
         }
+        Ok(total)
+    }
 
-        eprintln!(
-            "Scan done. visited={} parsed={} added={} ignored_unmatched={} ignored_backup={} export_prefix={}",
-            visited,
-            parsed_ok,
-            added,
-            ignored_unmatched,
-            ignored_backup,
-            self.force_experiment_prefix_export
-        );
+    /// Add one parsed file (e.g. from `scan_iter`) to the model. Returns false if it was
+    /// dropped as a backup copy of an already known file or as an empty placeholder.
+    pub fn add_parsed(&mut self, mut parsed: ParsedFile) -> bool {
+        if !self.options.keep_empty {
+            if let Some(reason) = placeholder_reason(&parsed) {
+                self.placeholders.push((parsed, reason.to_string()));
+                return false;
+            }
+        }
 
-        Ok((added, visited))
+        // md5 (unless omit_md5 is set internally)
+        if let Err(e) = parsed.ensure_md5sum() {
+            eprintln!("WARN: md5 failed for {}: {}", parsed.path, e);
+            // you can choose continue or keep; I'd keep but mark md5sum None
+        }
+
+        // backup dedup
+        match self.backup_of(&parsed) {
+            Backup::New => {}
+            Backup::Drop => return false,
+            Backup::Replace(old) => {
+                self.remove_from_model(&old);
+            }
+        }
+
+        self.update_export_flags(&parsed);
+        self.add_file(parsed);
+        true
+    }
+
+    /// Everything that was only warned about while scanning: dropped duplicate files and read
+    /// roles, unparsable files and basename collisions between different files.
+    pub fn strict_issues(&self) -> Vec<String> {
+        let mut out = self.issues.clone();
+        out.extend(self.duplicate_role_issues());
+        out
+    }
+
+    /// One line per FASTQ that lost its read role to another file of the same lane.
+    pub fn duplicate_role_issues(&self) -> Vec<String> {
+        let mut out = Vec::new();
+        for rec in self.samples.values() {
+            for (lane_key, lane) in &rec.lanes {
+                for dup in &lane.duplicates {
+                    out.push(format!(
+                        "duplicate read role for {}:{} lane {} ignored: {}",
+                        rec.experiment, rec.name, lane_key, dup.path
+                    ));
+                }
+            }
+        }
+        out
+    }
+
+    /// `--on-duplicate-role error`: fail with every lane that has several files for one role.
+    pub fn check_duplicate_roles(&self) -> Result<()> {
+        let issues = self.duplicate_role_issues();
+        if self.options.on_duplicate_role == DuplicateRole::Error && !issues.is_empty() {
+            return Err(GeoPrepError::DuplicateRoles { issues });
+        }
+        Ok(())
+    }
+
+    /// `--strict`: fail with the full list of `strict_issues` if there are any.
+    pub fn check_strict(&self) -> Result<()> {
+        let issues = self.strict_issues();
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(GeoPrepError::Strict { issues })
+        }
+    }
+
+    /// Move signal tracks and peak files to the sample of the same experiment whose name is the
+    /// longest prefix of the file name (`sampleA_rep1.bw` -> `sampleA`), followed by `.`, `_` or `-`.
+    /// Tracks without such a sample keep their provisional sample.
+    fn attach_tracks_by_prefix(&mut self) {
+        // (from, processed label, to)
+        let mut moves: Vec<(SampleKey, String, SampleKey)> = Vec::new();
+        for (key, rec) in &self.samples {
+            for (label, pf) in rec.processed.iter().filter(|(_, pf)| matches!(pf.kind, ParsedKind::Track { .. })) {
+                let base = pf.basename();
+                let target = self
+                    .samples
+                    .keys()
+                    .filter(|k| k.experiment == key.experiment && k.sample != key.sample)
+                    .filter(|k| {
+                        base.strip_prefix(k.sample.as_str())
+                            .is_some_and(|rest| rest.starts_with(['.', '_', '-']))
+                    })
+                    .max_by_key(|k| k.sample.len());
+                if let Some(to) = target {
+                    moves.push((key.clone(), label.clone(), to.clone()));
+                }
+            }
+        }
+        for (from, label, to) in moves {
+            let Some(mut pf) = self.samples.get_mut(&from).and_then(|rec| rec.processed.remove(&label)) else {
+                continue;
+            };
+            pf.sample = to.sample.clone();
+            if let Some(rec) = self.samples.get_mut(&to) {
+                rec.processed.entry(label).or_insert(pf);
+            }
+        }
+        self.samples.retain(|_, rec| rec.total_len() > 0);
     }
 
+    /// Reads the demultiplexer could not assign to a sample.
+    fn is_undetermined(parsed: &ParsedFile) -> bool {
+        matches!(parsed.kind, ParsedKind::Fastq { .. })
+            && (parsed.sample.eq_ignore_ascii_case("undetermined")
+                || parsed.sample.eq_ignore_ascii_case("unmapped"))
+    }
 
     /// The central “add_file”: takes a ParsedFile and routes it into the correct SampleRecord.
+    /// Undetermined/Unmapped FASTQs are not uploaded; they are tallied in `self.undetermined`.
     pub fn add_file(&mut self, parsed: ParsedFile) {
+        if Self::is_undetermined(&parsed) {
+            self.undetermined.push(parsed);
+            return;
+        }
+
         let key = SampleKey {
             experiment: parsed.experiment.clone(),
             sample: parsed.sample.clone(),
         };
 
-        let rec = self.samples.entry(key).or_insert_with(|| {
-            let mut r = SampleRecord::default();
-            r.name = parsed.sample.clone();
-            r.experiment = parsed.experiment.clone(); // add this field to SampleRecord (recommended)
-            r
+        let rec = self.samples.entry(key).or_insert_with(|| SampleRecord {
+            name: parsed.sample.clone(),
+            experiment: parsed.experiment.clone(),
+            ..Default::default()
         });
 
+        // duplicates are only reported; `--strict` turns them into errors
+        let mut dropped: Option<String> = None;
         match parsed.kind.clone() {
-            ParsedKind::TenX => {
-                // you can keep "one 10x per sample" rule
-                if rec.tenx.is_some() {
-                    eprintln!("Duplicate 10x bundle for {}:{} ignored: {}", rec.experiment, rec.name, parsed.path);
+            ParsedKind::TenX { bundle, .. } => {
+                // one 10x bundle per label (filtered, raw, ...) and sample
+                if let Some(existing) = rec.tenx.get(&bundle) {
+                    if existing.path != parsed.path {
+                        dropped = Some(format!("Duplicate 10x '{}' bundle for {}:{} ignored: {}", bundle, rec.experiment, rec.name, parsed.path));
+                    }
                 } else {
-                    rec.tenx = Some(parsed);
+                    rec.tenx.insert(bundle, parsed);
                 }
             }
             ParsedKind::H5 => {
-                if parsed.basename() != "filtered_feature_bc_matrix.h5" {
-                    // ignore
-                }
-                else if rec.h5_files.is_some() {
+                // keyed by file stem: filtered_feature_bc_matrix, raw_feature_bc_matrix, molecule_info, ...
+                let label = parsed.stem();
+                if let Some(existing) = rec.h5_files.get(&label) {
                     // if exact same path, ignore; otherwise warn
-                    if rec.h5_files.as_ref().unwrap().path == parsed.path {
-                        // ignore
-                    } else {
-                        eprintln!("Duplicate H5 for {}:{} ignored: {}", rec.experiment, rec.name, parsed.path);
+                    if existing.path != parsed.path {
+                        dropped = Some(format!("Duplicate H5 '{}' for {}:{} ignored: {}", label, rec.experiment, rec.name, parsed.path));
                     }
                 } else {
-                    rec.h5_files = Some(parsed);
+                    rec.h5_files.insert(label, parsed);
                 }
             }
             ParsedKind::Fastq { lane, role } => {
-                rec.lanes.entry(lane).or_default().add_read(&role, parsed);
+                rec.add_fastq(lane, &role, parsed, self.options.on_duplicate_role);
             }
+            ParsedKind::Fragments => {
+                let base = parsed.basename();
+                if let Some(existing) = rec.fragments.get(&base) {
+                    if existing.path != parsed.path {
+                        dropped = Some(format!("Duplicate fragments file for {}:{} ignored: {}", rec.experiment, rec.name, parsed.path));
+                    }
+                } else {
+                    rec.fragments.insert(base, parsed);
+                }
+            }
+            ParsedKind::Alignment { .. } => {
+                let base = parsed.basename();
+                if let Some(existing) = rec.alignments.get(&base) {
+                    if existing.path != parsed.path {
+                        dropped = Some(format!("Duplicate alignment file for {}:{} ignored: {}", rec.experiment, rec.name, parsed.path));
+                    }
+                } else {
+                    rec.alignments.insert(base, parsed);
+                }
+            }
+            ParsedKind::PacBio { .. } => {
+                let base = parsed.basename();
+                if let Some(existing) = rec.long_reads.get(&base) {
+                    if existing.path != parsed.path {
+                        dropped = Some(format!("Duplicate PacBio file for {}:{} ignored: {}", rec.experiment, rec.name, parsed.path));
+                    }
+                } else {
+                    rec.long_reads.insert(base, parsed);
+                }
+            }
+            ParsedKind::ProcessedMatrix { .. } | ParsedKind::Track { .. } => {
+                let base = parsed.basename();
+                if let Some(existing) = rec.processed.get(&base) {
+                    if existing.path != parsed.path {
+                        dropped = Some(format!("Duplicate processed file for {}:{} ignored: {}", rec.experiment, rec.name, parsed.path));
+                    }
+                } else {
+                    rec.processed.insert(base, parsed);
+                }
+            }
+        }
+        if let Some(msg) = dropped {
+            eprintln!("{}", msg);
+            self.issues.push(msg);
         }
     }
 
     // ---------- global policy ----------
 
-    fn should_ignore_as_backup(&mut self, parsed: &ParsedFile) -> bool {
+    /// Same basename + same md5 as a file seen before => a backup copy. `options.dedup` decides
+    /// which copy is kept; every decision is recorded in `dropped_copies`.
+    fn backup_of(&mut self, parsed: &ParsedFile) -> Backup {
         let base = parsed.basename();
 
-        // Only dedup file artifacts (need md5); directories can’t be deduped here
-        let md5 = match parsed.md5sum.as_ref() {
+        // Only dedup file artifacts (need md5 or preview hash); directories can’t be deduped here
+        let md5 = match parsed.dedup_key() {
             Some(m) => m,
-            None => return false,
+            None => return Backup::New,
+        };
+
+        let copy = SeenCopy { path: parsed.path.clone(), experiment: parsed.experiment.clone() };
+        let by_md5 = self.seen.entry(key128(base.as_bytes())).or_default();
+        let md5_key = key128(md5.as_bytes());
+        let kept = match by_md5.get(&md5_key) {
+            None => {
+                by_md5.insert(md5_key, copy);
+                return Backup::New;
+            }
+            // the same file once more (each triplet member yields the same bundle)
+            Some(kept) if kept.path == parsed.path => return Backup::Drop,
+            Some(kept) => kept.clone(),
         };
 
-        let by_md5 = self.seen.entry(base).or_default();
+        if self.options.dedup == DedupPolicy::Off {
+            // both copies are exported, so their basename needs the experiment prefix
+            self.conflicting_basenames.insert(base);
+            return Backup::New;
+        }
+        if self.options.prefers_copy(&parsed.path, &kept.path) {
+            by_md5.insert(md5_key, copy);
+            self.dropped_copies.push((md5, parsed.path.clone(), kept.path.clone()));
+            Backup::Replace(kept.path)
+        } else {
+            self.dropped_copies.push((md5, kept.path.clone(), parsed.path.clone()));
+            Backup::Drop
+        }
+    }
 
-        // same basename + same md5 => backup duplicate (ignore)
-        if by_md5.contains_key(md5) {
-            // optional: log once
-            // eprintln!("Backup duplicate ignored (same md5): {}", parsed.path);
-            return true;
+    /// Whole lanes copied under other file names (renamed backups of a library): every read
+    /// role has the same md5 as in another lane. `--prefer-path` and `options.dedup` decide
+    /// which lane stays (`prefers_copy` on the first read path, else the first in path order);
+    /// the files of the other lanes go to `dropped_copies`. Returns (kept folder, dropped folder, lanes) for
+    /// every pair of directory trees, so users see which tree was treated as authoritative.
+    pub fn dedup_lanes(&mut self) -> Vec<(String, String, usize)> {
+        if self.options.dedup == DedupPolicy::Off {
+            return Vec::new();
+        }
+        // (role, md5) of every read -> (first read path, sample, lane) of the lanes holding them
+        let mut by_content: BTreeMap<Vec<(String, String)>, Vec<LaneRef>> = BTreeMap::new();
+        for (key, rec) in &self.samples {
+            for (lane_key, lane) in &rec.lanes {
+                let content: Option<Vec<(String, String)>> =
+                    lane.reads.iter().map(|(role, pf)| Some((role.clone(), pf.dedup_key()?))).collect();
+                let Some(content) = content.filter(|c| !c.is_empty()) else { continue };
+                let first = lane.reads.values().map(|pf| pf.path.clone()).min().unwrap_or_default();
+                by_content.entry(content).or_default().push((first, key.clone(), lane_key.clone()));
+            }
         }
 
-        by_md5.insert(md5.to_string(), parsed.clone());
-        false
+        let folder = |path: &str| Path::new(path).parent().map(portable_path).unwrap_or_default();
+        let mut trees: BTreeMap<(String, String), usize> = BTreeMap::new();
+        for (content, mut lanes) in by_content {
+            if lanes.len() < 2 {
+                continue;
+            }
+            lanes.sort();
+            let mut kept = 0;
+            for i in 1..lanes.len() {
+                if self.options.prefers_copy(&lanes[i].0, &lanes[kept].0) {
+                    kept = i;
+                }
+            }
+            let (kept_first, kept_key, kept_lane) = lanes[kept].clone();
+            let kept_reads: BTreeMap<String, String> = self.samples[&kept_key].lanes[&kept_lane]
+                .reads
+                .iter()
+                .map(|(role, pf)| (role.clone(), pf.path.clone()))
+                .collect();
+            for (first, key, lane_key) in lanes.into_iter().filter(|(first, ..)| *first != kept_first) {
+                let Some(lane) = self.samples.get_mut(&key).and_then(|rec| rec.lanes.remove(&lane_key)) else {
+                    continue;
+                };
+                for ((role, md5), pf) in content.iter().zip(lane.reads.into_values()) {
+                    self.dropped_copies.push((md5.clone(), kept_reads[role].clone(), pf.path));
+                }
+                *trees.entry((folder(&kept_first), folder(&first))).or_default() += 1;
+            }
+        }
+        self.samples.retain(|_, rec| rec.total_len() > 0);
+        trees.into_iter().map(|((kept, dropped), lanes)| (kept, dropped, lanes)).collect()
+    }
+
+    /// Remove the file with this path from whichever sample holds it (and the sample if it is
+    /// left without files); returns the removed file.
+    pub(crate) fn remove_from_model(&mut self, path: &str) -> Option<ParsedFile> {
+        let (key, pf, empty) = self.samples.iter_mut().find_map(|(key, rec)| {
+            rec.remove_path(path).map(|pf| (key.clone(), pf, rec.all_paths().next().is_none()))
+        })?;
+        if empty {
+            self.samples.remove(&key);
+        }
+        Some(pf)
+    }
+
+    /// (md5, kept path, dropped path) of every copy removed by the dedup policy.
+    pub fn dropped_copies(&self) -> &[(String, String, String)] {
+        &self.dropped_copies
+    }
+
+    /// What the dedup policy did: one row per dropped copy with the copy that was kept.
+    ///
+    /// Output columns:
+    /// MD5, Kept, Dropped
+    pub fn write_dedup_report<P: AsRef<Path>>(&self, out_path: P) -> Result<usize> {
+        let mut w = create_output(out_path)?;
+        writeln!(w, "MD5\tKept\tDropped")?;
+        for (md5, kept, dropped) in &self.dropped_copies {
+            writeln!(w, "{}\t{}\t{}", md5, kept, dropped)?;
+        }
+        w.commit()?;
+        Ok(self.dropped_copies.len())
     }
 
     fn update_export_flags(&mut self, parsed: &ParsedFile) {
         let base = parsed.basename();
-        let md5 = match parsed.md5sum.as_ref() {
+        let md5 = match parsed.dedup_key() {
             Some(m) => m,
             None => return, // dirs
         };
 
         // Look for other variants with same basename but different md5
-        if let Some(by_md5) = self.seen.get(&base) {
+        let md5 = key128(md5.as_bytes());
+        if let Some(by_md5) = self.seen.get(&key128(base.as_bytes())) {
             if by_md5.len() >= 2 {
                 // already a conflict; export must disambiguate
-                self.force_experiment_prefix_export = true;
+                self.conflicting_basenames.insert(base);
                 return;
             }
             for (other_md5, other_pf) in by_md5 {
-                if other_md5 != md5 {
+                if *other_md5 != md5 {
                     // different content with same basename
+                    self.conflicting_basenames.insert(base.clone());
                     if other_pf.experiment != parsed.experiment {
-                        self.force_experiment_prefix_export = true;
+                        self.issues.push(format!(
+                            "name collision: '{}' exists in experiments '{}' and '{}' with different content",
+                            base, other_pf.experiment, parsed.experiment
+                        ));
                     } else {
                         // same experiment, same basename, different content => this is dangerous
-                        self.force_experiment_prefix_export = true;
                         eprintln!(
                             "WARNING: same experiment '{}' has two different files with basename '{}' (md5 differs).",
                             parsed.experiment, base
                         );
+                        self.issues.push(format!(
+                            "name collision: experiment '{}' has two different files named '{}'",
+                            parsed.experiment, base
+                        ));
                     }
                 }
             }
@@ -269,102 +696,229 @@ impl SampleFiles {
 
     // ---------- naming helpers for writers ----------
 
-    /// GEO upload filename to use for a source path.
-    /// If force_experiment_prefix_export is true, prefixes with experiment id.
-    pub fn geo_filename(&self, experiment: &str, src_path: &str) -> String {
-        let base = Path::new(src_path)
-            .file_name()
-            .and_then(|s| s.to_str())
-            .unwrap_or(src_path);
-
-        if self.force_experiment_prefix_export {
-            format!("{}_{}", experiment, base)
-        } else {
-            base.to_string()
+    /// Decide the upload name of every file: its short name (`short_geo_filename`, which
+    /// already carries the sample for H5, fragments, processed files, ...) unless another
+    /// file is published under the same name, then `<experiment>_<short name>`. Runs after
+    /// every scan and `amend`; with `--conflict-suffix` the clashes are numbered instead.
+    /// Returns the number of prefixed (or numbered) files.
+    pub fn assign_upload_names(&mut self) -> usize {
+        if self.conflict_suffixes {
+            return self.use_conflict_suffixes();
+        }
+        let clashing = self.clashing_upload_names();
+        let mut prefixed = 0;
+        for pf in self.iter_all_parsed_files_mut() {
+            let short = pf.short_geo_filename();
+            pf.geo_name = Some(if clashing.contains(&short) {
+                prefixed += 1;
+                format!("{}_{}", pf.experiment, short)
+            } else {
+                short
+            });
         }
+        prefixed
     }
 
-    /// GEO sample name to use in tables (optional but recommended).
+    /// Short upload names that more than one file would be published under.
+    fn clashing_upload_names(&self) -> BTreeSet<String> {
+        let mut seen = HashSet::new();
+        self.iter_all_parsed_files()
+            .into_iter()
+            .map(|pf| pf.short_geo_filename())
+            .filter(|name| !seen.insert(name.clone()))
+            .collect()
+    }
+
+    /// GEO sample name to use in tables (optional but recommended). Only names used by
+    /// samples of several experiments are prefixed with the experiment id, or numbered in
+    /// experiment order with `--conflict-suffix` (`sampleA_1`, `sampleA_2`).
     pub fn geo_sample_name(&self, experiment: &str, sample: &str) -> String {
-        if self.force_experiment_prefix_export {
-            format!("{}_{}", experiment, sample)
-        } else {
+        if !self.sample_name_shared(experiment, sample) {
             sample.to_string()
+        } else if self.conflict_suffixes {
+            let n = self.samples.keys().filter(|k| k.sample == sample).take_while(|k| k.experiment != experiment).count();
+            format!("{}_{}", sample, n + 1)
+        } else {
+            format!("{}_{}", experiment, sample)
         }
     }
 
+    /// Organism of a sample from the `[organism]` section of `--sample-rules`.
+    pub fn organism(&self, experiment: &str, sample: &str) -> Option<&str> {
+        self.options.sample_rules.organism.lookup(experiment, sample)
+    }
 
-    /// Iterate all ParsedFiles that are intended to be exported/copied.
-    /// NOTE: If TenX is still stored as a directory, you probably want to zip first;
-    /// this will still list it, but scripts will fail to copy dirs with cp/copy-item.
-    fn iter_all_parsed_files(&self) -> Vec<&ParsedFile> {
-        let mut out: Vec<&ParsedFile> = Vec::new();
-
-        for (_key, rec) in &self.samples {
-            if let Some(pf) = rec.tenx.as_ref() {
-                out.push(pf);
-            }
-            if let Some(pf) = rec.h5_files.as_ref() {
-                out.push(pf);
-            }
-            for lane in rec.lanes.values() {
-                for pf in lane.reads.values() {
-                    out.push(pf);
+    /// `--conflict-suffix`: publish every file without the experiment prefix; names that then
+    /// clash get `_1`, `_2`, ... before the extension, numbered by (experiment, path). Call after
+    /// the last scan. Returns the number of suffixed files; the rename plan lists the mapping.
+    pub fn use_conflict_suffixes(&mut self) -> usize {
+        self.conflict_suffixes = true;
+        let mut by_name: BTreeMap<String, Vec<(String, String)>> = BTreeMap::new();
+        for pf in self.iter_all_parsed_files() {
+            by_name.entry(pf.short_geo_filename()).or_default().push((pf.experiment.clone(), pf.path.clone()));
+        }
+        let mut taken: HashSet<String> = by_name.iter().filter(|(_, f)| f.len() == 1).map(|(n, _)| n.clone()).collect();
+        let mut names: HashMap<String, String> = HashMap::new();
+        for (name, mut files) in by_name {
+            if files.len() > 1 {
+                files.sort();
+                let mut n = 0;
+                for (_, path) in files {
+                    let suffixed = loop {
+                        n += 1;
+                        let candidate = with_number_suffix(&name, n);
+                        if taken.insert(candidate.clone()) {
+                            break candidate;
+                        }
+                    };
+                    names.insert(path, suffixed);
                 }
             }
         }
+        let suffixed = names.len();
+        for pf in self.iter_all_parsed_files_mut() {
+            pf.geo_name = Some(names.remove(&pf.path).unwrap_or_else(|| pf.short_geo_filename()));
+        }
+        suffixed
+    }
 
-        out
+    /// Another experiment has a sample of this name.
+    fn sample_name_shared(&self, experiment: &str, sample: &str) -> bool {
+        self.samples.keys().any(|k| k.sample == sample && k.experiment != experiment)
     }
 
-    /// Like iter_all_parsed_files, but yields mutable refs (used for ensure_md5sum).
-    fn iter_all_parsed_files_mut(&mut self) -> Vec<&mut ParsedFile> {
-        let mut out: Vec<&mut ParsedFile> = Vec::new();
 
-        for (_key, rec) in &mut self.samples {
-            if let Some(pf) = rec.tenx.as_mut() {
-                out.push(pf);
-            }
-            if let Some(pf) = rec.h5_files.as_mut() {
-                out.push(pf);
-            }
-            for lane in rec.lanes.values_mut() {
-                for pf in lane.reads.values_mut() {
-                    out.push(pf);
-                }
-            }
-        }
+    /// Iterate all ParsedFiles that are intended to be exported/copied.
+    /// NOTE: If TenX is still stored as a directory, you probably want to zip first;
+    /// this will still list it, but scripts will fail to copy dirs with cp/copy-item.
+    pub(crate) fn iter_all_parsed_files(&self) -> Vec<&ParsedFile> {
+        self.samples.values().flat_map(|rec| rec.all_paths()).collect()
+    }
 
-        out
+    /// Like iter_all_parsed_files, but yields mutable refs (used for ensure_md5sum).
+    pub(crate) fn iter_all_parsed_files_mut(&mut self) -> Vec<&mut ParsedFile> {
+        self.samples.values_mut().flat_map(|rec| rec.all_paths_mut()).collect()
     }
 
 
     /// Write md5 table using GEO filename (basename or exp-prefixed basename, depending on geo_filename()).
-    pub fn write_md5_files_basename<P: AsRef<Path>>(&mut self, out_path: P) -> io::Result<()> {
+    /// With `options.stats` the FASTQ read count, mean read length and total bases are added as columns.
+    /// With `options.read_headers` the instrument, run, flowcell and lane of the first read follow.
+    pub fn write_md5_files_basename<P: AsRef<Path>>(&mut self, out_path: P) -> Result<()> {
+        self.write_md5_files_as(out_path, TableStyle::default())
+    }
+
+    /// `write_md5_files_basename` as TSV, CSV or XLSX (see `TableStyle`).
+    pub fn write_md5_files_as<P: AsRef<Path>>(&mut self, out_path: P, style: TableStyle) -> Result<()> {
+        let with_stats = self.options.stats;
+        let with_headers = self.options.read_headers;
+
         // Ensure md5 is computed for all file-path ParsedFiles that need it.
         for pf in self.iter_all_parsed_files_mut() {
+            if with_stats {
+                // failures were already reported while scanning; leave the cells empty
+                let _ = pf.ensure_stats();
+            }
             let _ = pf.ensure_md5sum()?; // dirs will return Ok(None)
         }
 
-        // Collect rows: (geo_file_name, md5)
-        let mut rows: Vec<(String, String)> = Vec::new();
+        // Collect rows: (geo_file_name, md5, stats and header cells)
+        let mut rows: Vec<(String, String, Vec<String>)> = Vec::new();
         for pf in self.iter_all_parsed_files() {
             let geo_name = pf.geo_filename();
-            let md5 = pf.md5sum.clone().unwrap_or_else(|| "none".to_string());
-            rows.push((geo_name, md5));
+            let md5 = pf.md5_display().to_string();
+            let mut extra = match &pf.stats {
+                Some(st) => vec![st.reads.to_string(), format!("{:.1}", st.mean_read_length()), st.bases.to_string()],
+                None if with_stats => vec![String::new(); 3],
+                None => Vec::new(),
+            };
+            match &pf.header {
+                Some(h) => extra.extend([h.instrument.clone(), h.run.clone(), h.flowcell.clone(), h.lane.to_string()]),
+                None if with_headers => extra.extend(vec![String::new(); 4]),
+                None => {}
+            }
+            rows.push((geo_name, md5, extra));
         }
 
-        // Stable ordering
-        rows.sort_by(|a, b| a.0.cmp(&b.0));
+        // Stable ordering: by file name, ties by checksum
+        rows.sort();
 
-        let f = File::create(out_path)?;
-        let mut w = BufWriter::new(f);
+        let mut header = vec!["file_name".to_string(), "md5sum".to_string()];
+        if with_stats {
+            header.extend(["read_count", "mean_read_length", "total_bases"].map(String::from));
+        }
+        if with_headers {
+            header.extend(["instrument", "run", "flowcell", "header_lane"].map(String::from));
+        }
+        let mut table = vec![header];
+        for (name, md5, extra) in rows {
+            let mut row = vec![name, md5];
+            row.extend(extra);
+            table.push(row);
+        }
+        write_table(out_path, style, &table)
+    }
 
-        writeln!(w, "file_name\tmd5sum")?;
-        for (name, md5) in rows {
-            writeln!(w, "{}\t{}", name, md5)?;
+    /// Write the GEO "RAW FILES" section: one row per raw file (FASTQs).
+    ///
+    /// Output columns:
+    /// file name, file type, file checksum, instrument model, single or paired-end
+    pub fn write_raw_files_table<P: AsRef<Path>>(&mut self, out_path: P) -> Result<()> {
+        for pf in self.iter_all_parsed_files_mut() {
+            let _ = pf.ensure_md5sum()?;
         }
-        Ok(())
+
+        let mut rows: Vec<(String, String, String, &'static str)> = Vec::new();
+        for rec in self.samples.values() {
+            let layout = rec.library_layout();
+            for pf in rec.all_paths().filter(|pf| pf.kind.is_raw()) {
+                rows.push((
+                    pf.geo_filename(),
+                    pf.kind.file_type(),
+                    pf.md5_display().to_string(),
+                    layout,
+                ));
+            }
+        }
+        rows.sort();
+
+        let mut w = create_output(out_path)?;
+        writeln!(w, "file name\tfile type\tfile checksum\tinstrument model\tsingle or paired-end")?;
+        for (name, file_type, md5, layout) in rows {
+            // instrument model is not known from the file system; left for the submitter
+            writeln!(w, "{}\t{}\t{}\t\t{}", name, file_type, md5, layout)?;
+        }
+        w.commit()
+    }
+
+    /// Write the GEO "PROCESSED DATA FILES" section: one row per processed file
+    /// (10x bundles, H5, h5ad, loom, RDS, count matrices).
+    ///
+    /// Output columns:
+    /// file name, file type, file checksum
+    pub fn write_processed_files_table<P: AsRef<Path>>(&mut self, out_path: P) -> Result<()> {
+        for pf in self.iter_all_parsed_files_mut() {
+            let _ = pf.ensure_md5sum()?;
+        }
+
+        let mut rows: Vec<(String, String, String)> = self
+            .iter_all_parsed_files()
+            .into_iter()
+            .filter(|pf| pf.kind.is_processed())
+            .map(|pf| (
+                pf.geo_filename(),
+                pf.kind.file_type(),
+                pf.md5_display().to_string(),
+            ))
+            .collect();
+        rows.sort();
+
+        let mut w = create_output(out_path)?;
+        writeln!(w, "file name\tfile type\tfile checksum")?;
+        for (name, file_type, md5) in rows {
+            writeln!(w, "{}\t{}\t{}", name, file_type, md5)?;
+        }
+        w.commit()
     }
 
     /// Generate bash script to copy all referenced files into DEST, using GEO filenames.
@@ -373,7 +927,7 @@ impl SampleFiles {
         &mut self,
         script_path: P,
         dest: &str,
-    ) -> io::Result<()> {
+    ) -> Result<()> {
         // Ensure md5 exists (optional but keeps everything consistent)
         for pf in self.iter_all_parsed_files_mut() {
             let _ = pf.ensure_md5sum()?;
@@ -394,11 +948,10 @@ impl SampleFiles {
 
         // sort within each group by destination name for stable scripts
         for v in groups.values_mut() {
-            v.sort_by(|a, b| a.0.cmp(&b.0));
+            v.sort();
         }
 
-        let f = File::create(script_path)?;
-        let mut w = BufWriter::new(f);
+        let mut w = create_output(script_path)?;
 
         writeln!(w, "#!/usr/bin/env bash")?;
         writeln!(w, "set -euo pipefail")?;
@@ -422,7 +975,7 @@ impl SampleFiles {
             writeln!(w)?;
         }
 
-        Ok(())
+        w.commit()
     }
 
 
@@ -432,7 +985,7 @@ impl SampleFiles {
         &mut self,
         script_path: P,
         dest: &str,
-    ) -> io::Result<()> {
+    ) -> Result<()> {
         for pf in self.iter_all_parsed_files_mut() {
             let _ = pf.ensure_md5sum()?;
         }
@@ -451,11 +1004,10 @@ impl SampleFiles {
         }
 
         for v in groups.values_mut() {
-            v.sort_by(|a, b| a.0.cmp(&b.0));
+            v.sort();
         }
 
-        let f = File::create(script_path)?;
-        let mut w = BufWriter::new(f);
+        let mut w = create_output(script_path)?;
 
         writeln!(w, "Param()")?;
         writeln!(w, "$ErrorActionPreference = 'Stop'")?;
@@ -477,65 +1029,63 @@ impl SampleFiles {
             writeln!(w)?;
         }
 
-        Ok(())
+        w.commit()
     }
 
-    /// Write a TSV listing FASTQ pairs (per sample+lane)
+    /// Write a TSV listing FASTQ pairs, one row per sample lane, with GEO upload names.
     ///
     /// Output columns:
-    /// Experiment, Sample, Lane, I1, I2, R1, R2
-    ///
-    /// Comment lines:
-    /// # EXPERIMENT: <name>
-    pub fn write_fastq_pairs_table<P: AsRef<Path>>(&self, out_path: P) -> io::Result<()> {
-        let mut f = BufWriter::new(File::create(out_path)?);
-
-        // We need a stable global header: determine maximum #lanes and role order.
-        // Approach: compute global max lanes and global role set.
-        // let mut global_roles: BTreeSet<String> = BTreeSet::new();
-        // let mut max_lanes: usize = 0;
-
-
-        // ---- header ----
-        write!(f, "Source_Path(s)\tSample_Lane\tPari1\tPair2\tPari3\tPair4")?;
-        writeln!(f)?;
-
-        // ---- rows ----
-        // Sort output by (experiment, sample) to keep stable
-        let mut keys: Vec<_> = self.samples.keys().cloned().collect();
-        keys.sort();
-
-        for key in keys {
-            let rec = self.samples.get(&key).unwrap();
+    /// Experiment, Sample, Lane, I1, I2, R1, R2 (+ one column per extra role, e.g. R3)
+    pub fn write_fastq_pairs_table<P: AsRef<Path>>(&self, out_path: P) -> Result<()> {
+        // I1/I2/R1/R2 are always present so the shape does not depend on the data
+        let mut roles: BTreeSet<String> = DEFAULT_ROLES.iter().map(|r| r.to_string()).collect();
+        for rec in self.samples.values() {
+            roles.extend(rec.all_roles_sorted(&self.options.extra_roles));
+        }
+        let roles = order_roles(roles, &self.options.extra_roles);
 
-            let src_folders = rec.collect_source_folders_for_record();
-            let sample_name = rec.name.clone();
+        let mut f = create_output(out_path)?;
+        writeln!(f, "Experiment\tSample\tLane\t{}", roles.join("\t"))?;
 
-            for pair in rec.lanes.values(){
-                writeln!(f, "{}\t{}\t{}", 
-                    src_folders, 
-                    sample_name, 
-                    pair.pair_row().join("\t")
+        // BTreeMap order = (experiment, sample), lanes in lane order
+        let fmt = |pf: &ParsedFile| pf.geo_filename();
+        for rec in self.samples.values() {
+            for (lane_key, lane) in &rec.lanes {
+                writeln!(
+                    f,
+                    "{}\t{}\t{}\t{}",
+                    rec.experiment,
+                    rec.name,
+                    lane_key,
+                    lane.row_cells(&roles, &fmt).join("\t")
                 )?;
             }
         }
 
-        Ok(())
+        f.commit()
     }
 
 
     /// Recreates your old sample table writer, now backed by ParsedFile.
     /// The table uses GEO upload filenames (geo_filename) for TenX/H5/FASTQ cells.
-    pub fn write_sample_files_basename<P: AsRef<Path>>(&self, out_path: P) -> io::Result<()> {
-        let mut f = BufWriter::new(File::create(out_path)?);
+    pub fn write_sample_files_basename<P: AsRef<Path>>(&self, out_path: P) -> Result<()> {
+        self.write_sample_files_as(out_path, TableStyle::default())
+    }
+
+    /// `write_sample_files_basename` as TSV, CSV or XLSX (see `TableStyle`).
+    pub fn write_sample_files_as<P: AsRef<Path>>(&self, out_path: P, style: TableStyle) -> Result<()> {
 
         // We need a stable global header: determine maximum #lanes and role order.
         // Approach: compute global max lanes and global role set.
         let mut global_roles: BTreeSet<String> = BTreeSet::new();
+        let mut global_bundles: BTreeSet<String> = BTreeSet::new();
+        let mut global_h5: BTreeSet<String> = BTreeSet::new();
         let mut max_lanes: usize = 0;
 
-        for (_key, rec) in &self.samples {
-            let roles = rec.all_roles_sorted();
+        for rec in self.samples.values() {
+            global_bundles.extend(rec.tenx.keys().cloned());
+            global_h5.extend(rec.h5_files.keys().cloned());
+            let roles = rec.all_roles_sorted(&self.options.extra_roles);
             for r in roles {
                 global_roles.insert(r);
             }
@@ -546,16 +1096,7 @@ impl SampleFiles {
         }
 
         // Prefer canonical ordering globally too
-        let mut roles_vec: Vec<String> = {
-            let mut tmp = Vec::new();
-            for r in ["I1", "I2", "R1", "R2"] {
-                if global_roles.remove(r) {
-                    tmp.push(r.to_string());
-                }
-            }
-            tmp.extend(global_roles.into_iter());
-            tmp
-        };
+        let mut roles_vec: Vec<String> = order_roles(global_roles, &self.options.extra_roles);
 
         if roles_vec.is_empty() {
             // still write a sane header if no fastqs found
@@ -563,63 +1104,573 @@ impl SampleFiles {
         }
 
         // ---- header ----
-        write!(f, "Source_Path(s)\tSample_Lane\tTenX\tH5")?;
-        for _lane_idx in 0..max_lanes {
-            for r in &roles_vec {
-                write!(f, "\t{}", r)?;
+        // one TenX column per encountered bundle label (filtered first, raw second, then the rest)
+        let bundles_vec: Vec<String> = {
+            let mut tmp = Vec::new();
+            for b in ["filtered", "raw"] {
+                if global_bundles.remove(b) {
+                    tmp.push(b.to_string());
+                }
             }
+            tmp.extend(global_bundles);
+            tmp
+        };
+
+        // one H5 column per encountered file stem (filtered matrix first, then the rest)
+        let h5_vec: Vec<String> = {
+            let mut tmp = Vec::new();
+            for h in ["filtered_feature_bc_matrix", "raw_feature_bc_matrix"] {
+                if global_h5.remove(h) {
+                    tmp.push(h.to_string());
+                }
+            }
+            tmp.extend(global_h5);
+            tmp
+        };
+
+        // Organism column only if the rule file assigns organisms
+        let with_organism = !self.options.sample_rules.organism.is_empty();
+        let mut header: Vec<String> = vec!["Source_Path(s)".into(), "Sample_Lane".into(), "Layout".into()];
+        if with_organism {
+            header.push("Organism".into());
+        }
+        // suggested library strategy, if any sample has one
+        let with_strategy = self.samples.values().any(|r| self.library_strategy(&r.experiment, &r.name).is_some());
+        if with_strategy {
+            header.extend(["Library_Strategy".into(), "Strategy_Confidence".into()]);
+        }
+        let attributes = &self.options.sample_attributes;
+        header.extend(attributes.keys.iter().cloned());
+        if bundles_vec.is_empty() {
+            header.push("TenX".into());
+        }
+        header.extend(bundles_vec.iter().map(|b| format!("TenX_{}", b)));
+        if h5_vec.is_empty() {
+            header.push("H5".into());
+        }
+        header.extend(h5_vec.iter().map(|h| format!("H5_{}", h)));
+        header.extend(["Fragments".into(), "Alignment".into(), "PacBio".into()]);
+        for _lane_idx in 0..max_lanes {
+            header.extend(roles_vec.iter().cloned());
         }
-        writeln!(f)?;
 
-        // ---- rows ----
-        // Sort output by (experiment, sample) to keep stable
-        let mut keys: Vec<_> = self.samples.keys().cloned().collect();
-        keys.sort();
+        // column group (and lane block for read roles) of every header cell, for `--columns`
+        let mut groups: Vec<(String, usize)> =
+            vec![("source".into(), 0), ("sample".into(), 0), ("layout".into(), 0)];
+        if with_organism {
+            groups.push(("organism".into(), 0));
+        }
+        if with_strategy {
+            groups.extend(std::iter::repeat_n(("strategy".to_string(), 0), 2));
+        }
+        groups.extend(std::iter::repeat_n(("attributes".to_string(), 0), attributes.keys.len()));
+        groups.extend(std::iter::repeat_n(("tenx".to_string(), 0), bundles_vec.len().max(1)));
+        groups.extend(std::iter::repeat_n(("h5".to_string(), 0), h5_vec.len().max(1)));
+        groups.extend(["fragments", "alignment", "pacbio"].map(|g| (g.to_string(), 0)));
+        for lane_idx in 0..max_lanes {
+            groups.extend(roles_vec.iter().map(|r| (r.clone(), lane_idx)));
+        }
+        let mut rows = vec![header];
 
-        for key in keys {
-            let rec = self.samples.get(&key).unwrap();
+        // ---- rows ----
+        // BTreeMap order = (experiment, sample), keeps the output stable
+        for rec in self.samples.values() {
 
-            let src_folders = rec.collect_source_folders_for_record();
+            let src_folders = self.public_path(&rec.collect_source_folders_for_record());
             let sample_name = rec.name.clone();
 
             // TenX/H5 cells: GEO upload name or empty
-            let tenx_cell = rec
-                .tenx
-                .as_ref()
-                .map(|pf| pf.geo_filename() )
-                .unwrap_or_default();
+            let fmt = |pf: &ParsedFile| pf.geo_filename();
+            let tenx_cells = if bundles_vec.is_empty() {
+                vec![String::new()]
+            } else {
+                rec.tenx_cells(&bundles_vec, &fmt)
+            };
 
-            let h5_cell = rec
-                .h5_files
-                .as_ref()
-                .map(|pf| pf.geo_filename() )
-                .unwrap_or_default();
+            let h5_cells = if h5_vec.is_empty() {
+                vec![String::new()]
+            } else {
+                rec.h5_cells(&h5_vec, &fmt)
+            };
 
-            write!(f, "{}\t{}\t{}\t{}", src_folders, sample_name, tenx_cell, h5_cell)?;
+            let fragments_cell = rec
+                .fragments
+                .values()
+                .map(|pf| pf.geo_filename())
+                .collect::<Vec<_>>()
+                .join(",");
+
+            let alignment_cell = rec
+                .alignments
+                .values()
+                .map(|pf| pf.geo_filename())
+                .collect::<Vec<_>>()
+                .join(",");
+
+            let pacbio_cell = rec
+                .long_reads
+                .values()
+                .map(|pf| pf.geo_filename())
+                .collect::<Vec<_>>()
+                .join(",");
+
+            // single / paired-end; empty for samples without FASTQs
+            let layout = if rec.lanes.is_empty() { "" } else { rec.library_layout() };
+
+            let mut row = vec![src_folders, sample_name, layout.to_string()];
+            if with_organism {
+                row.push(self.organism(&rec.experiment, &rec.name).unwrap_or_default().to_string());
+            }
+            if with_strategy {
+                let (strategy, confidence) = self
+                    .library_strategy(&rec.experiment, &rec.name)
+                    .map(|(s, c)| (s, c.as_str().to_string()))
+                    .unwrap_or_default();
+                row.extend([strategy, confidence]);
+            }
+            match attributes.get(&rec.experiment, &rec.name) {
+                Some(values) => row.extend(values.iter().cloned()),
+                None => row.extend(attributes.keys.iter().map(|_| String::new())),
+            }
+            row.extend(tenx_cells);
+            row.extend(h5_cells);
+            row.extend([fragments_cell, alignment_cell, pacbio_cell]);
 
             // Render lanes in sorted lane-key order, but pad to max_lanes
-            let mut lane_keys: Vec<String> = rec.lanes.keys().cloned().collect();
-            lane_keys.sort();
-
-            for i in 0..max_lanes {
-                if let Some(lk) = lane_keys.get(i) {
-                    let lane = rec.lanes.get(lk).unwrap();
-                    let fmt = |pf: &ParsedFile| pf.geo_filename();
-                    let cells = lane.row_cells(&roles_vec, &fmt);
-                    for c in cells {
-                        write!(f, "\t{}", c)?;
-                    }
+            let mut lanes = rec.lanes.values();
+
+            for _ in 0..max_lanes {
+                if let Some(lane) = lanes.next() {
+                    row.extend(lane.row_cells(&roles_vec, &fmt));
                 } else {
                     // pad missing lanes with empty cells
-                    for _ in &roles_vec {
-                        write!(f, "\t")?;
-                    }
+                    row.extend(roles_vec.iter().map(|_| String::new()));
                 }
             }
 
-            writeln!(f)?;
+            rows.push(row);
         }
 
-        Ok(())
+        let rows = apply_layout(rows, &groups, &self.options.sample_columns, self.options.hide_empty_columns);
+        write_table(out_path, style, &rows)
+    }
+
+    /// Long ("tidy") sample table: one row per file, fixed columns, so it can be read
+    /// programmatically no matter how many lanes or bundles a sample has.
+    /// Rows are sorted by experiment, sample, lane, role and GEO name.
+    ///
+    /// Output columns:
+    /// experiment, sample, lane, role, kind, geo_name, source_path, md5, size
+    pub fn write_sample_files_long<P: AsRef<Path>>(&mut self, out_path: P) -> Result<()> {
+        for pf in self.iter_all_parsed_files_mut() {
+            let _ = pf.ensure_md5sum()?;
+        }
+
+        let mut rows: Vec<Vec<String>> = self
+            .iter_all_parsed_files()
+            .into_iter()
+            .map(|pf| {
+                let (lane, role) = match &pf.kind {
+                    ParsedKind::Fastq { lane, role } => (lane.clone(), role.clone()),
+                    _ => (String::new(), String::new()),
+                };
+                vec![
+                    pf.experiment.clone(),
+                    pf.sample.clone(),
+                    lane,
+                    role,
+                    pf.kind.file_type(),
+                    pf.geo_filename(),
+                    self.public_path(&pf.path),
+                    match pf.md5_display() {
+                        "none" => String::new(),
+                        md5 => md5.to_string(),
+                    },
+                    pf.file_size().to_string(),
+                ]
+            })
+            .collect();
+        rows.sort_by(|a, b| (&a[0], &a[1], &a[2], &a[3], &a[5]).cmp(&(&b[0], &b[1], &b[2], &b[3], &b[5])));
+
+        let header = ["experiment", "sample", "lane", "role", "kind", "geo_name", "source_path", "md5", "size"];
+        rows.insert(0, header.map(String::from).to_vec());
+        write_table(out_path, TableStyle::default(), &rows)
+    }
+}
+
+/// `name_<n>` with the number before the extension: `a_R1.fastq.gz` -> `a_R1_2.fastq.gz`.
+fn with_number_suffix(name: &str, n: usize) -> String {
+    match name.find('.').filter(|&dot| dot > 0) {
+        Some(dot) => format!("{}_{}{}", &name[..dot], n, &name[dot..]),
+        None => format!("{}_{}", name, n),
+    }
+}
+
+
+/// Build the pending 10x archives in parallel before the (serial) classification.
+pub(crate) fn package_bundles(bundles: &[(PathBuf, PathBuf)], options: &ScanOptions) {
+    if bundles.is_empty() {
+        return;
+    }
+    let workers = match options.bundle_jobs {
+        0 => std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1).min(4),
+        n => n,
+    };
+    eprintln!("Packaging {} 10x bundle(s) on {} worker(s)", bundles.len(), workers);
+    bundle_dirs(bundles, options.bundle_format, options.zip_level, workers);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn pf(experiment: &str, sample: &str, kind: ParsedKind, path: &str) -> ParsedFile {
+        ParsedFile::test_file(experiment, sample, kind, path)
+    }
+
+    #[test]
+    fn add_file_keeps_every_h5_label() {
+        let mut data = SampleFiles::new();
+        data.add_file(pf("exp1", "sampleA", ParsedKind::H5, "exp1/sampleA/outs/filtered_feature_bc_matrix.h5"));
+        data.add_file(pf("exp1", "sampleA", ParsedKind::H5, "exp1/sampleA/outs/raw_feature_bc_matrix.h5"));
+        data.add_file(pf("exp1", "sampleA", ParsedKind::H5, "exp1/sampleA/outs/molecule_info.h5"));
+
+        let rec = data.samples.values().next().unwrap();
+        let labels: Vec<&str> = rec.h5_files.keys().map(|s| s.as_str()).collect();
+        assert_eq!(labels, vec!["filtered_feature_bc_matrix", "molecule_info", "raw_feature_bc_matrix"]);
+    }
+
+    #[test]
+    fn undetermined_fastqs_are_tallied_not_added() {
+        let mut data = SampleFiles::new();
+        let kind = ParsedKind::Fastq { lane: "S0_L001".into(), role: "R1".into() };
+        data.add_file(pf("exp1", "Undetermined", kind.clone(), "exp1/Undetermined_S0_L001_R1_001.fastq.gz"));
+        data.add_file(pf("exp1", "sampleA", kind, "exp1/sampleA_S1_L001_R1_001.fastq.gz"));
+
+        assert_eq!(data.len(), 1);
+        assert_eq!(data.undetermined.len(), 1);
+    }
+
+    #[test]
+    fn multiple_roots_merge_into_one_model() {
+        let tmp = tempfile::tempdir().unwrap();
+        let run_a = tmp.path().join("runA");
+        let run_b = tmp.path().join("runB");
+        for (root, lane) in [(&run_a, "L001"), (&run_b, "L002")] {
+            let exp = root.join("exp1");
+            std::fs::create_dir_all(&exp).unwrap();
+            std::fs::write(exp.join(format!("sampleA_S1_{}_R1_001.fastq.gz", lane)), lane).unwrap();
+        }
+
+        let mut data = SampleFiles::new();
+        let (added, _) = data
+            .ingest_dirs(&[&run_a, &run_b], &[".fastq.gz".to_string()], &[])
+            .unwrap();
+
+        assert_eq!(added, 2);
+        assert_eq!(data.len(), 1);
+        let rec = data.samples.values().next().unwrap();
+        assert_eq!(rec.experiment, "exp1");
+        assert_eq!(rec.lanes.len(), 2);
+    }
+
+    #[test]
+    fn max_depth_bounds_the_walk() {
+        let tmp = tempfile::tempdir().unwrap();
+        let exp = tmp.path().join("exp1");
+        std::fs::create_dir_all(exp.join("deep/deeper")).unwrap();
+        std::fs::write(exp.join("sampleA_S1_L001_R1_001.fastq.gz"), "a").unwrap();
+        std::fs::write(exp.join("deep/deeper/sampleB_S2_L001_R1_001.fastq.gz"), "b").unwrap();
+
+        let mut data = SampleFiles::new();
+        data.options.max_depth = Some(2);
+        data.ingest_dir(tmp.path(), &[".fastq.gz".to_string()], &[]).unwrap();
+
+        assert_eq!(data.len(), 1);
+        assert_eq!(data.samples.values().next().unwrap().name, "sampleA");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlink_policy_controls_linked_files_and_folders() {
+        use crate::sample_files::SymlinkPolicy;
+
+        let tmp = tempfile::tempdir().unwrap();
+        let archive = tmp.path().join("archive");
+        std::fs::create_dir_all(&archive).unwrap();
+        std::fs::write(archive.join("sampleB_S2_L001_R1_001.fastq.gz"), "b").unwrap();
+        let linked_file = tmp.path().join("linked_S3_L001_R1_001.fastq.gz");
+        std::fs::write(&linked_file, "c").unwrap();
+
+        let root = tmp.path().join("root");
+        let exp = root.join("exp1");
+        std::fs::create_dir_all(&exp).unwrap();
+        std::fs::write(exp.join("sampleA_S1_L001_R1_001.fastq.gz"), "a").unwrap();
+        std::os::unix::fs::symlink(&archive, exp.join("archive")).unwrap();
+        std::os::unix::fs::symlink(&linked_file, exp.join("linked_S3_L001_R1_001.fastq.gz")).unwrap();
+
+        let count = |policy| {
+            let mut data = SampleFiles::new();
+            data.options.follow_symlinks = policy;
+            data.ingest_dir(&root, &[".fastq.gz".to_string()], &[]).unwrap();
+            data.len()
+        };
+        assert_eq!(count(SymlinkPolicy::Yes), 3);
+        assert_eq!(count(SymlinkPolicy::FilesOnly), 2);
+        assert_eq!(count(SymlinkPolicy::No), 1);
+    }
+
+    #[test]
+    fn read_only_accessors_expose_the_model() {
+        let mut data = SampleFiles::new();
+        let r1 = ParsedKind::Fastq { lane: "S1_L001".into(), role: "R1".into() };
+        let i1 = ParsedKind::Fastq { lane: "S1_L001".into(), role: "I1".into() };
+        data.add_file(pf("exp2", "sampleB", r1.clone(), "exp2/sampleB_S1_L001_R1_001.fastq.gz"));
+        data.add_file(pf("exp1", "sampleA", r1, "exp1/sampleA_S1_L001_R1_001.fastq.gz"));
+        data.add_file(pf("exp1", "sampleA", i1, "exp1/sampleA_S1_L001_I1_001.fastq.gz"));
+
+        assert_eq!(data.experiments(), vec!["exp1", "exp2"]);
+        assert_eq!(data.roles(), vec!["I1".to_string(), "R1".to_string()]);
+
+        let key = SampleKey { experiment: "exp1".into(), sample: "sampleA".into() };
+        assert_eq!(data.sample(&key).map(|r| r.name()), Some("sampleA"));
+        let files = data.files_for_sample(&key);
+        assert_eq!(files.len(), 2);
+        assert!(files.iter().all(|f| f.experiment() == "exp1"));
+        assert_eq!(data.samples().count(), 2);
+    }
+
+    #[test]
+    fn pairs_table_has_one_row_per_lane() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut data = SampleFiles::new();
+        for (lane, role) in [("L001", "R1"), ("L001", "R2"), ("L002", "R1")] {
+            let kind = ParsedKind::Fastq { lane: lane.into(), role: role.into() };
+            data.add_file(pf("exp1", "sampleA", kind, &format!("exp1/sampleA_{lane}_{role}_001.fastq.gz")));
+        }
+        let out = tmp.path().join("pairs.tsv");
+        data.write_fastq_pairs_table(&out).unwrap();
+        let text = std::fs::read_to_string(&out).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "Experiment\tSample\tLane\tI1\tI2\tR1\tR2");
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            lines[1],
+            "exp1\tsampleA\tL001\t\t\texp1_sampleA_L001_R1_001.fastq.gz\texp1_sampleA_L001_R2_001.fastq.gz"
+        );
+        assert_eq!(lines[2], "exp1\tsampleA\tL002\t\t\texp1_sampleA_L002_R1_001.fastq.gz\t");
+    }
+
+    #[test]
+    fn long_table_has_one_row_per_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        let exp = tmp.path().join("exp1");
+        std::fs::create_dir_all(&exp).unwrap();
+        std::fs::write(exp.join("sampleA_S1_L001_R2_001.fastq.gz"), "r2").unwrap();
+        std::fs::write(exp.join("sampleA_S1_L001_R1_001.fastq.gz"), "r1").unwrap();
+
+        let mut data = SampleFiles::new();
+        data.ingest_dir(tmp.path(), &[".fastq.gz".to_string()], &[]).unwrap();
+        let out = tmp.path().join("long.tsv");
+        data.write_sample_files_long(&out).unwrap();
+        let text = std::fs::read_to_string(&out).unwrap();
+        let lines: Vec<Vec<&str>> = text.lines().map(|l| l.split('\t').collect()).collect();
+        assert_eq!(lines[0], ["experiment", "sample", "lane", "role", "kind", "geo_name", "source_path", "md5", "size"]);
+        assert_eq!(lines.len(), 3);
+        assert_eq!(&lines[1][..5], ["exp1", "sampleA", "S1_L001", "R1", "fastq"]);
+        assert_eq!(lines[1][7].len(), 32);
+        assert_eq!(lines[2][3], "R2");
+        assert_eq!(lines[2][8], "2");
+    }
+
+    #[test]
+    fn tracks_move_to_the_longest_matching_sample() {
+        let mut data = SampleFiles::new();
+        let fastq = ParsedKind::Fastq { lane: "L001".into(), role: "R1".into() };
+        data.add_file(pf("exp1", "sampleA", fastq.clone(), "exp1/sampleA_L001_R1_001.fastq.gz"));
+        data.add_file(pf("exp1", "sampleA_ctrl", fastq, "exp1/sampleA_ctrl_L001_R1_001.fastq.gz"));
+        let track = ParsedKind::Track { format: "bigWig".into() };
+        data.add_file(pf("exp1", "sampleA-rep1", track.clone(), "exp1/tracks/sampleA-rep1.bw"));
+        data.add_file(pf("exp1", "sampleA_ctrl_treat", track.clone(), "exp1/tracks/sampleA_ctrl_treat.bw"));
+        data.add_file(pf("exp1", "other", track, "exp1/tracks/other.bw"));
+
+        data.attach_tracks_by_prefix();
+
+        let processed = |sample: &str| -> Vec<String> {
+            let key = SampleKey { experiment: "exp1".into(), sample: sample.into() };
+            data.samples[&key].processed.keys().cloned().collect()
+        };
+        assert_eq!(processed("sampleA"), vec!["sampleA-rep1.bw"]);
+        assert_eq!(processed("sampleA_ctrl"), vec!["sampleA_ctrl_treat.bw"]);
+        assert_eq!(processed("other"), vec!["other.bw"]);
+        assert_eq!(data.len(), 3);
+    }
+
+    #[test]
+    fn strict_mode_collects_swallowed_conditions() {
+        let mut data = SampleFiles::new();
+        let kind = ParsedKind::Fastq { lane: "L001".into(), role: "R1".into() };
+        data.add_file(pf("exp1", "sampleA", kind.clone(), "exp1/sampleA_L001_R1_001.fastq.gz"));
+        assert!(data.check_strict().is_ok());
+
+        data.add_file(pf("exp1", "sampleA", kind, "exp1/copy/sampleA_L001_R1_001.fastq.gz"));
+        let tenx = ParsedKind::TenX { bundle: "filtered".into(), format: "zip".into() };
+        data.add_file(pf("exp1", "sampleA", tenx.clone(), "exp1/sampleA/outs/filtered.zip"));
+        data.add_file(pf("exp1", "sampleA", tenx, "exp1/old/sampleA/outs/filtered.zip"));
+
+        let err = data.check_strict().unwrap_err().to_string();
+        assert!(err.starts_with("2 issues in strict mode"));
+        assert!(err.contains("Duplicate 10x 'filtered' bundle for exp1:sampleA ignored"));
+        assert!(err.contains("duplicate read role for exp1:sampleA lane L001 ignored: exp1/copy/"));
+    }
+
+    #[test]
+    fn only_conflicting_names_get_the_experiment_prefix() {
+        let mut data = SampleFiles::new();
+        let kind = ParsedKind::Fastq { lane: "L001".into(), role: "R1".into() };
+        for (experiment, sample, path, md5) in [
+            ("exp1", "sampleA", "exp1/sampleA_L001_R1_001.fastq.gz", "aaa"),
+            ("exp2", "sampleA", "exp2/sampleA_L001_R1_001.fastq.gz", "bbb"),
+            ("exp1", "sampleB", "exp1/sampleB_L001_R1_001.fastq.gz", "ccc"),
+        ] {
+            let mut file = pf(experiment, sample, kind.clone(), path);
+            file.md5sum = Some(md5.to_string());
+            assert!(data.add_parsed(file));
+        }
+        // H5 files are published as <sample>_<basename>: no clash between the two samples
+        for sample in ["sampleA", "sampleB"] {
+            let mut h5 = pf("exp1", sample, ParsedKind::H5, &format!("exp1/{sample}/outs/filtered_feature_bc_matrix.h5"));
+            h5.md5sum = Some(format!("md5_{sample}"));
+            data.add_file(h5);
+        }
+        assert!(data.experiment_prefix_forced());
+        assert_eq!(data.conflicting_basenames().collect::<Vec<_>>(), ["sampleA_L001_R1_001.fastq.gz"]);
+        assert_eq!(data.assign_upload_names(), 2);
+
+        let tmp = tempfile::tempdir().unwrap();
+        let table = tmp.path().join("md5sum.tsv");
+        data.write_md5_files_basename(&table).unwrap();
+        let names: Vec<String> =
+            std::fs::read_to_string(&table).unwrap().lines().skip(1).map(|l| l.split('\t').next().unwrap().to_string()).collect();
+        assert_eq!(
+            names,
+            [
+                "exp1_sampleA_L001_R1_001.fastq.gz",
+                "exp2_sampleA_L001_R1_001.fastq.gz",
+                "sampleA_filtered_feature_bc_matrix.h5",
+                "sampleB_L001_R1_001.fastq.gz",
+                "sampleB_filtered_feature_bc_matrix.h5",
+            ]
+        );
+        assert_eq!(data.geo_sample_name("exp2", "sampleA"), "exp2_sampleA");
+        assert_eq!(data.geo_sample_name("exp1", "sampleB"), "sampleB");
+    }
+
+    #[test]
+    fn conflict_suffixes_number_clashing_names() {
+        let mut data = SampleFiles::new();
+        let kind = ParsedKind::Fastq { lane: "L001".into(), role: "R1".into() };
+        data.add_file(pf("exp2", "sampleA", kind.clone(), "exp2/sampleA_L001_R1_001.fastq.gz"));
+        data.add_file(pf("exp1", "sampleA", kind.clone(), "exp1/sampleA_L001_R1_001.fastq.gz"));
+        data.add_file(pf("exp1", "sampleB", kind, "exp1/sampleB_L001_R1_001.fastq.gz"));
+        let h5 = pf("exp1", "sampleB", ParsedKind::H5, "exp1/sampleB/filtered_feature_bc_matrix.h5");
+        data.add_file(h5);
+
+        assert_eq!(data.use_conflict_suffixes(), 2);
+        let names: Vec<String> = data.iter_all_parsed_files().iter().map(|pf| pf.geo_filename()).collect();
+        assert_eq!(
+            names,
+            [
+                "sampleA_L001_R1_001_1.fastq.gz",
+                "sampleB_filtered_feature_bc_matrix.h5",
+                "sampleB_L001_R1_001.fastq.gz",
+                "sampleA_L001_R1_001_2.fastq.gz",
+            ]
+        );
+        assert_eq!(data.geo_sample_name("exp2", "sampleA"), "sampleA_2");
+        assert_eq!(data.geo_sample_name("exp1", "sampleB"), "sampleB");
+        assert_eq!(with_number_suffix("README", 3), "README_3");
+    }
+
+    #[test]
+    fn dedup_policy_decides_which_copy_is_kept() {
+        let copy = |path: &str| {
+            let kind = ParsedKind::Fastq { lane: "L001".into(), role: "R1".into() };
+            let mut f = pf("exp1", "sampleA", kind, path);
+            f.md5sum = Some("abc".to_string());
+            f
+        };
+        let paths = ["backup/old/exp1/sampleA_L001_R1_001.fastq.gz", "exp1/sampleA_L001_R1_001.fastq.gz"];
+        let kept_path = |policy: &str| {
+            let mut data = SampleFiles::builder().dedup(policy.parse().unwrap()).build();
+            let added: Vec<bool> = paths.iter().map(|p| data.add_parsed(copy(p))).collect();
+            let kept: Vec<String> = data.iter_all_parsed_files().iter().map(|f| f.path.clone()).collect();
+            (added, kept, data.dropped_copies.len())
+        };
+
+        assert_eq!(kept_path("keep-first"), (vec![true, false], vec![paths[0].to_string()], 1));
+        assert_eq!(kept_path("keep-shortest-path"), (vec![true, true], vec![paths[1].to_string()], 1));
+        assert_eq!(kept_path("prefer-path:exp1/*"), (vec![true, true], vec![paths[1].to_string()], 1));
+        assert_eq!(kept_path("off").2, 0);
+        assert!(crate::sample_files::scan_options::glob_match("*/old/*.fastq.gz", paths[0]));
+        assert!(!crate::sample_files::scan_options::glob_match("*/old/*.fq.gz", paths[0]));
+    }
+
+    #[test]
+    fn prefer_path_globs_rank_the_copies_in_order() {
+        let copy = |path: &str| {
+            let kind = ParsedKind::Fastq { lane: "L001".into(), role: "R1".into() };
+            let mut f = pf("exp1", "sampleA", kind, path);
+            f.md5sum = Some("abc".to_string());
+            f
+        };
+        let paths = ["backup", "scratch", "primary"].map(|root| format!("/mnt/{root}/exp1/sampleA_L001_R1_001.fastq.gz"));
+        let kept_path = |globs: &[&str]| {
+            let mut data = SampleFiles::builder().prefer_paths(globs.iter().copied()).build();
+            for p in &paths {
+                data.add_parsed(copy(p));
+            }
+            let kept: Vec<String> = data.iter_all_parsed_files().iter().map(|f| f.path.clone()).collect();
+            (kept, data.dropped_copies.len())
+        };
+
+        assert_eq!(kept_path(&["*/primary/*", "*/backup/*"]), (vec![paths[2].clone()], 2));
+        assert_eq!(kept_path(&["*/backup/*", "*/primary/*"]), (vec![paths[0].clone()], 2));
+        // unmatched copies rank last, ties fall back to the dedup policy (keep the first)
+        assert_eq!(kept_path(&["*/scratch/*"]), (vec![paths[1].clone()], 2));
+        assert_eq!(kept_path(&[]), (vec![paths[0].clone()], 2));
+
+        // a tie goes to the smaller path, whatever order the copies come in
+        let mut data = SampleFiles::builder().build();
+        for p in paths.iter().rev() {
+            data.add_parsed(copy(p));
+        }
+        assert_eq!(data.iter_all_parsed_files()[0].path, paths[0]);
+    }
+
+    #[test]
+    fn renamed_lane_copies_are_dropped_as_a_whole() {
+        let read = |sample: &str, dir: &str, role: &str, md5: &str| {
+            let kind = ParsedKind::Fastq { lane: "S1_L001".into(), role: role.into() };
+            let mut f = pf("exp1", sample, kind, &format!("{dir}/{sample}_S1_L001_{role}_001.fastq.gz"));
+            f.md5sum = Some(md5.to_string());
+            f
+        };
+        let mut data = SampleFiles::builder().dedup("prefer-path:exp1/primary/*".parse().unwrap()).build();
+        for (sample, dir) in [("sampleA_renamed", "exp1/backup"), ("sampleA", "exp1/primary")] {
+            data.add_parsed(read(sample, dir, "R1", "md5_r1"));
+            data.add_parsed(read(sample, dir, "R2", "md5_r2"));
+        }
+        // only one role in common is no copy of the lane
+        data.add_parsed(read("sampleB", "exp1/backup", "R1", "md5_r1"));
+        data.add_parsed(read("sampleB", "exp1/backup", "R2", "md5_other"));
+
+        let trees = data.dedup_lanes();
+        assert_eq!(trees, [("exp1/primary".to_string(), "exp1/backup".to_string(), 1)]);
+        let samples: Vec<&str> = data.samples.keys().map(|k| k.sample.as_str()).collect();
+        assert_eq!(samples, ["sampleA", "sampleB"]);
+        assert_eq!(data.dropped_copies.len(), 2);
+        assert_eq!(data.dropped_copies[0].1, "exp1/primary/sampleA_S1_L001_R1_001.fastq.gz");
+        assert_eq!(data.dropped_copies[0].2, "exp1/backup/sampleA_renamed_S1_L001_R1_001.fastq.gz");
     }
 }
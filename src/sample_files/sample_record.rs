@@ -1,5 +1,6 @@
 //sample_record.rs
-use super::{LaneFastqs, ParsedFile};
+use super::{DuplicateRole, FastqHeader, LaneFastqs, ParsedFile};
+use super::lane_fastqs::order_roles;
 
 use std::collections::{BTreeMap, BTreeSet};
 use std::path::{Path};
@@ -7,29 +8,102 @@ use std::path::{Path};
 
 #[derive(Debug, Default)]
 pub struct SampleRecord {
-    pub name: String,
+    pub(crate) name: String,
 
-    /// 10x bundle (zip), optional
-    pub tenx: Option<ParsedFile>,
+    /// 10x bundles (zip) keyed by bundle label (`filtered`, `raw`, `velocyto`, ...)
+    pub(crate) tenx: BTreeMap<String, ParsedFile>,
 
     /// keep a experiment hint in case of duplicate sample names!
-    pub experiment: String,
+    pub(crate) experiment: String,
 
-    /// h5 file, optional
-    pub h5_files: Option<ParsedFile>,
+    /// h5 files keyed by file stem (`filtered_feature_bc_matrix`, `molecule_info`, ...)
+    pub(crate) h5_files: BTreeMap<String, ParsedFile>,
 
     /// FASTQ lanes grouped by lane key, each containing role→path (R1/R2/I1/...)
-    pub lanes: BTreeMap<String, LaneFastqs>,
+    pub(crate) lanes: BTreeMap<String, LaneFastqs>,
+
+    /// ATAC fragments file and its tabix index, keyed by basename
+    pub(crate) fragments: BTreeMap<String, ParsedFile>,
+
+    /// BAM/CRAM files (+ indices) keyed by basename; only filled with `--include-bam`
+    pub(crate) alignments: BTreeMap<String, ParsedFile>,
+
+    /// PacBio HiFi/subreads BAMs and CCS FASTQs keyed by basename
+    pub(crate) long_reads: BTreeMap<String, ParsedFile>,
+
+    /// other processed data files (h5ad, loom, RDS, count matrices) keyed by basename
+    pub(crate) processed: BTreeMap<String, ParsedFile>,
+
+    /// lanes sequenced on several flowcells (top-ups), stored as `<lane>_<run>` lane keys
+    pub(crate) topup_lanes: BTreeSet<String>,
+}
+
+/// Run of a FASTQ for top-up lane keys: the flowcell of the first read header (read now if the
+/// scan did not), with `folder_fallback` else the parent folder name. Letters and digits only.
+fn run_tag(pf: &ParsedFile, folder_fallback: bool) -> Option<String> {
+    let flowcell = match &pf.header {
+        Some(h) => Some(h.flowcell.clone()),
+        None => FastqHeader::from_file(Path::new(&pf.path)).ok().flatten().map(|h| h.flowcell),
+    };
+    let folder = || {
+        Path::new(&pf.path).parent()?.file_name()?.to_str().map(str::to_string).filter(|_| folder_fallback)
+    };
+    flowcell
+        .filter(|f| !f.is_empty())
+        .or_else(folder)
+        .map(|t| t.chars().filter(|c| c.is_ascii_alphanumeric()).collect::<String>())
+        .filter(|t| !t.is_empty())
 }
 
 impl SampleRecord {
+
+    // ---------- read-only accessors ----------
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn experiment(&self) -> &str {
+        &self.experiment
+    }
+
+    /// 10x bundles keyed by bundle label
+    pub fn tenx(&self) -> &BTreeMap<String, ParsedFile> {
+        &self.tenx
+    }
+
+    /// h5 files keyed by file stem
+    pub fn h5_files(&self) -> &BTreeMap<String, ParsedFile> {
+        &self.h5_files
+    }
+
+    /// FASTQ lanes keyed by lane key
+    pub fn lanes(&self) -> &BTreeMap<String, LaneFastqs> {
+        &self.lanes
+    }
+
+    pub fn fragments(&self) -> &BTreeMap<String, ParsedFile> {
+        &self.fragments
+    }
+
+    pub fn alignments(&self) -> &BTreeMap<String, ParsedFile> {
+        &self.alignments
+    }
+
+    pub fn long_reads(&self) -> &BTreeMap<String, ParsedFile> {
+        &self.long_reads
+    }
+
+    pub fn processed(&self) -> &BTreeMap<String, ParsedFile> {
+        &self.processed
+    }
     
     pub fn fastq_source_folders(&self) -> String
     {
         let mut folders: BTreeSet<String> = BTreeSet::new();
 
-        for (_lane_key, lane) in &self.lanes {
-            for (_role, path) in &lane.reads {
+        for lane in self.lanes.values() {
+            for path in lane.reads.values() {
                 if let Some(parent) = Path::new(&path.path).parent() {
                     folders.insert(parent.to_string_lossy().to_string());
                 }
@@ -39,22 +113,33 @@ impl SampleRecord {
         folders.into_iter().collect::<Vec<_>>().join(",")
     }
 
-    /// Render a single flattened row for this sample: Sample + TenX + H5 + (lane blocks...)
-    pub fn row_cells<F>(&self, roles: &[String], fmt: &F, max_lanes: usize) -> Vec<String>
+    /// Render a single flattened row for this sample:
+    /// Sample + one TenX cell per bundle label + one H5 cell per label + Fragments + Alignment + PacBio + (lane blocks...)
+    pub fn row_cells<F>(
+        &self,
+        bundles: &[String],
+        h5_labels: &[String],
+        roles: &[String],
+        fmt: &F,
+        max_lanes: usize,
+    ) -> Vec<String>
     where
         F: Fn(&ParsedFile) -> String,
     {
-        let mut out = Vec::new();
-
         // first columns
-        out.push(self.fastq_source_folders());
-        out.push(self.name.clone());
-        out.push(self.tenx.as_ref().map(|p| p.path.clone()).unwrap_or_default());
-        out.push(self.h5_files.as_ref().map(|p| p.path.clone()).unwrap_or_default());
+        let mut out = vec![
+            self.fastq_source_folders(),
+            self.name.clone(),
+        ];
+        out.extend(self.tenx_cells(bundles, fmt));
+        out.extend(self.h5_cells(h5_labels, fmt));
+        out.push(self.fragments.values().map(|p| p.path.clone()).collect::<Vec<_>>().join(","));
+        out.push(self.alignments.values().map(|p| p.path.clone()).collect::<Vec<_>>().join(","));
+        out.push(self.long_reads.values().map(|p| p.path.clone()).collect::<Vec<_>>().join(","));
 
         // lane blocks (sorted by key)
         let mut lane_count = 0usize;
-        for (_lane_key, lane) in &self.lanes {
+        for lane in self.lanes.values() {
             out.extend(lane.row_cells(roles, fmt));
             lane_count += 1;
         }
@@ -62,38 +147,166 @@ impl SampleRecord {
         // pad missing lane blocks to max_lanes
         let missing_lanes = max_lanes.saturating_sub(lane_count);
         if missing_lanes > 0 {
-            out.extend(std::iter::repeat(String::new()).take(missing_lanes * roles.len()));
+            out.extend(std::iter::repeat_n(String::new(), missing_lanes * roles.len()));
         }
 
         out
     }
 
+    /// Render one cell per 10x bundle label in the provided `bundles` order.
+    pub fn tenx_cells<F>(&self, bundles: &[String], fmt: &F) -> Vec<String>
+    where
+        F: Fn(&ParsedFile) -> String,
+    {
+        bundles
+            .iter()
+            .map(|label| self.tenx.get(label).map(fmt).unwrap_or_default())
+            .collect()
+    }
+
+    /// Render one cell per H5 label in the provided `labels` order.
+    pub fn h5_cells<F>(&self, labels: &[String], fmt: &F) -> Vec<String>
+    where
+        F: Fn(&ParsedFile) -> String,
+    {
+        labels
+            .iter()
+            .map(|label| self.h5_files.get(label).map(fmt).unwrap_or_default())
+            .collect()
+    }
+
     /// Iterate all file paths that belong to this sample record:
-    /// - TenX bundle (if any)
-    /// - H5 file (if any)
+    /// - TenX bundles (if any)
+    /// - H5 files (if any)
     /// - all lane read files (FASTQs)
+    /// - fragments files
+    /// - BAM/CRAM files
+    /// - PacBio files
+    /// - other processed files
     pub fn all_paths<'a>(&'a self) -> impl Iterator<Item = &'a ParsedFile> + 'a {
-        let tenx = self.tenx.as_ref().into_iter();
-        let h5   = self.h5_files.as_ref().into_iter();
+        let tenx = self.tenx.values();
+        let h5   = self.h5_files.values();
         let fastqs = self
             .lanes
             .values()
-            .flat_map(|lane| lane.reads.values())
-            .map(|s| s);
+            .flat_map(|lane| lane.reads.values());
+        let fragments = self.fragments.values();
+        let alignments = self.alignments.values();
+        let long_reads = self.long_reads.values();
+        let processed = self.processed.values();
 
-        tenx.chain(h5).chain(fastqs)
+        tenx.chain(h5)
+            .chain(fastqs)
+            .chain(fragments)
+            .chain(alignments)
+            .chain(long_reads)
+            .chain(processed)
+    }
+
+    /// Mutable counterpart of `all_paths` (used for ensure_md5sum).
+    pub fn all_paths_mut<'a>(&'a mut self) -> impl Iterator<Item = &'a mut ParsedFile> + 'a {
+        let tenx = self.tenx.values_mut();
+        let h5   = self.h5_files.values_mut();
+        let fastqs = self
+            .lanes
+            .values_mut()
+            .flat_map(|lane| lane.reads.values_mut());
+        let fragments = self.fragments.values_mut();
+        let alignments = self.alignments.values_mut();
+        let long_reads = self.long_reads.values_mut();
+        let processed = self.processed.values_mut();
+
+        tenx.chain(h5)
+            .chain(fastqs)
+            .chain(fragments)
+            .chain(alignments)
+            .chain(long_reads)
+            .chain(processed)
+    }
+
+    /// Add a FASTQ to `lane`. A second file for a taken role from another flowcell is a
+    /// top-up: the lane is split into `<lane>_<flowcell>` keys, one per run, so both files are
+    /// submitted. With `DuplicateRole::Split` the parent folder stands in for an unreadable
+    /// flowcell. All other duplicates follow `strategy` (see `LaneFastqs::add_read_with`).
+    pub(crate) fn add_fastq(&mut self, lane: String, role: &str, pf: ParsedFile, strategy: DuplicateRole) {
+        let folder_fallback = strategy == DuplicateRole::Split;
+        if self.topup_lanes.contains(&lane) {
+            if let Some(tag) = run_tag(&pf, folder_fallback) {
+                self.lanes.entry(format!("{lane}_{tag}")).or_default().add_read_with(role, pf, strategy);
+                return;
+            }
+        }
+        let existing = self.lanes.get(&lane).and_then(|l| l.reads.get(role)).filter(|e| e.path != pf.path);
+        if let Some(existing) = existing {
+            if let (Some(old), Some(new)) = (run_tag(existing, folder_fallback), run_tag(&pf, folder_fallback)) {
+                if old != new {
+                    eprintln!(
+                        "Top-up sequencing: lane {} of {}:{} split into {}_{} and {}_{}",
+                        lane, self.experiment, self.name, lane, old, lane, new
+                    );
+                    let runs = self.lanes.remove(&lane).unwrap_or_default();
+                    self.lanes.insert(format!("{lane}_{old}"), runs);
+                    self.lanes.entry(format!("{lane}_{new}")).or_default().add_read_with(role, pf, strategy);
+                    self.topup_lanes.insert(lane);
+                    return;
+                }
+            }
+        }
+        self.lanes.entry(lane).or_default().add_read_with(role, pf, strategy);
+    }
+
+    /// Take the file with this path out of the record (empty lanes are dropped as well).
+    pub(crate) fn remove_path(&mut self, path: &str) -> Option<ParsedFile> {
+        for map in [
+            &mut self.tenx,
+            &mut self.h5_files,
+            &mut self.fragments,
+            &mut self.alignments,
+            &mut self.long_reads,
+            &mut self.processed,
+        ] {
+            if let Some(key) = map.iter().find(|(_, pf)| pf.path == path).map(|(k, _)| k.clone()) {
+                return map.remove(&key);
+            }
+        }
+        for (lane_key, lane) in self.lanes.iter_mut() {
+            if let Some(role) = lane.reads.iter().find(|(_, pf)| pf.path == path).map(|(r, _)| r.clone()) {
+                let removed = lane.reads.remove(&role);
+                if lane.reads.is_empty() {
+                    let lane_key = lane_key.clone();
+                    self.lanes.remove(&lane_key);
+                }
+                return removed;
+            }
+        }
+        None
     }
 
     /// Number of lanes
     pub fn len(&self) -> usize {
         self.lanes.len()
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.lanes.is_empty()
+    }
+
     pub fn total_len(&self) -> usize{
         let fastq = self.len();
-        let tenx  = self.tenx.iter().count();
-        let h5    = self.h5_files.iter().count();
+        let tenx  = self.tenx.len();
+        let h5    = self.h5_files.len();
 
-        fastq + tenx + h5
+        fastq + tenx + h5 + self.fragments.len() + self.alignments.len() + self.long_reads.len() + self.processed.len()
+    }
+
+    /// GEO "single or paired-end" value: paired-end as soon as one lane has R1 and R2.
+    /// Lanes that disagree are reported by `SampleFiles::layout_warnings`.
+    pub fn library_layout(&self) -> &'static str {
+        if self.lanes.values().any(|lane| lane.layout() == "paired-end") {
+            "paired-end"
+        } else {
+            "single"
+        }
     }
 
     /// GEO sample name: prefix with experiment when conflicts exist.
@@ -114,25 +327,10 @@ impl SampleRecord {
 
     /// Unique parent folders for all files referenced by this record, comma-separated.
     pub fn collect_source_folders_for_record(&self) -> String {
-        let mut set: BTreeSet<String> = BTreeSet::new();
-
-        if let Some(pf) = self.tenx.as_ref() {
-            if let Some(par) = Self::parent_dir_string(&pf.path) {
-                set.insert(par);
-            }
-        }
-        if let Some(pf) = self.h5_files.as_ref() {
-            if let Some(par) = Self::parent_dir_string(&pf.path) {
-                set.insert(par);
-            }
-        }
-        for lane in self.lanes.values() {
-            for pf in lane.reads.values() {
-                if let Some(par) = Self::parent_dir_string(&pf.path) {
-                    set.insert(par);
-                }
-            }
-        }
+        let set: BTreeSet<String> = self
+            .all_paths()
+            .filter_map(|pf| Self::parent_dir_string(&pf.path))
+            .collect();
 
         set.into_iter().collect::<Vec<_>>().join(",")
     }
@@ -144,8 +342,8 @@ impl SampleRecord {
         lanes
     }
 
-    /// Role names in stable order (I1/I2/R1/R2 first, then the rest alphabetically).
-    pub fn all_roles_sorted(&self) -> Vec<String> {
+    /// Role names in stable order (I1/I2/R1/R2 first, then `extra_roles`, then the rest alphabetically).
+    pub fn all_roles_sorted(&self, extra_roles: &[String]) -> Vec<String> {
         let mut set: BTreeSet<String> = BTreeSet::new();
         for lane in self.lanes.values() {
             for role in lane.reads.keys() {
@@ -153,14 +351,6 @@ impl SampleRecord {
             }
         }
 
-        let preferred = ["I1", "I2", "R1", "R2"];
-        let mut out = Vec::new();
-        for r in preferred {
-            if set.remove(r) {
-                out.push(r.to_string());
-            }
-        }
-        out.extend(set.into_iter());
-        out
+        order_roles(set, extra_roles)
     }
 }
@@ -0,0 +1,417 @@
+// src/sample_files/sample_rules.rs
+//! User defined sample detection (`--sample-rules rules.toml`).
+//!
+//! ```toml
+//! [[rule]]
+//! kind = "h5"              # fastq, h5, 10x, fragments, alignment, pacbio, processed, track or "*"
+//! folder_above = "outs"    # sample = folder above the nearest ancestor named `outs`
+//!
+//! [[rule]]
+//! kind = "fastq"
+//! regex = '^Lib-([A-Za-z0-9]+)_'   # sample = group `sample` or group 1, matched on the file name
+//! ```
+//!
+//! Rules are tried in file order; the first one that applies to the file kind and yields a
+//! sample wins. Files no rule matches fall back to the built-in detection.
+//!
+//! ```toml
+//! [merge_samples]
+//! sampleA = ["sampleA", "sampleA_reseq"]   # GEO sample = detected samples
+//! ```
+//!
+//! Merged samples become one GEO sample; FASTQ lanes keep the detected name as prefix
+//! (`sampleA_reseq_L001`) so lanes of both libraries are retained.
+//!
+//! ```toml
+//! [[split_samples]]
+//! sample = "sampleA"            # detected (or merged) sample
+//! geo_name = "sampleA_mouse2"
+//! lanes = ["L003", "L004"]      # FASTQ lanes and/or
+//! flowcell = "HXXXXXDSX2"       # flowcell from the first read header
+//! ```
+//!
+//! FASTQs of `sample` matching every given criterion are moved to `geo_name`.
+//!
+//! ```toml
+//! [organism]
+//! default = "Homo sapiens"
+//! experiments = { mouse_exp = "Mus musculus" }
+//! samples = { xenograft = "Mus musculus", "exp1/sampleB" = "Mus musculus" }
+//! ```
+//!
+//! Organism of every sample for the sample table and the GEO metadata: the sample entry
+//! (`experiment/sample` before a bare sample name) wins over the experiment, then `default`.
+//! `[library_strategy]` takes the same keys and replaces the inferred library strategy.
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::error::{GeoPrepError, Result};
+use crate::sample_files::fastq_header::filename_lane_number;
+use crate::sample_files::{ParsedFile, ParsedKind};
+
+/// How a rule derives the sample from the file path.
+#[derive(Debug, Clone)]
+pub enum SampleSource {
+    /// folder above the nearest ancestor folder with this name
+    FolderAbove(String),
+    /// folder directly below the nearest ancestor folder with this name
+    FolderBelow(String),
+    /// the folder holding the file
+    Parent,
+    /// named group `sample` (or group 1) of a regex matched against the file name
+    Regex(Regex),
+}
+
+#[derive(Debug, Clone)]
+pub struct SampleRule {
+    /// `ParsedKind` the rule applies to (see `kind_key`), `*` for all
+    pub kind: String,
+    pub source: SampleSource,
+}
+
+/// Ordered sample detection rules; empty = built-in detection only.
+#[derive(Debug, Clone, Default)]
+pub struct SampleRules {
+    pub rules: Vec<SampleRule>,
+    /// detected sample -> GEO sample (`[merge_samples]`)
+    pub merge: HashMap<String, String>,
+    /// FASTQs moved to another sample by lane or flowcell (`[[split_samples]]`)
+    pub split: Vec<SplitRule>,
+    /// organism per sample (`[organism]`)
+    pub organism: SampleValues,
+    /// library strategy per sample, replaces the inferred one (`[library_strategy]`)
+    pub library_strategy: SampleValues,
+}
+
+/// Per sample value of the rule file (`[organism]`, `[library_strategy]`); all empty = unset.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SampleValues {
+    pub default: Option<String>,
+    /// experiment -> value
+    #[serde(default)]
+    pub experiments: HashMap<String, String>,
+    /// sample or `experiment/sample` -> value
+    #[serde(default)]
+    pub samples: HashMap<String, String>,
+}
+
+impl SampleValues {
+    pub fn is_empty(&self) -> bool {
+        self.default.is_none() && self.experiments.is_empty() && self.samples.is_empty()
+    }
+
+    /// Value of `sample` in `experiment`, None if neither an entry nor a default applies.
+    pub fn lookup(&self, experiment: &str, sample: &str) -> Option<&str> {
+        self.samples
+            .get(&format!("{}/{}", experiment, sample))
+            .or_else(|| self.samples.get(sample))
+            .or_else(|| self.experiments.get(experiment))
+            .or(self.default.as_ref())
+            .map(String::as_str)
+    }
+}
+
+/// Moves the FASTQs of `sample` that match all given criteria to `geo_name`.
+#[derive(Debug, Clone)]
+pub struct SplitRule {
+    pub sample: String,
+    pub geo_name: String,
+    /// lane numbers (empty = any lane)
+    pub lanes: Vec<u32>,
+    pub flowcell: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RulesFile {
+    #[serde(default)]
+    rule: Vec<RawRule>,
+    #[serde(default)]
+    merge_samples: BTreeMap<String, Vec<String>>,
+    #[serde(default)]
+    split_samples: Vec<RawSplit>,
+    #[serde(default)]
+    organism: SampleValues,
+    #[serde(default)]
+    library_strategy: SampleValues,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawSplit {
+    sample: String,
+    geo_name: String,
+    #[serde(default)]
+    lanes: Vec<String>,
+    flowcell: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawRule {
+    kind: String,
+    folder_above: Option<String>,
+    folder_below: Option<String>,
+    #[serde(default)]
+    parent: bool,
+    regex: Option<String>,
+}
+
+const KINDS: [&str; 9] = ["fastq", "h5", "10x", "fragments", "alignment", "pacbio", "processed", "track", "*"];
+
+/// Rule file name of a kind.
+pub fn kind_key(kind: &ParsedKind) -> &'static str {
+    match kind {
+        ParsedKind::Fastq { .. } => "fastq",
+        ParsedKind::H5 => "h5",
+        ParsedKind::TenX { .. } => "10x",
+        ParsedKind::Fragments => "fragments",
+        ParsedKind::Alignment { .. } => "alignment",
+        ParsedKind::PacBio { .. } => "pacbio",
+        ParsedKind::ProcessedMatrix { .. } => "processed",
+        ParsedKind::Track { .. } => "track",
+    }
+}
+
+impl SampleRule {
+    /// Sample of `p` by this rule, None if the rule does not apply.
+    pub fn apply(&self, kind: &ParsedKind, p: &Path) -> Option<String> {
+        if self.kind != "*" && self.kind != kind_key(kind) {
+            return None;
+        }
+        match &self.source {
+            SampleSource::FolderAbove(marker) => ParsedFile::folder_above_marker(p, marker),
+            SampleSource::FolderBelow(marker) => ParsedFile::folder_below_marker(p, marker),
+            SampleSource::Parent => p.parent()?.file_name()?.to_str().map(str::to_string),
+            SampleSource::Regex(re) => {
+                let caps = re.captures(p.file_name()?.to_str()?)?;
+                caps.name("sample").or_else(|| caps.get(1)).map(|m| m.as_str().to_string())
+            }
+        }
+        .filter(|s| !s.is_empty())
+    }
+}
+
+impl SampleRules {
+    /// Parse a TOML rule file (see the module docs).
+    pub fn parse(text: &str, path: &Path) -> Result<Self> {
+        let invalid = |message: String| GeoPrepError::Config { path: path.to_path_buf(), message };
+        let file: RulesFile = toml::from_str(text).map_err(|e| invalid(e.message().to_string()))?;
+        let mut rules = Vec::new();
+        for (i, raw) in file.rule.into_iter().enumerate() {
+            let n = i + 1;
+            let kind = raw.kind.to_ascii_lowercase();
+            if !KINDS.contains(&kind.as_str()) {
+                return Err(invalid(format!("rule {n}: unknown kind '{}' ({})", raw.kind, KINDS.join(", "))));
+            }
+            let mut sources = Vec::new();
+            if let Some(m) = raw.folder_above {
+                sources.push(SampleSource::FolderAbove(m));
+            }
+            if let Some(m) = raw.folder_below {
+                sources.push(SampleSource::FolderBelow(m));
+            }
+            if raw.parent {
+                sources.push(SampleSource::Parent);
+            }
+            if let Some(re) = raw.regex {
+                let re = Regex::new(&re).map_err(|e| invalid(format!("rule {n}: {e}")))?;
+                if re.captures_len() < 2 {
+                    return Err(invalid(format!("rule {n}: regex needs a capture group for the sample")));
+                }
+                sources.push(SampleSource::Regex(re));
+            }
+            if sources.len() != 1 {
+                return Err(invalid(format!(
+                    "rule {n}: set exactly one of folder_above, folder_below, parent, regex"
+                )));
+            }
+            rules.push(SampleRule { kind, source: sources.remove(0) });
+        }
+        let mut merge = HashMap::new();
+        for (geo, detected) in file.merge_samples {
+            for d in detected {
+                if let Some(other) = merge.insert(d.clone(), geo.clone()) {
+                    return Err(invalid(format!("merge_samples: '{d}' is listed for '{other}' and '{geo}'")));
+                }
+            }
+        }
+        let mut split = Vec::new();
+        for (i, raw) in file.split_samples.into_iter().enumerate() {
+            let n = i + 1;
+            let lanes = raw
+                .lanes
+                .iter()
+                .map(|l| filename_lane_number(l).or_else(|| l.parse().ok()))
+                .collect::<Option<Vec<u32>>>()
+                .ok_or_else(|| invalid(format!("split_samples {n}: lanes must look like L001 or 1")))?;
+            if lanes.is_empty() && raw.flowcell.is_none() {
+                return Err(invalid(format!("split_samples {n}: set lanes and/or flowcell")));
+            }
+            split.push(SplitRule { sample: raw.sample, geo_name: raw.geo_name, lanes, flowcell: raw.flowcell });
+        }
+        Ok(SampleRules { rules, merge, split, organism: file.organism, library_strategy: file.library_strategy })
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::parse(&fs::read_to_string(path.as_ref())?, path.as_ref())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+            && self.merge.is_empty()
+            && self.split.is_empty()
+            && self.organism.is_empty()
+            && self.library_strategy.is_empty()
+    }
+
+    /// GEO sample a FASTQ of `sample` in `lane` is split off to, None if no split rule matches.
+    /// `flowcell` is only called (once) if a rule for `sample` asks for the flowcell.
+    pub fn split_name(
+        &self,
+        sample: &str,
+        lane: &str,
+        flowcell: impl FnOnce() -> Option<String>,
+    ) -> Option<&str> {
+        let mut flowcell = Some(flowcell);
+        let mut read: Option<Option<String>> = None;
+        let lane = filename_lane_number(lane);
+        self.split.iter().filter(|r| r.sample == sample).find_map(|r| {
+            if !r.lanes.is_empty() && !lane.is_some_and(|l| r.lanes.contains(&l)) {
+                return None;
+            }
+            if let Some(want) = &r.flowcell {
+                let got = read.get_or_insert_with(|| flowcell.take().and_then(|f| f()));
+                if got.as_deref() != Some(want.as_str()) {
+                    return None;
+                }
+            }
+            Some(r.geo_name.as_str())
+        })
+    }
+
+    /// GEO sample `detected` is merged into, None if it is not listed in `[merge_samples]`.
+    pub fn merged_name(&self, detected: &str) -> Option<&str> {
+        self.merge.get(detected).map(String::as_str)
+    }
+
+    /// Sample from the first matching rule.
+    pub fn sample_for(&self, kind: &ParsedKind, p: &Path) -> Option<String> {
+        self.rules.iter().find_map(|r| r.apply(kind, p))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RULES: &str = r#"
+        [[rule]]
+        kind = "fastq"
+        regex = '^Lib-(?P<sample>[A-Za-z0-9]+)_'
+
+        [[rule]]
+        kind = "h5"
+        folder_above = "count"
+
+        [[rule]]
+        kind = "*"
+        parent = true
+    "#;
+
+    #[test]
+    fn first_matching_rule_wins() {
+        let rules = SampleRules::parse(RULES, Path::new("rules.toml")).unwrap();
+        let fastq = ParsedKind::Fastq { lane: "L001".to_string(), role: "R1".to_string() };
+        let sample = |kind: &ParsedKind, p: &str| rules.sample_for(kind, Path::new(p));
+
+        assert_eq!(sample(&fastq, "exp1/raw/Lib-42a_S1_L001_R1_001.fastq.gz").as_deref(), Some("42a"));
+        // regex does not match: the catch-all parent rule applies
+        assert_eq!(sample(&fastq, "exp1/mouse3/x_S1_L001_R1_001.fastq.gz").as_deref(), Some("mouse3"));
+        assert_eq!(sample(&ParsedKind::H5, "exp1/sampleB/count/analysis/matrix.h5").as_deref(), Some("sampleB"));
+        assert_eq!(sample(&ParsedKind::Fragments, "exp1/sampleC/fragments.tsv.gz").as_deref(), Some("sampleC"));
+    }
+
+    #[test]
+    fn merged_samples_keep_their_lanes() {
+        let tmp = tempfile::tempdir().unwrap();
+        let exp = tmp.path().join("exp1");
+        fs::create_dir_all(&exp).unwrap();
+        for name in ["sampleA_S1_L001_R1_001.fastq.gz", "sampleA_reseq_S1_L001_R1_001.fastq.gz"] {
+            fs::write(exp.join(name), name).unwrap();
+        }
+        let rules =
+            SampleRules::parse("[merge_samples]\nsampleA = [\"sampleA_reseq\"]\n", Path::new("rules.toml")).unwrap();
+        let mut data = crate::sample_files::SampleFiles::builder().sample_rules(rules).build();
+        data.ingest(tmp.path()).unwrap();
+        assert_eq!(data.samples.len(), 1);
+        let rec = data.samples.values().next().unwrap();
+        assert_eq!(rec.name, "sampleA");
+        assert_eq!(rec.lanes.keys().collect::<Vec<_>>(), ["S1_L001", "sampleA_reseq_S1_L001"]);
+    }
+
+    #[test]
+    fn samples_are_split_by_lane_and_flowcell() {
+        let text = r#"
+            [[split_samples]]
+            sample = "sampleA"
+            geo_name = "sampleA_run2"
+            lanes = ["L003", "4"]
+
+            [[split_samples]]
+            sample = "sampleA"
+            geo_name = "sampleA_fc2"
+            flowcell = "HBBBBDSX2"
+        "#;
+        let rules = SampleRules::parse(text, Path::new("rules.toml")).unwrap();
+        let no_header = || -> Option<String> { panic!("flowcell read for a lane rule") };
+        assert_eq!(rules.split_name("sampleA", "S1_L004", no_header), Some("sampleA_run2"));
+        assert_eq!(rules.split_name("sampleB", "S1_L004", no_header), None);
+        let fc = |f: &str| {
+            let f = f.to_string();
+            move || Some(f)
+        };
+        assert_eq!(rules.split_name("sampleA", "L001", fc("HBBBBDSX2")), Some("sampleA_fc2"));
+        assert_eq!(rules.split_name("sampleA", "L001", fc("HAAAADSX2")), None);
+    }
+
+    #[test]
+    fn organism_by_sample_then_experiment_then_default() {
+        let text = r#"
+            [organism]
+            default = "Homo sapiens"
+            experiments = { mouse = "Mus musculus" }
+            samples = { xeno = "Mus musculus", "exp1/sampleB" = "Danio rerio" }
+        "#;
+        let organism = SampleRules::parse(text, Path::new("rules.toml")).unwrap().organism;
+        assert_eq!(organism.lookup("exp1", "sampleA"), Some("Homo sapiens"));
+        assert_eq!(organism.lookup("exp1", "sampleB"), Some("Danio rerio"));
+        assert_eq!(organism.lookup("exp2", "sampleB"), Some("Homo sapiens"));
+        assert_eq!(organism.lookup("exp2", "xeno"), Some("Mus musculus"));
+        assert_eq!(organism.lookup("mouse", "sampleA"), Some("Mus musculus"));
+        assert_eq!(SampleValues::default().lookup("exp1", "sampleA"), None);
+    }
+
+    #[test]
+    fn invalid_rules_are_rejected() {
+        let path = Path::new("rules.toml");
+        for text in [
+            "[[rule]]\nkind = \"bam\"\nparent = true\n",
+            "[[rule]]\nkind = \"h5\"\n",
+            "[[rule]]\nkind = \"h5\"\nparent = true\nfolder_above = \"outs\"\n",
+            "[[rule]]\nkind = \"fastq\"\nregex = '^Lib-'\n",
+            "[[rule]]\nkind = \"fastq\"\nglob = '*'\n",
+            "[merge_samples]\na = [\"x\", \"y\"]\nb = [\"y\"]\n",
+            "[[split_samples]]\nsample = \"a\"\ngeo_name = \"b\"\n",
+            "[[split_samples]]\nsample = \"a\"\ngeo_name = \"b\"\nlanes = [\"first\"]\n",
+            "[organism]\nspecies = \"Homo sapiens\"\n",
+        ] {
+            assert!(SampleRules::parse(text, path).is_err(), "{text}");
+        }
+    }
+}
@@ -0,0 +1,179 @@
+// src/sample_files/sample_sheet.rs
+//! Illumina `SampleSheet.csv` (bcl2fastq `[Data]`, BCL Convert `[BCLConvert_Data]`) as the
+//! authoritative source of sample names and indexes (`--sample-sheets`).
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::error::{GeoPrepError, Result};
+
+pub const SAMPLE_SHEET_FILE: &str = "SampleSheet.csv";
+
+/// One sample of the demultiplexing sheet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SheetRow {
+    pub sample_id: String,
+    pub sample_name: Option<String>,
+    pub index: Option<String>,
+    pub index2: Option<String>,
+    pub project: Option<String>,
+}
+
+impl SheetRow {
+    /// `Sample_Name`, or `Sample_ID` if the sheet has no name for the sample.
+    pub fn name(&self) -> &str {
+        self.sample_name.as_deref().unwrap_or(&self.sample_id)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SampleSheet {
+    pub path: PathBuf,
+    pub rows: Vec<SheetRow>,
+}
+
+fn cells(line: &str) -> Vec<String> {
+    line.split(',').map(|c| c.trim().trim_matches('"').to_string()).collect()
+}
+
+impl SampleSheet {
+    /// Rows of the data section; a file without `[...]` section headers is all data.
+    /// Rows repeated per lane are listed once.
+    pub fn parse(text: &str, path: &Path) -> Result<Self> {
+        let has_sections = text.lines().any(|l| l.trim_start().starts_with('['));
+        let mut in_data = !has_sections;
+        let mut header: Option<Vec<String>> = None;
+        let mut rows: Vec<SheetRow> = Vec::new();
+        for line in text.lines() {
+            let line = line.trim();
+            if line.starts_with('[') {
+                let section = line.split(']').next().unwrap_or_default().to_ascii_lowercase();
+                in_data = section == "[data" || section == "[bclconvert_data";
+                header = None;
+                continue;
+            }
+            if !in_data || line.trim_matches(',').is_empty() {
+                continue;
+            }
+            let Some(columns) = &header else {
+                header = Some(cells(line).iter().map(|c| c.to_ascii_lowercase()).collect());
+                continue;
+            };
+            let values = cells(line);
+            let get = |name: &str| {
+                columns
+                    .iter()
+                    .position(|c| c == name)
+                    .and_then(|i| values.get(i))
+                    .filter(|v| !v.is_empty())
+                    .cloned()
+            };
+            let Some(sample_id) = get("sample_id") else { continue };
+            if rows.iter().any(|r| r.sample_id == sample_id) {
+                continue;
+            }
+            rows.push(SheetRow {
+                sample_id,
+                sample_name: get("sample_name"),
+                index: get("index"),
+                index2: get("index2"),
+                project: get("sample_project"),
+            });
+        }
+        if header.is_none() && rows.is_empty() {
+            return Err(GeoPrepError::Config {
+                path: path.to_path_buf(),
+                message: "no [Data] section with a Sample_ID column".to_string(),
+            });
+        }
+        Ok(SampleSheet { path: path.to_path_buf(), rows })
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::parse(&fs::read_to_string(path.as_ref())?, path.as_ref())
+    }
+
+    /// Row of a demultiplexed FASTQ named `<Sample_Name|Sample_ID>_S<n>_...`
+    /// (bcl2fastq uses the name, BCL Convert the id); the longest match wins.
+    pub fn row_for(&self, file_name: &str) -> Option<&SheetRow> {
+        let matches = |prefix: &str| {
+            file_name
+                .strip_prefix(prefix)
+                .and_then(|rest| rest.strip_prefix("_S"))
+                .is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_digit()))
+        };
+        self.rows
+            .iter()
+            .flat_map(|r| std::iter::once((r, r.sample_id.as_str())).chain(r.sample_name.as_deref().map(|n| (r, n))))
+            .filter(|(_, prefix)| matches(prefix))
+            .max_by_key(|(_, prefix)| prefix.len())
+            .map(|(r, _)| r)
+    }
+}
+
+/// Sample sheets of the run folders, looked up once per folder and shared between clones.
+#[derive(Debug, Clone, Default)]
+pub struct SampleSheets {
+    cache: Arc<Mutex<HashMap<PathBuf, Option<Arc<SampleSheet>>>>>,
+}
+
+impl SampleSheets {
+    /// Sheet of the nearest ancestor folder of `file` holding a `SampleSheet.csv`.
+    /// Unreadable sheets are reported once on stderr and ignored.
+    pub fn for_file(&self, file: &Path) -> Option<Arc<SampleSheet>> {
+        let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        for dir in file.ancestors().skip(1).take_while(|d| !d.as_os_str().is_empty()) {
+            let entry = cache.entry(dir.to_path_buf()).or_insert_with(|| {
+                let path = dir.join(SAMPLE_SHEET_FILE);
+                if !path.is_file() {
+                    return None;
+                }
+                match SampleSheet::load(&path) {
+                    Ok(sheet) => Some(Arc::new(sheet)),
+                    Err(e) => {
+                        eprintln!("WARN: ignoring sample sheet {}: {}", path.display(), e);
+                        None
+                    }
+                }
+            });
+            if let Some(sheet) = entry {
+                return Some(sheet.clone());
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHEET: &str = "[Header]\nIEMFileVersion,5\n\n[Reads]\n151\n151\n\n[Data]\n\
+        Lane,Sample_ID,Sample_Name,index,index2,Sample_Project\n\
+        1,S001,Liver_Rep1,ACGTACGT,TTGCAAGG,ProjA\n\
+        2,S001,Liver_Rep1,ACGTACGT,TTGCAAGG,ProjA\n\
+        1,S002,Liver_Rep1_b,GGTTCCAA,,ProjA\n\
+        1,S003,,CCAAGGTT,,ProjB\n";
+
+    #[test]
+    fn data_rows_are_read_once() {
+        let sheet = SampleSheet::parse(SHEET, Path::new(SAMPLE_SHEET_FILE)).unwrap();
+        assert_eq!(sheet.rows.len(), 3);
+        assert_eq!(sheet.rows[0].index2.as_deref(), Some("TTGCAAGG"));
+        assert_eq!(sheet.rows[1].index2, None);
+        assert_eq!(sheet.rows[2].name(), "S003");
+        assert_eq!(sheet.rows[2].project.as_deref(), Some("ProjB"));
+        assert!(SampleSheet::parse("[Header]\nDate,2024\n", Path::new(SAMPLE_SHEET_FILE)).is_err());
+    }
+
+    #[test]
+    fn fastqs_are_matched_by_name_or_id() {
+        let sheet = SampleSheet::parse(SHEET, Path::new(SAMPLE_SHEET_FILE)).unwrap();
+        let id = |f: &str| sheet.row_for(f).map(|r| r.sample_id.as_str());
+        assert_eq!(id("Liver_Rep1_S1_L001_R1_001.fastq.gz"), Some("S001"));
+        assert_eq!(id("Liver_Rep1_b_S2_L001_R1_001.fastq.gz"), Some("S002"));
+        assert_eq!(id("S003_S3_L001_R1_001.fastq.gz"), Some("S003"));
+        assert_eq!(id("Kidney_S4_L001_R1_001.fastq.gz"), None);
+    }
+}
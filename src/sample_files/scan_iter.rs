@@ -0,0 +1,304 @@
+// src/sample_files/scan_iter.rs
+use std::collections::{BTreeSet, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::error::{GeoPrepError, Result};
+use crate::interrupt;
+use crate::sample_files::checksum::MD5SUMS_FILE;
+use crate::sample_files::demux_stats::is_demux_report_dir;
+use crate::sample_files::key_index::{key128, KeySet};
+use crate::sample_files::walk::{WalkConfig, WalkEntry, Walker};
+use crate::sample_files::{DemuxStats, ParsedFile, SampleFiles, ScanOptions, SymlinkPolicy};
+
+/// Lazily walks a scan root and classifies every relevant entry.
+///
+/// Yields `Ok(ParsedFile)` for recognised files/bundles and `Err` for entries that looked
+/// relevant but could not be parsed; nothing is added to a model until the caller does so
+/// (e.g. with `SampleFiles::add_parsed`).
+pub struct ScanIter {
+    walker: Walker,
+    scan_root: PathBuf,
+    options: ScanOptions,
+    visited_dirs: HashSet<(u64, u64)>,
+    /// key128 of every canonical path handed out, so a file reached twice via links counts once
+    visited_paths: KeySet,
+    /// entries seen by the walk so far
+    pub visited: usize,
+    /// files dropped by the suffix filter so far
+    pub ignored_unmatched: usize,
+    /// (file, reason) of every file that was not classified, only with
+    /// `ScanOptions::audit_unclassified`
+    pub unclassified: Vec<(PathBuf, String)>,
+    /// read counts of the skipped demultiplexing reports, only with `ScanOptions::demux_stats`
+    pub demux_stats: DemuxStats,
+}
+
+impl ScanIter {
+    /// Walk `scan_root` honouring the suffixes, excludes, depth and symlink settings of `options`.
+    pub fn new<P: AsRef<Path>>(scan_root: P, options: ScanOptions) -> Self {
+        let scan_root = scan_root.as_ref().to_path_buf();
+        let config = WalkConfig {
+            root: scan_root.clone(),
+            follow_links: options.follow_symlinks == SymlinkPolicy::Yes,
+            min_depth: options.min_depth,
+            max_depth: options.max_depth,
+            prune: is_demux_report_dir,
+        };
+        ScanIter {
+            walker: Walker::new(config, options.walk_threads),
+            scan_root,
+            visited_paths: KeySet::with_limit(options.spill_index),
+            options,
+            visited_dirs: HashSet::new(),
+            visited: 0,
+            ignored_unmatched: 0,
+            unclassified: Vec::new(),
+            demux_stats: DemuxStats::default(),
+        }
+    }
+
+    /// Record an unclassified file; our own md5 caches are not worth reporting.
+    fn audit(&mut self, p: &Path, reason: impl Into<String>) {
+        let own_cache = p.file_name().is_some_and(|n| {
+            let n = n.to_string_lossy();
+            n.ends_with(".md5sum") || n == MD5SUMS_FILE
+        });
+        if self.options.audit_unclassified && !own_cache && p.is_file() {
+            self.unclassified.push((p.to_path_buf(), reason.into()));
+        }
+    }
+
+    /// Walk filters that decide whether `p` is handed to the parser at all.
+    fn wanted(&mut self, entry: &WalkEntry) -> bool {
+        let p = entry.path();
+
+        // without following, links are yielded but not descended into
+        if entry.path_is_symlink() {
+            match self.options.follow_symlinks {
+                SymlinkPolicy::Yes => {}
+                SymlinkPolicy::No => return false,
+                SymlinkPolicy::FilesOnly if !p.is_file() => return false,
+                SymlinkPolicy::FilesOnly => {}
+            }
+        }
+
+        // bcl2fastq/BCL Convert Reports/, Stats/ and InterOp/ folders are never descended into
+        if entry.is_dir() && entry.depth() > 0 && is_demux_report_dir(p) {
+            self.walker.skip_current_dir(p);
+            if self.options.demux_stats {
+                if let Some(stats) = DemuxStats::from_report_dir(p) {
+                    self.demux_stats.merge(stats);
+                }
+            }
+            if self.options.audit_unclassified {
+                self.unclassified.push((p.to_path_buf(), "demultiplexing report folder (skipped)".to_string()));
+            }
+            return false;
+        }
+
+        // Exclude early
+        if is_excluded_path(p, &self.options.excludes) {
+            self.audit(p, "excluded (--exclude)");
+            return false;
+        }
+
+        // Optional suffix filter early (lets user ignore e.g. .mtx.gz)
+        // Note: directories won't match suffixes; that's fine because TenX is triggered by matrix.mtx.gz.
+        if p.is_file() && !matches_suffixes(p, &self.options.suffixes) {
+            self.ignored_unmatched += 1;
+            self.audit(p, "suffix not selected (--suffix)");
+            return false;
+        }
+
+        if let Ok(md) = p.metadata() {
+            if md.is_file() {
+                if let Some(reason) = self.options.size_time_filter(&md) {
+                    self.audit(p, reason);
+                    return false;
+                }
+            }
+        }
+
+        // directory loop protection (unix only; ok)
+        if let Ok(md) = p.metadata() {
+            if md.is_dir() {
+                #[cfg(unix)]
+                {
+                    use std::os::unix::fs::MetadataExt;
+                    let key = (md.dev(), md.ino());
+                    if !self.visited_dirs.insert(key) {
+                        return false;
+                    }
+                }
+            }
+        }
+
+        // avoid reprocessing same path
+        let canon = std::fs::canonicalize(p).unwrap_or_else(|_| p.to_path_buf());
+        self.visited_paths.insert(key128(canon.as_os_str().as_encoded_bytes()))
+    }
+}
+
+impl ScanIter {
+    /// Walk the whole tree once and keep the files that pass the walk filters, together with
+    /// the 10x archives that still have to be packaged, so these can be built in parallel
+    /// before the (serial) classification with `classify`.
+    pub(crate) fn collect_wanted(&mut self) -> (Vec<PathBuf>, Vec<(PathBuf, PathBuf)>) {
+        let mut files = Vec::new();
+        let mut jobs = BTreeSet::new();
+        while let Some(entry) = self.walker.next() {
+            if interrupt::interrupted() {
+                break;
+            }
+            self.visited += 1;
+            if !self.wanted(&entry) || entry.is_dir() {
+                continue;
+            }
+            if let Some(job) = ParsedFile::pending_tenx_bundle(&self.scan_root, entry.path(), &self.options) {
+                jobs.insert(job);
+            }
+            files.push(entry.path().to_path_buf());
+        }
+        (files, jobs.into_iter().collect())
+    }
+
+    /// Classify one entry that passed the walk filters; None if it is not relevant.
+    pub(crate) fn classify(&mut self, p: &Path) -> Option<Result<ParsedFile>> {
+        match ParsedFile::from_path_with(&self.scan_root, p, &self.options) {
+            Ok(Some(pf)) => Some(Ok(pf)),
+            Ok(None) => {
+                let reason = ParsedFile::unclassified_reason(p, &self.options);
+                self.audit(p, reason);
+                None
+            }
+            Err(e) => {
+                self.audit(p, format!("parse failure: {}", e));
+                Some(Err(GeoPrepError::Parse { path: p.to_path_buf(), source: Box::new(e) }))
+            }
+        }
+    }
+}
+
+impl Iterator for ScanIter {
+    type Item = Result<ParsedFile>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if interrupt::interrupted() {
+                return None;
+            }
+            let entry = self.walker.next()?; // unreadable entries are skipped, as before
+            self.visited += 1;
+            if !self.wanted(&entry) {
+                continue;
+            }
+            if let Some(item) = self.classify(entry.path()) {
+                return Some(item);
+            }
+        }
+    }
+}
+
+fn is_excluded_path(p: &Path, excludes: &[String]) -> bool {
+    if excludes.is_empty() {
+        return false;
+    }
+    let p_str = p.to_string_lossy();
+    for ex in excludes {
+        if ex.is_empty() { continue; }
+        if p_str.contains(ex) { return true; }
+        if p.components().any(|c| c.as_os_str().to_string_lossy() == ex.as_str()) {
+            return true;
+        }
+    }
+    false
+}
+
+fn matches_suffixes(p: &Path, suffixes: &[String]) -> bool {
+    if suffixes.is_empty() {
+        return true; // treat empty as "no filter"
+    }
+    let s = p.to_string_lossy();
+    suffixes.iter().any(|suf| s.ends_with(suf))
+}
+
+impl SampleFiles {
+    /// Iterate the classified files below `scan_root` without adding them to the model,
+    /// so callers can filter, report progress or stop early. Uses the configured `options`.
+    pub fn scan_iter<P: AsRef<Path>>(&self, scan_root: P) -> ScanIter {
+        ScanIter::new(scan_root, self.options.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scan_iter_yields_without_adding() {
+        let tmp = tempfile::tempdir().unwrap();
+        let exp = tmp.path().join("exp1");
+        std::fs::create_dir_all(&exp).unwrap();
+        std::fs::write(exp.join("sampleA_S1_L001_R1_001.fastq.gz"), "a").unwrap();
+        std::fs::write(exp.join("sampleB_S2_L001_R1_001.fastq.gz"), "b").unwrap();
+        std::fs::write(exp.join("sampleC.fastq.gz"), "c").unwrap();
+
+        let mut data = SampleFiles::builder().suffixes([".fastq.gz"]).build();
+        let items: Vec<_> = data.scan_iter(tmp.path()).collect();
+        assert_eq!(items.len(), 3);
+        assert_eq!(items.iter().filter(|r| r.is_err()).count(), 1);
+        assert!(data.is_empty());
+
+        for pf in items.into_iter().flatten().filter(|pf| pf.sample != "sampleB") {
+            data.add_parsed(pf);
+        }
+        assert_eq!(data.len(), 1);
+    }
+
+    #[test]
+    fn unclassified_files_are_audited_with_a_reason() {
+        let tmp = tempfile::tempdir().unwrap();
+        let exp = tmp.path().join("exp1");
+        std::fs::create_dir_all(&exp).unwrap();
+        std::fs::write(exp.join("sampleA_S1_L001_R1_001.fastq.gz"), "a").unwrap();
+        std::fs::write(exp.join("sampleC.fastq.gz"), "c").unwrap();
+        std::fs::write(exp.join("sampleD.bam.fastq.gz"), "s").unwrap();
+        std::fs::write(exp.join("notes.txt"), "n").unwrap();
+        std::fs::write(exp.join("notes.txt.md5sum"), "n").unwrap();
+
+        let data = SampleFiles::builder().suffixes([".fastq.gz"]).audit_unclassified(true).build();
+        let mut iter = data.scan_iter(tmp.path());
+        assert_eq!(iter.by_ref().filter(|r| r.is_ok()).count(), 1);
+
+        let reasons: std::collections::BTreeMap<String, String> = iter
+            .unclassified
+            .iter()
+            .map(|(p, r)| (p.file_name().unwrap().to_string_lossy().to_string(), r.clone()))
+            .collect();
+        assert_eq!(reasons.len(), 3);
+        assert_eq!(reasons["sampleD.bam.fastq.gz"], "accession-like name (see --keep-accession-like)");
+        assert_eq!(reasons["notes.txt"], "suffix not selected (--suffix)");
+        assert!(reasons["sampleC.fastq.gz"].starts_with("parse failure: "));
+    }
+
+    #[test]
+    fn size_and_date_filters_skip_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        let exp = tmp.path().join("exp1");
+        std::fs::create_dir_all(&exp).unwrap();
+        std::fs::write(exp.join("sampleA_S1_L001_R1_001.fastq.gz"), "").unwrap();
+        std::fs::write(exp.join("sampleB_S2_L001_R1_001.fastq.gz"), "bbbb").unwrap();
+
+        let data = SampleFiles::builder().suffixes([".fastq.gz"]).min_size(Some(1)).build();
+        let samples: Vec<String> = data.scan_iter(tmp.path()).flatten().map(|pf| pf.sample).collect();
+        assert_eq!(samples, ["sampleB"]);
+
+        let tomorrow = std::time::SystemTime::now() + std::time::Duration::from_secs(86_400);
+        let data = SampleFiles::builder().suffixes([".fastq.gz"]).newer_than(Some(tomorrow)).build();
+        assert_eq!(data.scan_iter(tmp.path()).count(), 0);
+
+        assert_eq!(crate::sample_files::scan_options::parse_size("1.5K"), Ok(1536));
+        let date = crate::sample_files::scan_options::parse_date("2024-03-01").unwrap();
+        assert_eq!(date.duration_since(std::time::UNIX_EPOCH).unwrap().as_secs(), 1_709_251_200);
+    }
+}
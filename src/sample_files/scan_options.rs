@@ -0,0 +1,501 @@
+// src/sample_files/scan_options.rs
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::error::{GeoPrepError, Result};
+use crate::sample_files::{HashIo, SampleAttributes, SampleRules, SampleSheets};
+
+/// Which symbolic links `SampleFiles::ingest_dir` follows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SymlinkPolicy {
+    /// follow every link, including links to folders (classic behaviour)
+    #[default]
+    Yes,
+    /// ignore all symbolic links
+    No,
+    /// collect linked files but never descend into linked folders (e.g. tape-backed archives)
+    FilesOnly,
+}
+
+impl std::str::FromStr for SymlinkPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "yes" => Ok(SymlinkPolicy::Yes),
+            "no" => Ok(SymlinkPolicy::No),
+            "files-only" => Ok(SymlinkPolicy::FilesOnly),
+            other => Err(format!("unknown symlink policy '{}' (yes, no, files-only)", other)),
+        }
+    }
+}
+
+/// Where `ParsedFile::ensure_md5sum` caches computed md5 sums.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Md5Store {
+    /// one `<file>.md5sum` sidecar next to every file
+    #[default]
+    Sidecar,
+    /// a single `MD5SUMS` file per directory (fewer tiny files on Lustre/GPFS)
+    Directory,
+}
+
+impl std::str::FromStr for Md5Store {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "sidecar" => Ok(Md5Store::Sidecar),
+            "directory" => Ok(Md5Store::Directory),
+            other => Err(format!("unknown md5 store '{}' (sidecar, directory)", other)),
+        }
+    }
+}
+
+/// `--recompress-to`: what happens to bzip2/xz/zstd (and plain) FASTQs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RecompressTo {
+    /// upload them as they are (reported, GEO requires gzip)
+    #[default]
+    Keep,
+    /// write a gzip copy and upload that
+    Gz,
+}
+
+impl std::str::FromStr for RecompressTo {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "keep" => Ok(RecompressTo::Keep),
+            "gz" => Ok(RecompressTo::Gz),
+            other => Err(format!("unknown recompression target '{}' (keep, gz)", other)),
+        }
+    }
+}
+
+/// Archive format of the packaged 10x matrix triplets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BundleFormat {
+    #[default]
+    Zip,
+    TarGz,
+}
+
+impl BundleFormat {
+    /// File extension without the leading dot (`zip`, `tar.gz`).
+    pub fn extension(&self) -> &'static str {
+        match self {
+            BundleFormat::Zip => "zip",
+            BundleFormat::TarGz => "tar.gz",
+        }
+    }
+}
+
+impl std::str::FromStr for BundleFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "zip" => Ok(BundleFormat::Zip),
+            "tar.gz" | "tgz" => Ok(BundleFormat::TarGz),
+            other => Err(format!("unknown bundle format '{}' (zip, tar.gz)", other)),
+        }
+    }
+}
+
+/// How 10x matrix triplets are submitted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TenxMode {
+    /// one archive per triplet folder (see `BundleFormat`)
+    #[default]
+    Bundle,
+    /// barcodes, features and matrix as three renamed processed files
+    Individual,
+}
+
+impl std::str::FromStr for TenxMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "zip" | "bundle" => Ok(TenxMode::Bundle),
+            "individual" => Ok(TenxMode::Individual),
+            other => Err(format!("unknown 10x mode '{}' (zip, individual)", other)),
+        }
+    }
+}
+
+/// Which copy survives when the same file (same basename and md5) is found more than once.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum DedupPolicy {
+    /// keep the copy found first in path order, drop later ones (classic behaviour)
+    #[default]
+    KeepFirst,
+    /// keep the copy with the shortest path
+    KeepShortestPath,
+    /// keep the copy whose path matches this glob (`*` and `?`), e.g. `*/primary_project/*`
+    PreferPath(String),
+    /// keep every copy
+    Off,
+}
+
+impl DedupPolicy {
+    /// True if `candidate` should replace the already kept copy `kept`.
+    pub fn prefers(&self, candidate: &str, kept: &str) -> bool {
+        match self {
+            DedupPolicy::KeepFirst | DedupPolicy::Off => false,
+            DedupPolicy::KeepShortestPath => candidate.len() < kept.len(),
+            DedupPolicy::PreferPath(glob) => glob_match(glob, candidate) && !glob_match(glob, kept),
+        }
+    }
+}
+
+impl std::str::FromStr for DedupPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        if let Some(glob) = s.strip_prefix("prefer-path:").or_else(|| s.strip_prefix("prefer-path=")) {
+            return Ok(DedupPolicy::PreferPath(glob.to_string()));
+        }
+        match s.to_ascii_lowercase().as_str() {
+            "keep-first" => Ok(DedupPolicy::KeepFirst),
+            "keep-shortest-path" => Ok(DedupPolicy::KeepShortestPath),
+            "off" => Ok(DedupPolicy::Off),
+            other => Err(format!(
+                "unknown dedup policy '{}' (keep-first, keep-shortest-path, prefer-path:<glob>, off)",
+                other
+            )),
+        }
+    }
+}
+
+/// Which FASTQ keeps a read role when a lane has several files for it (`--on-duplicate-role`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicateRole {
+    /// keep the file found first in path order (classic behaviour)
+    #[default]
+    KeepFirst,
+    /// keep the most recently modified file
+    KeepNewest,
+    /// keep the largest file
+    KeepLargest,
+    /// keep the first, but fail the run after the scan
+    Error,
+    /// keep both as top-up runs: lane keys `<lane>_<flowcell or parent folder>`
+    Split,
+}
+
+impl std::str::FromStr for DuplicateRole {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "keep-first" => Ok(DuplicateRole::KeepFirst),
+            "keep-newest" => Ok(DuplicateRole::KeepNewest),
+            "keep-largest" => Ok(DuplicateRole::KeepLargest),
+            "error" => Ok(DuplicateRole::Error),
+            "split" => Ok(DuplicateRole::Split),
+            other => Err(format!(
+                "unknown duplicate role strategy '{}' (error, keep-newest, keep-largest, keep-first, split)",
+                other
+            )),
+        }
+    }
+}
+
+/// Shell style match of the whole `text`: `*` matches any run of characters (including `/`),
+/// `?` exactly one.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let (p, t): (Vec<char>, Vec<char>) = (pattern.chars().collect(), text.chars().collect());
+    let (mut pi, mut ti) = (0, 0);
+    // position of the last `*` in the pattern and the text position it currently covers up to
+    let mut star: Option<(usize, usize)> = None;
+    while ti < t.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == t[ti]) {
+            pi += 1;
+            ti += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some((pi, ti));
+            pi += 1;
+        } else if let Some((sp, st)) = star {
+            pi = sp + 1;
+            ti = st + 1;
+            star = Some((sp, st + 1));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|&c| c == '*')
+}
+
+/// `--min-size`/`--max-size` value: bytes with an optional binary unit (`500`, `10K`, `1.5G`).
+pub fn parse_size(s: &str) -> std::result::Result<u64, String> {
+    let t = s.trim();
+    let (num, factor) = match t.chars().last().map(|c| c.to_ascii_uppercase()) {
+        Some('K') => (&t[..t.len() - 1], 1u64 << 10),
+        Some('M') => (&t[..t.len() - 1], 1 << 20),
+        Some('G') => (&t[..t.len() - 1], 1 << 30),
+        Some('T') => (&t[..t.len() - 1], 1 << 40),
+        _ => (t, 1),
+    };
+    let value: f64 = num.trim().parse().map_err(|_| format!("invalid size '{}' (e.g. 500, 10K, 1.5G)", s))?;
+    if value < 0.0 {
+        return Err(format!("invalid size '{}' (e.g. 500, 10K, 1.5G)", s));
+    }
+    Ok((value * factor as f64) as u64)
+}
+
+/// `--newer-than` value: a `YYYY-MM-DD` date, taken as midnight UTC.
+pub fn parse_date(s: &str) -> std::result::Result<SystemTime, String> {
+    let invalid = || format!("invalid date '{}' (expected YYYY-MM-DD)", s);
+    let parts: Vec<&str> = s.trim().split('-').collect();
+    if parts.len() != 3 {
+        return Err(invalid());
+    }
+    let y: i64 = parts[0].parse().map_err(|_| invalid())?;
+    let m: i64 = parts[1].parse().map_err(|_| invalid())?;
+    let d: i64 = parts[2].parse().map_err(|_| invalid())?;
+    if !(1..=12).contains(&m) || !(1..=31).contains(&d) || y < 1970 {
+        return Err(invalid());
+    }
+    // days since 1970-01-01 (Howard Hinnant's days_from_civil)
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+    Ok(UNIX_EPOCH + Duration::from_secs(days as u64 * 86_400))
+}
+
+/// Behaviour switches for `SampleFiles::ingest_dir` / `ParsedFile::from_path_with`.
+///
+/// The defaults reproduce the classic behaviour (FASTQ, 10x and H5 only).
+#[derive(Debug, Clone, Default)]
+pub struct ScanOptions {
+    /// file suffixes treated as target files by `SampleFiles::ingest` (empty = all files)
+    pub suffixes: Vec<String>,
+
+    /// path names ignored by `SampleFiles::ingest`
+    pub excludes: Vec<String>,
+
+    /// classify `.bam`/`.cram` files and their indices as raw data (`ParsedKind::Alignment`)
+    pub include_bam: bool,
+
+    /// count reads/bases of every FASTQ (shares the file read with the md5 computation)
+    pub stats: bool,
+
+    /// parse instrument, run, flowcell and lane from the first read header of every FASTQ
+    pub read_headers: bool,
+
+    /// Smart-seq2 plates: FASTQ samples are `<plate>_<well>` (`Plate1_A01_R1.fastq.gz`)
+    pub plate_wells: bool,
+
+    /// take FASTQ sample names from the `SampleSheet.csv` of the run folder (None = off)
+    pub sample_sheets: Option<SampleSheets>,
+
+    /// read per sample and lane read counts from the skipped bcl2fastq/BCL Convert report folders
+    pub demux_stats: bool,
+
+    /// record every file that was seen but not classified, with the reason
+    pub audit_unclassified: bool,
+
+    /// fully decompress every gzip file (FASTQs, triplet members, fragments) to verify its CRC
+    pub validate_gzip: bool,
+
+    /// submit 10x matrix triplets as one archive or as three individual files
+    pub tenx_mode: TenxMode,
+
+    /// archive format of the packaged 10x matrix triplets
+    pub bundle_format: BundleFormat,
+
+    /// staging folder for the 10x archives (mirroring the input layout) instead of next to the data
+    pub bundle_dir: Option<PathBuf>,
+
+    /// compression level of uncompressed 10x archive members (None = default); `.gz` members
+    /// are always stored
+    pub zip_level: Option<u32>,
+
+    /// worker threads packaging 10x archives before the scan (0 = up to 4, by available cores)
+    pub bundle_jobs: usize,
+
+    /// where computed md5 sums are cached (sidecars or one `MD5SUMS` per directory)
+    pub md5_store: Md5Store,
+
+    /// read buffer size and memory-map threshold used for hashing
+    pub hash_io: HashIo,
+
+    /// hash on this many threads after the walk, largest files first (0/1 = while walking)
+    pub hash_jobs: usize,
+
+    /// list folders on this many threads (0/1 = one `walkdir` walk)
+    pub walk_threads: usize,
+
+    /// keep at most this many visited-path keys in memory, spill the rest to disk (None = all in memory)
+    pub spill_index: Option<usize>,
+
+    /// use cached md5 sums even when the file size or mtime no longer match
+    pub trust_cache: bool,
+
+    /// recompute the md5 of every file with a `.md5sum` sidecar and report mismatches
+    pub sidecar_check: bool,
+
+    /// which copy of a file found several times (same basename and md5) is kept
+    pub dedup: DedupPolicy,
+
+    /// `--prefer-path`: globs in priority order; a copy under an earlier glob wins over the
+    /// `dedup` policy (unless dedup is off)
+    pub prefer_paths: Vec<String>,
+
+    /// which FASTQ keeps a read role that is found twice in one lane
+    pub on_duplicate_role: DuplicateRole,
+
+    /// write a gzip copy of every uncompressed FASTQ (next to it or below `bundle_dir`) and
+    /// submit that instead
+    pub compress_plain: bool,
+
+    /// gzip copies of bzip2/xz/zstd and plain FASTQs (next to them or below `bundle_dir`)
+    pub recompress_to: RecompressTo,
+
+    /// keep zero byte files, empty gzip files and FASTQs without reads (skipped by default)
+    pub keep_empty: bool,
+
+    /// disable the public-accession skip heuristic (SRR/GSM/... prefixes, converted artifacts)
+    pub keep_accession_like: bool,
+
+    /// read roles recognised in addition to R1/R2/I1/I2 (e.g. R3, UMI), upper case
+    pub extra_roles: Vec<String>,
+
+    /// do not descend deeper than this many levels below the scan root (WalkDir `max_depth`)
+    pub max_depth: Option<usize>,
+
+    /// ignore entries shallower than this many levels below the scan root (WalkDir `min_depth`)
+    pub min_depth: Option<usize>,
+
+    /// one experiment label for every file, overrides the folder based detection
+    pub experiment: Option<String>,
+
+    /// experiment = folder this many levels below the scan root (1 = first folder, the default)
+    pub experiment_depth: Option<usize>,
+
+    /// experiment = folder above the nearest ancestor folder with this name (e.g. `fastq`);
+    /// files without such an ancestor fall back to `experiment_depth`
+    pub experiment_marker: Option<String>,
+
+    /// sample name globs; if not empty, only matching samples are parsed and hashed
+    pub only_samples: Vec<String>,
+
+    /// sample name globs that are never parsed or hashed
+    pub skip_samples: Vec<String>,
+
+    /// skip files smaller than this many bytes (e.g. zero-byte placeholders)
+    pub min_size: Option<u64>,
+
+    /// skip files larger than this many bytes
+    pub max_size: Option<u64>,
+
+    /// skip files last modified before this time
+    pub newer_than: Option<SystemTime>,
+
+    /// symbolic link handling while walking the scan roots
+    pub follow_symlinks: SymlinkPolicy,
+
+    /// column groups and read roles of the sample table, in output order (empty = all)
+    pub sample_columns: Vec<String>,
+
+    /// drop sample table columns that are empty for every sample
+    pub hide_empty_columns: bool,
+
+    /// PacBio movie name -> sample name (from `--pacbio-map`)
+    pub pacbio_movie_samples: HashMap<String, String>,
+
+    /// user sample detection rules tried before the built-in ones (from `--sample-rules`)
+    pub sample_rules: SampleRules,
+
+    /// free-form attribute columns per sample (from `--attributes`)
+    pub sample_attributes: SampleAttributes,
+}
+
+impl ScanOptions {
+    /// Why the `--min-size`/`--max-size`/`--newer-than` filters drop a file, None if it is kept.
+    pub fn size_time_filter(&self, md: &fs::Metadata) -> Option<&'static str> {
+        if self.min_size.is_some_and(|min| md.len() < min) {
+            return Some("smaller than --min-size");
+        }
+        if self.max_size.is_some_and(|max| md.len() > max) {
+            return Some("larger than --max-size");
+        }
+        if let Some(since) = self.newer_than {
+            if md.modified().map(|t| t < since).unwrap_or(false) {
+                return Some("older than --newer-than");
+            }
+        }
+        None
+    }
+
+    /// True if the copy at `candidate` should replace the kept copy at `kept`: the earlier
+    /// `prefer_paths` glob wins, same rank falls back to the `dedup` policy and a copy neither
+    /// prefers goes to the smaller path, so the kept copy never depends on the walk order.
+    pub fn prefers_copy(&self, candidate: &str, kept: &str) -> bool {
+        let rank = |path: &str| self.prefer_paths.iter().position(|g| glob_match(g, path)).unwrap_or(usize::MAX);
+        match rank(candidate).cmp(&rank(kept)) {
+            std::cmp::Ordering::Less => true,
+            std::cmp::Ordering::Greater => false,
+            std::cmp::Ordering::Equal => {
+                self.dedup.prefers(candidate, kept) || (!self.dedup.prefers(kept, candidate) && candidate < kept)
+            }
+        }
+    }
+
+    /// Sample filter of `--only-sample` / `--skip-sample`, applied right after sample detection.
+    pub fn sample_selected(&self, sample: &str) -> bool {
+        (self.only_samples.is_empty() || self.only_samples.iter().any(|g| glob_match(g, sample)))
+            && !self.skip_samples.iter().any(|g| glob_match(g, sample))
+    }
+
+    /// Read a two column (movie, sample) mapping file. Tab or whitespace separated,
+    /// `#` starts a comment line and a `movie` header line is skipped.
+    pub fn load_pacbio_map<P: AsRef<Path>>(&mut self, path: P) -> Result<usize> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+        let mut n = 0usize;
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut cols = line.split_whitespace();
+            let (movie, sample) = match (cols.next(), cols.next()) {
+                (Some(m), Some(s)) => (m, s),
+                _ => {
+                    return Err(GeoPrepError::InvalidInput {
+                        path: path.to_path_buf(),
+                        line: i + 1,
+                        message: format!("PacBio map line needs <movie> <sample>: '{line}'"),
+                    })
+                }
+            };
+            if movie.eq_ignore_ascii_case("movie") {
+                continue;
+            }
+            self.pacbio_movie_samples.insert(movie.to_string(), sample.to_string());
+            n += 1;
+        }
+        Ok(n)
+    }
+
+    /// Read the `--sample-rules` TOML file; returns the number of rules.
+    pub fn load_sample_rules<P: AsRef<Path>>(&mut self, path: P) -> Result<usize> {
+        self.sample_rules = SampleRules::load(path)?;
+        Ok(self.sample_rules.rules.len())
+    }
+
+    /// Read the `--attributes` table; returns the number of sample rows.
+    pub fn load_sample_attributes<P: AsRef<Path>>(&mut self, path: P) -> Result<usize> {
+        self.sample_attributes = SampleAttributes::load(path)?;
+        Ok(self.sample_attributes.len())
+    }
+}
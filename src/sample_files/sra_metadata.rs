@@ -0,0 +1,115 @@
+// src/sample_files/sra_metadata.rs
+use std::collections::BTreeSet;
+use std::io::Write;
+use std::path::Path;
+
+use crate::error::Result;
+use crate::output::create_output;
+use crate::sample_files::SampleFiles;
+
+/// One SRA library: a sample with its raw files.
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct Library {
+    name: String,
+    layout: &'static str,
+    file_types: String,
+    /// (file name, md5), sorted by name
+    files: Vec<(String, String)>,
+}
+
+impl SampleFiles {
+    /// SRA run metadata for submitting raw reads to SRA directly: one row per library (sample)
+    /// with `library_layout` (PAIRED/SINGLE from the read roles), `filetype` and the raw files
+    /// as `filename`, `filename2`, ... each followed by its `MD5_checksum` column.
+    /// Samples without raw files are left out.
+    pub fn write_sra_metadata<P: AsRef<Path>>(&mut self, out_path: P) -> Result<()> {
+        for pf in self.iter_all_parsed_files_mut().into_iter().filter(|pf| pf.kind.is_raw()) {
+            let _ = pf.ensure_md5sum()?;
+        }
+
+        let mut rows: Vec<Library> = Vec::new();
+        for rec in self.samples.values() {
+            let raw: Vec<_> = rec.all_paths().filter(|pf| pf.kind.is_raw()).collect();
+            if raw.is_empty() {
+                continue;
+            }
+            let layout = if rec.library_layout() == "paired-end" { "PAIRED" } else { "SINGLE" };
+            let file_types: BTreeSet<String> = raw.iter().map(|pf| pf.kind.file_type()).collect();
+            let mut files: Vec<(String, String)> = raw
+                .iter()
+                .map(|pf| (pf.geo_filename(), pf.md5_display().to_string()))
+                .collect();
+            files.sort();
+            rows.push(Library {
+                name: self.geo_sample_name(&rec.experiment, &rec.name),
+                layout,
+                file_types: file_types.into_iter().collect::<Vec<_>>().join(","),
+                files,
+            });
+        }
+        rows.sort();
+        let max_files = rows.iter().map(|r| r.files.len()).max().unwrap_or(0).max(1);
+
+        let mut w = create_output(out_path)?;
+        write!(w, "sample_name\tlibrary_ID\tlibrary_layout\tfiletype")?;
+        for i in 1..=max_files {
+            let n = if i == 1 { String::new() } else { i.to_string() };
+            write!(w, "\tfilename{}\tMD5_checksum{}", n, n)?;
+        }
+        writeln!(w)?;
+        for lib in &rows {
+            write!(w, "{}\t{}\t{}\t{}", lib.name, lib.name, lib.layout, lib.file_types)?;
+            for i in 0..max_files {
+                match lib.files.get(i) {
+                    Some((name, md5)) => write!(w, "\t{}\t{}", name, md5)?,
+                    None => write!(w, "\t\t")?,
+                }
+            }
+            writeln!(w)?;
+        }
+        w.commit()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sample_files::{ParsedFile, ParsedKind};
+
+    fn pf(sample: &str, kind: ParsedKind, path: &str) -> ParsedFile {
+        ParsedFile {
+            md5sum: Some(format!("md5_{}", sample)),
+            ..ParsedFile::test_file("exp1", sample, kind, path)
+        }
+    }
+
+    #[test]
+    fn one_row_per_library_with_layout_and_checksums() {
+        let mut data = SampleFiles::new();
+        for role in ["R1", "R2"] {
+            let kind = ParsedKind::Fastq { lane: "L001".to_string(), role: role.to_string() };
+            data.add_file(pf("sampleA", kind, &format!("exp1/sampleA_L001_{role}_001.fastq.gz")));
+        }
+        let kind = ParsedKind::Fastq { lane: "L001".to_string(), role: "R1".to_string() };
+        data.add_file(pf("sampleB", kind, "exp1/sampleB_L001_R1_001.fastq.gz"));
+        data.add_file(pf("sampleC", ParsedKind::H5, "exp1/sampleC/outs/filtered_feature_bc_matrix.h5"));
+
+        let tmp = tempfile::tempdir().unwrap();
+        let out = tmp.path().join("sra.tsv");
+        data.write_sra_metadata(&out).unwrap();
+        let text = std::fs::read_to_string(&out).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+
+        assert_eq!(
+            lines[0],
+            "sample_name\tlibrary_ID\tlibrary_layout\tfiletype\tfilename\tMD5_checksum\tfilename2\tMD5_checksum2"
+        );
+        assert_eq!(
+            lines[1],
+            "sampleA\tsampleA\tPAIRED\tfastq\texp1_sampleA_L001_R1_001.fastq.gz\tmd5_sampleA\t\
+             exp1_sampleA_L001_R2_001.fastq.gz\tmd5_sampleA"
+        );
+        assert_eq!(lines[2], "sampleB\tsampleB\tSINGLE\tfastq\texp1_sampleB_L001_R1_001.fastq.gz\tmd5_sampleB\t\t");
+        assert_eq!(lines.len(), 3);
+    }
+}
@@ -0,0 +1,218 @@
+// src/sample_files/state.rs
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{GeoPrepError, Result};
+use crate::output::create_output;
+use crate::sample_files::{FastqHeader, SampleFiles};
+
+/// Bumped whenever the JSON layout changes incompatibly.
+pub const STATE_VERSION: u32 = 1;
+
+/// One exported file of a scan.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateFile {
+    pub experiment: String,
+    pub sample: String,
+    pub file_type: String,
+    pub geo_name: String,
+    pub path: String,
+    pub size: u64,
+    pub md5sum: Option<String>,
+    /// first read header, only with `--read-headers`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub header: Option<FastqHeader>,
+}
+
+/// Machine readable snapshot of a scan (`<prefix>_state.json`), used by `diff`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScanState {
+    pub version: u32,
+    pub files: Vec<StateFile>,
+}
+
+impl ScanState {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let state_error = |message: String| GeoPrepError::State { path: path.to_path_buf(), message };
+        let state: ScanState = serde_json::from_reader(BufReader::new(File::open(path)?))
+            .map_err(|e| state_error(e.to_string()))?;
+        if state.version != STATE_VERSION {
+            return Err(state_error(format!(
+                "unsupported version {} (expected {})",
+                state.version, STATE_VERSION
+            )));
+        }
+        Ok(state)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let mut w = create_output(path)?;
+        serde_json::to_writer_pretty(&mut w, self)
+            .map_err(|e| GeoPrepError::State { path: path.to_path_buf(), message: e.to_string() })?;
+        w.commit()
+    }
+
+    /// (experiment, sample, geo_name) -> file
+    fn keyed(&self) -> BTreeMap<(String, String, String), &StateFile> {
+        self.files
+            .iter()
+            .map(|f| ((f.experiment.clone(), f.sample.clone(), f.geo_name.clone()), f))
+            .collect()
+    }
+}
+
+impl SampleFiles {
+    /// Snapshot of all exported files including their md5 sums.
+    pub fn scan_state(&mut self) -> Result<ScanState> {
+        for pf in self.iter_all_parsed_files_mut() {
+            let _ = pf.ensure_md5sum()?;
+        }
+        let mut files: Vec<StateFile> = self
+            .iter_all_parsed_files()
+            .into_iter()
+            .map(|pf| StateFile {
+                experiment: pf.experiment.clone(),
+                sample: pf.sample.clone(),
+                file_type: pf.kind.file_type(),
+                geo_name: pf.geo_filename(),
+                path: pf.path.clone(),
+                size: pf.file_size(),
+                md5sum: pf.md5sum.clone(),
+                header: pf.header.clone(),
+            })
+            .collect();
+        files.sort_by(|a, b| a.geo_name.cmp(&b.geo_name));
+        Ok(ScanState { version: STATE_VERSION, files })
+    }
+
+    /// Write `scan_state()` as JSON.
+    pub fn write_state_json<P: AsRef<Path>>(&mut self, out_path: P) -> Result<()> {
+        self.scan_state()?.save(out_path)
+    }
+}
+
+/// Files added, removed and changed (md5 differs) between two scans.
+#[derive(Debug, Default)]
+pub struct StateDiff {
+    pub added: Vec<StateFile>,
+    pub removed: Vec<StateFile>,
+    /// (old, new)
+    pub changed: Vec<(StateFile, StateFile)>,
+}
+
+impl StateDiff {
+    pub fn between(old: &ScanState, new: &ScanState) -> Self {
+        let (old, new) = (old.keyed(), new.keyed());
+        let mut diff = StateDiff::default();
+        for (key, o) in &old {
+            match new.get(key) {
+                None => diff.removed.push((*o).clone()),
+                Some(n) if o.md5sum != n.md5sum => diff.changed.push(((*o).clone(), (*n).clone())),
+                Some(_) => {}
+            }
+        }
+        for (key, n) in &new {
+            if !old.contains_key(key) {
+                diff.added.push((*n).clone());
+            }
+        }
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    /// Human readable report grouped by experiment/sample.
+    pub fn report(&self) -> String {
+        // (experiment, sample) -> lines
+        let mut per_sample: BTreeMap<(String, String), Vec<String>> = BTreeMap::new();
+        for f in &self.added {
+            per_sample
+                .entry((f.experiment.clone(), f.sample.clone()))
+                .or_default()
+                .push(format!("  + {}", f.geo_name));
+        }
+        for f in &self.removed {
+            per_sample
+                .entry((f.experiment.clone(), f.sample.clone()))
+                .or_default()
+                .push(format!("  - {}", f.geo_name));
+        }
+        for (o, n) in &self.changed {
+            per_sample
+                .entry((n.experiment.clone(), n.sample.clone()))
+                .or_default()
+                .push(format!(
+                    "  ~ {} (md5 {} -> {})",
+                    n.geo_name,
+                    o.md5sum.as_deref().unwrap_or("none"),
+                    n.md5sum.as_deref().unwrap_or("none")
+                ));
+        }
+
+        let mut out = String::new();
+        for ((experiment, sample), mut lines) in per_sample {
+            lines.sort_by(|a, b| a[4..].cmp(&b[4..]));
+            out.push_str(&format!("{} / {}\n", experiment, sample));
+            for l in lines {
+                out.push_str(&l);
+                out.push('\n');
+            }
+        }
+        out.push_str(&format!(
+            "{} added, {} removed, {} changed\n",
+            self.added.len(),
+            self.removed.len(),
+            self.changed.len()
+        ));
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sf(sample: &str, geo_name: &str, md5: &str) -> StateFile {
+        StateFile {
+            experiment: "exp1".to_string(),
+            sample: sample.to_string(),
+            file_type: "fastq".to_string(),
+            geo_name: geo_name.to_string(),
+            path: format!("/data/{}", geo_name),
+            size: 1,
+            md5sum: Some(md5.to_string()),
+            header: None,
+        }
+    }
+
+    #[test]
+    fn diff_reports_added_removed_and_changed() {
+        let old = ScanState {
+            version: STATE_VERSION,
+            files: vec![sf("A", "A_R1.fastq.gz", "aa"), sf("A", "A_R2.fastq.gz", "bb")],
+        };
+        let new = ScanState {
+            version: STATE_VERSION,
+            files: vec![sf("A", "A_R1.fastq.gz", "cc"), sf("B", "B_R1.fastq.gz", "dd")],
+        };
+
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("state.json");
+        old.save(&path).unwrap();
+        let old = ScanState::load(&path).unwrap();
+
+        let diff = StateDiff::between(&old, &new);
+        assert_eq!(diff.added.len(), 1);
+        assert_eq!(diff.removed[0].geo_name, "A_R2.fastq.gz");
+        assert_eq!(diff.changed[0].1.md5sum.as_deref(), Some("cc"));
+        assert!(diff.report().contains("~ A_R1.fastq.gz (md5 aa -> cc)"));
+    }
+}
@@ -0,0 +1,82 @@
+// src/sample_files/triplet.rs
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+use flate2::read::MultiGzDecoder;
+
+fn gz_lines(path: &Path) -> io::Result<impl Iterator<Item = io::Result<String>>> {
+    Ok(BufReader::new(MultiGzDecoder::new(File::open(path)?)).lines())
+}
+
+/// `(rows, cols)` from the size line of a MatrixMarket file (after the `%` comments).
+fn mtx_dimensions(path: &Path) -> io::Result<(u64, u64)> {
+    for line in gz_lines(path)? {
+        let line = line?;
+        if line.starts_with('%') || line.trim().is_empty() {
+            continue;
+        }
+        let dims: Vec<u64> = line.split_whitespace().filter_map(|v| v.parse().ok()).collect();
+        if dims.len() < 2 {
+            break;
+        }
+        return Ok((dims[0], dims[1]));
+    }
+    Err(io::Error::new(io::ErrorKind::InvalidData, "no size line"))
+}
+
+fn count_lines(path: &Path) -> io::Result<u64> {
+    let mut n = 0;
+    for line in gz_lines(path)? {
+        line?;
+        n += 1;
+    }
+    Ok(n)
+}
+
+/// Compare the dimensions announced by `matrix.mtx.gz` with the number of features
+/// (`features.tsv.gz` or `genes.tsv.gz`) and barcodes of a 10x triplet folder.
+/// Returns a description of the first inconsistency; a truncated barcode or feature list
+/// shows up as a count mismatch (or a read error).
+pub fn triplet_dimension_issue(dir: &Path) -> Option<String> {
+    let features = ["features.tsv.gz", "genes.tsv.gz"].iter().map(|f| dir.join(f)).find(|p| p.is_file())?;
+    let check = || -> io::Result<Option<String>> {
+        let (rows, cols) = mtx_dimensions(&dir.join("matrix.mtx.gz"))?;
+        let n_features = count_lines(&features)?;
+        let n_barcodes = count_lines(&dir.join("barcodes.tsv.gz"))?;
+        let mut problems = Vec::new();
+        if rows != n_features {
+            problems.push(format!("matrix has {} rows but {} features", rows, n_features));
+        }
+        if cols != n_barcodes {
+            problems.push(format!("matrix has {} columns but {} barcodes", cols, n_barcodes));
+        }
+        Ok((!problems.is_empty()).then(|| problems.join(", ")))
+    };
+    check().unwrap_or_else(|e| Some(format!("can not read triplet: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn write_gz(path: &Path, text: &str) {
+        let mut gz = flate2::write::GzEncoder::new(File::create(path).unwrap(), flate2::Compression::default());
+        gz.write_all(text.as_bytes()).unwrap();
+        gz.finish().unwrap();
+    }
+
+    #[test]
+    fn dimensions_are_compared_with_barcodes_and_features() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        write_gz(&dir.join("matrix.mtx.gz"), "%%MatrixMarket matrix coordinate integer general\n%\n2 3 1\n1 1 5\n");
+        write_gz(&dir.join("features.tsv.gz"), "G1\tGene1\tGene Expression\nG2\tGene2\tGene Expression\n");
+        write_gz(&dir.join("barcodes.tsv.gz"), "AAAC-1\nAAAG-1\nAAAT-1\n");
+        assert_eq!(triplet_dimension_issue(dir), None);
+
+        write_gz(&dir.join("barcodes.tsv.gz"), "AAAC-1\nAAAG-1\n");
+        assert_eq!(triplet_dimension_issue(dir).as_deref(), Some("matrix has 3 columns but 2 barcodes"));
+    }
+}
@@ -0,0 +1,660 @@
+// src/sample_files/validation.rs
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Write;
+use std::path::Path;
+
+use crate::error::Result;
+use crate::output::create_output;
+use crate::sample_files::fastq_header::filename_lane_number;
+use crate::sample_files::lane_fastqs::PAIR_MIN_SIZE_RATIO;
+use crate::sample_files::{Container, ParsedFile, ParsedKind, SampleFiles};
+
+/// One row of the `*_warnings.tsv` report.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ValidationWarning {
+    pub category: String,
+    pub experiment: String,
+    pub sample: String,
+    pub lane: String,
+    pub message: String,
+}
+
+impl SampleFiles {
+    /// R1/R2 pairs of the same lane that are likely truncated or mismatched.
+    pub fn pairing_warnings(&self) -> Vec<ValidationWarning> {
+        let mut out = Vec::new();
+        for rec in self.samples.values() {
+            for (lane_key, lane) in &rec.lanes {
+                if let Some(message) = lane.pairing_issue(PAIR_MIN_SIZE_RATIO) {
+                    out.push(ValidationWarning {
+                        category: "pair_mismatch".to_string(),
+                        experiment: rec.experiment.clone(),
+                        sample: rec.name.clone(),
+                        lane: lane_key.clone(),
+                        message,
+                    });
+                }
+            }
+        }
+        out
+    }
+
+    /// FASTQs that were ignored because a lane already had a file for their read role.
+    /// Lanes lacking a mate are reported by `missing_mates`.
+    pub fn lane_role_warnings(&self) -> Vec<ValidationWarning> {
+        let mut out = Vec::new();
+        for rec in self.samples.values() {
+            for (lane_key, lane) in &rec.lanes {
+                let warn = |category: &str, message: String| ValidationWarning {
+                    category: category.to_string(),
+                    experiment: rec.experiment.clone(),
+                    sample: rec.name.clone(),
+                    lane: lane_key.clone(),
+                    message,
+                };
+                for dup in &lane.duplicates {
+                    let role = match &dup.kind {
+                        ParsedKind::Fastq { role, .. } => role.as_str(),
+                        _ => "",
+                    };
+                    let kept = lane.reads.get(role).map(|pf| pf.path.as_str()).unwrap_or_default();
+                    out.push(warn(
+                        "duplicate_role",
+                        format!("{} ignored, lane already has {} ({})", dup.path, role, kept),
+                    ));
+                }
+            }
+        }
+        out
+    }
+
+    /// Lanes with only one mate (R1 without R2 or R2 without R1) in samples that have at least
+    /// one complete R1/R2 lane, i.e. a mate file that most likely got lost on the way.
+    /// Listed in the upload summary and the warnings table.
+    pub fn missing_mates(&self) -> Vec<ValidationWarning> {
+        self.scrubbed(self.mate_warnings())
+    }
+
+    fn mate_warnings(&self) -> Vec<ValidationWarning> {
+        let mut out = Vec::new();
+        for rec in self.samples.values() {
+            if !rec.lanes.values().any(|lane| lane.layout() == "paired-end") {
+                continue;
+            }
+            for (lane_key, lane) in &rec.lanes {
+                let message = match (lane.reads.get("R1"), lane.reads.get("R2")) {
+                    (Some(r1), None) => format!("R2 missing for {}", r1.basename()),
+                    (None, Some(r2)) => format!("R1 missing for {}", r2.basename()),
+                    _ => continue,
+                };
+                out.push(ValidationWarning {
+                    category: "missing_mate".to_string(),
+                    experiment: rec.experiment.clone(),
+                    sample: rec.name.clone(),
+                    lane: lane_key.clone(),
+                    message,
+                });
+            }
+        }
+        out
+    }
+
+    /// The findings with their messages as `public_text` (no original names after `--anonymize`).
+    fn scrubbed(&self, mut warnings: Vec<ValidationWarning>) -> Vec<ValidationWarning> {
+        for w in &mut warnings {
+            w.message = self.public_text(&w.message);
+        }
+        warnings
+    }
+
+    /// Files with identical content (same md5) assigned to different samples, usually a copy/paste
+    /// mistake. Only files whose md5 is already known are compared, so run this after the md5 table.
+    pub fn duplicate_content_warnings(&self) -> Vec<ValidationWarning> {
+        // md5 -> files with that checksum
+        let mut by_md5: BTreeMap<&str, Vec<&ParsedFile>> = BTreeMap::new();
+        for pf in self.iter_all_parsed_files() {
+            if let Some(md5) = pf.md5sum.as_deref() {
+                by_md5.entry(md5).or_default().push(pf);
+            }
+        }
+        let mut out = Vec::new();
+        for (md5, files) in by_md5 {
+            let samples: BTreeSet<(&str, &str)> =
+                files.iter().map(|pf| (pf.experiment.as_str(), pf.sample.as_str())).collect();
+            if samples.len() < 2 {
+                continue;
+            }
+            let mut paths: Vec<&str> = files.iter().map(|pf| pf.path.as_str()).collect();
+            paths.sort();
+            for (experiment, sample) in samples {
+                out.push(ValidationWarning {
+                    category: "duplicate_content".to_string(),
+                    experiment: experiment.to_string(),
+                    sample: sample.to_string(),
+                    lane: String::new(),
+                    message: format!("identical content (md5 {}) across samples: {}", md5, paths.join(", ")),
+                });
+            }
+        }
+        out
+    }
+
+    /// Samples whose lanes disagree on the library layout (some paired-end, some single).
+    pub fn layout_warnings(&self) -> Vec<ValidationWarning> {
+        let mut out = Vec::new();
+        for rec in self.samples.values() {
+            let layouts: BTreeSet<&str> = rec.lanes.values().map(|lane| lane.layout()).collect();
+            if layouts.len() > 1 {
+                let per_lane: Vec<String> =
+                    rec.lanes.iter().map(|(key, lane)| format!("{} {}", key, lane.layout())).collect();
+                out.push(ValidationWarning {
+                    category: "layout_mismatch".to_string(),
+                    experiment: rec.experiment.clone(),
+                    sample: rec.name.clone(),
+                    lane: String::new(),
+                    message: format!("inconsistent library layout across lanes: {}", per_lane.join(", ")),
+                });
+            }
+        }
+        out
+    }
+
+    /// FASTQs whose first read header names another lane than the file name (`--read-headers`).
+    pub fn header_warnings(&self) -> Vec<ValidationWarning> {
+        let mut out = Vec::new();
+        for rec in self.samples.values() {
+            for (lane_key, lane) in &rec.lanes {
+                for pf in lane.reads.values() {
+                    let (Some(header), ParsedKind::Fastq { lane: file_lane, .. }) = (&pf.header, &pf.kind) else {
+                        continue;
+                    };
+                    match filename_lane_number(file_lane) {
+                        Some(n) if n != header.lane => out.push(ValidationWarning {
+                            category: "lane_mismatch".to_string(),
+                            experiment: rec.experiment.clone(),
+                            sample: rec.name.clone(),
+                            lane: lane_key.clone(),
+                            message: format!(
+                                "{}: file name says lane {}, first read header says lane {} (flowcell {})",
+                                pf.basename(),
+                                n,
+                                header.lane,
+                                header.flowcell
+                            ),
+                        }),
+                        _ => {}
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    /// `--sample-sheets`: FASTQs in a run folder with a `SampleSheet.csv` that match no sheet
+    /// row, and (`--read-headers`) FASTQs whose header index differs from the sheet index.
+    pub fn sample_sheet_warnings(&self) -> Vec<ValidationWarning> {
+        let Some(sheets) = &self.options.sample_sheets else {
+            return Vec::new();
+        };
+        let mut out = Vec::new();
+        for rec in self.samples.values() {
+            for (lane_key, lane) in &rec.lanes {
+                for pf in lane.reads.values() {
+                    let Some(sheet) = sheets.for_file(Path::new(&pf.path)) else { continue };
+                    let warn = |category: &str, message: String| ValidationWarning {
+                        category: category.to_string(),
+                        experiment: rec.experiment.clone(),
+                        sample: rec.name.clone(),
+                        lane: lane_key.clone(),
+                        message,
+                    };
+                    let Some(row) = sheet.row_for(&pf.basename()) else {
+                        out.push(warn(
+                            "not_in_sample_sheet",
+                            format!("{} matches no sample of {}", pf.basename(), sheet.path.display()),
+                        ));
+                        continue;
+                    };
+                    // i7 only: the i5 read direction depends on the instrument
+                    let header_i7 = pf
+                        .header
+                        .as_ref()
+                        .and_then(|h| h.index.as_deref())
+                        .map(|i| i.split('+').next().unwrap_or(i));
+                    if let (Some(seen), Some(expected)) = (header_i7, row.index.as_deref()) {
+                        if !seen.contains('N') && !seen.eq_ignore_ascii_case(expected) {
+                            out.push(warn(
+                                "sample_sheet_index",
+                                format!(
+                                    "{}: read header index {} but sample sheet index {} ({})",
+                                    pf.basename(),
+                                    seen,
+                                    expected,
+                                    row.sample_id
+                                ),
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    /// Samples whose FASTQs come from different flowcells or carry different sample indices
+    /// (`--read-headers`); usually two libraries collided on one sample name.
+    /// Indices with `N` calls are ignored.
+    pub fn mixed_run_warnings(&self) -> Vec<ValidationWarning> {
+        let mut out = Vec::new();
+        for rec in self.samples.values() {
+            // flowcell/index -> number of files
+            let mut flowcells: BTreeMap<String, usize> = BTreeMap::new();
+            let mut indices: BTreeMap<String, usize> = BTreeMap::new();
+            for pf in rec.lanes.values().flat_map(|lane| lane.reads.values()) {
+                let Some(header) = &pf.header else { continue };
+                *flowcells
+                    .entry(format!("{}:{}:{}", header.instrument, header.run, header.flowcell))
+                    .or_default() += 1;
+                if let Some(index) = header.index.as_ref().filter(|i| !i.contains('N')) {
+                    *indices.entry(index.clone()).or_default() += 1;
+                }
+            }
+            let describe = |counts: &BTreeMap<String, usize>| {
+                counts.iter().map(|(k, n)| format!("{} ({} files)", k, n)).collect::<Vec<_>>().join(", ")
+            };
+            let warn = |category: &str, message: String| ValidationWarning {
+                category: category.to_string(),
+                experiment: rec.experiment.clone(),
+                sample: rec.name.clone(),
+                lane: String::new(),
+                message,
+            };
+            if flowcells.len() > 1 {
+                out.push(warn("mixed_flowcells", format!("files from different flowcells: {}", describe(&flowcells))));
+            }
+            if indices.len() > 1 {
+                out.push(warn("mixed_indices", format!("files with different sample indices: {}", describe(&indices))));
+            }
+        }
+        out
+    }
+
+    /// Corrupt or truncated gzip payloads found with `--validate-gzip`.
+    pub fn gzip_warnings(&self) -> Vec<ValidationWarning> {
+        let mut out = Vec::new();
+        for rec in self.samples.values() {
+            for pf in rec.all_paths() {
+                let lane = match &pf.kind {
+                    ParsedKind::Fastq { lane, .. } => lane.clone(),
+                    _ => String::new(),
+                };
+                for err in &pf.gzip_errors {
+                    out.push(ValidationWarning {
+                        category: "gzip_corrupt".to_string(),
+                        experiment: rec.experiment.clone(),
+                        sample: rec.name.clone(),
+                        lane: lane.clone(),
+                        message: format!("{} ({})", err, pf.path),
+                    });
+                }
+            }
+        }
+        out
+    }
+
+    /// FASTQs that are not gzipped: GEO only accepts gzipped reads (`--compress-plain` and
+    /// `--recompress-to gz` write copies).
+    pub fn uncompressed_warnings(&self) -> Vec<ValidationWarning> {
+        let mut out = Vec::new();
+        for rec in self.samples.values() {
+            for (lane, pf) in rec.lanes.iter().flat_map(|(lane, l)| l.reads.values().map(move |pf| (lane, pf))) {
+                let (category, hint) = match Container::of_path(Path::new(&pf.path)) {
+                    Container::Gzip => continue,
+                    Container::Plain => ("uncompressed_fastq", "--compress-plain"),
+                    _ => ("fastq_container", "--recompress-to gz"),
+                };
+                out.push(ValidationWarning {
+                    category: category.to_string(),
+                    experiment: rec.experiment.clone(),
+                    sample: rec.name.clone(),
+                    lane: lane.clone(),
+                    message: format!("GEO requires gzipped FASTQs, use {} ({})", hint, pf.path),
+                });
+            }
+        }
+        out
+    }
+
+    /// 10x triplets whose `matrix.mtx.gz` dimensions do not match the barcode/feature counts.
+    pub fn triplet_warnings(&self) -> Vec<ValidationWarning> {
+        let mut out = Vec::new();
+        for rec in self.samples.values() {
+            for pf in rec.tenx.values() {
+                if let Some(issue) = &pf.triplet_issue {
+                    out.push(ValidationWarning {
+                        category: "triplet_mismatch".to_string(),
+                        experiment: rec.experiment.clone(),
+                        sample: rec.name.clone(),
+                        lane: String::new(),
+                        message: format!("{} ({})", issue, pf.path),
+                    });
+                }
+            }
+        }
+        out
+    }
+
+    /// Files whose `.md5sum` sidecar no longer matches their content (`--sidecar-check`).
+    pub fn sidecar_warnings(&self) -> Vec<ValidationWarning> {
+        let mut out = Vec::new();
+        for rec in self.samples.values() {
+            for pf in rec.all_paths() {
+                let lane = match &pf.kind {
+                    ParsedKind::Fastq { lane, .. } => lane.clone(),
+                    _ => String::new(),
+                };
+                let message = match pf.verify_md5_sidecar() {
+                    Ok(Some((stored, actual))) if stored != actual => {
+                        format!("sidecar md5 {} but file has {} ({})", stored, actual, pf.path)
+                    }
+                    Ok(_) => continue,
+                    Err(e) => format!("could not verify sidecar: {} ({})", e, pf.path),
+                };
+                out.push(ValidationWarning {
+                    category: "md5_mismatch".to_string(),
+                    experiment: rec.experiment.clone(),
+                    sample: rec.name.clone(),
+                    lane,
+                    message,
+                });
+            }
+        }
+        out
+    }
+
+    /// All validation findings, sorted.
+    pub fn validation_warnings(&self) -> Vec<ValidationWarning> {
+        let mut out = self.pairing_warnings();
+        out.extend(self.mate_warnings());
+        out.extend(self.lane_role_warnings());
+        out.extend(self.layout_warnings());
+        out.extend(self.header_warnings());
+        out.extend(self.mixed_run_warnings());
+        out.extend(self.sample_sheet_warnings());
+        out.extend(self.gzip_warnings());
+        out.extend(self.uncompressed_warnings());
+        out.extend(self.triplet_warnings());
+        out.extend(self.duplicate_content_warnings());
+        out.extend(self.name_warnings());
+        if self.options.sidecar_check {
+            out.extend(self.sidecar_warnings());
+        }
+        let mut out = self.scrubbed(out);
+        out.sort();
+        out
+    }
+
+    /// Write all validation findings as TSV (header only if everything is fine).
+    ///
+    /// Output columns:
+    /// Category, Experiment, Sample, Lane, Message
+    pub fn write_warnings_table<P: AsRef<Path>>(&self, out_path: P) -> Result<usize> {
+        let warnings = self.validation_warnings();
+
+        let mut w = create_output(out_path)?;
+        writeln!(w, "Category\tExperiment\tSample\tLane\tMessage")?;
+        for v in &warnings {
+            writeln!(w, "{}\t{}\t{}\t{}\t{}", v.category, v.experiment, v.sample, v.lane, v.message)?;
+        }
+        w.commit()?;
+        Ok(warnings.len())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::GeoPrepError;
+    use crate::sample_files::{DuplicateRole, FastqHeader, FastqStats, ParsedFile};
+
+    fn fastq(sample: &str, lane: &str, role: &str, reads: u64) -> ParsedFile {
+        ParsedFile {
+            stats: Some(FastqStats { reads, bases: reads * 50 }),
+            ..ParsedFile::test_file(
+                "exp1",
+                sample,
+                ParsedKind::Fastq { lane: lane.to_string(), role: role.to_string() },
+                &format!("exp1/{sample}_{lane}_{role}_001.fastq.gz"),
+            )
+        }
+    }
+
+    #[test]
+    fn read_count_mismatch_is_reported() {
+        let mut data = SampleFiles::new();
+        data.add_file(fastq("sampleA", "L001", "R1", 100));
+        data.add_file(fastq("sampleA", "L001", "R2", 100));
+        data.add_file(fastq("sampleA", "L002", "R1", 100));
+        data.add_file(fastq("sampleA", "L002", "R2", 60));
+
+        let warnings = data.validation_warnings();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].lane, "L002");
+        assert_eq!(warnings[0].category, "pair_mismatch");
+    }
+
+    #[test]
+    fn missing_mates_and_duplicate_roles_are_reported() {
+        let mut data = SampleFiles::new();
+        data.add_file(fastq("sampleA", "L001", "R1", 100));
+        data.add_file(fastq("sampleA", "L001", "R2", 100));
+        data.add_file(fastq("sampleA", "L002", "R1", 100));
+        let mut dup = fastq("sampleA", "L001", "R1", 100);
+        dup.path = "exp1/copy/sampleA_L001_R1_001.fastq.gz".to_string();
+        data.add_file(dup);
+
+        let categories: Vec<(String, String)> = data
+            .validation_warnings()
+            .into_iter()
+            .map(|w| (w.category, w.lane))
+            .collect();
+        assert_eq!(
+            categories,
+            vec![
+                ("duplicate_role".to_string(), "L001".to_string()),
+                ("layout_mismatch".to_string(), String::new()),
+                ("missing_mate".to_string(), "L002".to_string()),
+            ]
+        );
+        // the warnings table and the upload summary share one missing mate rule
+        let in_table: Vec<ValidationWarning> =
+            data.validation_warnings().into_iter().filter(|w| w.category == "missing_mate").collect();
+        assert_eq!(in_table, data.missing_mates());
+    }
+
+    #[test]
+    fn duplicate_roles_follow_the_strategy() {
+        let tmp = tempfile::tempdir().unwrap();
+        let copies = |strategy: DuplicateRole| {
+            let mut data = SampleFiles::builder().on_duplicate_role(strategy).build();
+            for (dir, bytes) in [("small", 10), ("large", 20)] {
+                let path = tmp.path().join(dir).join("sampleA_L001_R1_001.fastq.gz");
+                std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+                std::fs::write(&path, vec![b'@'; bytes]).unwrap();
+                let mut pf = fastq("sampleA", "L001", "R1", 100);
+                pf.path = path.to_string_lossy().into_owned();
+                data.add_file(pf);
+            }
+            data
+        };
+        let kept = |data: &SampleFiles| {
+            let lane = &data.samples.values().next().unwrap().lanes["L001"];
+            (lane.reads["R1"].path.contains("large"), lane.duplicates.len())
+        };
+
+        assert_eq!(kept(&copies(DuplicateRole::KeepFirst)), (false, 1));
+        assert_eq!(kept(&copies(DuplicateRole::KeepLargest)), (true, 1));
+        assert!(copies(DuplicateRole::KeepFirst).check_duplicate_roles().is_ok());
+        let err = copies(DuplicateRole::Error).check_duplicate_roles().unwrap_err();
+        assert!(matches!(err, GeoPrepError::DuplicateRoles { ref issues } if issues.len() == 1), "{err}");
+    }
+
+    #[test]
+    fn topup_runs_get_their_own_lanes() {
+        let topup = |strategy: DuplicateRole, headers: bool| {
+            let mut data = SampleFiles::builder().on_duplicate_role(strategy).build();
+            for (run, flowcell) in [("run1", "FCA"), ("run2", "FCB")] {
+                for role in ["R1", "R2"] {
+                    let mut pf = fastq("sampleA", "L001", role, 100);
+                    pf.path = format!("exp1/{run}/sampleA_L001_{role}_001.fastq.gz");
+                    if headers {
+                        pf.header = FastqHeader::parse(&format!("@A1:7:{flowcell}:1:1101:1:1 1:N:0:ACGT"));
+                    }
+                    data.add_file(pf);
+                }
+            }
+            let rec = data.samples.values().next().unwrap();
+            let lanes: Vec<(&str, usize)> = rec.lanes.iter().map(|(k, l)| (k.as_str(), l.reads.len())).collect();
+            format!("{lanes:?}")
+        };
+
+        assert_eq!(topup(DuplicateRole::KeepFirst, true), r#"[("L001_FCA", 2), ("L001_FCB", 2)]"#);
+        assert_eq!(topup(DuplicateRole::Split, false), r#"[("L001_run1", 2), ("L001_run2", 2)]"#);
+        // without a flowcell the folders only count with `split`
+        assert_eq!(topup(DuplicateRole::KeepFirst, false), r#"[("L001", 2)]"#);
+    }
+
+    #[test]
+    fn fastqs_are_checked_against_the_sample_sheet() {
+        let tmp = tempfile::tempdir().unwrap();
+        std::fs::write(
+            tmp.path().join("SampleSheet.csv"),
+            "[Data]\nSample_ID,Sample_Name,index\nS1,sampleA,ACGTACGT\n",
+        )
+        .unwrap();
+        let run = tmp.path().join("Project1");
+        let mut data = SampleFiles::builder().sample_sheets(true).build();
+        for (sample, index) in [("sampleA", "TTTTCCCC"), ("sampleB", "ACGTACGT")] {
+            let mut pf = fastq(sample, "S1_L001", "R1", 100);
+            pf.path = run.join(format!("{sample}_S1_L001_R1_001.fastq.gz")).to_string_lossy().to_string();
+            pf.header = FastqHeader::parse(&format!("@A1:1:FC1:1:1101:1:1 1:N:0:{index}"));
+            data.add_file(pf);
+        }
+
+        let found: Vec<(String, String)> =
+            data.sample_sheet_warnings().into_iter().map(|w| (w.category, w.sample)).collect();
+        assert_eq!(
+            found,
+            vec![
+                ("sample_sheet_index".to_string(), "sampleA".to_string()),
+                ("not_in_sample_sheet".to_string(), "sampleB".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_mates_are_listed_for_paired_samples() {
+        let mut data = SampleFiles::new();
+        data.add_file(fastq("sampleA", "L001", "R1", 100));
+        data.add_file(fastq("sampleA", "L001", "R2", 100));
+        data.add_file(fastq("sampleA", "L002", "R1", 100));
+        data.add_file(fastq("sampleA", "L003", "R2", 100));
+        data.add_file(fastq("sampleB", "L001", "R1", 100));
+
+        let missing: Vec<(String, String)> =
+            data.missing_mates().into_iter().map(|w| (w.lane, w.message)).collect();
+        assert_eq!(
+            missing,
+            vec![
+                ("L002".to_string(), "R2 missing for sampleA_L002_R1_001.fastq.gz".to_string()),
+                ("L003".to_string(), "R1 missing for sampleA_L003_R2_001.fastq.gz".to_string()),
+            ]
+        );
+        assert!(data.upload_summary().contains("MISSING MATES (2 lanes)"));
+    }
+
+    #[test]
+    fn identical_files_in_different_samples_are_reported() {
+        let mut data = SampleFiles::new();
+        for (sample, md5) in [("sampleA", "aaa"), ("sampleB", "aaa"), ("sampleC", "ccc")] {
+            let mut r1 = fastq(sample, "L001", "R1", 100);
+            r1.md5sum = Some(md5.to_string());
+            data.add_file(r1);
+        }
+
+        let warnings = data.duplicate_content_warnings();
+        let samples: Vec<&str> = warnings.iter().map(|w| w.sample.as_str()).collect();
+        assert_eq!(samples, ["sampleA", "sampleB"]);
+        assert_eq!(
+            warnings[0].message,
+            "identical content (md5 aaa) across samples: exp1/sampleA_L001_R1_001.fastq.gz, \
+             exp1/sampleB_L001_R1_001.fastq.gz"
+        );
+    }
+
+    #[test]
+    fn mixed_lane_layouts_are_reported() {
+        let mut data = SampleFiles::new();
+        data.add_file(fastq("sampleA", "L001", "R1", 100));
+        data.add_file(fastq("sampleA", "L001", "R2", 100));
+        data.add_file(fastq("sampleA", "L002", "R1", 100));
+        data.add_file(fastq("sampleB", "L001", "R1", 100));
+
+        let warnings = data.layout_warnings();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].sample, "sampleA");
+        assert_eq!(
+            warnings[0].message,
+            "inconsistent library layout across lanes: L001 paired-end, L002 single"
+        );
+    }
+
+    fn header(flowcell: &str, lane: u32, index: &str) -> FastqHeader {
+        FastqHeader {
+            instrument: "A01234".to_string(),
+            run: "123".to_string(),
+            flowcell: flowcell.to_string(),
+            lane,
+            index: Some(index.to_string()),
+        }
+    }
+
+    #[test]
+    fn header_lane_must_match_file_name() {
+        let mut data = SampleFiles::new();
+        let mut pf = fastq("sampleA", "L002", "R1", 100);
+        pf.header = Some(header("HXXXXXDSX", 1, "ACGT"));
+        data.add_file(pf);
+
+        let warnings = data.header_warnings();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].category, "lane_mismatch");
+        assert!(warnings[0].message.contains("file name says lane 2, first read header says lane 1"));
+    }
+
+    #[test]
+    fn mixed_flowcells_and_indices_are_reported() {
+        let mut data = SampleFiles::new();
+        for (sample, lane, flowcell, index) in [
+            ("sampleA", "L001", "HAAAAAAXX", "ACGT"),
+            ("sampleA", "L002", "HBBBBBBXX", "TTGA"),
+            ("sampleB", "L001", "HAAAAAAXX", "GGCC"),
+            ("sampleB", "L002", "HAAAAAAXX", "GGNC"),
+        ] {
+            let mut pf = fastq(sample, lane, "R1", 100);
+            pf.header = Some(header(flowcell, filename_lane_number(lane).unwrap(), index));
+            data.add_file(pf);
+        }
+
+        let warnings: Vec<(String, String)> =
+            data.mixed_run_warnings().into_iter().map(|w| (w.category, w.sample)).collect();
+        assert_eq!(
+            warnings,
+            vec![
+                ("mixed_flowcells".to_string(), "sampleA".to_string()),
+                ("mixed_indices".to_string(), "sampleA".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,328 @@
+// src/sample_files/walk.rs
+//! The directory walk behind `ScanIter`: `walkdir` on one thread (the default) or, with
+//! `--walk-threads N`, N threads listing folders in parallel, which hides the per-folder
+//! latency of network file systems. Both yield the root first and every folder before its
+//! content; unreadable entries are skipped.
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::sync::{Arc, Condvar, Mutex};
+
+use walkdir::WalkDir;
+
+/// One entry of the walk; for followed links `is_dir` describes the link target.
+#[derive(Debug, Clone)]
+pub struct WalkEntry {
+    path: PathBuf,
+    depth: usize,
+    is_dir: bool,
+    is_symlink: bool,
+}
+
+impl WalkEntry {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 0 for the scan root
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.is_dir
+    }
+
+    pub fn path_is_symlink(&self) -> bool {
+        self.is_symlink
+    }
+}
+
+/// What to walk: the scan root and the depth / symlink settings of the scan.
+#[derive(Debug, Clone)]
+pub struct WalkConfig {
+    pub root: PathBuf,
+    pub follow_links: bool,
+    pub min_depth: Option<usize>,
+    pub max_depth: Option<usize>,
+    /// folders that are reported but never descended into
+    pub prune: fn(&Path) -> bool,
+}
+
+pub enum Walker {
+    Serial(walkdir::IntoIter),
+    Parallel {
+        rx: Receiver<WalkEntry>,
+        stop: Arc<AtomicBool>,
+        /// folders whose content is dropped from the rest of the walk
+        skipped: Vec<PathBuf>,
+    },
+}
+
+impl Walker {
+    /// `threads` <= 1 walks with `walkdir` on the calling thread.
+    pub fn new(config: WalkConfig, threads: usize) -> Self {
+        if threads <= 1 {
+            let mut walker = WalkDir::new(&config.root).follow_links(config.follow_links);
+            if let Some(depth) = config.max_depth {
+                walker = walker.max_depth(depth);
+            }
+            if let Some(depth) = config.min_depth {
+                walker = walker.min_depth(depth);
+            }
+            return Walker::Serial(walker.into_iter());
+        }
+        let (tx, rx) = sync_channel(4096);
+        let stop = Arc::new(AtomicBool::new(false));
+        spawn_parallel(config, threads, tx, Arc::clone(&stop));
+        Walker::Parallel { rx, stop, skipped: Vec::new() }
+    }
+
+    /// Do not descend into `dir`, the entry returned last.
+    pub fn skip_current_dir(&mut self, dir: &Path) {
+        match self {
+            Walker::Serial(it) => it.skip_current_dir(),
+            Walker::Parallel { skipped, .. } => skipped.push(dir.to_path_buf()),
+        }
+    }
+}
+
+impl Iterator for Walker {
+    type Item = WalkEntry;
+
+    fn next(&mut self) -> Option<WalkEntry> {
+        match self {
+            Walker::Serial(it) => loop {
+                let Ok(entry) = it.next()? else { continue };
+                return Some(WalkEntry {
+                    depth: entry.depth(),
+                    is_dir: entry.file_type().is_dir(),
+                    is_symlink: entry.path_is_symlink(),
+                    path: entry.into_path(),
+                });
+            },
+            Walker::Parallel { rx, skipped, .. } => loop {
+                let entry = rx.recv().ok()?;
+                if !skipped.iter().any(|dir| entry.path.starts_with(dir) && entry.path != *dir) {
+                    return Some(entry);
+                }
+            },
+        }
+    }
+}
+
+impl Drop for Walker {
+    fn drop(&mut self) {
+        if let Walker::Parallel { stop, .. } = self {
+            stop.store(true, Ordering::SeqCst);
+        }
+    }
+}
+
+/// Folders still to be listed and the number of workers listing one right now.
+struct Queue {
+    dirs: Vec<(PathBuf, usize)>,
+    busy: usize,
+}
+
+fn spawn_parallel(config: WalkConfig, threads: usize, tx: SyncSender<WalkEntry>, stop: Arc<AtomicBool>) {
+    let config = Arc::new(config);
+    let queue = Arc::new((Mutex::new(Queue { dirs: Vec::new(), busy: 0 }), Condvar::new()));
+    // (dev, ino) of every folder descended into, so followed links can not loop (unix only)
+    let visited: Arc<Mutex<HashSet<(u64, u64)>>> = Arc::default();
+
+    let Ok(md) = fs::metadata(&config.root) else { return };
+    let root = WalkEntry {
+        path: config.root.clone(),
+        depth: 0,
+        is_dir: md.is_dir(),
+        is_symlink: fs::symlink_metadata(&config.root).is_ok_and(|m| m.file_type().is_symlink()),
+    };
+    if config.min_depth.is_none_or(|min| min == 0) && tx.send(root.clone()).is_err() {
+        return;
+    }
+    if root.is_dir && config.max_depth.is_none_or(|max| max > 0) {
+        first_visit(&visited, &md);
+        queue.0.lock().unwrap_or_else(|e| e.into_inner()).dirs.push((root.path, 0));
+    }
+
+    for _ in 0..threads {
+        let (config, queue, visited, tx, stop) =
+            (Arc::clone(&config), Arc::clone(&queue), Arc::clone(&visited), tx.clone(), Arc::clone(&stop));
+        std::thread::spawn(move || {
+            let (lock, cvar) = &*queue;
+            loop {
+                let (dir, depth) = {
+                    let mut q = lock.lock().unwrap_or_else(|e| e.into_inner());
+                    loop {
+                        if stop.load(Ordering::SeqCst) {
+                            return;
+                        }
+                        if let Some(job) = q.dirs.pop() {
+                            q.busy += 1;
+                            break job;
+                        }
+                        if q.busy == 0 {
+                            cvar.notify_all();
+                            return;
+                        }
+                        q = cvar.wait(q).unwrap_or_else(|e| e.into_inner());
+                    }
+                };
+                let subdirs = list_dir(&config, &dir, depth + 1, &visited, &tx);
+                let mut q = lock.lock().unwrap_or_else(|e| e.into_inner());
+                q.busy -= 1;
+                match subdirs {
+                    Some(subdirs) => q.dirs.extend(subdirs),
+                    None => stop.store(true, Ordering::SeqCst), // receiver is gone
+                }
+                cvar.notify_all();
+            }
+        });
+    }
+}
+
+/// Send the entries of `dir` (at `depth`) and return the folders to descend into;
+/// None once nobody is listening any more.
+fn list_dir(
+    config: &WalkConfig,
+    dir: &Path,
+    depth: usize,
+    visited: &Mutex<HashSet<(u64, u64)>>,
+    tx: &SyncSender<WalkEntry>,
+) -> Option<Vec<(PathBuf, usize)>> {
+    let mut subdirs = Vec::new();
+    let Ok(read) = fs::read_dir(dir) else { return Some(subdirs) };
+    let mut entries: Vec<fs::DirEntry> = read.filter_map(|e| e.ok()).collect();
+    entries.sort_by_key(|e| e.file_name());
+    for e in entries {
+        let Ok(ft) = e.file_type() else { continue };
+        let path = e.path();
+        let is_symlink = ft.is_symlink();
+        let target = if is_symlink && config.follow_links { fs::metadata(&path).ok() } else { None };
+        let is_dir = match &target {
+            Some(md) => md.is_dir(),
+            None => ft.is_dir(),
+        };
+        let descend = is_dir && config.max_depth.is_none_or(|max| depth < max) && !(config.prune)(&path);
+        if let Some(md) = target.filter(|md| md.is_dir()) {
+            // a link to a folder that is walked already: dropped, like walkdir's loop error
+            if seen_before(visited, &md) {
+                continue;
+            }
+            if descend {
+                first_visit(visited, &md);
+            }
+        } else if descend {
+            if let Ok(md) = fs::metadata(&path) {
+                first_visit(visited, &md);
+            }
+        }
+        if descend {
+            subdirs.push((path.clone(), depth));
+        }
+        if config.min_depth.is_none_or(|min| depth >= min) {
+            tx.send(WalkEntry { path, depth, is_dir, is_symlink }).ok()?;
+        }
+    }
+    Some(subdirs)
+}
+
+#[cfg(unix)]
+fn dir_key(md: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((md.dev(), md.ino()))
+}
+
+#[cfg(not(unix))]
+fn dir_key(_md: &fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// Remember a folder that is descended into.
+fn first_visit(visited: &Mutex<HashSet<(u64, u64)>>, md: &fs::Metadata) {
+    if let Some(key) = dir_key(md) {
+        visited.lock().unwrap_or_else(|e| e.into_inner()).insert(key);
+    }
+}
+
+fn seen_before(visited: &Mutex<HashSet<(u64, u64)>>, md: &fs::Metadata) -> bool {
+    dir_key(md).is_some_and(|key| visited.lock().unwrap_or_else(|e| e.into_inner()).contains(&key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths(walker: Walker, root: &Path) -> Vec<String> {
+        let mut out: Vec<String> = walker
+            .map(|e| e.path().strip_prefix(root).unwrap().to_string_lossy().replace('\\', "/"))
+            .collect();
+        out.sort();
+        out
+    }
+
+    #[test]
+    fn parallel_walk_matches_walkdir() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        for dir in ["exp1/s1", "exp1/s2/deep", "exp2", "exp2/Reports/html"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        for file in ["exp1/s1/a.fastq.gz", "exp1/s2/deep/b.fastq.gz", "exp2/c.h5", "exp2/Reports/html/x.html"] {
+            fs::write(root.join(file), "x").unwrap();
+        }
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(root.join("exp1"), root.join("exp1/s1/loop")).unwrap();
+
+        let config = |max_depth, prune: fn(&Path) -> bool| WalkConfig {
+            root: root.to_path_buf(),
+            follow_links: true,
+            min_depth: None,
+            max_depth,
+            prune,
+        };
+        let serial = paths(Walker::new(config(Some(3), |_| false), 1), root);
+        let parallel = paths(Walker::new(config(Some(3), |_| false), 4), root);
+        assert_eq!(serial, parallel);
+
+        // the link back to exp1 is listed but not followed again
+        let all = paths(Walker::new(config(None, |p| p.ends_with("Reports")), 4), root);
+        assert!(all.contains(&"exp1/s2/deep/b.fastq.gz".to_string()));
+        assert!(!all.iter().any(|p| p.starts_with("exp1/s1/loop/")));
+        assert!(all.contains(&"exp2/Reports".to_string()));
+        assert!(!all.contains(&"exp2/Reports/html".to_string()));
+    }
+
+    #[test]
+    fn model_does_not_depend_on_walk_threads() {
+        use crate::sample_files::SampleFiles;
+        use flate2::write::GzEncoder;
+        use std::io::Write;
+
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        // the same FASTQ in many folders: keep-first has to keep the same copy every time
+        for dir in ["exp1/z", "exp1/a", "exp1/m/deep", "exp1/b", "exp1/y/deep", "exp1/c"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+            let file = fs::File::create(root.join(dir).join("sampleA_S1_L001_R1_001.fastq.gz")).unwrap();
+            let mut gz = GzEncoder::new(file, flate2::Compression::default());
+            gz.write_all(b"@r1\nACGT\n+\nIIII\n").unwrap();
+            gz.finish().unwrap();
+        }
+        let model = |threads: usize| {
+            let mut data = SampleFiles::builder().suffixes([".fastq.gz"]).walk_threads(threads).build();
+            data.ingest(root).unwrap();
+            let kept: Vec<String> = data.iter_all_parsed_files().iter().map(|pf| pf.path.clone()).collect();
+            (kept, data.dropped_copies().to_vec())
+        };
+        let serial = model(1);
+        assert!(serial.0[0].ends_with("exp1/a/sampleA_S1_L001_R1_001.fastq.gz"));
+        assert_eq!(serial.1.len(), 5);
+        for _ in 0..5 {
+            assert_eq!(model(4), serial);
+        }
+    }
+}
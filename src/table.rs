@@ -0,0 +1,247 @@
+// src/table.rs
+//! Tabular outputs as TSV, CSV or XLSX. XLSX files are written with the `zip` crate as a
+//! minimal single-sheet workbook (inline strings), so no spreadsheet library is needed.
+use std::io::Write;
+use std::path::Path;
+
+use zip::write::FileOptions;
+use zip::{CompressionMethod, DateTime};
+
+use crate::error::{GeoPrepError, Result};
+use crate::output::create_output;
+
+/// File format of the sample and md5 tables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TableFormat {
+    /// tab separated, no quoting (classic)
+    #[default]
+    Tsv,
+    /// comma separated, RFC 4180 quoting
+    Csv,
+    /// Excel workbook with one sheet
+    Xlsx,
+}
+
+impl TableFormat {
+    /// File extension without the dot.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            TableFormat::Tsv => "tsv",
+            TableFormat::Csv => "csv",
+            TableFormat::Xlsx => "xlsx",
+        }
+    }
+}
+
+impl std::str::FromStr for TableFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "tsv" => Ok(TableFormat::Tsv),
+            "csv" => Ok(TableFormat::Csv),
+            "xlsx" => Ok(TableFormat::Xlsx),
+            other => Err(format!("unknown table format '{}' (tsv, csv, xlsx)", other)),
+        }
+    }
+}
+
+/// How a table is written: format plus an optional UTF-8 byte order mark (TSV/CSV only),
+/// which makes Excel detect the encoding of non-ASCII sample names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TableStyle {
+    pub format: TableFormat,
+    pub bom: bool,
+}
+
+/// Quote a CSV field if it contains a separator, quote or line break.
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// `0` -> `A`, `26` -> `AA`
+fn column_name(mut idx: usize) -> String {
+    let mut name = Vec::new();
+    loop {
+        name.push(b'A' + (idx % 26) as u8);
+        if idx < 26 {
+            break;
+        }
+        idx = idx / 26 - 1;
+    }
+    name.reverse();
+    String::from_utf8(name).unwrap_or_default()
+}
+
+fn sheet_xml(rows: &[Vec<String>]) -> String {
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+         <worksheet xmlns=\"http://schemas.openxmlformats.org/spreadsheetml/2006/main\"><sheetData>",
+    );
+    for (r, row) in rows.iter().enumerate() {
+        xml.push_str(&format!("<row r=\"{}\">", r + 1));
+        for (c, value) in row.iter().enumerate() {
+            if value.is_empty() {
+                continue;
+            }
+            xml.push_str(&format!(
+                "<c r=\"{}{}\" t=\"inlineStr\"><is><t xml:space=\"preserve\">{}</t></is></c>",
+                column_name(c),
+                r + 1,
+                xml_escape(value)
+            ));
+        }
+        xml.push_str("</row>");
+    }
+    xml.push_str("</sheetData></worksheet>");
+    xml
+}
+
+const XLSX_CONTENT_TYPES: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+<Types xmlns=\"http://schemas.openxmlformats.org/package/2006/content-types\">\
+<Default Extension=\"rels\" ContentType=\"application/vnd.openxmlformats-package.relationships+xml\"/>\
+<Default Extension=\"xml\" ContentType=\"application/xml\"/>\
+<Override PartName=\"/xl/workbook.xml\" ContentType=\"application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml\"/>\
+<Override PartName=\"/xl/worksheets/sheet1.xml\" ContentType=\"application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml\"/>\
+</Types>";
+
+const XLSX_ROOT_RELS: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+<Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">\
+<Relationship Id=\"rId1\" Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument\" Target=\"xl/workbook.xml\"/>\
+</Relationships>";
+
+const XLSX_WORKBOOK: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+<workbook xmlns=\"http://schemas.openxmlformats.org/spreadsheetml/2006/main\" \
+xmlns:r=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships\">\
+<sheets><sheet name=\"Sheet1\" sheetId=\"1\" r:id=\"rId1\"/></sheets></workbook>";
+
+const XLSX_WORKBOOK_RELS: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+<Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">\
+<Relationship Id=\"rId1\" Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet\" Target=\"worksheets/sheet1.xml\"/>\
+</Relationships>";
+
+/// Write `rows` (header first) to `path` in the given style, atomically.
+pub(crate) fn write_table<P: AsRef<Path>>(path: P, style: TableStyle, rows: &[Vec<String>]) -> Result<()> {
+    let mut w = create_output(path)?;
+    match style.format {
+        TableFormat::Tsv | TableFormat::Csv => {
+            if style.bom {
+                w.write_all("\u{feff}".as_bytes())?;
+            }
+            for row in rows {
+                let line = if style.format == TableFormat::Csv {
+                    row.iter().map(|c| csv_field(c)).collect::<Vec<_>>().join(",")
+                } else {
+                    row.join("\t")
+                };
+                writeln!(w, "{}", line)?;
+            }
+        }
+        TableFormat::Xlsx => {
+            // fixed timestamps keep reruns byte-identical
+            let opts: FileOptions<()> = FileOptions::default()
+                .compression_method(CompressionMethod::Deflated)
+                .last_modified_time(DateTime::default());
+            let mut zw = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+            for (name, content) in [
+                ("[Content_Types].xml", XLSX_CONTENT_TYPES.to_string()),
+                ("_rels/.rels", XLSX_ROOT_RELS.to_string()),
+                ("xl/workbook.xml", XLSX_WORKBOOK.to_string()),
+                ("xl/_rels/workbook.xml.rels", XLSX_WORKBOOK_RELS.to_string()),
+                ("xl/worksheets/sheet1.xml", sheet_xml(rows)),
+            ] {
+                zw.start_file(name, opts).map_err(std::io::Error::other)?;
+                zw.write_all(content.as_bytes())?;
+            }
+            let bytes = zw.finish().map_err(std::io::Error::other)?.into_inner();
+            w.write_all(&bytes)?;
+        }
+    }
+    w.commit()
+}
+
+/// Split one CSV line, undoing the quoting of `csv_field`.
+fn csv_cells(line: &str) -> Vec<String> {
+    let mut cells = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                cells.last_mut().unwrap().push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => cells.push(String::new()),
+            c => cells.last_mut().unwrap().push(c),
+        }
+    }
+    cells
+}
+
+/// Read a TSV or CSV table (by extension) as written by `write_table`, header first.
+/// A byte order mark and empty lines are skipped; XLSX files can not be read back.
+pub(crate) fn read_table<P: AsRef<Path>>(path: P) -> Result<Vec<Vec<String>>> {
+    let path = path.as_ref();
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_ascii_lowercase();
+    if ext == "xlsx" {
+        return Err(GeoPrepError::InvalidInput {
+            path: path.to_path_buf(),
+            line: 0,
+            message: "XLSX tables can not be read back, save the table as TSV or CSV".to_string(),
+        });
+    }
+    let text = std::fs::read_to_string(path)?;
+    Ok(text
+        .trim_start_matches('\u{feff}')
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| if ext == "csv" { csv_cells(line) } else { line.split('\t').map(String::from).collect() })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn table() -> Vec<Vec<String>> {
+        vec![
+            vec!["file_name".into(), "md5sum".into()],
+            vec!["a,b \"x\".fastq.gz".into(), "6f5902ac237024bdd0c176cb93063dc4".into()],
+        ]
+    }
+
+    #[test]
+    fn csv_quotes_and_bom() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("t.csv");
+        write_table(&path, TableStyle { format: TableFormat::Csv, bom: true }, &table()).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "\u{feff}file_name,md5sum\n\"a,b \"\"x\"\".fastq.gz\",6f5902ac237024bdd0c176cb93063dc4\n"
+        );
+        assert_eq!(read_table(&path).unwrap(), table());
+    }
+
+    #[test]
+    fn xlsx_is_a_workbook_with_inline_strings() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("t.xlsx");
+        write_table(&path, TableStyle { format: TableFormat::Xlsx, bom: false }, &table()).unwrap();
+        let mut archive = zip::ZipArchive::new(std::fs::File::open(&path).unwrap()).unwrap();
+        let mut sheet = String::new();
+        archive.by_name("xl/worksheets/sheet1.xml").unwrap().read_to_string(&mut sheet).unwrap();
+        assert!(sheet.contains("<c r=\"A2\" t=\"inlineStr\"><is><t xml:space=\"preserve\">a,b &quot;x&quot;.fastq.gz</t>"));
+        assert_eq!(column_name(0), "A");
+        assert_eq!(column_name(27), "AB");
+    }
+}
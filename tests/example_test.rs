@@ -157,6 +157,8 @@ fn cli_runs_on_example_tree_and_creates_outputs() {
         let sample_collection = PathBuf::from(format!("{}.tsv", prefix.display()));
         let md5_table = PathBuf::from(format!("{}_md5sum.tsv", prefix.display()));
         let pairs_table = PathBuf::from(format!("{}_pairs.tsv", prefix.display()));
+        let raw_table = PathBuf::from(format!("{}_raw_files.tsv", prefix.display()));
+        let processed_table = PathBuf::from(format!("{}_processed_files.tsv", prefix.display()));
         let script_path = if cfg!(windows) {
             PathBuf::from(format!("{}_collection_script.ps1", prefix.display()))
         } else {
@@ -166,6 +168,8 @@ fn cli_runs_on_example_tree_and_creates_outputs() {
         must_exist(&sample_collection)?;
         must_exist(&md5_table)?;
         must_exist(&pairs_table)?;
+        must_exist(&raw_table)?;
+        must_exist(&processed_table)?;
         must_exist(&script_path)?;
 
         Ok(())
@@ -177,3 +181,103 @@ fn cli_runs_on_example_tree_and_creates_outputs() {
 
     // On success: TempDir is dropped and cleaned up.
 }
+
+/// Run the CLI on `input` with the README example options, writing to `prefix`.
+fn run_cli(input: &Path, prefix: &Path) {
+    Command::new(cargo::cargo_bin!())
+        .arg("--input")
+        .arg(input.as_os_str())
+        .arg("--exclude")
+        .arg("geo_downloaded_data")
+        .arg("--suffix")
+        .arg(".fastq.gz")
+        .arg("--suffix")
+        .arg("matrix.mtx.gz")
+        .arg("--prefix")
+        .arg(prefix.as_os_str())
+        .assert()
+        .success();
+}
+
+/// Hashing and zipping are done in-process (no `md5sum`/`zip` binaries), so two
+/// independent runs on equal trees - on any OS - give byte-identical checksum tables.
+#[test]
+fn checksum_tables_are_identical_across_runs() {
+    let first = TempDir::new().expect("TempDir");
+    let second = TempDir::new().expect("TempDir");
+
+    let mut tables = Vec::new();
+    for tmp in [&first, &second] {
+        let input = create_example_tree(tmp.path()).expect("example tree");
+        let prefix = tmp.path().join("example");
+        run_cli(&input, &prefix);
+        let md5_table = fs::read_to_string(format!("{}_md5sum.tsv", prefix.display())).expect("md5 table");
+        let processed = fs::read_to_string(format!("{}_processed_files.tsv", prefix.display())).expect("processed table");
+        tables.push((md5_table, processed));
+    }
+    assert_eq!(tables[0], tables[1]);
+
+    // the digests are the real md5 sums of the input files
+    let r1 = first.path().join("INPUT/experiment_1/data/sampleA_R1.fastq.gz");
+    let expected = format!("{:x}", md5::compute(fs::read(&r1).unwrap()));
+    assert!(tables[0].0.lines().any(|l| l.ends_with(&expected) && l.contains("sampleA_R1.fastq.gz")));
+
+    // the 10x triplet zip uses forward slashes and needs no external tool
+    let zip = fs::read_dir(first.path().join("INPUT/experiment_1/sampleA/outs"))
+        .unwrap()
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .find(|p| p.extension().is_some_and(|x| x == "zip"))
+        .expect("triplet zip");
+    let archive = zip::ZipArchive::new(fs::File::open(zip).unwrap()).unwrap();
+    assert!(archive.file_names().all(|n| !n.contains('\\')));
+    assert_eq!(archive.len(), 3);
+}
+
+/// `--check` writes no tables and reports the outcome through the exit code.
+#[test]
+fn check_mode_only_sets_the_exit_code() {
+    let tmp = TempDir::new().expect("TempDir");
+    let input = create_example_tree(tmp.path()).expect("example tree");
+    let prefix = tmp.path().join("example");
+    let check = || {
+        let mut cmd = Command::new(cargo::cargo_bin!());
+        cmd.arg("--input")
+            .arg(input.as_os_str())
+            .arg("--exclude")
+            .arg("geo_downloaded_data")
+            .arg("--suffix")
+            .arg(".fastq.gz")
+            .arg("--prefix")
+            .arg(prefix.as_os_str())
+            .arg("--check");
+        cmd
+    };
+
+    check().assert().success();
+    assert!(!Path::new(&format!("{}.tsv", prefix.display())).exists());
+
+    // a FASTQ without a read role can not be parsed: an error
+    write_gzip_text(input.join("experiment_2/sampleC.fastq.gz"), "@r\nA\n+\nF\n").unwrap();
+    check().assert().code(2).stdout(predicates::str::contains("ERROR\t"));
+}
+
+/// Tables that can not be written end the run with the IO failure code (3).
+#[test]
+fn unwritable_outputs_exit_with_io_failure() {
+    let tmp = TempDir::new().expect("TempDir");
+    let input = create_example_tree(tmp.path()).expect("example tree");
+    let prefix = tmp.path().join("missing/dir/run");
+    Command::new(cargo::cargo_bin!())
+        .arg("--input")
+        .arg(input.as_os_str())
+        .arg("--exclude")
+        .arg("geo_downloaded_data")
+        .arg("--suffix")
+        .arg(".fastq.gz")
+        .arg("--prefix")
+        .arg(prefix.as_os_str())
+        .assert()
+        .code(3)
+        .stderr(predicates::str::contains("Could not write"));
+}